@@ -1,21 +1,230 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Stdio;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{ChildStdin, Stdio};
+use std::sync::{Arc, Mutex};
 use zbus::{interface, ConnectionBuilder};
-use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+use zbus::zvariant::{self, OwnedObjectPath, OwnedValue, Type, Value};
 
 const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.hardbore";
+// Set by --portal-selftest so the picker answers without opening a window.
+const SELFTEST_OPTION: &str = "hardbore_selftest";
+
+// Pattern kinds in the portal's filter tuples.
+const GLOB: u32 = 0;
+const MIME_TYPE: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, zvariant::Value)]
+struct FilterPattern {
+    kind: u32,
+    pattern: String,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Serializes as the portal's `(sa(us))`, so a filter goes back as it came.
+#[derive(Debug, Clone, Serialize, Deserialize, Type, zvariant::Value)]
 struct FileFilter {
     name: String,
-    patterns: Vec<String>,
+    patterns: Vec<FilterPattern>,
+}
+
+// One path HardBore returned, with the details it printed after it.
+#[derive(Debug, Clone, Deserialize)]
+struct Selection {
+    path: String,
+    #[serde(default)]
+    is_dir: bool,
+    #[serde(default)]
+    mime: Option<String>,
+}
+
+// ~/.config/hardbore/portal.toml, read for each dialog so edits apply without
+// restarting the portal.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct PortalConfig {
+    // Where dialogs start when the app doesn't say.
+    start_dir: Option<String>,
+    // Start each app's dialogs in the folder it last picked from, like GTK's
+    // portal does. On unless turned off.
+    remember_last_folder: bool,
+    window_width: Option<u32>,
+    window_height: Option<u32>,
+    // Dark or light window decorations; unset follows the system.
+    dark_mode: Option<bool>,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            start_dir: None,
+            remember_last_folder: true,
+            window_width: None,
+            window_height: None,
+            dark_mode: None,
+        }
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|d| d.is_absolute())
+        .or_else(|| Some(home_dir()?.join(fallback)))
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+impl PortalConfig {
+    fn load() -> Self {
+        let Some(path) = xdg_dir("XDG_CONFIG_HOME", ".config").map(|d| d.join("hardbore").join("portal.toml")) else {
+            return Self::default();
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        toml::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("[HardBore Portal] Ignoring {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    // The folder to start in when the app gave none: the one it last picked
+    // from if that's remembered, then the configured one, then our own.
+    fn start_folder(&self, app_id: &str) -> Option<String> {
+        let remembered = self.remember_last_folder.then(|| last_folder(app_id)).flatten();
+        remembered
+            .into_iter()
+            .chain(self.start_dir.as_deref().map(expand_home))
+            .find(|d| std::path::Path::new(d).is_dir())
+            .or_else(|| std::env::current_dir().ok().map(|d| d.to_string_lossy().to_string()))
+    }
+
+    // `parent_window` is passed through so HardBore can open over the caller,
+    // on the caller's monitor.
+    fn window_args(&self, parent_window: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        if !parent_window.is_empty() {
+            args.push("--parent-window".to_string());
+            args.push(parent_window.to_string());
+        }
+        if let (Some(width), Some(height)) = (self.window_width, self.window_height) {
+            args.push("--window-size".to_string());
+            args.push(format!("{}x{}", width, height));
+        }
+        if let Some(dark) = self.dark_mode {
+            args.push("--theme".to_string());
+            args.push(if dark { "dark" } else { "light" }.to_string());
+        }
+        args
+    }
+
+    // Remembers where a dialog for `app_id` ended up. Apps outside a sandbox
+    // have no ID and share nothing.
+    fn remember(&self, app_id: &str, folder: &str) {
+        if !self.remember_last_folder || app_id.is_empty() {
+            return;
+        }
+        let Some(path) = last_folders_file() else {
+            return;
+        };
+        let mut folders = read_last_folders();
+        folders.insert(app_id.to_string(), folder.to_string());
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&folders) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+// Kept with HardBore's other data, keyed by app ID.
+fn last_folders_file() -> Option<PathBuf> {
+    Some(xdg_dir("XDG_DATA_HOME", ".local/share")?.join("hardbore").join("portal-folders.json"))
+}
+
+fn read_last_folders() -> HashMap<String, String> {
+    last_folders_file()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn last_folder(app_id: &str) -> Option<String> {
+    if app_id.is_empty() {
+        return None;
+    }
+    read_last_folders().remove(app_id)
+}
+
+// The folder a selection was made in: a picked folder itself, or a file's parent.
+fn selection_folder(selection: &Selection) -> Option<String> {
+    if selection.is_dir {
+        return Some(selection.path.clone());
+    }
+    std::path::Path::new(&selection.path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
 }
 
 pub struct FileChooserPortal {
     hardbore_path: String,
 }
 
+pub struct AppChooserPortal {
+    hardbore_path: String,
+    // The dialogs still open, by request handle, so UpdateChoices can reach them.
+    open: Arc<Mutex<HashMap<String, ChildStdin>>>,
+}
+
+fn find_hardbore() -> String {
+    if std::path::Path::new("/usr/local/bin/hardbore").exists() {
+        "/usr/local/bin/hardbore".to_string()
+    } else if std::path::Path::new("/usr/bin/hardbore").exists() {
+        "/usr/bin/hardbore".to_string()
+    } else if let Ok(output) = std::process::Command::new("which").arg("hardbore").output() {
+        if output.status.success() {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        } else {
+            "hardbore".to_string()
+        }
+    } else {
+        "hardbore".to_string()
+    }
+}
+
+// HardBore started with the session's display and desktop settings; the
+// portal itself may run with fewer.
+fn hardbore_command(hardbore_path: &str, args: &[String]) -> std::process::Command {
+    let mut cmd = std::process::Command::new(hardbore_path);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for key in &[
+        "DISPLAY", "WAYLAND_DISPLAY", "XDG_RUNTIME_DIR", "HOME",
+        "DBUS_SESSION_BUS_ADDRESS", "GDK_BACKEND", "XDG_SESSION_TYPE",
+        "XDG_CURRENT_DESKTOP", "XDG_DATA_DIRS", "XDG_CONFIG_HOME",
+        "PATH", "LANG", "LC_ALL",
+    ] {
+        if let Ok(val) = std::env::var(key) {
+            cmd.env(key, val);
+        }
+    }
+    cmd
+}
+
 fn encode_file_uri(path: &str) -> String {
     let encoded: String = path
         .split('/')
@@ -35,6 +244,45 @@ fn encode_file_uri(path: &str) -> String {
     format!("file://{}", encoded)
 }
 
+// `*` and `?` wildcards, ignoring ASCII case like GTK's own filters.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => glob_matches(&pattern[1..], name) || (!name.is_empty() && glob_matches(pattern, &name[1..])),
+        (Some(b'?'), Some(_)) => glob_matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p.eq_ignore_ascii_case(n) => glob_matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime.split('/').next() == Some(prefix),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+impl FileFilter {
+    fn matches(&self, selection: &Selection) -> bool {
+        let name = selection.path.rsplit('/').next().unwrap_or(&selection.path);
+        self.patterns.iter().any(|p| match p.kind {
+            GLOB => glob_matches(p.pattern.as_bytes(), name.as_bytes()),
+            MIME_TYPE => selection.mime.as_deref().is_some_and(|m| mime_matches(&p.pattern, m)),
+            _ => false,
+        })
+    }
+}
+
+// The first filter every selected file fits, reported back as the one in use.
+// Folders say nothing about which filter applies.
+fn matching_filter<'a>(filters: &'a [FileFilter], selections: &[Selection]) -> Option<&'a FileFilter> {
+    let files: Vec<&Selection> = selections.iter().filter(|s| !s.is_dir).collect();
+    if files.is_empty() {
+        return None;
+    }
+    filters.iter().find(|f| files.iter().all(|s| f.matches(s)))
+}
+
 fn extract_bool(options: &HashMap<String, OwnedValue>, key: &str) -> bool {
     options
         .get(key)
@@ -116,11 +364,15 @@ fn extract_current_folder(options: &HashMap<String, OwnedValue>) -> Option<Strin
     None
 }
 
-fn extract_current_name(options: &HashMap<String, OwnedValue>) -> Option<String> {
-    let val = options.get("current_name")?;
+fn extract_string(options: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    let val = options.get(key)?;
     val.downcast_ref::<String>().ok().filter(|s| !s.is_empty())
 }
 
+fn extract_current_name(options: &HashMap<String, OwnedValue>) -> Option<String> {
+    extract_string(options, "current_name")
+}
+
 fn extract_filenames(options: &HashMap<String, OwnedValue>) -> Vec<String> {
     let Some(val) = options.get("files") else {
         return vec![];
@@ -184,10 +436,13 @@ fn parse_filters(options: &HashMap<String, OwnedValue>) -> Vec<FileFilter> {
                 if pat_tuple.len() < 2 {
                     continue;
                 }
-                let match_type = pat_tuple[0].as_u64().unwrap_or(99);
+                let kind = pat_tuple[0].as_u64().unwrap_or(99) as u32;
                 let pattern = pat_tuple[1].as_str().unwrap_or("");
-                if match_type == 0 && !pattern.is_empty() {
-                    patterns.push(pattern.to_string());
+                if (kind == GLOB || kind == MIME_TYPE) && !pattern.is_empty() {
+                    patterns.push(FilterPattern {
+                        kind,
+                        pattern: pattern.to_string(),
+                    });
                 }
             }
         }
@@ -202,52 +457,14 @@ fn parse_filters(options: &HashMap<String, OwnedValue>) -> Vec<FileFilter> {
 
 impl FileChooserPortal {
     pub fn new() -> Self {
-        let hardbore_path = if std::path::Path::new("/usr/local/bin/hardbore").exists() {
-            "/usr/local/bin/hardbore".to_string()
-        } else if std::path::Path::new("/usr/bin/hardbore").exists() {
-            "/usr/bin/hardbore".to_string()
-        } else if let Ok(output) = std::process::Command::new("which").arg("hardbore").output() {
-            if output.status.success() {
-                String::from_utf8_lossy(&output.stdout).trim().to_string()
-            } else {
-                "hardbore".to_string()
-            }
-        } else {
-            "hardbore".to_string()
-        };
-
-        Self { hardbore_path }
-    }
-
-    async fn launch_picker(&self, args: &[String]) -> Vec<String> {
-        let mut env_vars: Vec<(String, String)> = Vec::new();
-        for key in &[
-            "DISPLAY", "WAYLAND_DISPLAY", "XDG_RUNTIME_DIR", "HOME",
-            "DBUS_SESSION_BUS_ADDRESS", "GDK_BACKEND", "XDG_SESSION_TYPE",
-            "XDG_CURRENT_DESKTOP", "XDG_DATA_DIRS", "XDG_CONFIG_HOME",
-            "PATH", "LANG", "LC_ALL",
-        ] {
-            if let Ok(val) = std::env::var(key) {
-                env_vars.push((key.to_string(), val));
-            }
+        Self {
+            hardbore_path: find_hardbore(),
         }
+    }
 
-        let hardbore_path = self.hardbore_path.clone();
-        let args = args.to_vec();
-
-        let handle = std::thread::spawn(move || {
-            let mut cmd = std::process::Command::new(&hardbore_path);
-            cmd.args(&args)
-                .stdin(Stdio::null())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped());
-
-            for (key, val) in &env_vars {
-                cmd.env(key, val);
-            }
-
-            cmd.output()
-        });
+    async fn launch_picker(&self, args: &[String]) -> Vec<Selection> {
+        let mut cmd = hardbore_command(&self.hardbore_path, args);
+        let handle = std::thread::spawn(move || cmd.output());
 
         let output = match handle.join() {
             Ok(Ok(output)) => output,
@@ -255,17 +472,28 @@ impl FileChooserPortal {
             Err(_) => return vec![],
         };
 
-        if output.status.success() {
-            String::from_utf8_lossy(&output.stdout)
-                .lines()
-                .filter_map(|line| {
-                    line.strip_prefix("HARDBORE_SELECTED:")
-                        .map(|s| s.to_string())
-                })
-                .collect()
-        } else {
-            vec![]
+        if !output.status.success() {
+            return vec![];
+        }
+        // Older HardBore builds print only the paths.
+        let mut selections: Vec<Selection> = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(path) = line.strip_prefix("HARDBORE_SELECTED:") {
+                selections.push(Selection {
+                    path: path.to_string(),
+                    is_dir: false,
+                    mime: None,
+                });
+            } else if let Some(details) = line.strip_prefix("HARDBORE_SELECTION:") {
+                let Ok(details) = serde_json::from_str::<Selection>(details) else {
+                    continue;
+                };
+                if let Some(last) = selections.last_mut().filter(|s| s.path == details.path) {
+                    *last = details;
+                }
+            }
         }
+        selections
     }
 
     fn build_picker_args(
@@ -281,18 +509,24 @@ impl FileChooserPortal {
             args.push("--multiple".to_string());
         }
 
-        let extensions: Vec<String> = filters
+        // HardBore hides files of other types, so a pattern it can't express
+        // (anything but `*.ext` or a MIME type) means no restriction at all.
+        let types: Option<Vec<String>> = filters
             .iter()
-            .flat_map(|f| {
-                f.patterns.iter().filter_map(|p| {
-                    p.strip_prefix("*.").map(|ext| ext.to_string())
-                })
+            .flat_map(|f| &f.patterns)
+            .map(|p| match p.kind {
+                MIME_TYPE => Some(p.pattern.clone()),
+                _ => p
+                    .pattern
+                    .strip_prefix("*.")
+                    .filter(|ext| !ext.contains(['*', '?', '[']))
+                    .map(str::to_string),
             })
             .collect();
 
-        if !extensions.is_empty() {
+        if let Some(types) = types.filter(|t| !t.is_empty()) {
             args.push("--types".to_string());
-            args.push(extensions.join(","));
+            args.push(types.join(","));
         }
 
         if let Some(folder) = current_folder {
@@ -308,7 +542,7 @@ impl FileChooserPortal {
         args
     }
 
-    fn build_response(uris: Vec<String>) -> (u32, HashMap<String, OwnedValue>) {
+    fn build_response(uris: Vec<String>, current_filter: Option<&FileFilter>) -> (u32, HashMap<String, OwnedValue>) {
         if uris.is_empty() {
             return (1, HashMap::new());
         }
@@ -320,6 +554,9 @@ impl FileChooserPortal {
             }
             Err(_) => return (2, HashMap::new()),
         }
+        if let Some(Ok(filter)) = current_filter.map(|f| OwnedValue::try_from(Value::from(f.clone()))) {
+            result.insert("current_filter".to_string(), filter);
+        }
         (0, result)
     }
 }
@@ -334,72 +571,73 @@ impl FileChooserPortal {
     async fn open_file(
         &self,
         _handle: OwnedObjectPath,
-        _app_id: &str,
-        _parent_window: &str,
+        app_id: &str,
+        parent_window: &str,
         _title: &str,
         options: HashMap<String, OwnedValue>,
     ) -> (u32, HashMap<String, OwnedValue>) {
         let multiple = extract_bool(&options, "multiple");
         let directory = extract_bool(&options, "directory");
-        let mut current_folder = extract_current_folder(&options);
+        let config = PortalConfig::load();
+        let current_folder = extract_current_folder(&options).or_else(|| config.start_folder(app_id));
         let filters = parse_filters(&options);
-        
-        if current_folder.is_none() {
-            if let Ok(cwd) = std::env::current_dir() {
-                current_folder = Some(cwd.to_string_lossy().to_string());
-            }
-        }
 
         let mode = if directory { "--picker-dirs" } else { "--picker" };
-        let args = Self::build_picker_args(
+        let mut args = Self::build_picker_args(
             mode,
             multiple,
             &filters,
             current_folder.as_deref(),
             None,
         );
+        args.extend(config.window_args(parent_window));
+        if extract_bool(&options, SELFTEST_OPTION) {
+            args.push("--headless".to_string());
+        }
 
         let selected = self.launch_picker(&args).await;
-        let uris: Vec<String> = selected.iter().map(|p| encode_file_uri(p)).collect();
-        Self::build_response(uris)
+        if let Some(folder) = selected.first().and_then(selection_folder) {
+            config.remember(app_id, &folder);
+        }
+        let uris: Vec<String> = selected.iter().map(|s| encode_file_uri(&s.path)).collect();
+        Self::build_response(uris, matching_filter(&filters, &selected))
     }
 
     async fn save_file(
         &self,
         _handle: OwnedObjectPath,
-        _app_id: &str,
-        _parent_window: &str,
+        app_id: &str,
+        parent_window: &str,
         _title: &str,
         options: HashMap<String, OwnedValue>,
     ) -> (u32, HashMap<String, OwnedValue>) {
-        let mut current_folder = extract_current_folder(&options);
+        let config = PortalConfig::load();
+        let current_folder = extract_current_folder(&options).or_else(|| config.start_folder(app_id));
         let current_name = extract_current_name(&options);
         let filters = parse_filters(&options);
-        
-        if current_folder.is_none() {
-            if let Ok(cwd) = std::env::current_dir() {
-                current_folder = Some(cwd.to_string_lossy().to_string());
-            }
-        }
 
-        let args = Self::build_picker_args(
+        let mut args = Self::build_picker_args(
             "--picker-save",
             false,
             &filters,
             current_folder.as_deref(),
             current_name.as_deref(),
         );
+        args.extend(config.window_args(parent_window));
 
         let selected = self.launch_picker(&args).await;
-        let uris: Vec<String> = selected.iter().map(|p| encode_file_uri(p)).collect();
-        Self::build_response(uris)
+        if let Some(folder) = selected.first().and_then(selection_folder) {
+            config.remember(app_id, &folder);
+        }
+        let uris: Vec<String> = selected.iter().map(|s| encode_file_uri(&s.path)).collect();
+        Self::build_response(uris, matching_filter(&filters, &selected))
     }
 
     async fn save_files(
         &self,
         _handle: OwnedObjectPath,
-        _app_id: &str,
-        _parent_window: &str,
+        app_id: &str,
+        parent_window: &str,
         _title: &str,
         options: HashMap<String, OwnedValue>,
     ) -> (u32, HashMap<String, OwnedValue>) {
@@ -415,26 +653,27 @@ impl FileChooserPortal {
             }
         }
         
+        let config = PortalConfig::load();
         if current_folder.is_none() {
-            if let Ok(cwd) = std::env::current_dir() {
-                current_folder = Some(cwd.to_string_lossy().to_string());
-            }
+            current_folder = config.start_folder(app_id);
         }
 
-        let args = Self::build_picker_args(
+        let mut args = Self::build_picker_args(
             "--picker-dirs",
             false,
             &[],
             current_folder.as_deref(),
             None,
         );
+        args.extend(config.window_args(parent_window));
 
         let selected = self.launch_picker(&args).await;
         if selected.is_empty() {
             return (1, HashMap::new());
         }
 
-        let chosen_dir = &selected[0];
+        let chosen_dir = &selected[0].path;
+        config.remember(app_id, chosen_dir);
         let uris: Vec<String> = if filenames.is_empty() {
             vec![encode_file_uri(chosen_dir)]
         } else {
@@ -444,19 +683,187 @@ impl FileChooserPortal {
                 .collect()
         };
 
-        Self::build_response(uris)
+        Self::build_response(uris, None)
     }
 }
 
+impl AppChooserPortal {
+    fn new() -> Self {
+        Self {
+            hardbore_path: find_hardbore(),
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn build_chooser_args(choices: &[String], options: &HashMap<String, OwnedValue>) -> Vec<String> {
+        let mut args = vec!["--choose-app".to_string()];
+        if !choices.is_empty() {
+            args.push("--choices".to_string());
+            args.push(choices.join(","));
+        }
+        let target = extract_string(options, "filename").or_else(|| extract_string(options, "uri"));
+        for (flag, value) in [
+            ("--content-type", extract_string(options, "content_type")),
+            ("--target", target),
+            ("--last-choice", extract_string(options, "last_choice")),
+        ] {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+        }
+        args
+    }
+}
+
+#[interface(name = "org.freedesktop.impl.portal.AppChooser")]
+impl AppChooserPortal {
+    #[zbus(property, name = "version")]
+    async fn version(&self) -> u32 {
+        2
+    }
+
+    async fn choose_application(
+        &self,
+        handle: OwnedObjectPath,
+        _app_id: &str,
+        parent_window: &str,
+        choices: Vec<String>,
+        options: HashMap<String, OwnedValue>,
+    ) -> (u32, HashMap<String, OwnedValue>) {
+        let mut args = Self::build_chooser_args(&choices, &options);
+        args.extend(PortalConfig::load().window_args(parent_window));
+        let mut cmd = hardbore_command(&self.hardbore_path, &args);
+        cmd.stdin(Stdio::piped());
+        let Ok(mut child) = cmd.spawn() else {
+            return (2, HashMap::new());
+        };
+        let key = handle.as_str().to_string();
+        if let Some(stdin) = child.stdin.take() {
+            self.open.lock().unwrap().insert(key.clone(), stdin);
+        }
+
+        // Waited for off the executor, so UpdateChoices can be served meanwhile.
+        let output = tokio::task::spawn_blocking(move || child.wait_with_output()).await;
+        self.open.lock().unwrap().remove(&key);
+        let Ok(Ok(output)) = output else {
+            return (2, HashMap::new());
+        };
+
+        let choice = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("HARDBORE_APP:").map(str::to_string));
+        let Some(choice) = choice.filter(|_| output.status.success()) else {
+            return (1, HashMap::new());
+        };
+        let mut result = HashMap::new();
+        match Value::new(choice).try_into() {
+            Ok(owned) => {
+                result.insert("choice".to_string(), owned);
+                (0, result)
+            }
+            Err(_) => (2, HashMap::new()),
+        }
+    }
+
+    async fn update_choices(&self, handle: OwnedObjectPath, choices: Vec<String>) {
+        if let Some(stdin) = self.open.lock().unwrap().get_mut(handle.as_str()) {
+            let _ = writeln!(stdin, "HARDBORE_CHOICES:{}", choices.join(","));
+            let _ = stdin.flush();
+        }
+    }
+}
+
+async fn serve() -> zbus::Result<zbus::Connection> {
+    ConnectionBuilder::session()?
+        .name(BUS_NAME)?
+        .serve_at(PORTAL_PATH, FileChooserPortal::new())?
+        .serve_at(PORTAL_PATH, AppChooserPortal::new())?
+        .build()
+        .await
+}
+
+fn report(check: &str, ok: bool, detail: &str) -> bool {
+    println!("[{}] {}: {}", if ok { " OK " } else { "FAIL" }, check, detail);
+    ok
+}
+
+// `--portal-selftest`: sends OpenFile to the backend over the session bus,
+// as xdg-desktop-portal would, with HardBore told to answer without a window.
+// Serves the backend from this process if nothing owns its name yet.
+async fn self_test() -> bool {
+    let hardbore_path = find_hardbore();
+    let found = hardbore_path.contains('/') && std::path::Path::new(&hardbore_path).is_file();
+    let mut ok = report("HardBore binary", found, &hardbore_path);
+
+    let connection = match zbus::Connection::session().await {
+        Ok(connection) => connection,
+        Err(e) => return report("Session bus", false, &e.to_string()),
+    };
+    let owned = match zbus::fdo::DBusProxy::new(&connection).await {
+        Ok(proxy) => match zbus::names::BusName::try_from(BUS_NAME) {
+            Ok(name) => proxy.name_has_owner(name).await.unwrap_or(false),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+    let _server = if owned {
+        report("Bus name", true, &format!("{} is owned by a running portal", BUS_NAME));
+        None
+    } else {
+        match serve().await {
+            Ok(server) => {
+                report("Bus name", true, &format!("{} was free; serving it from this process", BUS_NAME));
+                Some(server)
+            }
+            Err(e) => return report("Bus name", false, &format!("could not own {}: {}", BUS_NAME, e)),
+        }
+    };
+
+    let folder = std::env::temp_dir().to_string_lossy().to_string();
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("directory", Value::from(true));
+    options.insert("current_folder", Value::from([folder.as_bytes(), b"\0"].concat()));
+    options.insert(SELFTEST_OPTION, Value::from(true));
+    let handle = "/org/freedesktop/portal/desktop/request/hardbore/selftest";
+
+    let started = std::time::Instant::now();
+    let reply = connection
+        .call_method(
+            Some(BUS_NAME),
+            PORTAL_PATH,
+            Some("org.freedesktop.impl.portal.FileChooser"),
+            "OpenFile",
+            &(zvariant::ObjectPath::try_from(handle).unwrap(), "", "", "HardBore self-test", options),
+        )
+        .await;
+    let elapsed = started.elapsed().as_millis();
+    let (code, results) = match reply.map(|r| r.body().deserialize::<(u32, HashMap<String, OwnedValue>)>()) {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => return report("OpenFile round-trip", false, &format!("unreadable reply: {}", e)),
+        Err(e) => return report("OpenFile round-trip", false, &e.to_string()),
+    };
+    let uris: Vec<String> = results
+        .get("uris")
+        .and_then(|v| Vec::<String>::try_from(v.try_clone().ok()?).ok())
+        .unwrap_or_default();
+    let expected = encode_file_uri(&folder);
+    ok &= report(
+        "OpenFile round-trip",
+        code == 0 && uris.contains(&expected),
+        &format!("response {} in {} ms, uris {:?}", code, elapsed, uris),
+    );
+    ok
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let portal = FileChooserPortal::new();
+    if std::env::args().any(|a| a == "--portal-selftest") {
+        let passed = self_test().await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
-    let _connection = ConnectionBuilder::session()?
-        .name("org.freedesktop.impl.portal.desktop.hardbore")?
-        .serve_at(PORTAL_PATH, portal)?
-        .build()
-        .await?;
+    let _connection = serve().await?;
 
     std::future::pending::<()>().await;
     Ok(())
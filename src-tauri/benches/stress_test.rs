@@ -1,5 +1,9 @@
+use hardbore_lib::copyio::{self, CopyBackend};
 use hardbore_lib::fs_engine::{crawl_directory, read_directory};
 use hardbore_lib::indexer::Indexer;
+use hardbore_lib::jobs::{JobKind, JobManager};
+use hardbore_lib::transfer::{plan_transfer, run_transfer, SkipIdentical, TransferMode};
+use std::sync::Arc;
 use std::time::Instant;
 use std::fs;
 
@@ -96,7 +100,7 @@ fn main() {
     
     for query in &queries {
         let start = Instant::now();
-        let results = indexer.search_fts(query, 100);
+        let results = indexer.search_fts(query, 100, true);
         let search_time = start.elapsed().as_micros();
         total_results += results.len();
         total_time += search_time;
@@ -112,7 +116,7 @@ fn main() {
     println!("Test 5: Fuzzy Search Performance");
     
     let start = Instant::now();
-    let results = indexer.search_fuzzy("conf", 50);
+    let results = indexer.search_fuzzy("conf", 50, true);
     let fuzzy_time = start.elapsed();
     
     println!("  Query: 'conf'");
@@ -137,13 +141,58 @@ fn main() {
     
     let start = Instant::now();
     let indexer2 = Indexer::new(db_path).expect("Failed");
-    let results = indexer2.search_fts("test", 50);
+    let results = indexer2.search_fts("test", 50, true);
     let cold_time = start.elapsed();
     
     println!("  Results: {}", results.len());
     println!("  Time: {}", format_duration(cold_time.as_millis()));
     println!();
 
+    println!("Test 8: Many-Small-File Copy (2000 files, 4-64 KB)");
+
+    let copy_dir = temp_dir.join("copy");
+    let source = copy_dir.join("source");
+    let mut source_bytes = 0u64;
+    for i in 0..2000u64 {
+        let dir = source.join(format!("pkg{}", i % 100));
+        fs::create_dir_all(&dir).ok();
+        let size = 4096 + (i * 7919) % (60 * 1024);
+        fs::write(dir.join(format!("file{}.js", i)), vec![b'x'; size as usize]).ok();
+        source_bytes += size;
+    }
+
+    let jobs = JobManager::new();
+    for backend in [CopyBackend::ReadWrite, CopyBackend::IoUring] {
+        for buffer in [64 * 1024, 1 << 20, 4 << 20] {
+            copyio::set_backend(backend);
+            copyio::set_buffer_size(Some(buffer));
+            let destination = copy_dir.join(format!("{:?}-{}", backend, buffer));
+            fs::create_dir_all(&destination).ok();
+
+            let job = jobs.create(JobKind::Copy, "bench", Vec::new(), Arc::new(|_| {}));
+            let plan = plan_transfer(
+                job.id(),
+                "bench",
+                vec![source.to_string_lossy().to_string()],
+                destination.to_string_lossy().to_string(),
+                TransferMode::Copy,
+                SkipIdentical::Never,
+            );
+            let start = Instant::now();
+            let result = run_transfer(&copy_dir, &plan, &job);
+            let copy_time = start.elapsed();
+
+            println!("  {:?}, {} buffer: {} ({:.1} MB/s){}",
+                backend,
+                format_size(buffer as u64),
+                format_duration(copy_time.as_millis()),
+                source_bytes as f64 / 1_048_576.0 / copy_time.as_secs_f64(),
+                result.err().map(|e| format!(" - {}", e)).unwrap_or_default());
+        }
+    }
+    copyio::set_buffer_size(None);
+    println!();
+
     fs::remove_dir_all(temp_dir).ok();
     
     println!("Done ;)");
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+#[cfg(any(target_os = "linux", windows))]
+use crate::rawpath;
+#[cfg(target_os = "linux")]
+use crate::users::{group_name, user_name};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AclTag {
+    UserObj,
+    User,
+    GroupObj,
+    Group,
+    Mask,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AclEntry {
+    pub tag: AclTag,
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+// Only POSIX ACLs on Linux can be edited. NTFS ACLs are shown read-only, as
+// the lines icacls prints in `raw`, with `editable` false.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileAcl {
+    pub path: String,
+    pub entries: Vec<AclEntry>,
+    pub default_entries: Vec<AclEntry>,
+    pub editable: bool,
+    pub raw: Vec<String>,
+}
+
+// On-disk layout of system.posix_acl_* xattrs (see linux/posix_acl_xattr.h).
+#[cfg(target_os = "linux")]
+const ACL_XATTR_VERSION: u32 = 2;
+#[cfg(target_os = "linux")]
+const ACL_ACCESS: &str = "system.posix_acl_access";
+#[cfg(target_os = "linux")]
+const ACL_DEFAULT: &str = "system.posix_acl_default";
+
+#[cfg(target_os = "linux")]
+fn tag_to_raw(tag: AclTag) -> u16 {
+    match tag {
+        AclTag::UserObj => 0x01,
+        AclTag::User => 0x02,
+        AclTag::GroupObj => 0x04,
+        AclTag::Group => 0x08,
+        AclTag::Mask => 0x10,
+        AclTag::Other => 0x20,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn tag_from_raw(raw: u16) -> Option<AclTag> {
+    match raw {
+        0x01 => Some(AclTag::UserObj),
+        0x02 => Some(AclTag::User),
+        0x04 => Some(AclTag::GroupObj),
+        0x08 => Some(AclTag::Group),
+        0x10 => Some(AclTag::Mask),
+        0x20 => Some(AclTag::Other),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn make_entry(tag: AclTag, id: Option<u32>, perm: u16) -> AclEntry {
+    let name = match (tag, id) {
+        (AclTag::User, Some(uid)) => user_name(uid),
+        (AclTag::Group, Some(gid)) => group_name(gid),
+        _ => None,
+    };
+    AclEntry {
+        tag,
+        id,
+        name,
+        read: perm & 0o4 != 0,
+        write: perm & 0o2 != 0,
+        execute: perm & 0o1 != 0,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn c_path(path: &Path) -> Result<CString, String> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| "Invalid path".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn read_xattr(path: &CString, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let name = CString::new(name).unwrap();
+    let size = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+    if size < 0 {
+        let err = std::io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENODATA) | Some(libc::ENOTSUP) => Ok(None),
+            _ => Err(format!("Failed to read ACL: {}", err)),
+        };
+    }
+
+    let mut buf = vec![0u8; size as usize];
+    let read = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if read < 0 {
+        return Err(format!("Failed to read ACL: {}", std::io::Error::last_os_error()));
+    }
+    buf.truncate(read as usize);
+    Ok(Some(buf))
+}
+
+#[cfg(target_os = "linux")]
+fn decode_acl(buf: &[u8]) -> Vec<AclEntry> {
+    if buf.len() < 4 || u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) != ACL_XATTR_VERSION {
+        return Vec::new();
+    }
+
+    buf[4..]
+        .chunks_exact(8)
+        .filter_map(|chunk| {
+            let tag = tag_from_raw(u16::from_le_bytes([chunk[0], chunk[1]]))?;
+            let perm = u16::from_le_bytes([chunk[2], chunk[3]]);
+            let id = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            let id = matches!(tag, AclTag::User | AclTag::Group).then_some(id);
+            Some(make_entry(tag, id, perm))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn encode_acl(entries: &[AclEntry]) -> Vec<u8> {
+    let mut sorted = entries.to_vec();
+    sorted.sort_by_key(|e| (tag_to_raw(e.tag), e.id.unwrap_or(0)));
+
+    let mut buf = ACL_XATTR_VERSION.to_le_bytes().to_vec();
+    for entry in &sorted {
+        let perm = (entry.read as u16) << 2 | (entry.write as u16) << 1 | entry.execute as u16;
+        buf.extend_from_slice(&tag_to_raw(entry.tag).to_le_bytes());
+        buf.extend_from_slice(&perm.to_le_bytes());
+        buf.extend_from_slice(&entry.id.unwrap_or(u32::MAX).to_le_bytes());
+    }
+    buf
+}
+
+#[cfg(target_os = "linux")]
+fn validate_acl(entries: &[AclEntry]) -> Result<(), String> {
+    let count = |tag: AclTag| entries.iter().filter(|e| e.tag == tag).count();
+
+    for tag in [AclTag::UserObj, AclTag::GroupObj, AclTag::Other] {
+        if count(tag) != 1 {
+            return Err(format!("ACL must contain exactly one {:?} entry", tag));
+        }
+    }
+    if count(AclTag::Mask) > 1 {
+        return Err("ACL must contain at most one Mask entry".to_string());
+    }
+    if (count(AclTag::User) > 0 || count(AclTag::Group) > 0) && count(AclTag::Mask) == 0 {
+        return Err("ACL with named entries requires a Mask entry".to_string());
+    }
+    if entries
+        .iter()
+        .any(|e| matches!(e.tag, AclTag::User | AclTag::Group) && e.id.is_none())
+    {
+        return Err("Named ACL entries require an id".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_acl(path: &Path) -> Result<FileAcl, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to get metadata: {}", e))?;
+    let cpath = c_path(path)?;
+
+    let entries = match read_xattr(&cpath, ACL_ACCESS)? {
+        Some(buf) => decode_acl(&buf),
+        None => Vec::new(),
+    };
+
+    // Files without an extended ACL still have the three base entries implied by
+    // their mode bits.
+    let entries = if entries.is_empty() {
+        let mode = metadata.permissions().mode();
+        vec![
+            make_entry(AclTag::UserObj, None, ((mode >> 6) & 0o7) as u16),
+            make_entry(AclTag::GroupObj, None, ((mode >> 3) & 0o7) as u16),
+            make_entry(AclTag::Other, None, (mode & 0o7) as u16),
+        ]
+    } else {
+        entries
+    };
+
+    let default_entries = if metadata.is_dir() {
+        read_xattr(&cpath, ACL_DEFAULT)?
+            .map(|buf| decode_acl(&buf))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    Ok(FileAcl {
+        path: rawpath::encode_path(path),
+        entries,
+        default_entries,
+        editable: true,
+        raw: Vec::new(),
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_acl(path: &Path, entries: &[AclEntry], default_entries: Option<&[AclEntry]>) -> Result<(), String> {
+    validate_acl(entries)?;
+    let cpath = c_path(path)?;
+
+    let write = |name: &str, buf: &[u8]| -> Result<(), String> {
+        let name = CString::new(name).unwrap();
+        let rc = unsafe {
+            libc::setxattr(
+                cpath.as_ptr(),
+                name.as_ptr(),
+                buf.as_ptr() as *const libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if rc != 0 {
+            return Err(format!("Failed to write ACL: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    };
+
+    write(ACL_ACCESS, &encode_acl(entries))?;
+
+    match default_entries {
+        Some([]) => {
+            let name = CString::new(ACL_DEFAULT).unwrap();
+            let rc = unsafe { libc::removexattr(cpath.as_ptr(), name.as_ptr()) };
+            if rc != 0 {
+                let err = std::io::Error::last_os_error();
+                if err.raw_os_error() != Some(libc::ENODATA) {
+                    return Err(format!("Failed to remove default ACL: {}", err));
+                }
+            }
+        }
+        Some(defaults) => {
+            validate_acl(defaults)?;
+            write(ACL_DEFAULT, &encode_acl(defaults))?;
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn get_acl(path: &Path) -> Result<FileAcl, String> {
+    let output = std::process::Command::new("icacls")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run icacls: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    // icacls prints the path before the first ACE and a summary line at the end.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let printed = path.to_string_lossy();
+    let raw = stdout
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("Successfully processed"))
+        .map(|l| l.strip_prefix(printed.as_ref()).unwrap_or(l).trim().to_string())
+        .collect();
+
+    Ok(FileAcl {
+        path: rawpath::encode_path(path),
+        entries: Vec::new(),
+        default_entries: Vec::new(),
+        editable: false,
+        raw,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn get_acl(_path: &Path) -> Result<FileAcl, String> {
+    Err("ACLs are not supported on this platform".to_string())
+}
+
+// NTFS ACLs are view-only; there's no editor for them.
+#[cfg(not(target_os = "linux"))]
+pub fn set_acl(_path: &Path, _entries: &[AclEntry], _default_entries: Option<&[AclEntry]>) -> Result<(), String> {
+    Err("Editing ACLs is not supported on this platform".to_string())
+}
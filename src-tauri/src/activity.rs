@@ -0,0 +1,153 @@
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::indexer::descendant_range;
+
+// What the index saw happen to paths, as the change journals and the app's
+// own file operations reported it. The index itself only knows what exists
+// now; this is how deletions and renames stay visible afterwards.
+pub(crate) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS main.activity (
+        id INTEGER PRIMARY KEY,
+        time INTEGER NOT NULL,
+        path TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        is_dir INTEGER NOT NULL,
+        from_path TEXT
+    );
+    CREATE INDEX IF NOT EXISTS main.idx_activity_path ON activity(path);
+    CREATE INDEX IF NOT EXISTS main.idx_activity_time ON activity(time);
+";
+
+// Older events are dropped during index maintenance.
+const KEEP_SECS: i64 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Created,
+    Modified,
+    Deleted,
+    Moved,
+}
+
+impl ActivityKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ActivityKind::Created => "created",
+            ActivityKind::Modified => "modified",
+            ActivityKind::Deleted => "deleted",
+            ActivityKind::Moved => "moved",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "created" => Some(ActivityKind::Created),
+            "modified" => Some(ActivityKind::Modified),
+            "deleted" => Some(ActivityKind::Deleted),
+            "moved" => Some(ActivityKind::Moved),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub path: String,
+    pub kind: ActivityKind,
+    // Unix seconds.
+    pub time: i64,
+    pub is_dir: bool,
+    // Where a moved entry came from.
+    pub from: Option<String>,
+    // Set for entries still in the index.
+    pub size: Option<u64>,
+}
+
+pub(crate) fn record(
+    conn: &Connection,
+    path: &str,
+    kind: ActivityKind,
+    is_dir: bool,
+    from: Option<&str>,
+) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO main.activity (time, path, kind, is_dir, from_path) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute((chrono::Utc::now().timestamp(), path, kind.as_str(), is_dir as i32, from))?;
+    Ok(())
+}
+
+pub(crate) fn prune(conn: &Connection) -> SqliteResult<usize> {
+    conn.execute(
+        "DELETE FROM main.activity WHERE time < ?1",
+        [chrono::Utc::now().timestamp() - KEEP_SECS],
+    )
+}
+
+// Newest first: the logged events under `root` since `since`, plus indexed
+// entries modified since then that weren't logged, e.g. ones a crawl picked
+// up. `conn` needs the `files` view.
+pub(crate) fn timeline(conn: &Connection, root: &str, since: i64, limit: usize) -> SqliteResult<Vec<ActivityEvent>> {
+    let (prefix, upper) = descendant_range(root);
+    let mut events: Vec<ActivityEvent> = {
+        let mut stmt = conn.prepare(
+            "SELECT a.path, a.kind, a.time, a.is_dir, a.from_path, f.size FROM main.activity a
+             LEFT JOIN files f ON f.path = a.path AND a.kind != 'deleted'
+             WHERE a.time >= ?1
+               AND ((a.path > ?2 AND a.path < ?3) OR (a.from_path > ?2 AND a.from_path < ?3))
+             ORDER BY a.time DESC, a.id DESC
+             LIMIT ?4",
+        )?;
+        let rows = stmt.query_map((since, &prefix, &upper, limit as i64), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i32>(3)? != 0,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })?;
+        rows.filter_map(|r| r.ok())
+            .filter_map(|(path, kind, time, is_dir, from, size)| {
+                Some(ActivityEvent {
+                    path,
+                    kind: ActivityKind::parse(&kind)?,
+                    time,
+                    is_dir,
+                    from,
+                    size: size.filter(|_| !is_dir).map(|s| s as u64),
+                })
+            })
+            .collect()
+    };
+
+    let logged: HashSet<String> = events.iter().map(|e| e.path.clone()).collect();
+    let mut stmt = conn.prepare(
+        "SELECT path, modified, size FROM files
+         WHERE path > ?1 AND path < ?2 AND is_dir = 0 AND modified >= ?3
+         ORDER BY modified DESC
+         LIMIT ?4",
+    )?;
+    let indexed = stmt
+        .query_map((&prefix, &upper, since, limit as i64), |row| {
+            Ok(ActivityEvent {
+                path: row.get(0)?,
+                kind: ActivityKind::Modified,
+                time: row.get(1)?,
+                is_dir: false,
+                from: None,
+                size: row.get::<_, Option<i64>>(2)?.map(|s| s as u64),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .filter(|e| !logged.contains(&e.path));
+    events.extend(indexed);
+
+    events.sort_by_key(|e| std::cmp::Reverse(e.time));
+    events.truncate(limit);
+    Ok(events)
+}
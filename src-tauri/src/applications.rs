@@ -0,0 +1,256 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::mime;
+use crate::shortcuts::{self, desktop_entry, LaunchKind, LaunchTarget};
+
+// An installed application, read from its .desktop file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Application {
+    // The desktop file ID, such as `org.gnome.Evince`; what portals and
+    // mimeapps.list refer to applications by.
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+    pub mime_types: Vec<String>,
+    // The user's or system's default for the type it was looked up for.
+    pub is_default: bool,
+    #[serde(skip)]
+    exec: String,
+    #[serde(skip)]
+    terminal: bool,
+    #[serde(skip)]
+    working_dir: Option<String>,
+    #[serde(skip)]
+    no_display: bool,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+// What the portal asked to choose between, when HardBore was started with
+// `--choose-app`. The choices can change while the dialog is open.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChooserRequest {
+    pub choices: Vec<String>,
+    pub content_type: Option<String>,
+    // The file or URI being opened, only shown.
+    pub target: Option<String>,
+    pub last_choice: Option<String>,
+}
+
+impl ChooserRequest {
+    // The choices that are installed, the last one chosen first. Without any
+    // choices, every application for the content type is offered instead.
+    pub fn applications(&self) -> Vec<Application> {
+        let mut apps = if self.choices.is_empty() {
+            for_mime(self.content_type.as_deref().unwrap_or_default())
+        } else {
+            let installed = all();
+            self.choices.iter().filter_map(|id| installed.get(id).cloned()).collect()
+        };
+        if let Some(last) = &self.last_choice {
+            if let Some(i) = apps.iter().position(|a| &a.id == last) {
+                let app = apps.remove(i);
+                apps.insert(0, app);
+            }
+        }
+        apps
+    }
+}
+
+// $XDG_DATA_HOME first, so a user's own entries hide the system's.
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::data_dir().into_iter().collect();
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(data_dirs.split(':').map(PathBuf::from));
+    dirs
+}
+
+// Files in subfolders get IDs with `-` for each `/`, per the Desktop Entry spec.
+fn scan(root: &Path, dir: &Path, found: &mut HashMap<String, Application>) {
+    let Ok(children) = fs::read_dir(dir) else {
+        return;
+    };
+    for child in children.flatten() {
+        let path = child.path();
+        if path.is_dir() {
+            scan(root, &path, found);
+            continue;
+        }
+        if path.extension().is_none_or(|e| e != "desktop") {
+            continue;
+        }
+        let Some(id) = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(|p| p.with_extension("").to_str().map(|p| p.replace('/', "-")))
+        else {
+            continue;
+        };
+        if found.contains_key(&id) {
+            continue;
+        }
+        // Hidden entries still claim their ID, so they hide the ones further down.
+        if let Some(app) = fs::read_to_string(&path).ok().and_then(|c| parse(&id, &path, &c)) {
+            found.insert(id, app);
+        } else {
+            found.insert(id.clone(), hidden(id, path));
+        }
+    }
+}
+
+fn hidden(id: String, path: PathBuf) -> Application {
+    Application {
+        id,
+        name: String::new(),
+        icon: None,
+        mime_types: Vec::new(),
+        is_default: false,
+        exec: String::new(),
+        terminal: false,
+        working_dir: None,
+        no_display: true,
+        path,
+    }
+}
+
+fn parse(id: &str, path: &Path, content: &str) -> Option<Application> {
+    let entry = desktop_entry(content);
+    if entry.get("Type").copied() != Some("Application") || entry.get("Hidden").copied() == Some("true") {
+        return None;
+    }
+    Some(Application {
+        id: id.to_string(),
+        name: entry.get("Name")?.to_string(),
+        icon: entry.get("Icon").filter(|i| !i.is_empty()).map(|i| i.to_string()),
+        mime_types: entry
+            .get("MimeType")
+            .map(|m| m.split(';').filter(|t| !t.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        is_default: false,
+        exec: entry.get("Exec")?.to_string(),
+        terminal: entry.get("Terminal").copied() == Some("true"),
+        working_dir: entry.get("Path").filter(|p| !p.is_empty()).map(|p| p.to_string()),
+        no_display: entry.get("NoDisplay").copied() == Some("true"),
+        path: path.to_path_buf(),
+    })
+}
+
+// Every installed application by ID, including ones not shown in menus; the
+// entries that only hide others are left out.
+fn all() -> HashMap<String, Application> {
+    let mut found = HashMap::new();
+    for dir in data_dirs() {
+        let root = dir.join("applications");
+        scan(&root, &root, &mut found);
+    }
+    found.retain(|_, app| !app.exec.is_empty());
+    found
+}
+
+pub fn find(id: &str) -> Option<Application> {
+    all().remove(id.trim_end_matches(".desktop"))
+}
+
+// The associations of every mimeapps.list, most important first.
+#[derive(Default)]
+struct Associations {
+    defaults: Vec<String>,
+    added: Vec<String>,
+    removed: HashSet<String>,
+}
+
+fn associations(mime: &str) -> Associations {
+    let mut files: Vec<PathBuf> = dirs::config_dir().map(|d| d.join("mimeapps.list")).into_iter().collect();
+    let config_dirs = std::env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_string());
+    files.extend(config_dirs.split(':').map(|d| PathBuf::from(d).join("mimeapps.list")));
+    files.extend(data_dirs().into_iter().map(|d| d.join("applications").join("mimeapps.list")));
+
+    let mut found = Associations::default();
+    for file in files {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let mut section = "";
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                section = line;
+                continue;
+            }
+            let Some(ids) = line.split_once('=').filter(|(key, _)| key.trim() == mime).map(|(_, ids)| ids) else {
+                continue;
+            };
+            let ids = ids
+                .split(';')
+                .map(|id| id.trim().trim_end_matches(".desktop").to_string())
+                .filter(|id| !id.is_empty());
+            match section {
+                "[Default Applications]" => found.defaults.extend(ids),
+                "[Added Associations]" => found.added.extend(ids.filter(|id| !found.removed.contains(id))),
+                "[Removed Associations]" => found.removed.extend(ids),
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+// The applications that open `mime`, the default first: the ones mimeapps.list
+// adds, then the ones that declare the type themselves.
+pub fn for_mime(mime: &str) -> Vec<Application> {
+    let mut installed = all();
+    let associations = associations(mime);
+    let default = associations.defaults.iter().find(|id| installed.contains_key(*id)).cloned();
+
+    let mut ids: Vec<String> = default.iter().chain(&associations.added).cloned().collect();
+    let mut declared: Vec<&Application> = installed
+        .values()
+        .filter(|app| !app.no_display && !associations.removed.contains(&app.id))
+        .filter(|app| !mime.is_empty() && app.mime_types.iter().any(|t| mime::mime_matches(t, mime)))
+        .collect();
+    declared.sort_by_cached_key(|app| app.name.to_lowercase());
+    ids.extend(declared.into_iter().map(|app| app.id.clone()));
+
+    let mut seen = HashSet::new();
+    ids.into_iter()
+        .filter(|id| seen.insert(id.clone()))
+        .filter_map(|id| installed.remove(&id))
+        .map(|mut app| {
+            app.is_default = default.as_ref() == Some(&app.id);
+            app
+        })
+        .collect()
+}
+
+// Opens `files` in the application. One that takes a single file per launch
+// (`%f` or `%u`) is started once for each.
+pub fn launch(app: &Application, files: &[String]) -> Result<(), String> {
+    let takes_list = app.exec.contains("%F") || app.exec.contains("%U");
+    let groups: Vec<&[String]> = if takes_list || files.len() <= 1 {
+        vec![files]
+    } else {
+        files.chunks(1).collect()
+    };
+    for group in groups {
+        let args = shortcuts::expand_exec(&app.exec, Some(&app.name), app.icon.as_deref(), &app.path, group);
+        let (target, arguments) = args.split_first().ok_or_else(|| format!("{} has no command", app.name))?;
+        shortcuts::run(&LaunchTarget {
+            kind: LaunchKind::Application,
+            name: Some(app.name.clone()),
+            target: target.clone(),
+            arguments: arguments.to_vec(),
+            working_dir: app.working_dir.clone(),
+            icon: app.icon.clone(),
+            terminal: app.terminal,
+        })?;
+    }
+    Ok(())
+}
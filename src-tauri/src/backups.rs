@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+use crate::preflight;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BackupTool {
+    Restic,
+    Borg,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSnapshot {
+    pub id: String,
+    pub time: i64,
+    pub hostname: Option<String>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: i64,
+}
+
+fn parse_time(value: Option<&Value>) -> i64 {
+    let Some(s) = value.and_then(|v| v.as_str()) else {
+        return 0;
+    };
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return t.timestamp();
+    }
+    // borg prints local time without an offset.
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+        .map(|t| t.timestamp())
+        .unwrap_or(0)
+}
+
+// Passwords go through the environment so they never show up in `ps` output.
+fn command(tool: BackupTool, repository: &str, password: Option<&str>) -> Command {
+    let mut cmd = match tool {
+        BackupTool::Restic => {
+            let mut c = Command::new("restic");
+            c.arg("--repo").arg(repository).arg("--no-lock");
+            c
+        }
+        BackupTool::Borg => Command::new("borg"),
+    };
+    if let Some(password) = password {
+        match tool {
+            BackupTool::Restic => cmd.env("RESTIC_PASSWORD", password),
+            BackupTool::Borg => cmd.env("BORG_PASSPHRASE", password),
+        };
+    }
+    cmd
+}
+
+fn run(mut cmd: Command, tool: BackupTool) -> Result<String, String> {
+    let output = cmd.output().map_err(|e| format!("Failed to run {:?}: {}", tool, e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn list_snapshots(tool: BackupTool, repository: &str, password: Option<&str>) -> Result<Vec<BackupSnapshot>, String> {
+    let mut cmd = command(tool, repository, password);
+    match tool {
+        BackupTool::Restic => cmd.args(["snapshots", "--json"]),
+        BackupTool::Borg => cmd.args(["list", "--json", repository]),
+    };
+    let stdout = run(cmd, tool)?;
+    let json: Value = serde_json::from_str(&stdout).map_err(|e| format!("Failed to parse output: {}", e))?;
+
+    let items = match tool {
+        BackupTool::Restic => json.as_array().cloned().unwrap_or_default(),
+        BackupTool::Borg => json["archives"].as_array().cloned().unwrap_or_default(),
+    };
+
+    let mut snapshots: Vec<BackupSnapshot> = items
+        .iter()
+        .map(|item| {
+            let id = match tool {
+                BackupTool::Restic => item["short_id"].as_str().or(item["id"].as_str()),
+                BackupTool::Borg => item["name"].as_str(),
+            };
+            BackupSnapshot {
+                id: id.unwrap_or_default().to_string(),
+                time: parse_time(item.get("time")),
+                hostname: item["hostname"].as_str().map(|s| s.to_string()),
+                paths: item["paths"]
+                    .as_array()
+                    .map(|a| a.iter().filter_map(|p| p.as_str().map(|s| s.to_string())).collect())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.time));
+    Ok(snapshots)
+}
+
+pub fn list_dir(
+    tool: BackupTool,
+    repository: &str,
+    snapshot: &str,
+    path: &str,
+    password: Option<&str>,
+) -> Result<Vec<BackupEntry>, String> {
+    let dir = format!("/{}", path.trim_matches('/'));
+    let mut cmd = command(tool, repository, password);
+    match tool {
+        BackupTool::Restic => cmd.args(["ls", "--json", snapshot, &dir]),
+        BackupTool::Borg => cmd
+            .args(["list", "--json-lines"])
+            .arg(format!("{}::{}", repository, snapshot))
+            .arg(dir.trim_start_matches('/')),
+    };
+    let stdout = run(cmd, tool)?;
+
+    let parent = Path::new(&dir);
+    let mut entries: Vec<BackupEntry> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|item| {
+            let raw_path = item["path"].as_str()?;
+            let full = format!("/{}", raw_path.trim_start_matches('/'));
+            let entry_path = Path::new(&full);
+            // Both tools list recursively below the requested directory.
+            if entry_path.parent() != Some(parent) {
+                return None;
+            }
+            let kind = item["type"].as_str().unwrap_or_default();
+            Some(BackupEntry {
+                name: entry_path.file_name()?.to_string_lossy().to_string(),
+                path: full.clone(),
+                is_dir: kind == "dir" || kind == "d",
+                size: item["size"].as_u64().unwrap_or(0),
+                modified: parse_time(item.get("mtime")),
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+    Ok(entries)
+}
+
+pub fn extract(
+    tool: BackupTool,
+    repository: &str,
+    snapshot: &str,
+    path: &str,
+    dest_dir: &str,
+    password: Option<&str>,
+    expected_size: u64,
+) -> Result<String, String> {
+    let dest = Path::new(dest_dir);
+    preflight::check_destination(dest, expected_size).map_err(|e| e.message)?;
+
+    let source = format!("/{}", path.trim_matches('/'));
+    let mut cmd = command(tool, repository, password);
+    match tool {
+        BackupTool::Restic => cmd
+            .args(["restore", snapshot, "--target", dest_dir, "--include", &source]),
+        BackupTool::Borg => cmd
+            .arg("extract")
+            .arg(format!("{}::{}", repository, snapshot))
+            .arg(source.trim_start_matches('/'))
+            .current_dir(dest),
+    };
+    run(cmd, tool)?;
+
+    // Both tools recreate the full original path below the target directory.
+    Ok(dest.join(source.trim_start_matches('/')).to_string_lossy().to_string())
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fs_engine::{crawl_directory, get_home_dir, read_directory};
+use crate::indexer::Indexer;
+
+const CRAWL_DEPTH: usize = 3;
+const SEARCH_QUERIES: &[&str] = &["config", "test", "main", "lib", "README"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkStep {
+    pub name: String,
+    pub items: usize,
+    pub bytes: Option<u64>,
+    pub duration_ms: f64,
+    pub items_per_second: f64,
+    pub error: Option<String>,
+}
+
+impl BenchmarkStep {
+    fn measured(name: &str, items: usize, bytes: Option<u64>, elapsed: Duration) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        Self {
+            name: name.to_string(),
+            items,
+            bytes,
+            duration_ms: seconds * 1000.0,
+            items_per_second: if seconds > 0.0 { items as f64 / seconds } else { 0.0 },
+            error: None,
+        }
+    }
+
+    fn failed(name: &str, error: String) -> Self {
+        Self {
+            name: name.to_string(),
+            items: 0,
+            bytes: None,
+            duration_ms: 0.0,
+            items_per_second: 0.0,
+            error: Some(error),
+        }
+    }
+}
+
+// Everything a bug report about slowness needs to compare two machines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    pub root: String,
+    pub steps: Vec<BenchmarkStep>,
+    pub total_ms: f64,
+}
+
+fn wait_for_index(indexer: &Indexer) {
+    // The index thread marks itself running only once it starts, so a finished
+    // run is recognised by its recorded elapsed time.
+    let mut seen_running = false;
+    loop {
+        thread::sleep(Duration::from_millis(50));
+        let status = indexer.get_status();
+        if status.is_running {
+            seen_running = true;
+        } else if seen_running || status.elapsed_ms > 0 {
+            break;
+        }
+    }
+}
+
+fn bench_index(root: &str, data_dir: &Path, steps: &mut Vec<BenchmarkStep>) {
+    let indexer = match Indexer::new(&data_dir.to_string_lossy()) {
+        Ok(indexer) => indexer,
+        Err(e) => {
+            steps.push(BenchmarkStep::failed("index", format!("Failed to create index: {}", e)));
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    indexer.index_directory(root, Some(CRAWL_DEPTH));
+    wait_for_index(&indexer);
+    steps.push(BenchmarkStep::measured("index", indexer.get_status().indexed_count, None, start.elapsed()));
+
+    let start = Instant::now();
+    let results: usize = SEARCH_QUERIES.iter().map(|q| indexer.search_fts(q, 100, true).len()).sum();
+    steps.push(BenchmarkStep::measured("search_fts", results, None, start.elapsed()));
+
+    let start = Instant::now();
+    let results = indexer.search_fuzzy("conf", 50, true).len();
+    steps.push(BenchmarkStep::measured("search_fuzzy", results, None, start.elapsed()));
+
+    drop(indexer);
+    let start = Instant::now();
+    match Indexer::new(&data_dir.to_string_lossy()) {
+        Ok(indexer) => {
+            let results = indexer.search_fts("test", 50, true).len();
+            steps.push(BenchmarkStep::measured("cold_start", results, None, start.elapsed()));
+        }
+        Err(e) => steps.push(BenchmarkStep::failed("cold_start", format!("Failed to open index: {}", e))),
+    }
+}
+
+// Runs against `root` (the home directory by default) with a throwaway index,
+// so the user's own index and settings are left untouched.
+pub fn run_self_benchmark(root: Option<String>) -> Result<BenchmarkReport, String> {
+    let root = root
+        .or_else(get_home_dir)
+        .ok_or_else(|| "Failed to determine a directory to benchmark".to_string())?;
+    if !Path::new(&root).is_dir() {
+        return Err(format!("Failed to benchmark: {} is not a directory", root));
+    }
+
+    let started = Instant::now();
+    let mut steps = Vec::new();
+
+    let start = Instant::now();
+    match read_directory(&root, true) {
+        Ok(contents) => steps.push(BenchmarkStep::measured(
+            "read",
+            contents.total_items,
+            Some(contents.total_size),
+            start.elapsed(),
+        )),
+        Err(e) => steps.push(BenchmarkStep::failed("read", e)),
+    }
+
+    let start = Instant::now();
+    let entries = crawl_directory(&root, Some(CRAWL_DEPTH));
+    let elapsed = start.elapsed();
+    let bytes = entries.iter().map(|e| e.size).sum();
+    steps.push(BenchmarkStep::measured("crawl", entries.len(), Some(bytes), elapsed));
+    drop(entries);
+
+    let data_dir = std::env::temp_dir().join(format!("hardbore-benchmark-{}", std::process::id()));
+    match fs::create_dir_all(&data_dir) {
+        Ok(()) => bench_index(&root, &data_dir, &mut steps),
+        Err(e) => steps.push(BenchmarkStep::failed("index", format!("Failed to create temp directory: {}", e))),
+    }
+    let _ = fs::remove_dir_all(&data_dir);
+
+    Ok(BenchmarkReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpus: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        root,
+        steps,
+        total_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::durability;
+use crate::filenames;
+use crate::openfiles;
+use crate::rawpath;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameOp {
+    pub from: String,
+    pub to: String,
+}
+
+// Catches a case-only rename on a case-insensitive filesystem, where the
+// target "exists" because it is the source itself.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn staging_path(from: &Path, index: usize) -> PathBuf {
+    from.with_file_name(format!(".hardbore-rename-{}-{}", std::process::id(), index))
+}
+
+// Checks the new name against the filesystem it lands on. With `sanitize` an
+// invalid name is rewritten instead of rejected.
+fn portable_target(to: PathBuf, sanitize: bool) -> Result<PathBuf, String> {
+    let (Some(parent), Some(name)) = (to.parent(), to.file_name()) else {
+        return Ok(to);
+    };
+    let target = filenames::target_fs_for(parent);
+    let issues = filenames::validate_filename(name, target);
+    if issues.is_empty() {
+        return Ok(to);
+    }
+    if !sanitize {
+        let reasons: Vec<&str> = issues.iter().map(|i| i.describe()).collect();
+        return Err(format!("\"{}\" {}", name.to_string_lossy(), reasons.join(" and ")));
+    }
+    Ok(parent.join(filenames::sanitize_filename(name, target)))
+}
+
+fn validate(ops: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    let mut sources = HashSet::new();
+    let mut targets = HashSet::new();
+    for (from, to) in ops {
+        fs::symlink_metadata(from).map_err(|e| format!("Failed to rename {}: {}", from.display(), e))?;
+        if to.file_name().is_none_or(|n| n.is_empty()) {
+            return Err(format!("Invalid new name for {}", from.display()));
+        }
+        if !to.parent().is_some_and(Path::is_dir) {
+            return Err(format!("Target directory of {} does not exist", to.display()));
+        }
+        if !sources.insert(from) {
+            return Err(format!("{} is renamed twice", from.display()));
+        }
+        if !targets.insert(to) {
+            return Err(format!("More than one file would be renamed to {}", to.display()));
+        }
+    }
+    // A target may only exist if it is itself being renamed away, which is
+    // what makes swaps and cycles like a→b, b→a work.
+    for (from, to) in ops {
+        if fs::symlink_metadata(to).is_ok() && !ops.iter().any(|(source, _)| source == to || same_file(source, to)) {
+            return Err(format!("Cannot rename {}: {} already exists", from.display(), to.display()));
+        }
+    }
+    Ok(())
+}
+
+// Undoes completed steps newest first, so staged names unwind in order.
+fn rollback(done: &[(PathBuf, PathBuf)], error: String) -> String {
+    let failed: Vec<String> = done
+        .iter()
+        .rev()
+        .filter(|(from, to)| durability::rename(to, from).is_err())
+        .map(|(from, to)| format!("{} (left as {})", from.display(), to.display()))
+        .collect();
+    if failed.is_empty() {
+        format!("{}; no files were renamed", error)
+    } else {
+        format!("{}; could not restore {}", error, failed.join(", "))
+    }
+}
+
+// `source` is the file's original path, used in messages in place of its staging name.
+fn step(source: &Path, from: &Path, to: &Path, done: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), String> {
+    // fs::rename replaces an existing target on unix; after staging, anything
+    // already at a target is a real conflict.
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    durability::rename(from, to).map_err(|e| {
+        format!(
+            "Failed to rename {}: {}{}",
+            source.display(),
+            e,
+            openfiles::in_use_hint(from)
+        )
+    })?;
+    done.push((from.to_path_buf(), to.to_path_buf()));
+    Ok(())
+}
+
+// Renames every source to a staging name first and then to its final name,
+// so chains and cycles never overwrite each other. Any failure rolls the whole
+// batch back. Returns the new paths in the order given.
+pub fn apply_bulk_rename(ops: &[RenameOp], sanitize: bool) -> Result<Vec<String>, String> {
+    let targets = ops
+        .iter()
+        .map(|op| portable_target(rawpath::decode(&op.to), sanitize))
+        .collect::<Result<Vec<_>, _>>()?;
+    let moves: Vec<(PathBuf, PathBuf)> = ops
+        .iter()
+        .zip(&targets)
+        .map(|(op, to)| (rawpath::decode(&op.from), to.clone()))
+        .filter(|(from, to)| from != to)
+        .collect();
+    validate(&moves)?;
+
+    let mut done = Vec::new();
+    let staged: Vec<PathBuf> = moves.iter().enumerate().map(|(i, (from, _))| staging_path(from, i)).collect();
+    let result = moves
+        .iter()
+        .zip(&staged)
+        .try_for_each(|((from, _), staging)| step(from, from, staging, &mut done))
+        .and_then(|()| {
+            moves
+                .iter()
+                .zip(&staged)
+                .try_for_each(|((from, to), staging)| step(from, staging, to, &mut done))
+        });
+    if let Err(e) = result {
+        return Err(rollback(&done, e));
+    }
+
+    Ok(targets.iter().map(|t| rawpath::encode_path(t)).collect())
+}
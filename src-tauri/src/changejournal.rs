@@ -0,0 +1,744 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::events;
+use crate::indexer::Indexer;
+use crate::rawpath;
+
+// Changes are written to the index in batches, at most this often.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const POLL_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JournalBackend {
+    Fanotify,
+    UsnJournal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalStatus {
+    pub root: String,
+    pub backend: JournalBackend,
+    // Index rows written or removed so far.
+    pub applied: u64,
+    // Set once the watch has stopped on its own.
+    pub error: Option<String>,
+}
+
+// What the change feed reported since the last flush: paths to bring in line
+// with the disk, and renames whose rows can simply be moved.
+#[derive(Default)]
+struct Changes {
+    paths: HashSet<PathBuf>,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+struct Watch {
+    root: String,
+    backend: JournalBackend,
+    stop: Arc<AtomicBool>,
+    applied: Arc<AtomicU64>,
+    error: Arc<Mutex<Option<String>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watch {
+    fn halt(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Keeps whole-volume indexes current from the filesystem's own change feed
+// instead of recrawling millions of files.
+pub struct ChangeJournals {
+    watches: Mutex<Vec<Watch>>,
+}
+
+impl Default for ChangeJournals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeJournals {
+    pub fn new() -> Self {
+        Self {
+            watches: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Fails straight away when the platform or the process's privileges
+    // don't allow it, so the caller can fall back to periodic crawls.
+    pub fn start(&self, root: &str, indexer: Indexer) -> Result<JournalBackend, String> {
+        let mut watches = self.watches.lock().unwrap();
+        watches.retain_mut(|w| {
+            let finished = w.thread.as_ref().is_some_and(|t| t.is_finished());
+            if finished {
+                w.halt();
+            }
+            !finished
+        });
+        if let Some(watch) = watches.iter().find(|w| w.root == root) {
+            return Ok(watch.backend);
+        }
+
+        let root_path = rawpath::decode(root);
+        let source = platform::Source::open(&root_path)?;
+        let backend = platform::BACKEND;
+        let stop = Arc::new(AtomicBool::new(false));
+        let applied = Arc::new(AtomicU64::new(0));
+        let error = Arc::new(Mutex::new(None));
+
+        let thread = {
+            let root = root.to_string();
+            let stop = stop.clone();
+            let applied = applied.clone();
+            let error = error.clone();
+            thread::spawn(move || {
+                if let Err(e) = run(source, &root, &root_path, &indexer, &stop, &applied) {
+                    tracing::warn!(root = %root, error = %e, "change journal stopped");
+                    *error.lock().unwrap() = Some(e);
+                }
+            })
+        };
+        tracing::info!(root = %root, backend = ?backend, "change journal started");
+        watches.push(Watch {
+            root: root.to_string(),
+            backend,
+            stop,
+            applied,
+            error,
+            thread: Some(thread),
+        });
+        Ok(backend)
+    }
+
+    pub fn stop(&self, root: &str) {
+        let mut watches = self.watches.lock().unwrap();
+        for watch in watches.iter_mut().filter(|w| w.root == root) {
+            watch.halt();
+        }
+        watches.retain(|w| w.root != root);
+    }
+
+    pub fn list(&self) -> Vec<JournalStatus> {
+        self.watches
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|w| JournalStatus {
+                root: w.root.clone(),
+                backend: w.backend,
+                applied: w.applied.load(Ordering::Relaxed),
+                error: w.error.lock().unwrap().clone(),
+            })
+            .collect()
+    }
+}
+
+fn run(
+    mut source: platform::Source,
+    root: &str,
+    root_path: &Path,
+    indexer: &Indexer,
+    stop: &AtomicBool,
+    applied: &AtomicU64,
+) -> Result<(), String> {
+    let mut changed = Changes::default();
+    let mut last_flush = Instant::now();
+    let mut recrawl = false;
+    while !stop.load(Ordering::Relaxed) {
+        if source.poll(POLL_TIMEOUT, &mut changed)? {
+            // Events were dropped; only a full crawl can tell what changed.
+            tracing::warn!(root = %root, "change journal overflowed, reindexing");
+            changed = Changes::default();
+            recrawl = true;
+        }
+        // A crawl running already may have passed the folders that changed, so
+        // overflows during it are folded into one more crawl once it's done.
+        if recrawl && !indexer.is_crawling(root) {
+            recrawl = false;
+            indexer.index_directory(root, None);
+        }
+        if changed.paths.is_empty() || last_flush.elapsed() < FLUSH_INTERVAL {
+            continue;
+        }
+        // Both ends of a rename are in `paths` as well: a folder moved in or
+        // out of the root, or one whose rows couldn't be moved, is picked up
+        // from there.
+        let moves: Vec<(String, String)> = changed
+            .renames
+            .drain(..)
+            .filter(|(from, to)| from.starts_with(root_path) && to.starts_with(root_path))
+            .map(|(from, to)| (rawpath::encode(from.as_os_str()), rawpath::encode(to.as_os_str())))
+            .collect();
+        match indexer.rebase_paths(&moves) {
+            Ok(rows) => {
+                applied.fetch_add(rows as u64, Ordering::Relaxed);
+            }
+            Err(e) => tracing::warn!(root = %root, error = %e, "failed to move renamed paths in the index"),
+        }
+        // The index's own database lives on a watched volume too.
+        let paths: Vec<String> = changed
+            .paths
+            .drain()
+            .filter(|p| p.starts_with(root_path) && !indexer.owns(p))
+            .map(|p| rawpath::encode(p.as_os_str()))
+            .collect();
+        events::publish(events::Origin::Watcher, &paths, &moves);
+        if !paths.is_empty() {
+            match indexer.apply_changes(&paths) {
+                Ok(rows) => {
+                    applied.fetch_add(rows as u64, Ordering::Relaxed);
+                }
+                // Usually a crawl holding the write lock; the paths are tried
+                // again with the next batch.
+                Err(e) => {
+                    tracing::warn!(root = %root, error = %e, "failed to apply changes to the index");
+                    changed.paths.extend(paths.iter().map(|p| rawpath::decode(p)));
+                }
+            }
+        }
+        last_flush = Instant::now();
+    }
+    Ok(())
+}
+
+// fanotify reports every change on a filesystem, with the directory as a file
+// handle plus the entry's name. It needs CAP_SYS_ADMIN, and Linux 5.9 for
+// names with directory events.
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::{CString, OsStr};
+    use std::io;
+    use std::mem::size_of;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::{Changes, JournalBackend};
+
+    pub const BACKEND: JournalBackend = JournalBackend::Fanotify;
+
+    pub struct Source {
+        fd: libc::c_int,
+        mount_fd: libc::c_int,
+    }
+
+    impl Drop for Source {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+                libc::close(self.mount_fd);
+            }
+        }
+    }
+
+    impl Source {
+        pub fn open(root: &Path) -> Result<Source, String> {
+            let c_root = CString::new(root.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+            let flags = libc::FAN_CLASS_NOTIF | libc::FAN_CLOEXEC | libc::FAN_NONBLOCK | libc::FAN_REPORT_DFID_NAME;
+            let fd = unsafe { libc::fanotify_init(flags, (libc::O_RDONLY | libc::O_LARGEFILE) as u32) };
+            if fd < 0 {
+                let e = io::Error::last_os_error();
+                return Err(match e.raw_os_error() {
+                    Some(libc::EPERM) => "Watching a whole filesystem needs CAP_SYS_ADMIN".to_string(),
+                    Some(libc::EINVAL) => "This kernel is too old to report file names with fanotify".to_string(),
+                    _ => format!("Failed to start fanotify: {}", e),
+                });
+            }
+            let mask = libc::FAN_CREATE
+                | libc::FAN_DELETE
+                | libc::FAN_MOVED_FROM
+                | libc::FAN_MOVED_TO
+                | libc::FAN_MODIFY
+                | libc::FAN_ATTRIB
+                | libc::FAN_ONDIR;
+            let mark = |mask| unsafe {
+                libc::fanotify_mark(
+                    fd,
+                    libc::FAN_MARK_ADD | libc::FAN_MARK_FILESYSTEM,
+                    mask,
+                    libc::AT_FDCWD,
+                    c_root.as_ptr(),
+                )
+            };
+            // FAN_RENAME (Linux 5.17) pairs a move's old and new name in one
+            // event; older kernels only report the two halves.
+            let marked = match mark(mask | libc::FAN_RENAME) {
+                0 => 0,
+                _ => mark(mask),
+            };
+            if marked < 0 {
+                let e = io::Error::last_os_error();
+                unsafe { libc::close(fd) };
+                return Err(format!("Failed to watch {}: {}", root.display(), e));
+            }
+            let mount_fd = unsafe { libc::open(c_root.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC) };
+            if mount_fd < 0 {
+                let e = io::Error::last_os_error();
+                unsafe { libc::close(fd) };
+                return Err(format!("Failed to open {}: {}", root.display(), e));
+            }
+            Ok(Source { fd, mount_fd })
+        }
+
+        // Returns true when the kernel's queue overflowed and events were lost.
+        pub fn poll(&mut self, timeout: Duration, changed: &mut Changes) -> Result<bool, String> {
+            let mut pfd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut pfd, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+                return Ok(false);
+            }
+            let mut buf = vec![0u8; 64 * 1024];
+            let n = unsafe { libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len()) };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                return match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => Ok(false),
+                    _ => Err(format!("Failed to read fanotify events: {}", e)),
+                };
+            }
+            let buf = &buf[..n as usize];
+
+            let mut overflowed = false;
+            let mut offset = 0;
+            while offset + size_of::<libc::fanotify_event_metadata>() <= buf.len() {
+                let meta: libc::fanotify_event_metadata =
+                    unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+                let len = meta.event_len as usize;
+                if len < size_of::<libc::fanotify_event_metadata>() || offset + len > buf.len() {
+                    break;
+                }
+                if meta.fd >= 0 {
+                    unsafe { libc::close(meta.fd) };
+                }
+                if meta.mask & libc::FAN_Q_OVERFLOW != 0 {
+                    overflowed = true;
+                } else {
+                    self.collect(&buf[offset..offset + len], meta.metadata_len as usize, changed);
+                }
+                offset += len;
+            }
+            Ok(overflowed)
+        }
+
+        fn collect(&self, event: &[u8], mut at: usize, changed: &mut Changes) {
+            let mut renamed: (Option<PathBuf>, Option<PathBuf>) = (None, None);
+            while at + size_of::<libc::fanotify_event_info_header>() <= event.len() {
+                let header: libc::fanotify_event_info_header =
+                    unsafe { std::ptr::read_unaligned(event[at..].as_ptr().cast()) };
+                let len = header.len as usize;
+                if len == 0 || at + len > event.len() {
+                    return;
+                }
+                let named = matches!(
+                    header.info_type,
+                    libc::FAN_EVENT_INFO_TYPE_DFID_NAME
+                        | libc::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME
+                        | libc::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME
+                );
+                if named || header.info_type == libc::FAN_EVENT_INFO_TYPE_DFID {
+                    if let Some(path) = self.resolve(&event[at..at + len], named) {
+                        match header.info_type {
+                            libc::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME => renamed.0 = Some(path.clone()),
+                            libc::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME => renamed.1 = Some(path.clone()),
+                            _ => {}
+                        }
+                        changed.paths.insert(path);
+                    }
+                }
+                at += len;
+            }
+            if let (Some(from), Some(to)) = renamed {
+                changed.renames.push((from, to));
+            }
+        }
+
+        // The record holds a `struct file_handle` for the directory, then the
+        // entry's NUL-terminated name.
+        fn resolve(&self, record: &[u8], named: bool) -> Option<PathBuf> {
+            let start = size_of::<libc::fanotify_event_info_fid>();
+            let handle_bytes = u32::from_ne_bytes(record.get(start..start + 4)?.try_into().ok()?) as usize;
+            let handle = record.get(start..start + 8 + handle_bytes)?;
+
+            // open_by_handle_at reads the handle as an aligned struct.
+            let mut aligned = vec![0u64; handle.len().div_ceil(8)];
+            unsafe {
+                std::ptr::copy_nonoverlapping(handle.as_ptr(), aligned.as_mut_ptr().cast::<u8>(), handle.len());
+            }
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_open_by_handle_at,
+                    self.mount_fd,
+                    aligned.as_ptr(),
+                    libc::O_PATH | libc::O_CLOEXEC,
+                )
+            } as libc::c_int;
+            // The directory itself may be gone by now; its own event covers it.
+            if fd < 0 {
+                return None;
+            }
+            let dir = std::fs::read_link(format!("/proc/self/fd/{}", fd));
+            unsafe { libc::close(fd) };
+            let dir = dir.ok()?;
+
+            if !named {
+                return Some(dir);
+            }
+            let name = &record[start + 8 + handle_bytes..];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            match name {
+                b"" | b"." => Some(dir),
+                _ => Some(dir.join(OsStr::from_bytes(name))),
+            }
+        }
+    }
+}
+
+// The NTFS change journal records every change on the volume with the parent
+// folder's file ID; reading it needs administrator rights.
+#[cfg(windows)]
+mod platform {
+    use std::collections::HashMap;
+    use std::ffi::{c_void, OsStr, OsString};
+    use std::io;
+    use std::mem::size_of;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::path::{Component, Path, PathBuf, Prefix};
+    use std::time::Duration;
+
+    use super::{Changes, JournalBackend};
+
+    pub const BACKEND: JournalBackend = JournalBackend::UsnJournal;
+
+    const INVALID_HANDLE_VALUE: isize = -1;
+    const GENERIC_READ: u32 = 0x8000_0000;
+    const FILE_SHARE_ALL: u32 = 0x1 | 0x2 | 0x4;
+    const OPEN_EXISTING: u32 = 3;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FSCTL_QUERY_USN_JOURNAL: u32 = 0x0009_00F4;
+    const FSCTL_READ_USN_JOURNAL: u32 = 0x0009_00BB;
+    const ERROR_ACCESS_DENIED: i32 = 5;
+    const ERROR_JOURNAL_ENTRY_DELETED: i32 = 1181;
+    const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+    const USN_REASON_FILE_DELETE: u32 = 0x200;
+    const USN_REASON_RENAME_OLD_NAME: u32 = 0x1000;
+    const USN_REASON_RENAME_NEW_NAME: u32 = 0x2000;
+    const DIR_CACHE_LIMIT: usize = 100_000;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct UsnJournalData {
+        journal_id: u64,
+        first_usn: i64,
+        next_usn: i64,
+        lowest_valid_usn: i64,
+        max_usn: i64,
+        maximum_size: u64,
+        allocation_delta: u64,
+    }
+
+    #[repr(C)]
+    struct ReadUsnJournalData {
+        start_usn: i64,
+        reason_mask: u32,
+        return_only_on_close: u32,
+        timeout: u64,
+        bytes_to_wait_for: u64,
+        journal_id: u64,
+    }
+
+    #[repr(C)]
+    struct FileIdDescriptor {
+        size: u32,
+        kind: u32,
+        id: [u64; 2],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateFileW(
+            file_name: *const u16,
+            access: u32,
+            share_mode: u32,
+            security: *mut c_void,
+            disposition: u32,
+            flags: u32,
+            template: isize,
+        ) -> isize;
+        fn DeviceIoControl(
+            device: isize,
+            code: u32,
+            in_buffer: *const c_void,
+            in_size: u32,
+            out_buffer: *mut c_void,
+            out_size: u32,
+            returned: *mut u32,
+            overlapped: *mut c_void,
+        ) -> i32;
+        fn OpenFileById(
+            volume: isize,
+            id: *const FileIdDescriptor,
+            access: u32,
+            share_mode: u32,
+            security: *mut c_void,
+            flags: u32,
+        ) -> isize;
+        fn GetFinalPathNameByHandleW(file: isize, path: *mut u16, len: u32, flags: u32) -> u32;
+        fn CloseHandle(handle: isize) -> i32;
+    }
+
+    pub struct Source {
+        volume: isize,
+        journal_id: u64,
+        next_usn: i64,
+        dirs: HashMap<u64, PathBuf>,
+        // A rename's old name, by file ID, until the record with its new name.
+        renaming: Option<(u64, PathBuf)>,
+    }
+
+    impl Drop for Source {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.volume) };
+        }
+    }
+
+    fn query_journal(volume: isize) -> io::Result<UsnJournalData> {
+        let mut data = UsnJournalData::default();
+        let mut returned = 0u32;
+        let ok = unsafe {
+            DeviceIoControl(
+                volume,
+                FSCTL_QUERY_USN_JOURNAL,
+                std::ptr::null(),
+                0,
+                (&mut data as *mut UsnJournalData).cast(),
+                size_of::<UsnJournalData>() as u32,
+                &mut returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(data)
+    }
+
+    fn u16_at(b: &[u8], at: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?))
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+    }
+
+    fn u64_at(b: &[u8], at: usize) -> Option<u64> {
+        Some(u64::from_le_bytes(b.get(at..at + 8)?.try_into().ok()?))
+    }
+
+    impl Source {
+        pub fn open(root: &Path) -> Result<Source, String> {
+            let Some(Component::Prefix(prefix)) = root.components().next() else {
+                return Err("The change journal needs a path on a local drive".to_string());
+            };
+            let letter = match prefix.kind() {
+                Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => letter as char,
+                _ => return Err("The change journal needs a path on a local drive".to_string()),
+            };
+            let device: Vec<u16> = OsStr::new(&format!(r"\\.\{}:", letter)).encode_wide().chain(Some(0)).collect();
+            let volume = unsafe {
+                CreateFileW(
+                    device.as_ptr(),
+                    GENERIC_READ,
+                    FILE_SHARE_ALL,
+                    std::ptr::null_mut(),
+                    OPEN_EXISTING,
+                    0,
+                    0,
+                )
+            };
+            if volume == INVALID_HANDLE_VALUE {
+                let e = io::Error::last_os_error();
+                return Err(match e.raw_os_error() {
+                    Some(ERROR_ACCESS_DENIED) => "Reading the NTFS change journal needs administrator rights".to_string(),
+                    _ => format!("Failed to open volume {}: {}", letter, e),
+                });
+            }
+            match query_journal(volume) {
+                Ok(data) => Ok(Source {
+                    volume,
+                    journal_id: data.journal_id,
+                    next_usn: data.next_usn,
+                    dirs: HashMap::new(),
+                    renaming: None,
+                }),
+                Err(e) => {
+                    unsafe { CloseHandle(volume) };
+                    Err(format!("Volume {} has no change journal: {}", letter, e))
+                }
+            }
+        }
+
+        fn dir_path(&mut self, file_ref: u64) -> Option<PathBuf> {
+            if let Some(path) = self.dirs.get(&file_ref) {
+                return Some(path.clone());
+            }
+            let descriptor = FileIdDescriptor {
+                size: size_of::<FileIdDescriptor>() as u32,
+                kind: 0,
+                id: [file_ref, 0],
+            };
+            let handle = unsafe {
+                OpenFileById(
+                    self.volume,
+                    &descriptor,
+                    0,
+                    FILE_SHARE_ALL,
+                    std::ptr::null_mut(),
+                    FILE_FLAG_BACKUP_SEMANTICS,
+                )
+            };
+            if handle == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            let mut buf = vec![0u16; 1024];
+            let len = unsafe { GetFinalPathNameByHandleW(handle, buf.as_mut_ptr(), buf.len() as u32, 0) } as usize;
+            unsafe { CloseHandle(handle) };
+            if len == 0 || len > buf.len() {
+                return None;
+            }
+            let path = OsString::from_wide(&buf[..len]).to_string_lossy().to_string();
+            let path = PathBuf::from(path.strip_prefix(r"\\?\").unwrap_or(&path));
+            if self.dirs.len() >= DIR_CACHE_LIMIT {
+                self.dirs.clear();
+            }
+            self.dirs.insert(file_ref, path.clone());
+            Some(path)
+        }
+
+        // Returns true when records were lost because the journal wrapped.
+        pub fn poll(&mut self, timeout: Duration, changed: &mut Changes) -> Result<bool, String> {
+            let request = ReadUsnJournalData {
+                start_usn: self.next_usn,
+                reason_mask: u32::MAX,
+                return_only_on_close: 0,
+                timeout: 0,
+                bytes_to_wait_for: 0,
+                journal_id: self.journal_id,
+            };
+            let mut buf = vec![0u64; 8 * 1024];
+            let mut returned = 0u32;
+            let ok = unsafe {
+                DeviceIoControl(
+                    self.volume,
+                    FSCTL_READ_USN_JOURNAL,
+                    (&request as *const ReadUsnJournalData).cast(),
+                    size_of::<ReadUsnJournalData>() as u32,
+                    buf.as_mut_ptr().cast(),
+                    (buf.len() * 8) as u32,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                let e = io::Error::last_os_error();
+                if e.raw_os_error() == Some(ERROR_JOURNAL_ENTRY_DELETED) {
+                    let data = query_journal(self.volume).map_err(|e| format!("Failed to query change journal: {}", e))?;
+                    self.journal_id = data.journal_id;
+                    self.next_usn = data.next_usn;
+                    self.dirs.clear();
+                    self.renaming = None;
+                    return Ok(true);
+                }
+                return Err(format!("Failed to read change journal: {}", e));
+            }
+            let bytes: &[u8] = unsafe { std::slice::from_raw_parts(buf.as_ptr().cast(), returned as usize) };
+            if let Some(next) = u64_at(bytes, 0) {
+                self.next_usn = next as i64;
+            }
+            if bytes.len() <= 8 {
+                std::thread::sleep(timeout);
+                return Ok(false);
+            }
+
+            // USN_RECORD_V2; V3 and V4 records only appear on ReFS and with range tracking.
+            let mut at = 8;
+            while let Some(record_len) = u32_at(bytes, at).map(|l| l as usize) {
+                if record_len == 0 || at + record_len > bytes.len() {
+                    break;
+                }
+                let record = &bytes[at..at + record_len];
+                at += record_len;
+                if u16_at(record, 4) != Some(2) {
+                    continue;
+                }
+                let (Some(file), Some(parent), Some(reason), Some(attributes), Some(name_len), Some(name_at)) = (
+                    u64_at(record, 8),
+                    u64_at(record, 16),
+                    u32_at(record, 40),
+                    u32_at(record, 52),
+                    u16_at(record, 56),
+                    u16_at(record, 58),
+                ) else {
+                    continue;
+                };
+                let Some(name) = record.get(name_at as usize..name_at as usize + name_len as usize) else {
+                    continue;
+                };
+                let name: Vec<u16> = name.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                // Cached paths of anything below a renamed or deleted folder are stale.
+                if attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+                    && reason & (USN_REASON_RENAME_NEW_NAME | USN_REASON_FILE_DELETE) != 0
+                {
+                    self.dirs.clear();
+                }
+                if let Some(dir) = self.dir_path(parent) {
+                    let path = dir.join(OsString::from_wide(&name));
+                    if reason & USN_REASON_RENAME_OLD_NAME != 0 {
+                        self.renaming = Some((file, path.clone()));
+                    } else if reason & USN_REASON_RENAME_NEW_NAME != 0 {
+                        if let Some((_, from)) = self.renaming.take_if(|(id, _)| *id == file) {
+                            changed.renames.push((from, path.clone()));
+                        }
+                    }
+                    changed.paths.insert(path);
+                }
+            }
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{Changes, JournalBackend};
+
+    pub const BACKEND: JournalBackend = JournalBackend::Fanotify;
+
+    pub struct Source;
+
+    impl Source {
+        pub fn open(_root: &Path) -> Result<Source, String> {
+            Err("Change journals are not supported on this platform".to_string())
+        }
+
+        pub fn poll(&mut self, _timeout: Duration, _changed: &mut Changes) -> Result<bool, String> {
+            Ok(false)
+        }
+    }
+}
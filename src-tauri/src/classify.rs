@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use crate::mime::mime_for_extension;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileCategory {
+    Document,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Code,
+    Executable,
+    Font,
+    DiskImage,
+    #[default]
+    Other,
+}
+
+impl FileCategory {
+    pub const ALL: [FileCategory; 10] = [
+        Self::Document,
+        Self::Image,
+        Self::Audio,
+        Self::Video,
+        Self::Archive,
+        Self::Code,
+        Self::Executable,
+        Self::Font,
+        Self::DiskImage,
+        Self::Other,
+    ];
+
+    // Stored in the index, so these must stay stable.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Document => "Document",
+            Self::Image => "Image",
+            Self::Audio => "Audio",
+            Self::Video => "Video",
+            Self::Archive => "Archive",
+            Self::Code => "Code",
+            Self::Executable => "Executable",
+            Self::Font => "Font",
+            Self::DiskImage => "DiskImage",
+            Self::Other => "Other",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == s)
+    }
+}
+
+// Extensions whose MIME type is missing or misleading (`.ts` is video/mp2t,
+// `.iso` is usually application/octet-stream), checked before the MIME database.
+fn by_extension(ext: &str) -> Option<FileCategory> {
+    use FileCategory::*;
+    let category = match ext {
+        "pdf" | "doc" | "docx" | "odt" | "rtf" | "txt" | "md" | "markdown" | "rst" | "tex" | "epub" | "mobi"
+        | "xls" | "xlsx" | "ods" | "csv" | "ppt" | "pptx" | "odp" | "pages" | "numbers" | "key" | "org" => Document,
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cxx" | "cs" | "go" | "java" | "kt" | "kts" | "scala" | "py" | "rb"
+        | "php" | "pl" | "lua" | "js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx" | "svelte" | "vue" | "html" | "htm"
+        | "css" | "scss" | "sass" | "less" | "json" | "yaml" | "yml" | "toml" | "xml" | "ini" | "sql" | "sh"
+        | "bash" | "zsh" | "fish" | "ps1" | "swift" | "m" | "mm" | "dart" | "zig" | "nim" | "hs" | "ml" | "ex"
+        | "exs" | "erl" | "clj" | "r" | "jl" | "rhai" | "cmake" | "mk" | "gradle" | "lock" => Code,
+        "exe" | "msi" | "com" | "bat" | "cmd" | "appimage" | "app" | "apk" | "deb" | "rpm" | "flatpak" | "snap"
+        | "run" | "bin" => Executable,
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "tbz2" | "xz" | "txz" | "zst" | "7z" | "rar" | "lz" | "lzma" | "lz4"
+        | "cab" | "cpio" | "ar" | "jar" | "war" | "whl" | "crate" | "gem" => Archive,
+        "iso" | "img" | "dmg" | "vhd" | "vhdx" | "vmdk" | "qcow2" | "vdi" | "raw" | "toast" | "udf" => DiskImage,
+        "ttf" | "otf" | "woff" | "woff2" | "ttc" | "pfb" | "pfa" | "fon" | "bdf" | "pcf" => Font,
+        "svg" | "heic" | "heif" | "avif" | "jxl" | "psd" | "xcf" | "kra" | "cr2" | "nef" | "arw" | "dng" | "raf"
+        | "orf" | "rw2" => Image,
+        "flac" | "opus" | "m4a" | "aac" | "wv" | "ape" | "mka" => Audio,
+        "mkv" | "webm" | "m4v" | "mts" | "m2ts" | "vob" | "ogv" => Video,
+        _ => return None,
+    };
+    Some(category)
+}
+
+fn by_mime(mime: &str) -> Option<FileCategory> {
+    let (top, sub) = mime.split_once('/')?;
+    match top {
+        "image" => Some(FileCategory::Image),
+        "audio" => Some(FileCategory::Audio),
+        "video" => Some(FileCategory::Video),
+        "font" => Some(FileCategory::Font),
+        "text" => Some(FileCategory::Document),
+        _ if sub.contains("font") => Some(FileCategory::Font),
+        _ if sub.contains("zip") || sub.contains("compressed") || sub.contains("archive") => {
+            Some(FileCategory::Archive)
+        }
+        _ => None,
+    }
+}
+
+// Runs for every entry read or indexed, so it only looks at the name and the
+// metadata already at hand, never at file contents.
+pub fn classify_entry(extension: Option<&str>, is_dir: bool, executable: bool) -> FileCategory {
+    if is_dir {
+        return FileCategory::Other;
+    }
+    if let Some(ext) = extension {
+        let ext = ext.to_lowercase();
+        if let Some(category) = by_extension(&ext).or_else(|| mime_for_extension(&ext).and_then(by_mime)) {
+            return category;
+        }
+    }
+    if executable {
+        FileCategory::Executable
+    } else {
+        FileCategory::Other
+    }
+}
@@ -0,0 +1,255 @@
+use jwalk::WalkDir;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::indexer::RECLAIMABLE_DIRS;
+use crate::rawpath;
+
+const STALE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const SKIPPED_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CleanupKind {
+    EmptyDirectory,
+    BrokenSymlink,
+    EmptyFile,
+    StaleTempFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupCandidate {
+    pub path: String,
+    pub kind: CleanupKind,
+    pub size: u64,
+    pub modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub removed: Vec<String>,
+    pub errors: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+fn is_temp_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.ends_with(".tmp")
+        || lower.ends_with(".temp")
+        || lower.ends_with(".part")
+        || lower.ends_with(".crdownload")
+        || lower.ends_with(".lck")
+        || lower.ends_with('~')
+        || lower.starts_with("~$")
+        || (lower.starts_with(".~lock.") && lower.ends_with('#'))
+}
+
+// Compared after resolving `..` and symlinked folders, so a path can't name
+// its way out of the root. The entry itself isn't resolved, as it may be a
+// broken symlink; a path ending in `..` has no file name and never passes.
+fn is_inside(root: &Path, target: &Path) -> bool {
+    let (Ok(root), Some(parent), Some(name)) = (fs::canonicalize(root), target.parent(), target.file_name()) else {
+        return false;
+    };
+    let Ok(parent) = fs::canonicalize(parent) else {
+        return false;
+    };
+    let target = parent.join(name);
+    target != root && target.starts_with(&root)
+}
+
+fn classify(path: &Path, metadata: &fs::Metadata) -> Option<CleanupKind> {
+    if metadata.is_symlink() {
+        return match fs::metadata(path) {
+            Ok(_) => None,
+            Err(_) => Some(CleanupKind::BrokenSymlink),
+        };
+    }
+
+    if metadata.is_dir() {
+        let mut children = fs::read_dir(path).ok()?;
+        return children.next().is_none().then_some(CleanupKind::EmptyDirectory);
+    }
+
+    let name = path.file_name()?.to_string_lossy();
+    if is_temp_name(&name) {
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|m| SystemTime::now().duration_since(m).ok())
+            .unwrap_or_default();
+        if age >= STALE_AGE {
+            return Some(CleanupKind::StaleTempFile);
+        }
+    }
+
+    if metadata.len() == 0 {
+        return Some(CleanupKind::EmptyFile);
+    }
+
+    None
+}
+
+pub fn find_cleanup_candidates(root_path: &Path) -> Result<Vec<CleanupCandidate>, String> {
+    if !root_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path.display()));
+    }
+
+    let walker = WalkDir::new(root_path)
+        .skip_hidden(false)
+        .parallelism(jwalk::Parallelism::RayonNewPool(num_cpus::get()))
+        .process_read_dir(|_, _, _, children| {
+            for child in children.iter_mut().flatten() {
+                if SKIPPED_DIRS.iter().any(|d| child.file_name == *d) {
+                    child.read_children_path = None;
+                }
+            }
+        });
+
+    let candidates = walker
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.depth > 0)
+        .filter(|entry| !SKIPPED_DIRS.iter().any(|d| entry.file_name == *d))
+        .filter_map(|entry| {
+            let path = entry.path();
+            let metadata = fs::symlink_metadata(&path).ok()?;
+            let kind = classify(&path, &metadata)?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+
+            Some(CleanupCandidate {
+                path: rawpath::encode_path(&path),
+                kind,
+                size: if metadata.is_file() { metadata.len() } else { 0 },
+                modified,
+            })
+        })
+        .collect();
+
+    Ok(candidates)
+}
+
+// Every path is re-checked right before removal so a file that gained content or a
+// directory that gained children since the scan is never deleted.
+// `paths` are encoded as the scan reported them, and reported back the same way.
+pub fn remove_cleanup_candidates(root_path: &Path, paths: &[String]) -> CleanupReport {
+    let mut removed = Vec::new();
+    let mut errors = Vec::new();
+    let mut freed_bytes = 0;
+
+    for path in paths {
+        let target = &rawpath::decode(path);
+        if !is_inside(root_path, target) {
+            errors.push(format!("{}: Not inside {}", path, root_path.display()));
+            continue;
+        }
+
+        let metadata = match fs::symlink_metadata(target) {
+            Ok(m) => m,
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+
+        let result = match classify(target, &metadata) {
+            Some(CleanupKind::EmptyDirectory) => fs::remove_dir(target),
+            Some(_) => fs::remove_file(target),
+            None => {
+                errors.push(format!("{}: No longer a cleanup candidate", path));
+                continue;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                if metadata.is_file() {
+                    freed_bytes += metadata.len();
+                }
+                removed.push(path.clone());
+            }
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    CleanupReport {
+        removed,
+        errors,
+        freed_bytes,
+    }
+}
+
+// The same test `suggest_reclaim` makes on the index, made again on disk:
+// a `target` folder only counts as build output beside a Cargo.toml.
+fn is_reclaimable(target: &Path) -> bool {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    RECLAIMABLE_DIRS.iter().any(|(n, _)| *n == name)
+        && (name != "target" || target.parent().is_some_and(|p| p.join("Cargo.toml").is_file()))
+}
+
+// Only directories whose name marks them as regenerable (build output, dependency
+// folders, caches) can be removed in one click; anything else needs a manual delete.
+// `suggested` holds the folders a fresh `suggest_reclaim` offered, and nothing
+// outside it is removed.
+pub fn reclaim_directories(root_path: &Path, paths: &[String], suggested: &HashSet<String>) -> CleanupReport {
+    let mut removed = Vec::new();
+    let mut errors = Vec::new();
+    let mut freed_bytes = 0;
+
+    for path in paths {
+        let target = &rawpath::decode(path);
+        if !is_inside(root_path, target) {
+            errors.push(format!("{}: Not inside {}", path, root_path.display()));
+            continue;
+        }
+        if !suggested.contains(path) || !is_reclaimable(target) {
+            errors.push(format!("{}: Not a reclaimable directory", path));
+            continue;
+        }
+
+        match fs::symlink_metadata(target) {
+            Ok(m) if m.is_dir() => {}
+            Ok(_) => {
+                errors.push(format!("{}: Not a directory", path));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        }
+
+        let size: u64 = WalkDir::new(target)
+            .skip_hidden(false)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| fs::symlink_metadata(e.path()).ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum();
+
+        match fs::remove_dir_all(target) {
+            Ok(()) => {
+                freed_bytes += size;
+                removed.push(path.clone());
+            }
+            Err(e) => errors.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    CleanupReport {
+        removed,
+        errors,
+        freed_bytes,
+    }
+}
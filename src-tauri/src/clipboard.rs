@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PathFormat {
+    Plain,
+    Quoted,
+    Uri,
+    Windows,
+}
+
+pub(crate) fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let encoded: String = path
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+pub(crate) fn shell_quote(path: &str) -> String {
+    let safe = !path.is_empty()
+        && path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '+' | ',' | ':' | '@' | '%'));
+    if safe {
+        path.to_string()
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+pub fn format_paths(paths: &[String], format: PathFormat) -> String {
+    match format {
+        PathFormat::Plain => paths.join("\n"),
+        // Quoted paths are meant to be pasted into a shell, so keep them on one line.
+        PathFormat::Quoted => paths.iter().map(|p| shell_quote(p)).collect::<Vec<_>>().join(" "),
+        PathFormat::Uri => paths.iter().map(|p| file_uri(p)).collect::<Vec<_>>().join("\n"),
+        PathFormat::Windows => paths.iter().map(|p| p.replace('/', "\\")).collect::<Vec<_>>().join("\n"),
+    }
+}
+
+fn clipboard_commands() -> Vec<Command> {
+    let mut commands = Vec::new();
+
+    #[cfg(target_os = "macos")]
+    commands.push(Command::new("pbcopy"));
+
+    #[cfg(windows)]
+    {
+        let mut c = Command::new("powershell");
+        c.args([
+            "-NoProfile",
+            "-Command",
+            "[Console]::InputEncoding = [Text.Encoding]::UTF8; Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+        ]);
+        commands.push(c);
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            commands.push(Command::new("wl-copy"));
+        }
+        let mut xclip = Command::new("xclip");
+        xclip.args(["-selection", "clipboard"]);
+        commands.push(xclip);
+        let mut xsel = Command::new("xsel");
+        xsel.args(["--clipboard", "--input"]);
+        commands.push(xsel);
+    }
+
+    commands
+}
+
+pub fn set_text(text: &str) -> Result<(), String> {
+    let mut last_error = "No clipboard tool available".to_string();
+
+    for mut cmd in clipboard_commands() {
+        // The X11/Wayland tools fork a daemon that keeps serving the selection;
+        // it must not hold on to our pipes or waiting would never return.
+        let child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(c) => c,
+            Err(e) => {
+                last_error = format!("Failed to run {:?}: {}", cmd.get_program(), e);
+                continue;
+            }
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+        }
+        match child.wait() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => last_error = format!("{:?} exited with {}", cmd.get_program(), status),
+            Err(e) => last_error = format!("Failed to write to clipboard: {}", e),
+        }
+    }
+
+    Err(last_error)
+}
+
+pub fn copy_paths(paths: &[String], format: PathFormat) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No paths to copy".to_string());
+    }
+    let text = format_paths(paths, format);
+    set_text(&text)?;
+    Ok(text)
+}
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::classify::FileCategory;
+use crate::fs_engine::DirectoryContents;
+use crate::shortcuts::LaunchTarget;
+
+// Bits of `ColumnarListing::flags`.
+pub const IS_DIR: u8 = 1;
+pub const IS_SYMLINK: u8 = 2;
+pub const HIDDEN: u8 = 4;
+pub const PLACEHOLDER: u8 = 8;
+
+// A listing with one array per field instead of one object per entry. With
+// 100k entries the repeated keys are most of a JSON listing, and building that
+// many objects is what makes serializing and parsing it slow.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnarListing {
+    pub path: String,
+    pub parent: Option<String>,
+    pub total_items: usize,
+    pub total_size: u64,
+    pub total_disk_size: u64,
+    pub total_size_text: String,
+    pub name: Vec<String>,
+    pub entry_path: Vec<String>,
+    pub flags: Vec<u8>,
+    pub size: Vec<u64>,
+    pub disk_size: Vec<u64>,
+    pub modified: Vec<i64>,
+    pub permissions: Vec<String>,
+    pub owner: Vec<u32>,
+    pub group: Vec<u32>,
+    pub owner_name: Vec<Option<String>>,
+    pub group_name: Vec<Option<String>>,
+    pub extension: Vec<Option<String>>,
+    pub category: Vec<FileCategory>,
+    pub size_text: Vec<String>,
+    pub modified_text: Vec<String>,
+    // Rarely set, so keyed by entry index rather than padded with nulls.
+    pub emblems: BTreeMap<usize, Vec<String>>,
+    pub launch: BTreeMap<usize, LaunchTarget>,
+    pub columns: BTreeMap<usize, BTreeMap<String, serde_json::Value>>,
+}
+
+// What `read_dir_packed` returns: small listings stay as rows, since turning
+// them back into entries costs the frontend more than it saves.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "layout")]
+pub enum ListingPayload {
+    Rows(DirectoryContents),
+    Columns(Box<ColumnarListing>),
+}
+
+impl ListingPayload {
+    pub fn pack(contents: DirectoryContents, min_entries: usize) -> Self {
+        if contents.entries.len() < min_entries {
+            ListingPayload::Rows(contents)
+        } else {
+            ListingPayload::Columns(Box::new(ColumnarListing::from(contents)))
+        }
+    }
+}
+
+impl From<DirectoryContents> for ColumnarListing {
+    fn from(contents: DirectoryContents) -> Self {
+        let count = contents.entries.len();
+        let mut listing = ColumnarListing {
+            path: contents.path,
+            parent: contents.parent,
+            total_items: contents.total_items,
+            total_size: contents.total_size,
+            total_disk_size: contents.total_disk_size,
+            total_size_text: contents.total_size_text,
+            name: Vec::with_capacity(count),
+            entry_path: Vec::with_capacity(count),
+            flags: Vec::with_capacity(count),
+            size: Vec::with_capacity(count),
+            disk_size: Vec::with_capacity(count),
+            modified: Vec::with_capacity(count),
+            permissions: Vec::with_capacity(count),
+            owner: Vec::with_capacity(count),
+            group: Vec::with_capacity(count),
+            owner_name: Vec::with_capacity(count),
+            group_name: Vec::with_capacity(count),
+            extension: Vec::with_capacity(count),
+            category: Vec::with_capacity(count),
+            size_text: Vec::with_capacity(count),
+            modified_text: Vec::with_capacity(count),
+            emblems: BTreeMap::new(),
+            launch: BTreeMap::new(),
+            columns: BTreeMap::new(),
+        };
+
+        for (i, entry) in contents.entries.into_iter().enumerate() {
+            let mut flags = 0;
+            for (set, bit) in [
+                (entry.is_dir, IS_DIR),
+                (entry.is_symlink, IS_SYMLINK),
+                (entry.hidden, HIDDEN),
+                (entry.placeholder, PLACEHOLDER),
+            ] {
+                if set {
+                    flags |= bit;
+                }
+            }
+            listing.name.push(entry.name);
+            listing.entry_path.push(entry.path);
+            listing.flags.push(flags);
+            listing.size.push(entry.size);
+            listing.disk_size.push(entry.disk_size);
+            listing.modified.push(entry.modified);
+            listing.permissions.push(entry.permissions);
+            listing.owner.push(entry.owner);
+            listing.group.push(entry.group);
+            listing.owner_name.push(entry.owner_name);
+            listing.group_name.push(entry.group_name);
+            listing.extension.push(entry.extension);
+            listing.category.push(entry.category);
+            listing.size_text.push(entry.size_text);
+            listing.modified_text.push(entry.modified_text);
+            if !entry.emblems.is_empty() {
+                listing.emblems.insert(i, entry.emblems);
+            }
+            if let Some(launch) = entry.launch {
+                listing.launch.insert(i, launch);
+            }
+            if !entry.columns.is_empty() {
+                listing.columns.insert(i, entry.columns);
+            }
+        }
+        listing
+    }
+}
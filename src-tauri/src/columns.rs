@@ -0,0 +1,519 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use crate::emblems::repo_root;
+use crate::fs_engine::FileEntry;
+use crate::tags;
+
+const CACHE_LIMIT: usize = 100_000;
+const LINE_COUNT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+const GIT_LOG_MAX_COMMITS: &str = "5000";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    pub id: String,
+    pub label: String,
+}
+
+// Columns are only computed when the UI asks for them by id, so a hidden
+// column costs nothing.
+pub trait ColumnProvider: Send + Sync {
+    fn id(&self) -> &'static str;
+    fn label(&self) -> &'static str;
+
+    // Values for the entries of one directory, in order.
+    fn compute(&self, _dir: &Path, entries: &[&FileEntry]) -> Vec<Option<Value>> {
+        entries.par_iter().map(|e| self.compute_file(e)).collect()
+    }
+
+    fn compute_file(&self, _entry: &FileEntry) -> Option<Value> {
+        None
+    }
+
+    // Cached values are reused while the file's size and mtime are unchanged.
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+fn providers() -> &'static RwLock<Vec<Arc<dyn ColumnProvider>>> {
+    static PROVIDERS: OnceLock<RwLock<Vec<Arc<dyn ColumnProvider>>>> = OnceLock::new();
+    PROVIDERS.get_or_init(|| {
+        RwLock::new(vec![
+            Arc::new(ImageDimensions),
+            Arc::new(LineCount),
+            Arc::new(GitLastCommit),
+            Arc::new(MediaDuration),
+            Arc::new(Tags),
+            Arc::new(WhereFrom),
+        ])
+    })
+}
+
+pub fn register(provider: Arc<dyn ColumnProvider>) {
+    providers().write().unwrap().push(provider);
+}
+
+pub fn available() -> Vec<ColumnInfo> {
+    providers()
+        .read()
+        .unwrap()
+        .iter()
+        .map(|p| ColumnInfo {
+            id: p.id().to_string(),
+            label: p.label().to_string(),
+        })
+        .collect()
+}
+
+struct CachedValue {
+    modified: i64,
+    size: u64,
+    value: Option<Value>,
+}
+
+static CACHE: Mutex<Option<HashMap<(String, &'static str), CachedValue>>> = Mutex::new(None);
+
+fn cached(entry: &FileEntry, column: &'static str) -> Option<Option<Value>> {
+    let cache = CACHE.lock().unwrap();
+    let hit = cache.as_ref()?.get(&(entry.path.clone(), column))?;
+    (hit.modified == entry.modified && hit.size == entry.size).then(|| hit.value.clone())
+}
+
+fn store(entry: &FileEntry, column: &'static str, value: &Option<Value>) {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(
+        (entry.path.clone(), column),
+        CachedValue {
+            modified: entry.modified,
+            size: entry.size,
+            value: value.clone(),
+        },
+    );
+}
+
+// Entries are grouped by parent so listings that span several directories
+// (search results, recent files) still give providers one call per directory.
+pub fn fill(entries: &mut [FileEntry], columns: &[String]) {
+    let selected: Vec<Arc<dyn ColumnProvider>> = providers()
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|p| columns.iter().any(|c| c == p.id()))
+        .cloned()
+        .collect();
+    if selected.is_empty() {
+        return;
+    }
+
+    let mut by_dir: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let dir = Path::new(&entry.path).parent().unwrap_or(Path::new("")).to_path_buf();
+        by_dir.entry(dir).or_default().push(i);
+    }
+
+    for provider in &selected {
+        let id = provider.id();
+        for (dir, indices) in &by_dir {
+            let mut missing: Vec<usize> = Vec::new();
+            for &i in indices {
+                match provider.cacheable().then(|| cached(&entries[i], id)).flatten() {
+                    Some(Some(value)) => {
+                        entries[i].columns.insert(id.to_string(), value);
+                    }
+                    Some(None) => {}
+                    None => missing.push(i),
+                }
+            }
+            if missing.is_empty() {
+                continue;
+            }
+
+            let refs: Vec<&FileEntry> = missing.iter().map(|&i| &entries[i]).collect();
+            let values = provider.compute(dir, &refs);
+            for (&i, value) in missing.iter().zip(values) {
+                if provider.cacheable() {
+                    store(&entries[i], id, &value);
+                }
+                if let Some(value) = value {
+                    entries[i].columns.insert(id.to_string(), value);
+                }
+            }
+        }
+    }
+}
+
+fn read_u16_le(b: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn read_u32_be(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u32_le(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64_be(b: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(b.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn read_head(path: &Path, len: usize) -> Option<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len);
+    File::open(path).ok()?.take(len as u64).read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+struct ImageDimensions;
+
+// JPEG stores the size in the start-of-frame segment, which can come after
+// large EXIF blocks, so segments are skipped by seeking rather than reading.
+fn jpeg_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut file = File::open(path).ok()?;
+    let mut marker = [0u8; 4];
+    file.seek(SeekFrom::Start(2)).ok()?;
+    loop {
+        file.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xFF {
+            return None;
+        }
+        let length = u16::from_be_bytes([marker[2], marker[3]]) as i64;
+        // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC).
+        if (0xC0..=0xCF).contains(&marker[1]) && !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) {
+            let mut frame = [0u8; 5];
+            file.read_exact(&mut frame).ok()?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]) as u32;
+            let width = u16::from_be_bytes([frame[3], frame[4]]) as u32;
+            return Some((width, height));
+        }
+        file.seek(SeekFrom::Current(length - 2)).ok()?;
+    }
+}
+
+fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let head = read_head(path, 32)?;
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((read_u32_be(&head, 16)?, read_u32_be(&head, 20)?));
+    }
+    if head.starts_with(b"GIF8") {
+        return Some((read_u16_le(&head, 6)?, read_u16_le(&head, 8)?));
+    }
+    if head.starts_with(b"BM") {
+        return Some((read_u32_le(&head, 18)?, (read_u32_le(&head, 22)? as i32).unsigned_abs()));
+    }
+    if head.starts_with(b"\xFF\xD8") {
+        return jpeg_dimensions(path);
+    }
+    if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        return match head.get(12..16)? {
+            b"VP8 " => Some((read_u16_le(&head, 26)? & 0x3FFF, read_u16_le(&head, 28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = read_u32_le(&head, 21)?;
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => {
+                let w = u32::from_le_bytes([head[24], head[25], head[26], 0]) + 1;
+                let h = u32::from_le_bytes([head[27], head[28], head[29], 0]) + 1;
+                Some((w, h))
+            }
+            _ => None,
+        };
+    }
+    None
+}
+
+impl ColumnProvider for ImageDimensions {
+    fn id(&self) -> &'static str {
+        "image_dimensions"
+    }
+
+    fn label(&self) -> &'static str {
+        "Dimensions"
+    }
+
+    fn compute_file(&self, entry: &FileEntry) -> Option<Value> {
+        if entry.is_dir || entry.placeholder {
+            return None;
+        }
+        let (width, height) = image_dimensions(Path::new(&entry.path))?;
+        Some(json!({ "width": width, "height": height }))
+    }
+}
+
+struct LineCount;
+
+impl ColumnProvider for LineCount {
+    fn id(&self) -> &'static str {
+        "line_count"
+    }
+
+    fn label(&self) -> &'static str {
+        "Lines"
+    }
+
+    fn compute_file(&self, entry: &FileEntry) -> Option<Value> {
+        if entry.is_dir || entry.placeholder || entry.size > LINE_COUNT_MAX_BYTES {
+            return None;
+        }
+        let mut reader = BufReader::new(File::open(&entry.path).ok()?);
+        let mut lines = 0u64;
+        let mut last = b'\n';
+        let mut checked_binary = false;
+        loop {
+            let buf = reader.fill_buf().ok()?;
+            if buf.is_empty() {
+                break;
+            }
+            // A NUL near the start means a binary file, where lines are meaningless.
+            if !checked_binary {
+                if buf[..buf.len().min(8192)].contains(&0) {
+                    return None;
+                }
+                checked_binary = true;
+            }
+            lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+            last = buf[buf.len() - 1];
+            let len = buf.len();
+            reader.consume(len);
+        }
+        if last != b'\n' {
+            lines += 1;
+        }
+        Some(json!(lines))
+    }
+}
+
+struct GitLastCommit;
+
+impl ColumnProvider for GitLastCommit {
+    fn id(&self) -> &'static str {
+        "git_last_commit"
+    }
+
+    fn label(&self) -> &'static str {
+        "Last Commit"
+    }
+
+    // New commits do not touch file mtimes, so results are never cached.
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    fn compute(&self, dir: &Path, entries: &[&FileEntry]) -> Vec<Option<Value>> {
+        let none = || vec![None; entries.len()];
+        let Some(root) = repo_root(dir) else {
+            return none();
+        };
+        let Ok(prefix) = dir.strip_prefix(&root) else {
+            return none();
+        };
+        let Ok(output) = Command::new("git")
+            .args(["-c", "core.quotePath=false", "-C"])
+            .arg(dir)
+            .args(["log", "--format=%x00%ct", "--name-only", "--max-count", GIT_LOG_MAX_COMMITS, "--", "."])
+            .output()
+        else {
+            return none();
+        };
+
+        // Commits come newest first, so the first time a name shows up is its
+        // latest commit. Paths are relative to the repository root.
+        let mut latest: HashMap<String, i64> = HashMap::new();
+        let mut timestamp = 0i64;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(ts) = line.strip_prefix('\0') {
+                timestamp = ts.parse().unwrap_or(0);
+                continue;
+            }
+            let Some(name) = Path::new(line)
+                .strip_prefix(prefix)
+                .ok()
+                .and_then(|r| r.components().next())
+            else {
+                continue;
+            };
+            latest
+                .entry(name.as_os_str().to_string_lossy().to_string())
+                .or_insert(timestamp);
+        }
+
+        entries
+            .iter()
+            .map(|e| latest.get(&e.name).map(|ts| json!(ts)))
+            .collect()
+    }
+}
+
+struct MediaDuration;
+
+fn wav_duration(path: &Path) -> Option<f64> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut byte_rate = None;
+    let mut chunk = [0u8; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = read_u32_le(&chunk, 4)? as i64;
+        match &chunk[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 12];
+                file.read_exact(&mut fmt).ok()?;
+                byte_rate = read_u32_le(&fmt, 8);
+                file.seek(SeekFrom::Current(size - 12 + (size & 1))).ok()?;
+            }
+            b"data" => return Some(size as f64 / byte_rate.filter(|r| *r > 0)? as f64),
+            _ => {
+                file.seek(SeekFrom::Current(size + (size & 1))).ok()?;
+            }
+        }
+    }
+    None
+}
+
+fn flac_duration(path: &Path) -> Option<f64> {
+    let head = read_head(path, 42)?;
+    // STREAMINFO is always the first metadata block.
+    if !head.starts_with(b"fLaC") || head.get(4)? & 0x7F != 0 {
+        return None;
+    }
+    let packed = read_u64_be(&head, 18)?;
+    let sample_rate = (packed >> 44) as f64;
+    let total_samples = (packed & 0xF_FFFF_FFFF) as f64;
+    (sample_rate > 0.0 && total_samples > 0.0).then(|| total_samples / sample_rate)
+}
+
+// MP4, M4A and QuickTime keep the overall duration in `moov/mvhd`.
+fn mp4_duration(path: &Path) -> Option<f64> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut end = file_len;
+    let mut header = [0u8; 8];
+    loop {
+        let start = file.stream_position().ok()?;
+        if start + 8 > end {
+            return None;
+        }
+        file.read_exact(&mut header).ok()?;
+        let mut size = read_u32_be(&header, 0)? as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large).ok()?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = end - start;
+        }
+        if size < header_len {
+            return None;
+        }
+        match &header[4..8] {
+            b"moov" => end = start + size,
+            b"mvhd" => {
+                let mut body = [0u8; 32];
+                file.read_exact(&mut body).ok()?;
+                let (timescale, duration) = if body[0] == 1 {
+                    (read_u32_be(&body, 20)?, read_u64_be(&body, 24)?)
+                } else {
+                    (read_u32_be(&body, 12)?, read_u32_be(&body, 16)? as u64)
+                };
+                return (timescale > 0).then(|| duration as f64 / timescale as f64);
+            }
+            _ => {
+                file.seek(SeekFrom::Start(start + size)).ok()?;
+            }
+        }
+    }
+}
+
+// Formats without a cheap header field (MP3, Matroska, Ogg) need ffprobe.
+fn ffprobe_duration(path: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+impl ColumnProvider for MediaDuration {
+    fn id(&self) -> &'static str {
+        "media_duration"
+    }
+
+    fn label(&self) -> &'static str {
+        "Duration"
+    }
+
+    fn compute_file(&self, entry: &FileEntry) -> Option<Value> {
+        if entry.is_dir || entry.placeholder {
+            return None;
+        }
+        let path = Path::new(&entry.path);
+        let ext = entry.extension.as_deref()?.to_lowercase();
+        let seconds = match ext.as_str() {
+            "wav" => wav_duration(path),
+            "flac" => flac_duration(path),
+            "mp4" | "m4a" | "m4v" | "mov" | "3gp" => mp4_duration(path),
+            "mp3" | "ogg" | "oga" | "opus" | "mkv" | "webm" | "avi" | "wma" | "wmv" | "aac" => ffprobe_duration(path),
+            _ => None,
+        }?;
+        Some(json!(seconds))
+    }
+}
+
+struct Tags;
+
+impl ColumnProvider for Tags {
+    fn id(&self) -> &'static str {
+        "tags"
+    }
+
+    fn label(&self) -> &'static str {
+        "Tags"
+    }
+
+    // Tagging a file in Finder leaves its mtime alone.
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    fn compute_file(&self, entry: &FileEntry) -> Option<Value> {
+        let tags = tags::read_tags(Path::new(&entry.path));
+        (!tags.is_empty()).then(|| json!(tags))
+    }
+}
+
+struct WhereFrom;
+
+impl ColumnProvider for WhereFrom {
+    fn id(&self) -> &'static str {
+        "where_from"
+    }
+
+    fn label(&self) -> &'static str {
+        "Downloaded From"
+    }
+
+    fn compute_file(&self, entry: &FileEntry) -> Option<Value> {
+        if entry.is_dir {
+            return None;
+        }
+        tags::where_from(Path::new(&entry.path)).map(|url| json!(url))
+    }
+}
@@ -0,0 +1,201 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use crate::humanize::format_size;
+
+// Previews and icons worked out from a file's content are kept on disk under
+// a hash of that content, so the same photo copied to five places, or moved
+// and renamed, is worked out once. Entries are dropped least recently used
+// first once the folder outgrows its limit.
+pub const LIMIT_SETTING: &str = "content_cache_limit_mb";
+
+const DEFAULT_LIMIT_MB: u64 = 256;
+// Hashing more than this costs about as much as the work being saved.
+const MAX_HASHED: u64 = 64 * 1024 * 1024;
+const DIGEST_MEMORY_LIMIT: usize = 4096;
+const TEMP_SUFFIX: &str = ".tmp";
+
+static DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+static LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_LIMIT_MB * 1024 * 1024);
+// Bytes in the folder, counted when first needed.
+static USED: Mutex<Option<u64>> = Mutex::new(None);
+// A file's size and modification time, which its hash is good for.
+type Stamp = (u64, SystemTime);
+
+static DIGESTS: Mutex<Option<HashMap<PathBuf, (Stamp, String)>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClearReport {
+    pub files: usize,
+    pub reclaimed: u64,
+    pub reclaimed_text: String,
+}
+
+pub fn init(dir: PathBuf) {
+    *DIR.write().unwrap() = Some(dir);
+}
+
+pub fn set_limit_mb(limit_mb: Option<u64>) {
+    LIMIT.store(limit_mb.unwrap_or(DEFAULT_LIMIT_MB).max(1) * 1024 * 1024, Ordering::Relaxed);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn digest(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_HASHED {
+        return None;
+    }
+    let stamp: Stamp = (metadata.len(), metadata.modified().ok()?);
+    if let Some((known, digest)) = DIGESTS.lock().unwrap().as_ref().and_then(|d| d.get(path)) {
+        if *known == stamp {
+            return Some(digest.clone());
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path).ok()?, &mut hasher).ok()?;
+    let digest = hex(&hasher.finalize());
+    let mut digests = DIGESTS.lock().unwrap();
+    let digests = digests.get_or_insert_with(HashMap::new);
+    if digests.len() >= DIGEST_MEMORY_LIMIT {
+        digests.clear();
+    }
+    digests.insert(path.to_path_buf(), (stamp, digest.clone()));
+    Some(digest)
+}
+
+// One file per content and variant. The app version is part of the name, so
+// an update never reads what an older one wrote.
+fn entry(dir: &Path, digest: &str, variant: &str) -> PathBuf {
+    let variant = Sha256::digest(format!("{}:{}", env!("CARGO_PKG_VERSION"), variant).as_bytes());
+    dir.join(format!("{}-{}", digest, hex(&variant[..8])))
+}
+
+// What `compute` makes from the file at `path`, as bytes. `variant` tells
+// apart different things made from the same content, such as icon sizes.
+// Folders, large files and failures to hash go straight to `compute`.
+pub fn cached(path: &Path, variant: &str, compute: impl FnOnce() -> Result<Vec<u8>, String>) -> Result<Vec<u8>, String> {
+    let dir = DIR.read().unwrap().clone();
+    let Some((dir, digest)) = dir.and_then(|dir| Some((dir, digest(path)?))) else {
+        return compute();
+    };
+    let file = entry(&dir, &digest, variant);
+    if let Ok(data) = fs::read(&file) {
+        // The modification time doubles as the last use.
+        if let Ok(f) = File::options().write(true).open(&file) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        return Ok(data);
+    }
+
+    let data = compute()?;
+    if let Err(e) = store(&dir, &file, &data) {
+        tracing::warn!("Failed to write the content cache: {}", e);
+    }
+    Ok(data)
+}
+
+// `cached` for anything serializable.
+pub fn cached_json<T: Serialize + DeserializeOwned>(
+    path: &Path,
+    variant: &str,
+    compute: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    let mut computed = None;
+    let data = cached(path, variant, || {
+        let value = compute()?;
+        let data = serde_json::to_vec(&value).map_err(|e| e.to_string())?;
+        computed = Some(value);
+        Ok(data)
+    })?;
+    match computed {
+        Some(value) => Ok(value),
+        None => serde_json::from_slice(&data).map_err(|e| e.to_string()),
+    }
+}
+
+fn store(dir: &Path, file: &Path, data: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}{}", std::process::id(), TEMP_SUFFIX));
+    let temp = file.with_file_name(name);
+    fs::write(&temp, data)?;
+    if let Err(e) = fs::rename(&temp, file) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    let mut used = USED.lock().unwrap();
+    let total = match *used {
+        Some(total) => total + data.len() as u64,
+        None => entries(dir).iter().map(|(_, len, _)| len).sum(),
+    };
+    *used = Some(if total > LIMIT.load(Ordering::Relaxed) { evict(dir) } else { total });
+    Ok(())
+}
+
+fn entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().ends_with(TEMP_SUFFIX))
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            Some((e.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        })
+        .collect()
+}
+
+// Brings the folder down to three quarters of the limit, so the next few
+// entries don't each trigger another pass. Returns what's left.
+fn evict(dir: &Path) -> u64 {
+    let mut entries = entries(dir);
+    entries.sort_by_key(|(_, _, used)| *used);
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    let target = LIMIT.load(Ordering::Relaxed) / 4 * 3;
+    for (path, len, _) in entries {
+        if total <= target {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+    total
+}
+
+// Empties the content cache along with `others`, such as the icon cache,
+// and says how much space that freed.
+pub fn clear(others: &[PathBuf]) -> ClearReport {
+    let dir = DIR.read().unwrap().clone();
+    let mut files = 0;
+    let mut reclaimed = 0;
+    for dir in dir.iter().chain(others) {
+        for (path, len, _) in entries(dir) {
+            if fs::remove_file(&path).is_ok() {
+                files += 1;
+                reclaimed += len;
+            }
+        }
+    }
+    *USED.lock().unwrap() = None;
+    *DIGESTS.lock().unwrap() = None;
+    ClearReport {
+        files,
+        reclaimed,
+        reclaimed_text: format_size(reclaimed),
+    }
+}
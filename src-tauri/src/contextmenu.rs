@@ -0,0 +1,396 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::clipboard::file_uri;
+use crate::mime::{mime_matches, mime_type};
+
+const DEFAULT_DBUS_INTERFACE: &str = "org.hardbore.ContextMenuProvider";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MenuSource {
+    Config,
+    ServiceMenu,
+    DBus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextMenuEntry {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    pub source: MenuSource,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_interface() -> String {
+    DEFAULT_DBUS_INTERFACE.to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MenuDefinition {
+    id: String,
+    title: String,
+    #[serde(default)]
+    icon: Option<String>,
+    exec: String,
+    #[serde(default)]
+    mime: Vec<String>,
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default = "yes")]
+    files: bool,
+    #[serde(default)]
+    directories: bool,
+    #[serde(default = "yes")]
+    multiple: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DBusProvider {
+    service: String,
+    path: String,
+    #[serde(default = "default_interface")]
+    interface: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MenuFile {
+    #[serde(default)]
+    entry: Vec<MenuDefinition>,
+    #[serde(default)]
+    dbus: Vec<DBusProvider>,
+}
+
+struct LoadedMenus {
+    entries: Vec<(MenuSource, MenuDefinition)>,
+    providers: Vec<DBusProvider>,
+}
+
+fn menu_dirs(config_dir: &Path) -> Vec<(MenuSource, PathBuf)> {
+    let mut locations = vec![(MenuSource::Config, config_dir.join("context-menus"))];
+
+    // Dolphin service menus already cover many apps (Ark, Meld, VS Code, ...).
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(data) = dirs::data_dir() {
+            locations.push((MenuSource::ServiceMenu, data.join("kio/servicemenus")));
+        }
+        locations.push((MenuSource::ServiceMenu, PathBuf::from("/usr/local/share/kio/servicemenus")));
+        locations.push((MenuSource::ServiceMenu, PathBuf::from("/usr/share/kio/servicemenus")));
+    }
+
+    locations
+}
+
+fn parse_desktop_file(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+
+    for line in content.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.to_string();
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+fn split_list(value: Option<&String>) -> Vec<String> {
+    value
+        .map(|v| v.split(';').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn desktop_definitions(stem: &str, content: &str) -> Vec<MenuDefinition> {
+    let sections = parse_desktop_file(content);
+    let Some(main) = sections.get("Desktop Entry") else {
+        return Vec::new();
+    };
+    let mime = split_list(main.get("MimeType").or(main.get("ServiceTypes")));
+    let directories = mime.iter().any(|m| mime_matches(m, "inode/directory"));
+
+    let make = |id: String, section: &HashMap<String, String>| -> Option<MenuDefinition> {
+        Some(MenuDefinition {
+            id,
+            title: section.get("Name")?.clone(),
+            icon: section.get("Icon").cloned(),
+            exec: section.get("Exec")?.clone(),
+            mime: mime.clone(),
+            extensions: Vec::new(),
+            files: mime.is_empty() || mime.iter().any(|m| m != "inode/directory"),
+            directories,
+            multiple: true,
+        })
+    };
+
+    let actions = split_list(main.get("Actions"));
+    if actions.is_empty() {
+        return make(stem.to_string(), main).into_iter().collect();
+    }
+    actions
+        .iter()
+        .filter_map(|action| {
+            let section = sections.get(&format!("Desktop Action {}", action))?;
+            make(format!("{}.{}", stem, action), section)
+        })
+        .collect()
+}
+
+fn load_menus(config_dir: &Path) -> LoadedMenus {
+    let mut menus = LoadedMenus {
+        entries: Vec::new(),
+        providers: Vec::new(),
+    };
+
+    for (source, dir) in menu_dirs(config_dir) {
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = read_dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            match path.extension().and_then(|e| e.to_str()) {
+                Some("toml") => {
+                    let Ok(file) = toml::from_str::<MenuFile>(&content) else {
+                        continue;
+                    };
+                    for mut def in file.entry {
+                        def.id = format!("{}.{}", stem, def.id);
+                        menus.entries.push((source, def));
+                    }
+                    menus.providers.extend(file.dbus);
+                }
+                Some("desktop") => {
+                    for def in desktop_definitions(&stem, &content) {
+                        menus.entries.push((source, def));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    menus
+}
+
+fn applies(def: &MenuDefinition, paths: &[String]) -> bool {
+    if paths.is_empty() || (!def.multiple && paths.len() > 1) {
+        return false;
+    }
+    paths.iter().all(|path| {
+        let p = Path::new(path);
+        let is_dir = p.is_dir();
+        if (is_dir && !def.directories) || (!is_dir && !def.files) {
+            return false;
+        }
+        let ext_ok = def.extensions.is_empty()
+            || p.extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| def.extensions.iter().any(|x| x.trim_start_matches('.').eq_ignore_ascii_case(&ext)));
+        let mime_ok = def.mime.is_empty() || {
+            let mime = mime_type(p);
+            def.mime.iter().any(|m| mime_matches(m, &mime))
+        };
+        ext_ok && mime_ok
+    })
+}
+
+// GVariant text syntax for an `as` argument to gdbus.
+fn gvariant_strings(values: &[String]) -> String {
+    let items: Vec<String> = values
+        .iter()
+        .map(|v| format!("'{}'", v.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn gdbus_call(provider: &DBusProvider, method: &str, args: &[String]) -> Result<String, String> {
+    let output = Command::new("gdbus")
+        .args(["call", "--session", "--timeout", "2", "--dest", &provider.service])
+        .args(["--object-path", &provider.path])
+        .arg("--method")
+        .arg(format!("{}.{}", provider.interface, method))
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run gdbus: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Providers answer GetEntries(as paths) with a(sss): id, title, icon.
+fn dbus_entries(index: usize, provider: &DBusProvider, paths: &[String]) -> Vec<ContextMenuEntry> {
+    let Ok(stdout) = gdbus_call(provider, "GetEntries", &[gvariant_strings(paths)]) else {
+        return Vec::new();
+    };
+    let quoted: Vec<&str> = stdout.split('\'').skip(1).step_by(2).collect();
+    quoted
+        .chunks_exact(3)
+        .map(|item| ContextMenuEntry {
+            id: format!("dbus{}:{}", index, item[0]),
+            title: item[1].to_string(),
+            icon: Some(item[2].to_string()).filter(|i| !i.is_empty()),
+            source: MenuSource::DBus,
+        })
+        .collect()
+}
+
+pub fn get_context_menu(config_dir: &Path, paths: &[String]) -> Vec<ContextMenuEntry> {
+    let menus = load_menus(config_dir);
+    let mut entries: Vec<ContextMenuEntry> = menus
+        .entries
+        .iter()
+        .filter(|(_, def)| applies(def, paths))
+        .map(|(source, def)| ContextMenuEntry {
+            id: def.id.clone(),
+            title: def.title.clone(),
+            icon: def.icon.clone(),
+            source: *source,
+        })
+        .collect();
+
+    for (index, provider) in menus.providers.iter().enumerate() {
+        entries.extend(dbus_entries(index, provider, paths));
+    }
+
+    entries
+}
+
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+// Expands the desktop-entry field codes; unknown and deprecated codes are dropped.
+fn expand_exec(def: &MenuDefinition, paths: &[String]) -> Vec<String> {
+    let first = paths.first().cloned().unwrap_or_default();
+    let dir = Path::new(&first)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut argv = Vec::new();
+
+    for arg in split_exec(&def.exec) {
+        match arg.as_str() {
+            "%F" => argv.extend(paths.iter().cloned()),
+            "%U" => argv.extend(paths.iter().map(|p| file_uri(p))),
+            "%i" => {
+                if let Some(icon) = &def.icon {
+                    argv.push("--icon".to_string());
+                    argv.push(icon.clone());
+                }
+            }
+            _ => {
+                let mut expanded = String::new();
+                let mut chars = arg.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => expanded.push('%'),
+                        Some('f') => expanded.push_str(&first),
+                        Some('u') => expanded.push_str(&file_uri(&first)),
+                        Some('d') => expanded.push_str(&dir),
+                        Some('c') => expanded.push_str(&def.title),
+                        _ => {}
+                    }
+                }
+                if !expanded.is_empty() {
+                    argv.push(expanded);
+                }
+            }
+        }
+    }
+
+    argv
+}
+
+pub fn run_context_menu_entry(config_dir: &Path, id: &str, paths: &[String]) -> Result<(), String> {
+    let menus = load_menus(config_dir);
+
+    if let Some(rest) = id.strip_prefix("dbus") {
+        let (index, entry_id) = rest.split_once(':').ok_or("Unknown menu entry")?;
+        let provider = index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| menus.providers.get(i))
+            .ok_or("Unknown menu entry")?;
+        let entry_id = format!("'{}'", entry_id.replace('\\', "\\\\").replace('\'', "\\'"));
+        return gdbus_call(provider, "Activate", &[entry_id, gvariant_strings(paths)]).map(|_| ());
+    }
+
+    let (_, def) = menus
+        .entries
+        .iter()
+        .find(|(_, def)| def.id == id)
+        .ok_or("Unknown menu entry")?;
+    if !applies(def, paths) {
+        return Err("Menu entry does not apply to this selection".to_string());
+    }
+
+    let argv = expand_exec(def, paths);
+    let (program, args) = argv.split_first().ok_or("Menu entry has no command")?;
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(dir) = paths.first().and_then(|p| Path::new(p).parent()) {
+        cmd.current_dir(dir);
+    }
+    cmd.spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run {}: {}", program, e))
+}
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::RwLock;
+
+pub const BACKEND_SETTING: &str = "copy_backend";
+// Bytes per read/write; unset leaves it to the mount profiles.
+pub const BUFFER_SETTING: &str = "copy_buffer_size";
+
+const MIN_BUFFER: usize = 64 * 1024;
+const MAX_BUFFER: usize = 64 << 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum CopyBackend {
+    // Falls back to ReadWrite off Linux and where the kernel refuses io_uring.
+    #[default]
+    IoUring,
+    ReadWrite,
+}
+
+struct Config {
+    backend: CopyBackend,
+    buffer_size: Option<usize>,
+}
+
+static CONFIG: RwLock<Config> = RwLock::new(Config {
+    backend: CopyBackend::IoUring,
+    buffer_size: None,
+});
+
+pub fn init(backend: Option<CopyBackend>, buffer_size: Option<usize>) {
+    let mut config = CONFIG.write().unwrap();
+    config.backend = backend.unwrap_or_default();
+    config.buffer_size = buffer_size.map(|b| b.clamp(MIN_BUFFER, MAX_BUFFER));
+}
+
+pub fn set_backend(backend: CopyBackend) {
+    CONFIG.write().unwrap().backend = backend;
+}
+
+pub fn set_buffer_size(buffer_size: Option<usize>) {
+    CONFIG.write().unwrap().buffer_size = buffer_size.map(|b| b.clamp(MIN_BUFFER, MAX_BUFFER));
+}
+
+pub fn buffer_size() -> Option<usize> {
+    CONFIG.read().unwrap().buffer_size
+}
+
+// Copies `input` to `output` from the start, calling `on_chunk` with the size
+// of every chunk once it is written. An error from `on_chunk` stops the copy.
+pub fn copy_data(
+    input: &mut File,
+    output: &mut File,
+    buffer_size: usize,
+    mut on_chunk: impl FnMut(u64) -> Result<(), String>,
+) -> Result<(), String> {
+    let backend = CONFIG.read().unwrap().backend;
+    // A zeroed multi-megabyte buffer for a 4 KB file costs more than the copy.
+    let buffer_size = match input.metadata() {
+        Ok(metadata) => buffer_size.min((metadata.len() as usize).max(4096)),
+        Err(_) => buffer_size,
+    };
+    #[cfg(target_os = "linux")]
+    if backend == CopyBackend::IoUring {
+        if let Some(result) = uring::copy(input, output, buffer_size, &mut on_chunk) {
+            return result;
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = backend;
+    copy_read_write(input, output, buffer_size, &mut on_chunk)
+}
+
+// Asks the filesystem to copy on its own: NFS 4.2 and SMB shares do it on the
+// server, so the data never crosses the network. Returns None when the
+// filesystem can't, before anything was written.
+#[cfg(target_os = "linux")]
+pub fn server_side_copy(
+    input: &File,
+    output: &File,
+    mut on_chunk: impl FnMut(u64) -> Result<(), String>,
+) -> Option<Result<(), String>> {
+    use std::os::unix::io::AsRawFd;
+
+    // Bounded so progress, pause and cancel still get a look in.
+    const STEP: usize = 64 << 20;
+    let mut copied = 0u64;
+    loop {
+        let n = unsafe {
+            libc::copy_file_range(
+                input.as_raw_fd(),
+                std::ptr::null_mut(),
+                output.as_raw_fd(),
+                std::ptr::null_mut(),
+                STEP,
+                0,
+            )
+        };
+        if n < 0 {
+            let e = std::io::Error::last_os_error();
+            let unsupported = matches!(
+                e.raw_os_error(),
+                Some(libc::EXDEV | libc::EOPNOTSUPP | libc::ENOSYS | libc::EINVAL | libc::EBADF)
+            );
+            if copied == 0 && unsupported {
+                return None;
+            }
+            return Some(Err(format!("Failed to copy file: {}", e)));
+        }
+        if n == 0 {
+            return Some(Ok(()));
+        }
+        copied += n as u64;
+        if let Err(e) = on_chunk(n as u64) {
+            return Some(Err(e));
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn server_side_copy(
+    _input: &File,
+    _output: &File,
+    _on_chunk: impl FnMut(u64) -> Result<(), String>,
+) -> Option<Result<(), String>> {
+    None
+}
+
+fn copy_read_write(
+    input: &mut File,
+    output: &mut File,
+    buffer_size: usize,
+    on_chunk: &mut impl FnMut(u64) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut buf = vec![0u8; buffer_size];
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) => return Err(format!("Failed to read file: {}", e)),
+        };
+        output
+            .write_all(&buf[..n])
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        on_chunk(n as u64)?;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod uring {
+    use io_uring::{opcode, types, IoUring};
+    use std::cell::RefCell;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const READ: u64 = 0;
+    const WRITE: u64 = 1;
+
+    // Containers and hardened kernels often refuse io_uring; after the first
+    // refusal every copy goes straight to the fallback.
+    static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        // Setting up a ring costs a few syscalls, too much to repeat per small file.
+        static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+    }
+
+    fn completion_error(res: i32) -> io::Error {
+        io::Error::from_raw_os_error(-res)
+    }
+
+    // Submits `ops` and waits until all of them completed, so no buffer is
+    // ever still in the kernel's hands when this returns.
+    fn run(ring: &mut IoUring, ops: &[io_uring::squeue::Entry]) -> io::Result<[Option<i32>; 2]> {
+        for op in ops {
+            // SAFETY: every buffer an entry points to outlives the wait below.
+            unsafe { ring.submission().push(op) }.map_err(|_| io::Error::other("submission queue full"))?;
+        }
+        // An interrupted wait has still submitted the entries, so it has to be
+        // repeated rather than abandoned.
+        loop {
+            match ring.submit_and_wait(ops.len()) {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut results = [None, None];
+        for cqe in ring.completion() {
+            results[cqe.user_data() as usize] = Some(cqe.result());
+        }
+        Ok(results)
+    }
+
+    fn read_op(file: &File, buf: &mut [u8], offset: u64) -> io_uring::squeue::Entry {
+        opcode::Read::new(types::Fd(file.as_raw_fd()), buf.as_mut_ptr(), buf.len() as u32)
+            .offset(offset)
+            .build()
+            .user_data(READ)
+    }
+
+    fn write_op(file: &File, buf: &[u8], offset: u64) -> io_uring::squeue::Entry {
+        opcode::Write::new(types::Fd(file.as_raw_fd()), buf.as_ptr(), buf.len() as u32)
+            .offset(offset)
+            .build()
+            .user_data(WRITE)
+    }
+
+    // Each chunk's write goes out together with the next chunk's read, so the
+    // two overlap and every step costs one syscall. Returns None when no ring
+    // could be set up.
+    pub(super) fn copy(
+        input: &File,
+        output: &File,
+        buffer_size: usize,
+        on_chunk: &mut impl FnMut(u64) -> Result<(), String>,
+    ) -> Option<Result<(), String>> {
+        if UNAVAILABLE.load(Ordering::Relaxed) {
+            return None;
+        }
+        RING.with_borrow_mut(|slot| {
+            if slot.is_none() {
+                match IoUring::new(4) {
+                    Ok(ring) => *slot = Some(ring),
+                    Err(e) => {
+                        tracing::info!(error = %e, "io_uring unavailable, using plain reads and writes");
+                        UNAVAILABLE.store(true, Ordering::Relaxed);
+                        return None;
+                    }
+                }
+            }
+            let ring = slot.as_mut()?;
+            Some(copy_with(ring, input, output, buffer_size, on_chunk))
+        })
+    }
+
+    fn copy_with(
+        ring: &mut IoUring,
+        input: &File,
+        output: &File,
+        buffer_size: usize,
+        on_chunk: &mut impl FnMut(u64) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let read_failed = |e: io::Error| format!("Failed to read file: {}", e);
+        let write_failed = |e: io::Error| format!("Failed to write file: {}", e);
+        let mut buffers = [vec![0u8; buffer_size], vec![0u8; buffer_size]];
+        let mut current = 0;
+        let mut offset = 0u64;
+
+        let first = run(ring, &[read_op(input, &mut buffers[0], 0)]).map_err(read_failed)?;
+        let mut len = match first[READ as usize] {
+            Some(res) if res < 0 => return Err(read_failed(completion_error(res))),
+            Some(res) => res as usize,
+            None => return Err(read_failed(io::Error::other("missing completion"))),
+        };
+
+        while len > 0 {
+            let (done, next) = buffers.split_at_mut(1);
+            let (chunk, spare) = match current {
+                0 => (&done[0], &mut next[0]),
+                _ => (&next[0], &mut done[0]),
+            };
+            let ops = [
+                write_op(output, &chunk[..len], offset),
+                read_op(input, spare, offset + len as u64),
+            ];
+            let results = run(ring, &ops).map_err(write_failed)?;
+
+            let written = match results[WRITE as usize] {
+                Some(res) if res < 0 => return Err(write_failed(completion_error(res))),
+                Some(res) => res as usize,
+                None => return Err(write_failed(io::Error::other("missing completion"))),
+            };
+            // Short writes are rare on regular files; finish them synchronously.
+            if written < len {
+                output
+                    .write_all_at(&chunk[written..len], offset + written as u64)
+                    .map_err(write_failed)?;
+            }
+            on_chunk(len as u64)?;
+
+            offset += len as u64;
+            len = match results[READ as usize] {
+                Some(res) if res < 0 => return Err(read_failed(completion_error(res))),
+                Some(res) => res as usize,
+                None => return Err(read_failed(io::Error::other("missing completion"))),
+            };
+            current = 1 - current;
+        }
+        Ok(())
+    }
+}
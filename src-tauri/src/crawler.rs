@@ -0,0 +1,300 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+#[cfg(unix)]
+use std::collections::HashSet;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::fs_engine::{get_file_entry, CrawlOptions, FileEntry};
+use crate::rawpath;
+
+pub const THREADS_SETTING: &str = "crawl_threads";
+pub const MEMORY_SETTING: &str = "crawl_memory_mb";
+
+const DEFAULT_MEMORY_MB: usize = 256;
+// Entries are handed over in batches; one at a time costs more in locking
+// than reading the entry did.
+const BATCH: usize = 256;
+// Batches waiting for the consumer before the crawl threads block.
+const QUEUE_BATCHES: usize = 64;
+// Rough heap use of a FileEntry besides its path and name.
+const ENTRY_OVERHEAD: usize = 200;
+
+// 0 means one thread per CPU.
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+static MEMORY_MB: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_MB);
+
+pub fn set_threads(threads: Option<usize>) {
+    THREADS.store(threads.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn set_memory_mb(memory_mb: Option<usize>) {
+    MEMORY_MB.store(memory_mb.unwrap_or(DEFAULT_MEMORY_MB).max(16), Ordering::Relaxed);
+}
+
+fn thread_count() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => num_cpus::get(),
+        n => n,
+    }
+}
+
+fn entry_size(entry: &FileEntry) -> usize {
+    entry.path.len() + entry.name.len() + ENTRY_OVERHEAD
+}
+
+// Estimated bytes of entries found but not yet taken by the consumer. Past
+// the watermark, crawl threads stop reading folders until it drains.
+struct Budget {
+    pending: Mutex<usize>,
+    drained: Condvar,
+    limit: usize,
+    cancelled: AtomicBool,
+}
+
+impl Budget {
+    fn wait_for_room(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        while *pending >= self.limit && !self.cancelled.load(Ordering::Relaxed) {
+            pending = self.drained.wait(pending).unwrap();
+        }
+    }
+
+    fn add(&self, bytes: usize) {
+        *self.pending.lock().unwrap() += bytes;
+    }
+
+    fn release(&self, bytes: usize) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending = pending.saturating_sub(bytes);
+        self.drained.notify_all();
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.drained.notify_all();
+    }
+}
+
+// Folders left to read. Last in, first out keeps the walk depth-first, so the
+// queue holds one branch's worth of siblings rather than a whole level.
+struct WorkQueue {
+    state: Mutex<(Vec<(PathBuf, usize)>, usize)>,
+    ready: Condvar,
+}
+
+impl WorkQueue {
+    // The next folder, or None once the queue is empty and no thread is still
+    // reading a folder that could add more.
+    fn pop(&self, budget: &Budget) -> Option<(PathBuf, usize)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if budget.cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(dir) = state.0.pop() {
+                state.1 += 1;
+                return Some(dir);
+            }
+            if state.1 == 0 {
+                return None;
+            }
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+
+    fn push(&self, dirs: Vec<(PathBuf, usize)>) {
+        if dirs.is_empty() {
+            return;
+        }
+        self.state.lock().unwrap().0.extend(dirs);
+        self.ready.notify_all();
+    }
+
+    fn close(&self) {
+        let _state = self.state.lock().unwrap();
+        self.ready.notify_all();
+    }
+
+    fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.1 -= 1;
+        if state.1 == 0 {
+            self.ready.notify_all();
+        }
+    }
+}
+
+// Which folders are walked into, shared by all crawl threads.
+struct Walk {
+    max_depth: usize,
+    follow_symlinks: bool,
+    #[cfg(unix)]
+    same_filesystem: bool,
+    #[cfg(unix)]
+    root_dev: Option<u64>,
+    // Folders are keyed by (dev, inode): following symlinks can reach the same
+    // folder through several paths, and a loop would otherwise never end.
+    #[cfg(unix)]
+    visited: Mutex<HashSet<(u64, u64)>>,
+}
+
+impl Walk {
+    fn descends(&self, entry: &FileEntry, path: &std::path::Path, depth: usize) -> bool {
+        if !entry.is_dir || depth >= self.max_depth || (entry.is_symlink && !self.follow_symlinks) {
+            return false;
+        }
+        #[cfg(unix)]
+        if self.follow_symlinks || self.same_filesystem {
+            let Ok(metadata) = fs::metadata(path) else {
+                return false;
+            };
+            if self.same_filesystem && Some(metadata.dev()) != self.root_dev {
+                return false;
+            }
+            if self.follow_symlinks && !self.visited.lock().unwrap().insert((metadata.dev(), metadata.ino())) {
+                return false;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+        true
+    }
+}
+
+// A running crawl, yielding batches of entries as they're found. Every folder
+// comes before anything inside it. Dropping it stops the crawl threads.
+pub struct Crawl {
+    batches: Receiver<Vec<FileEntry>>,
+    budget: Arc<Budget>,
+}
+
+impl Iterator for Crawl {
+    type Item = Vec<FileEntry>;
+
+    fn next(&mut self) -> Option<Vec<FileEntry>> {
+        let batch = self.batches.recv().ok()?;
+        self.budget.release(batch.iter().map(entry_size).sum());
+        Some(batch)
+    }
+}
+
+impl Drop for Crawl {
+    fn drop(&mut self) {
+        self.budget.cancel();
+    }
+}
+
+fn send(batch: &mut Vec<FileEntry>, sender: &SyncSender<Vec<FileEntry>>, budget: &Budget) -> bool {
+    if batch.is_empty() {
+        return true;
+    }
+    budget.wait_for_room();
+    budget.add(batch.iter().map(entry_size).sum());
+    sender.send(std::mem::take(batch)).is_ok()
+}
+
+fn read_folders(
+    queue: &WorkQueue,
+    walk: &Walk,
+    sender: &SyncSender<Vec<FileEntry>>,
+    budget: &Budget,
+) {
+    while let Some((dir, depth)) = queue.pop(budget) {
+        let mut batch = Vec::new();
+        let mut subdirs = Vec::new();
+        let mut open = true;
+        if let Ok(children) = fs::read_dir(&dir) {
+            for child in children.flatten() {
+                let path = child.path();
+                let Some(entry) = get_file_entry(&path) else {
+                    continue;
+                };
+                if walk.descends(&entry, &path, depth + 1) {
+                    subdirs.push((path, depth + 1));
+                }
+                batch.push(entry);
+                if batch.len() >= BATCH && !send(&mut batch, sender, budget) {
+                    open = false;
+                    break;
+                }
+            }
+        }
+        // Subfolders are only queued once their own entries have been sent.
+        open = open && send(&mut batch, sender, budget);
+        if open {
+            queue.push(subdirs);
+        }
+        queue.done();
+        if !open {
+            // The consumer is gone; wake the threads waiting for folders too.
+            budget.cancel();
+            queue.close();
+        }
+    }
+}
+
+// Walks `root` on a fixed number of threads. Found entries wait in a bounded
+// queue, and reading stops while they exceed the memory budget, so a crawl of
+// millions of files runs at the pace of whatever consumes it.
+pub fn crawl(root: &str, options: &CrawlOptions) -> Crawl {
+    let (sender, batches) = sync_channel(QUEUE_BATCHES);
+    let budget = Arc::new(Budget {
+        pending: Mutex::new(0),
+        drained: Condvar::new(),
+        limit: MEMORY_MB.load(Ordering::Relaxed) << 20,
+        cancelled: AtomicBool::new(false),
+    });
+    let crawl = Crawl {
+        batches,
+        budget: budget.clone(),
+    };
+
+    let root_path = rawpath::decode(root);
+    let Some(root_entry) = get_file_entry(&root_path) else {
+        return crawl;
+    };
+    #[cfg(unix)]
+    let root_metadata = fs::metadata(&root_path).ok();
+    let walk = Walk {
+        max_depth: options.max_depth.unwrap_or(usize::MAX),
+        follow_symlinks: options.follow_symlinks,
+        #[cfg(unix)]
+        same_filesystem: options.same_filesystem,
+        #[cfg(unix)]
+        root_dev: root_metadata.as_ref().map(|m| m.dev()),
+        // Holds the root from the start, so a symlink back to it isn't walked again.
+        #[cfg(unix)]
+        visited: Mutex::new(root_metadata.iter().map(|m| (m.dev(), m.ino())).collect()),
+    };
+    // The root is walked into even when it's a symlink.
+    let descend = root_entry.is_dir && walk.max_depth > 0;
+    if sender.send(vec![root_entry]).is_err() || !descend {
+        return crawl;
+    }
+
+    let queue = Arc::new(WorkQueue {
+        state: Mutex::new((vec![(root_path, 0)], 0)),
+        ready: Condvar::new(),
+    });
+    let walk = Arc::new(walk);
+    let background = options.background;
+    for _ in 0..thread_count() {
+        let queue = queue.clone();
+        let walk = walk.clone();
+        let sender = sender.clone();
+        let budget = budget.clone();
+        thread::spawn(move || {
+            if background {
+                crate::iopriority::set_current_thread_background(true);
+            }
+            read_folders(&queue, &walk, &sender, &budget);
+        });
+    }
+    crawl
+}
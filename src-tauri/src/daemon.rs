@@ -0,0 +1,197 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::fs_engine::CrawlOptions;
+use crate::{portable, profile};
+
+// `hardbore --daemon` keeps the index, its crawls and its change journals in
+// one long-running process. Windows and the picker talk to it over a Unix
+// socket, one JSON request and one JSON reply per line, so search is warm
+// before any window opens and only the daemon ever writes the index.
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    Ping,
+    Search { query: String, limit: usize, include_hidden: bool },
+    Status,
+    Index { root: String, options: CrawlOptions },
+    Watch { root: String },
+    RecordChanges { paths: Vec<String> },
+    Rebase { moves: Vec<(String, String)> },
+}
+
+// Tauri's app data dir, worked out without a running app.
+pub fn data_dir() -> Option<PathBuf> {
+    portable::root()
+        .or_else(|| dirs::data_dir().map(|d| d.join("com.hardbore.app")))
+        .map(profile::within)
+}
+
+// $XDG_RUNTIME_DIR is private to the user and cleared at logout, which is
+// where a socket belongs; the data dir will do without one. Each profile
+// has a daemon of its own.
+pub fn socket_path() -> Option<PathBuf> {
+    let name = match profile::name() {
+        Some(profile) => format!("hardbore-daemon-{}.sock", profile),
+        None => "hardbore-daemon.sock".to_string(),
+    };
+    dirs::runtime_dir().or_else(data_dir).map(|d| d.join(name))
+}
+
+// A connection is opened per call, so a client can be shared by any thread.
+#[derive(Debug, Clone)]
+pub struct Client {
+    socket: PathBuf,
+}
+
+impl Client {
+    // Only a daemon that answers counts; a socket left behind doesn't.
+    pub fn connect() -> Option<Client> {
+        let client = Client { socket: socket_path()? };
+        client.call::<()>(&Request::Ping).ok()?;
+        Some(client)
+    }
+
+    #[cfg(unix)]
+    pub fn call<T: DeserializeOwned>(&self, request: &Request) -> Result<T, String> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.socket).map_err(|e| format!("Daemon unreachable: {}", e))?;
+        let _ = stream.set_read_timeout(Some(CALL_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(CALL_TIMEOUT));
+        let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+        line.push('\n');
+        stream.write_all(line.as_bytes()).map_err(|e| format!("Daemon unreachable: {}", e))?;
+
+        let mut reply = String::new();
+        BufReader::new(stream)
+            .read_line(&mut reply)
+            .map_err(|e| format!("No reply from the daemon: {}", e))?;
+        let reply: Result<serde_json::Value, String> =
+            serde_json::from_str(&reply).map_err(|e| format!("Bad reply from the daemon: {}", e))?;
+        serde_json::from_value(reply?).map_err(|e| format!("Bad reply from the daemon: {}", e))
+    }
+
+    #[cfg(not(unix))]
+    pub fn call<T: DeserializeOwned>(&self, _request: &Request) -> Result<T, String> {
+        Err("The daemon needs Unix sockets".to_string())
+    }
+}
+
+// The daemon this process found at startup, for code without a handle to the
+// app's state; like the active index.
+static CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+pub fn set_client(client: Option<Client>) {
+    *CLIENT.write().unwrap() = client;
+}
+
+pub fn client() -> Option<Client> {
+    CLIENT.read().unwrap().clone()
+}
+
+#[cfg(unix)]
+pub fn serve(data_dir: &Path) -> Result<(), String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+    use std::sync::Arc;
+
+    use crate::changejournal::ChangeJournals;
+    use crate::indexer::{self, Indexer};
+    use crate::jobs::JobManager;
+
+    let socket = socket_path().ok_or("No directory for the daemon's socket")?;
+    if Client::connect().is_some() {
+        return Err(format!("A daemon is already listening on {}", socket.display()));
+    }
+    let _ = std::fs::remove_file(&socket);
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let listener = UnixListener::bind(&socket).map_err(|e| format!("Failed to listen on {}: {}", socket.display(), e))?;
+    let _ = std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600));
+
+    std::fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    let indexer = Indexer::new(&data_dir.to_string_lossy()).map_err(|e| format!("Failed to initialize indexer: {}", e))?;
+    indexer::set_active(Some(indexer.clone()));
+
+    let jobs = JobManager::new();
+    let journals = Arc::new(ChangeJournals::new());
+    // Volumes already indexed are watched from the start where the
+    // process is allowed to; elsewhere a window asks for it as before.
+    for shard in indexer.shards().unwrap_or_default().into_iter().filter(|s| s.mounted) {
+        if let Err(e) = journals.start(&shard.mount, indexer.clone()) {
+            tracing::info!(mount = %shard.mount, "no change journal: {}", e);
+        }
+    }
+    tracing::info!(socket = %socket.display(), "daemon listening");
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let (indexer, jobs, journals) = (indexer.clone(), jobs.clone(), journals.clone());
+        std::thread::spawn(move || {
+            let Ok(reader) = stream.try_clone() else {
+                return;
+            };
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                let reply = match serde_json::from_str::<Request>(&line) {
+                    Ok(request) => handle(request, &indexer, &jobs, &journals),
+                    Err(e) => Err(format!("Bad request: {}", e)),
+                };
+                let Ok(mut reply) = serde_json::to_string(&reply) else {
+                    return;
+                };
+                reply.push('\n');
+                if stream.write_all(reply.as_bytes()).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_data_dir: &Path) -> Result<(), String> {
+    Err("The daemon needs Unix sockets".to_string())
+}
+
+#[cfg(unix)]
+fn handle(
+    request: Request,
+    indexer: &crate::indexer::Indexer,
+    jobs: &crate::jobs::JobManager,
+    journals: &crate::changejournal::ChangeJournals,
+) -> Result<serde_json::Value, String> {
+    use crate::jobs::JobKind;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    match request {
+        Request::Ping => Ok(json!(null)),
+        Request::Search { query, limit, include_hidden } => Ok(json!(indexer.search(&query, limit, include_hidden))),
+        Request::Status => Ok(json!(indexer.get_status())),
+        Request::Index { root, options } => {
+            let job = jobs.create(
+                JobKind::Indexing,
+                &format!("Indexing {}", root),
+                vec![root.clone()],
+                Arc::new(|_| {}),
+            );
+            indexer.index_directory_as_job(&root, options, job);
+            Ok(json!(null))
+        }
+        Request::Watch { root } => Ok(json!(journals.start(&root, indexer.clone())?)),
+        Request::RecordChanges { paths } => indexer.record_changes(&paths).map(|n| json!(n)).map_err(|e| e.to_string()),
+        Request::Rebase { moves } => indexer.rebase_paths(&moves).map(|n| json!(n)).map_err(|e| e.to_string()),
+    }
+}
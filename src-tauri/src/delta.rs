@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::fs_engine::{DirectoryContents, FileEntry};
+
+// Listings kept for diffing; the least recently refreshed one is dropped first.
+const MAX_TRACKED: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryDelta {
+    pub path: String,
+    pub parent: Option<String>,
+    pub token: u64,
+    // Set when `since_token` was unknown or stale; `added` then holds every entry.
+    pub reset: bool,
+    pub added: Vec<FileEntry>,
+    pub removed: Vec<String>,
+    pub changed: Vec<FileEntry>,
+    pub total_items: usize,
+    pub total_size: u64,
+    pub total_disk_size: u64,
+    pub total_size_text: String,
+}
+
+struct Snapshot {
+    token: u64,
+    entries: HashMap<String, FileEntry>,
+}
+
+#[derive(Default)]
+struct Tracked {
+    next_token: u64,
+    listings: HashMap<(String, bool), Snapshot>,
+    order: VecDeque<(String, bool)>,
+}
+
+#[derive(Clone, Default)]
+pub struct DeltaTracker {
+    tracked: Arc<Mutex<Tracked>>,
+}
+
+impl DeltaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Compares a fresh listing with the snapshot `since_token` refers to and
+    // replaces that snapshot with the new one.
+    pub fn diff(&self, contents: DirectoryContents, show_hidden: bool, since_token: Option<u64>) -> DirectoryDelta {
+        let key = (contents.path.clone(), show_hidden);
+        let mut tracked = self.tracked.lock().unwrap();
+        tracked.next_token += 1;
+        let token = tracked.next_token;
+
+        let previous = tracked
+            .listings
+            .remove(&key)
+            .filter(|s| Some(s.token) == since_token);
+        tracked.order.retain(|k| k != &key);
+
+        let (added, removed, changed) = match &previous {
+            None => (contents.entries.clone(), Vec::new(), Vec::new()),
+            Some(previous) => {
+                let mut added = Vec::new();
+                let mut changed = Vec::new();
+                for entry in &contents.entries {
+                    match previous.entries.get(&entry.path) {
+                        None => added.push(entry.clone()),
+                        Some(old) if old != entry => changed.push(entry.clone()),
+                        Some(_) => {}
+                    }
+                }
+                let current: std::collections::HashSet<&str> =
+                    contents.entries.iter().map(|e| e.path.as_str()).collect();
+                let removed = previous
+                    .entries
+                    .keys()
+                    .filter(|p| !current.contains(p.as_str()))
+                    .cloned()
+                    .collect();
+                (added, removed, changed)
+            }
+        };
+
+        let delta = DirectoryDelta {
+            path: contents.path,
+            parent: contents.parent,
+            token,
+            reset: previous.is_none(),
+            added,
+            removed,
+            changed,
+            total_items: contents.total_items,
+            total_size: contents.total_size,
+            total_disk_size: contents.total_disk_size,
+            total_size_text: contents.total_size_text,
+        };
+
+        let entries = contents.entries.into_iter().map(|e| (e.path.clone(), e)).collect();
+        tracked.listings.insert(key.clone(), Snapshot { token, entries });
+        tracked.order.push_back(key);
+        while tracked.order.len() > MAX_TRACKED {
+            if let Some(oldest) = tracked.order.pop_front() {
+                tracked.listings.remove(&oldest);
+            }
+        }
+
+        delta
+    }
+}
@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::encoding;
+
+// Text diffs hold both files in memory; anything bigger is compared as bytes.
+const MAX_TEXT_SIZE: u64 = 8 * 1024 * 1024;
+const MAX_UNIFIED_LEN: usize = 1024 * 1024;
+const MAX_BYTE_RANGES: usize = 1000;
+const CHUNK_SIZE: usize = 64 * 1024;
+const DIFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DiffMode {
+    Auto,
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    // Text or Binary; Auto is resolved before diffing.
+    pub mode: DiffMode,
+    pub identical: bool,
+    pub size_a: u64,
+    pub size_b: u64,
+    pub unified: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    // Differing byte spans of a binary diff; a length mismatch shows up as a
+    // final range past the end of the shorter file.
+    pub byte_ranges: Vec<ByteRange>,
+    pub truncated: bool,
+}
+
+fn looks_like_text(path: &Path) -> Result<bool, String> {
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .and_then(|f| f.take(8192).read_to_end(&mut head))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(encoding::has_utf16_bom(&head) || !head.contains(&0))
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(encoding::decode(&bytes, None, true)?.text)
+}
+
+fn text_diff(a: &Path, b: &Path, size_a: u64, size_b: u64) -> Result<FileDiff, String> {
+    if size_a > MAX_TEXT_SIZE || size_b > MAX_TEXT_SIZE {
+        return Err("Files are too large for a text diff".to_string());
+    }
+    let (old, new) = (read_text(a)?, read_text(b)?);
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(&old, &new);
+
+    let (mut additions, mut deletions) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => additions += 1,
+            ChangeTag::Delete => deletions += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let mut unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&a.to_string_lossy(), &b.to_string_lossy())
+        .to_string();
+    let truncated = unified.len() > MAX_UNIFIED_LEN;
+    if truncated {
+        let mut end = MAX_UNIFIED_LEN;
+        while !unified.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Cut at a line so the frontend never sees half a hunk line.
+        end = unified[..end].rfind('\n').map_or(end, |i| i + 1);
+        unified.truncate(end);
+    }
+
+    Ok(FileDiff {
+        mode: DiffMode::Text,
+        identical: additions == 0 && deletions == 0,
+        size_a,
+        size_b,
+        unified: Some(unified),
+        additions,
+        deletions,
+        byte_ranges: Vec::new(),
+        truncated,
+    })
+}
+
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// Streams both files, so size doesn't matter; adjacent differing bytes are
+// merged into one range.
+fn binary_diff(a: &Path, b: &Path, size_a: u64, size_b: u64) -> Result<FileDiff, String> {
+    let open = |p: &Path| {
+        fs::File::open(p)
+            .map(BufReader::new)
+            .map_err(|e| format!("Failed to open {}: {}", p.display(), e))
+    };
+    let (mut ra, mut rb) = (open(a)?, open(b)?);
+    let (mut buf_a, mut buf_b) = (vec![0u8; CHUNK_SIZE], vec![0u8; CHUNK_SIZE]);
+    let mut ranges: Vec<ByteRange> = Vec::new();
+    let mut truncated = false;
+    let mut offset = 0u64;
+    let common = size_a.min(size_b);
+
+    'outer: while offset < common {
+        let na = fill(&mut ra, &mut buf_a).map_err(|e| format!("Failed to read {}: {}", a.display(), e))?;
+        let nb = fill(&mut rb, &mut buf_b).map_err(|e| format!("Failed to read {}: {}", b.display(), e))?;
+        let n = na.min(nb);
+        if n == 0 {
+            break;
+        }
+        for i in 0..n {
+            if buf_a[i] == buf_b[i] {
+                continue;
+            }
+            let at = offset + i as u64;
+            if let Some(last) = ranges.last_mut().filter(|r| r.offset + r.length == at) {
+                last.length += 1;
+            } else if ranges.len() >= MAX_BYTE_RANGES {
+                truncated = true;
+                break 'outer;
+            } else {
+                ranges.push(ByteRange { offset: at, length: 1 });
+            }
+        }
+        offset += n as u64;
+    }
+    if size_a != size_b && !truncated {
+        match ranges.last_mut() {
+            Some(last) if last.offset + last.length == common => last.length += size_a.max(size_b) - common,
+            _ => ranges.push(ByteRange {
+                offset: common,
+                length: size_a.max(size_b) - common,
+            }),
+        }
+    }
+
+    Ok(FileDiff {
+        mode: DiffMode::Binary,
+        identical: ranges.is_empty(),
+        size_a,
+        size_b,
+        unified: None,
+        additions: 0,
+        deletions: 0,
+        byte_ranges: ranges,
+        truncated,
+    })
+}
+
+pub fn diff_files(a: &Path, b: &Path, mode: DiffMode) -> Result<FileDiff, String> {
+    let size = |p: &Path| -> Result<u64, String> {
+        let metadata = fs::metadata(p).map_err(|e| format!("Failed to read metadata: {}", e))?;
+        if metadata.is_dir() {
+            return Err(format!("{} is a directory", p.display()));
+        }
+        Ok(metadata.len())
+    };
+    let (size_a, size_b) = (size(a)?, size(b)?);
+
+    let mode = match mode {
+        DiffMode::Auto => {
+            let small = size_a <= MAX_TEXT_SIZE && size_b <= MAX_TEXT_SIZE;
+            if small && looks_like_text(a)? && looks_like_text(b)? {
+                DiffMode::Text
+            } else {
+                DiffMode::Binary
+            }
+        }
+        mode => mode,
+    };
+    match mode {
+        DiffMode::Text => text_diff(a, b, size_a, size_b),
+        _ => binary_diff(a, b, size_a, size_b),
+    }
+}
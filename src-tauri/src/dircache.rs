@@ -0,0 +1,112 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::fs_engine::{read_directory, DirectoryContents};
+use crate::rawpath;
+
+// Listings kept; the least recently used one is dropped first.
+const MAX_CACHED: usize = 64;
+// A directory's mtime only moves when entries are added, removed or renamed,
+// so changes inside files (a growing download) are picked up by age instead.
+const MAX_AGE: Duration = Duration::from_secs(30);
+// Coarse filesystems store mtimes in whole or even two seconds. A directory
+// changed this recently could change again without its mtime moving, so it
+// isn't cached until it has been quiet for a while.
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+struct Cached {
+    mtime: SystemTime,
+    read_at: Instant,
+    contents: DirectoryContents,
+}
+
+#[derive(Default)]
+struct Cache {
+    listings: HashMap<(String, bool), Cached>,
+    order: VecDeque<(String, bool)>,
+}
+
+impl Cache {
+    fn touch(&mut self, key: &(String, bool)) {
+        self.order.retain(|k| k != key);
+        self.order.push_back(key.clone());
+    }
+}
+
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+fn dir_mtime(path: &str) -> Option<SystemTime> {
+    fs::metadata(rawpath::decode(path)).and_then(|m| m.modified()).ok()
+}
+
+// Serves a cached listing while the directory's mtime is unchanged.
+// `force_refresh` always reads from disk and replaces what was cached.
+pub fn read(path: &str, show_hidden: bool, force_refresh: bool) -> Result<DirectoryContents, String> {
+    let key = (path.to_string(), show_hidden);
+    let mtime = dir_mtime(path);
+
+    if !force_refresh {
+        let mut guard = CACHE.lock().unwrap();
+        let cache = guard.get_or_insert_with(Cache::default);
+        let hit = cache
+            .listings
+            .get(&key)
+            .filter(|c| Some(c.mtime) == mtime && c.read_at.elapsed() < MAX_AGE)
+            .map(|c| c.contents.clone());
+        if let Some(contents) = hit {
+            cache.touch(&key);
+            return Ok(contents);
+        }
+    }
+
+    let contents = read_directory(path, show_hidden)?;
+    let settled = mtime.is_some_and(|m| m.elapsed().is_ok_and(|age| age >= MTIME_GRANULARITY));
+
+    let mut guard = CACHE.lock().unwrap();
+    let cache = guard.get_or_insert_with(Cache::default);
+    match (mtime, settled) {
+        (Some(mtime), true) => {
+            cache.listings.insert(
+                key.clone(),
+                Cached {
+                    mtime,
+                    read_at: Instant::now(),
+                    contents: contents.clone(),
+                },
+            );
+            cache.touch(&key);
+            while cache.order.len() > MAX_CACHED {
+                if let Some(oldest) = cache.order.pop_front() {
+                    cache.listings.remove(&oldest);
+                }
+            }
+        }
+        _ => {
+            cache.listings.remove(&key);
+            cache.order.retain(|k| k != &key);
+        }
+    }
+    Ok(contents)
+}
+
+// Drops the listings of `path` and of the directory holding it, for changes
+// made by the app itself that shouldn't wait for an mtime check.
+pub fn invalidate(path: &Path) {
+    let mut guard = CACHE.lock().unwrap();
+    let Some(cache) = guard.as_mut() else {
+        return;
+    };
+    let affected = |dir: &str| {
+        let dir = rawpath::decode(dir);
+        dir == path || path.parent() == Some(dir.as_path())
+    };
+    cache.listings.retain(|(dir, _), _| !affected(dir));
+    cache.order.retain(|(dir, _)| !affected(dir));
+}
+
+pub fn clear() {
+    *CACHE.lock().unwrap() = None;
+}
@@ -0,0 +1,443 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::jobs::{JobCallback, JobHandle, JobKind, JobManager};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const PARTIAL_SUFFIX: &str = ".part";
+const SOURCE_SUFFIX: &str = ".part.source";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DownloadState {
+    Queued,
+    Running,
+    Verifying,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadRequest {
+    pub url: String,
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadStatus {
+    pub id: u64,
+    pub url: String,
+    pub destination: String,
+    pub state: DownloadState,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub resumed: bool,
+    pub error: Option<String>,
+}
+
+// Kept next to a `.part` file: what it is a partial copy of, so it is only
+// ever resumed from the same URL and the same version of the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartialSource {
+    url: String,
+    // A strong ETag or Last-Modified, sent back as If-Range.
+    validator: Option<String>,
+    length: Option<u64>,
+}
+
+pub type DownloadCallback = Arc<dyn Fn(&DownloadStatus) + Send + Sync>;
+
+pub struct DownloadManager {
+    jobs: Arc<Mutex<Vec<DownloadStatus>>>,
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn file_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    let name = decode_percent(name);
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\' | '\0'))
+        .collect();
+    if name.is_empty() || name.contains("://") || name == "." || name == ".." {
+        "download".to_string()
+    } else {
+        name
+    }
+}
+
+pub(crate) fn decode_percent(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// `reserved` holds destinations other downloads are already writing to,
+// which don't exist on disk yet.
+fn unique_destination(dir: &Path, name: &str, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let free = |candidate: &PathBuf| {
+        !candidate.exists() && !partial_path(candidate).exists() && !reserved.contains(candidate)
+    };
+    let candidate = dir.join(name);
+    if free(&candidate) {
+        return candidate;
+    }
+
+    let path = Path::new(name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut counter = 1u32;
+    loop {
+        let candidate = dir.join(format!("{stem} ({counter}){ext}"));
+        if free(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    destination.with_file_name(name)
+}
+
+fn source_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(SOURCE_SUFFIX);
+    destination.with_file_name(name)
+}
+
+fn read_source(destination: &Path) -> Option<PartialSource> {
+    if !partial_path(destination).exists() {
+        return None;
+    }
+    serde_json::from_slice(&fs::read(source_path(destination)).ok()?).ok()
+}
+
+fn remove_partial(destination: &Path) {
+    let _ = fs::remove_file(partial_path(destination));
+    let _ = fs::remove_file(source_path(destination));
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn list(&self) -> Vec<DownloadStatus> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn clear_finished(&self) {
+        self.jobs
+            .lock()
+            .unwrap()
+            .retain(|j| matches!(j.state, DownloadState::Queued | DownloadState::Running | DownloadState::Verifying));
+    }
+
+    pub fn download_urls(
+        &self,
+        requests: Vec<DownloadRequest>,
+        dest_dir: &str,
+        job_manager: &JobManager,
+        on_update: DownloadCallback,
+        on_job: JobCallback,
+    ) -> Result<Vec<u64>, String> {
+        let dir = PathBuf::from(dest_dir);
+        if !dir.is_dir() {
+            return Err("Destination must be a directory".to_string());
+        }
+
+        let mut queued = Vec::new();
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            // Two URLs ending in the same name, in this batch or one still
+            // running, must not share a file.
+            let mut reserved: HashSet<PathBuf> = jobs
+                .iter()
+                .filter(|j| matches!(j.state, DownloadState::Queued | DownloadState::Running | DownloadState::Verifying))
+                .map(|j| PathBuf::from(&j.destination))
+                .collect();
+            for request in &requests {
+                let name = file_name_from_url(&request.url);
+                let target = dir.join(&name);
+                // A leftover `.part` file from the same URL is picked up again so an
+                // interrupted batch can simply be restarted. One left by another URL
+                // that ends in the same name is never appended to.
+                let resumable = !target.exists()
+                    && !reserved.contains(&target)
+                    && read_source(&target).is_some_and(|source| source.url == request.url);
+                let destination = if resumable {
+                    target
+                } else {
+                    unique_destination(&dir, &name, &reserved)
+                };
+                reserved.insert(destination.clone());
+                // Download ids are job ids, so the job manager can pause and cancel them.
+                let job = job_manager.create(
+                    JobKind::Download,
+                    &name,
+                    vec![destination.to_string_lossy().to_string()],
+                    on_job.clone(),
+                );
+                let status = DownloadStatus {
+                    id: job.id(),
+                    url: request.url.clone(),
+                    destination: destination.to_string_lossy().to_string(),
+                    state: DownloadState::Queued,
+                    downloaded: 0,
+                    total: None,
+                    resumed: false,
+                    error: None,
+                };
+                jobs.push(status.clone());
+                queued.push((status, request.sha256.clone(), job));
+            }
+        }
+
+        let ids = queued.iter().map(|(s, _, _)| s.id).collect();
+        let jobs = self.jobs.clone();
+
+        thread::spawn(move || {
+            for (mut status, sha256, job) in queued {
+                let update = |status: &DownloadStatus| {
+                    if let Some(job) = jobs.lock().unwrap().iter_mut().find(|j| j.id == status.id) {
+                        *job = status.clone();
+                    }
+                    on_update(status);
+                };
+
+                let result = job.start().and_then(|()| {
+                    status.state = DownloadState::Running;
+                    update(&status);
+                    Self::fetch(&mut status, sha256.as_deref(), &job, &update)
+                });
+                match &result {
+                    Ok(()) => status.state = DownloadState::Completed,
+                    Err(_) if job.is_cancelled() => status.state = DownloadState::Cancelled,
+                    Err(e) => {
+                        status.state = DownloadState::Failed;
+                        status.error = Some(e.clone());
+                    }
+                }
+                update(&status);
+                job.finish(result);
+            }
+        });
+
+        Ok(ids)
+    }
+
+    fn fetch(
+        status: &mut DownloadStatus,
+        sha256: Option<&str>,
+        job: &JobHandle,
+        update: &dyn Fn(&DownloadStatus),
+    ) -> Result<(), String> {
+        let destination = PathBuf::from(&status.destination);
+        let partial = partial_path(&destination);
+        let source = read_source(&destination).filter(|source| source.url == status.url);
+        let offset = match &source {
+            Some(_) => fs::metadata(&partial).map(|m| m.len()).unwrap_or(0),
+            None => 0,
+        };
+
+        let mut request = ureq::get(&status.url);
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+            // Should the file have changed on the server, it sends all of the new one.
+            if let Some(validator) = source.as_ref().and_then(|s| s.validator.as_deref()) {
+                request = request.set("If-Range", validator);
+            }
+        }
+
+        let response = match request.call() {
+            Ok(r) => r,
+            // The partial file is already complete; the server has nothing more to send.
+            Err(ureq::Error::Status(416, _))
+                if offset > 0 && source.as_ref().is_some_and(|s| s.length.is_none_or(|l| l == offset)) =>
+            {
+                return Self::finish(status, &partial, &destination, sha256, update);
+            }
+            Err(e) => return Err(format!("Request failed: {}", e)),
+        };
+
+        let resumed = offset > 0 && response.status() == 206;
+        let length: Option<u64> = response.header("Content-Length").and_then(|v| v.parse().ok());
+        let chunked = response.header("Transfer-Encoding").is_some_and(|v| !v.is_empty());
+        let mut validator = source.as_ref().and_then(|s| s.validator.clone());
+        if resumed {
+            let full_length = response
+                .header("Content-Range")
+                .and_then(|v| v.rsplit('/').next())
+                .and_then(|v| v.parse::<u64>().ok());
+            let known_length = source.as_ref().and_then(|s| s.length);
+            if known_length.is_some() && full_length.is_some() && known_length != full_length {
+                remove_partial(&destination);
+                return Err("The file changed on the server since the download stopped".to_string());
+            }
+        }
+        status.resumed = resumed;
+        status.downloaded = if resumed { offset } else { 0 };
+        status.total = length.map(|l| l + status.downloaded);
+        job.set_total(status.total);
+
+        if !resumed {
+            let etag = response.header("ETag").filter(|v| !v.starts_with("W/"));
+            let source = PartialSource {
+                url: status.url.clone(),
+                validator: etag.or(response.header("Last-Modified")).map(str::to_string),
+                length: status.total,
+            };
+            validator.clone_from(&source.validator);
+            if let Ok(data) = serde_json::to_vec(&source) {
+                let _ = fs::write(source_path(&destination), data);
+            }
+        }
+
+        let mut file = if resumed {
+            OpenOptions::new().append(true).open(&partial)
+        } else {
+            File::create(&partial)
+        }
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+        let mut reader = response.into_reader();
+        let mut buf = vec![0u8; 1 << 16];
+        let mut last_update = Instant::now();
+
+        loop {
+            job.checkpoint()?;
+
+            let n = reader.read(&mut buf).map_err(|e| format!("Download interrupted: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+            status.downloaded += n as u64;
+            job.throttle(n as u64);
+
+            job.progress(status.downloaded, None);
+            if last_update.elapsed() >= PROGRESS_INTERVAL {
+                update(status);
+                last_update = Instant::now();
+            }
+        }
+
+        file.flush().map_err(|e| format!("Failed to write file: {}", e))?;
+        drop(file);
+
+        match status.total {
+            Some(total) if status.downloaded < total => {
+                return Err(format!("Download incomplete: {} of {} bytes", status.downloaded, total));
+            }
+            Some(_) => {}
+            None => Self::confirm_end(&status.url, status.downloaded, validator.as_deref(), chunked)?,
+        }
+
+        Self::finish(status, &partial, &destination, sha256, update)
+    }
+
+    // Without a length, a chunked body cut off mid-chunk reads the same as one
+    // that ended, so the server is asked whether anything comes after what
+    // arrived. A body that ends by closing the connection is taken at its word
+    // when the server can't say.
+    fn confirm_end(url: &str, downloaded: u64, validator: Option<&str>, chunked: bool) -> Result<(), String> {
+        let mut request = ureq::get(url).set("Range", &format!("bytes={}-", downloaded));
+        if let Some(validator) = validator {
+            request = request.set("If-Range", validator);
+        }
+        let length = match request.call() {
+            Err(ureq::Error::Status(416, _)) => return Ok(()),
+            Ok(r) if r.status() == 206 => {
+                return Err(format!("Download incomplete: the server has more after {} bytes", downloaded));
+            }
+            Ok(r) => r.header("Content-Length").and_then(|v| v.parse::<u64>().ok()),
+            Err(_) => None,
+        };
+        match length {
+            Some(length) if length == downloaded => Ok(()),
+            Some(length) => Err(format!("Download incomplete: {} of {} bytes", downloaded, length)),
+            None if chunked => Err("Download may be incomplete: the server gave no length to check it against".to_string()),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(
+        status: &mut DownloadStatus,
+        partial: &Path,
+        destination: &Path,
+        sha256: Option<&str>,
+        update: &dyn Fn(&DownloadStatus),
+    ) -> Result<(), String> {
+        if let Some(expected) = sha256 {
+            status.state = DownloadState::Verifying;
+            update(status);
+
+            let actual = sha256_file(partial)?;
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                remove_partial(destination);
+                return Err(format!("Checksum mismatch: expected {}, got {}", expected, actual));
+            }
+        }
+
+        status.downloaded = fs::metadata(partial).map(|m| m.len()).unwrap_or(status.downloaded);
+        // A rename would replace a file that turned up there in the meantime.
+        if destination.exists() {
+            return Err(format!("{} already exists", destination.display()));
+        }
+        fs::rename(partial, destination).map_err(|e| format!("Failed to finalize download: {}", e))?;
+        let _ = fs::remove_file(source_path(destination));
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const SETTING: &str = "durable_writes";
+pub(crate) const PARTIAL_SUFFIX: &str = ".hardbore-partial";
+
+// Off by default: fsyncing every file makes large copies noticeably slower.
+static DURABLE: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    DURABLE.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    DURABLE.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn partial_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    destination.with_file_name(name)
+}
+
+pub fn sync_file(path: &Path) -> Result<(), String> {
+    File::open(path)
+        .and_then(|f| f.sync_all())
+        .map_err(|e| format!("Failed to sync file: {}", e))
+}
+
+// Makes a created, removed or renamed entry in `dir` itself durable.
+#[cfg(unix)]
+pub fn sync_dir(dir: &Path) -> Result<(), String> {
+    File::open(dir)
+        .and_then(|f| f.sync_all())
+        .map_err(|e| format!("Failed to sync directory: {}", e))
+}
+
+// NTFS journals directory changes and directories cannot be opened for flushing
+// without extra flags, so only file data is synced there.
+#[cfg(not(unix))]
+pub fn sync_dir(_dir: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+pub fn sync_parent(path: &Path) -> Result<(), String> {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => sync_dir(parent),
+        None => Ok(()),
+    }
+}
+
+// Copies through a temporary sibling that is renamed over `dst`, so `dst` is
+// either the old file or the complete new one, and both the data and the
+// directory entry are on disk before returning.
+pub fn copy_file(src: &Path, dst: &Path) -> Result<(), String> {
+    if !enabled() {
+        return fs::copy(src, dst)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to copy file: {}", e));
+    }
+
+    let temp = partial_path(dst);
+    let result = fs::copy(src, &temp)
+        .map_err(|e| format!("Failed to copy file: {}", e))
+        .and_then(|_| sync_file(&temp))
+        .and_then(|()| fs::rename(&temp, dst).map_err(|e| format!("Failed to copy file: {}", e)));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result?;
+    sync_parent(dst)
+}
+
+pub fn rename(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::rename(src, dst)?;
+    if enabled() {
+        sync_parent(dst).map_err(io::Error::other)?;
+        if src.parent() != dst.parent() {
+            sync_parent(src).map_err(io::Error::other)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use crate::jobs::{JobInfo, JobManager};
+#[cfg(target_os = "linux")]
+use crate::openfiles::who_has_open;
+#[cfg(not(target_os = "linux"))]
+use crate::openfiles::{parse_lsof, process_name};
+use crate::openfiles::FileHolder;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EjectReport {
+    pub ejected: bool,
+    pub blocking_jobs: Vec<JobInfo>,
+    pub holders: Vec<FileHolder>,
+    pub error: Option<String>,
+}
+
+impl EjectReport {
+    fn blocked(blocking_jobs: Vec<JobInfo>, holders: Vec<FileHolder>, error: Option<String>) -> Self {
+        Self {
+            ejected: false,
+            blocking_jobs,
+            holders,
+            error,
+        }
+    }
+}
+
+// fuser writes the pids to stdout, each with a suffix letter for the access kind.
+#[cfg(not(target_os = "linux"))]
+fn parse_fuser(output: &str) -> Vec<FileHolder> {
+    output
+        .split_whitespace()
+        .filter_map(|token| {
+            let digits: String = token.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .map(|pid| FileHolder {
+            pid,
+            command: process_name(pid),
+            path: None,
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn find_holders(mount_path: &Path) -> Vec<FileHolder> {
+    who_has_open(mount_path).unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn find_holders(mount_path: &Path) -> Vec<FileHolder> {
+    let own_pid = std::process::id();
+
+    let holders = match Command::new("lsof").args(["-F", "pcn", "--"]).arg(mount_path).output() {
+        Ok(output) => parse_lsof(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => Command::new("fuser")
+            .arg("-m")
+            .arg(mount_path)
+            .output()
+            .map(|o| parse_fuser(&String::from_utf8_lossy(&o.stdout)))
+            .unwrap_or_default(),
+    };
+
+    holders.into_iter().filter(|h| h.pid != own_pid).collect()
+}
+
+#[cfg(target_os = "linux")]
+fn sync_filesystem(mount_path: &Path) -> Result<(), String> {
+    use std::os::unix::io::AsRawFd;
+
+    let dir = std::fs::File::open(mount_path).map_err(|e| format!("Failed to open mount point: {}", e))?;
+    if unsafe { libc::syncfs(dir.as_raw_fd()) } != 0 {
+        return Err(format!("Failed to sync filesystem: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn sync_filesystem(_mount_path: &Path) -> Result<(), String> {
+    unsafe { libc::sync() };
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_filesystem(_mount_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn run(program: &str, args: &[impl AsRef<OsStr>]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn mount_device(mount_path: &Path) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    mounts.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let device = parts.next()?;
+        let mount_point = parts.next()?.replace("\\040", " ");
+        (Path::new(&mount_point) == mount_path && device.starts_with("/dev/")).then(|| device.to_string())
+    })
+}
+
+// udisks lets an unprivileged desktop user unmount and power off removable
+// drives; plain umount only works for user-mountable fstab entries.
+#[cfg(target_os = "linux")]
+fn unmount(mount_path: &Path) -> Result<(), String> {
+    if let Some(device) = mount_device(mount_path) {
+        if run("udisksctl", &["unmount", "-b", &device]).is_ok() {
+            let _ = run("udisksctl", &["power-off", "-b", &device]);
+            return Ok(());
+        }
+    }
+    run("umount", &[mount_path])
+}
+
+#[cfg(target_os = "macos")]
+fn unmount(mount_path: &Path) -> Result<(), String> {
+    run("diskutil", &[OsStr::new("eject"), mount_path.as_os_str()])
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn unmount(_mount_path: &Path) -> Result<(), String> {
+    Err("Ejecting devices is not supported on this platform".to_string())
+}
+
+pub fn safe_eject(mount_path: &Path, jobs: &JobManager) -> EjectReport {
+    if !mount_path.is_dir() {
+        return EjectReport::blocked(Vec::new(), Vec::new(), Some("Mount point does not exist".to_string()));
+    }
+
+    let blocking_jobs = jobs.active_under(mount_path);
+    if !blocking_jobs.is_empty() {
+        return EjectReport::blocked(blocking_jobs, Vec::new(), Some("Operations on this device are still running".to_string()));
+    }
+
+    if let Err(e) = sync_filesystem(mount_path) {
+        return EjectReport::blocked(Vec::new(), Vec::new(), Some(e));
+    }
+
+    let holders = find_holders(mount_path);
+    if !holders.is_empty() {
+        return EjectReport::blocked(Vec::new(), holders, Some("Files on this device are in use".to_string()));
+    }
+
+    match unmount(mount_path) {
+        Ok(()) => {
+            tracing::info!(mount = %mount_path.display(), "ejected device");
+            EjectReport {
+                ejected: true,
+                blocking_jobs: Vec::new(),
+                holders: Vec::new(),
+                error: None,
+            }
+        }
+        // Something may have opened a file between the check and the unmount.
+        Err(e) => {
+            tracing::warn!(mount = %mount_path.display(), "eject failed: {}", e);
+            EjectReport::blocked(Vec::new(), find_holders(mount_path), Some(format!("Failed to eject: {}", e)))
+        }
+    }
+}
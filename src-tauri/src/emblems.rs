@@ -0,0 +1,265 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use crate::fs_engine::FileEntry;
+
+// A provider looks at a whole listing at once, so anything expensive (running
+// git, querying a service) happens once per directory rather than once per row.
+pub trait EmblemProvider: Send + Sync {
+    fn annotate(&self, dir: &Path, entries: &mut [FileEntry]);
+}
+
+fn providers() -> &'static RwLock<Vec<Arc<dyn EmblemProvider>>> {
+    static PROVIDERS: OnceLock<RwLock<Vec<Arc<dyn EmblemProvider>>>> = OnceLock::new();
+    PROVIDERS.get_or_init(|| {
+        RwLock::new(vec![
+            Arc::new(SymlinkEmblems),
+            Arc::new(GitEmblems),
+            Arc::new(SharedEmblems),
+            Arc::new(SyncedEmblems),
+            Arc::new(EncryptedEmblems),
+            Arc::new(TaggedEmblems),
+        ])
+    })
+}
+
+pub fn register(provider: Arc<dyn EmblemProvider>) {
+    providers().write().unwrap().push(provider);
+}
+
+pub fn annotate(dir: &Path, entries: &mut [FileEntry]) {
+    for provider in providers().read().unwrap().iter() {
+        provider.annotate(dir, entries);
+    }
+}
+
+fn add(entry: &mut FileEntry, emblem: &str) {
+    if !entry.emblems.iter().any(|e| e == emblem) {
+        entry.emblems.push(emblem.to_string());
+    }
+}
+
+struct SymlinkEmblems;
+
+impl EmblemProvider for SymlinkEmblems {
+    fn annotate(&self, _dir: &Path, entries: &mut [FileEntry]) {
+        for entry in entries.iter_mut().filter(|e| e.is_symlink) {
+            add(entry, "symlink");
+        }
+    }
+}
+
+struct GitEmblems;
+
+pub(crate) fn repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|a| a.join(".git").exists()).map(Path::to_path_buf)
+}
+
+// Higher wins when a directory contains files in several states.
+fn git_emblem(code: &str) -> (&'static str, u8) {
+    match code {
+        "??" => ("git-untracked", 1),
+        "DD" | "AA" => ("git-conflict", 4),
+        _ if code.contains('U') => ("git-conflict", 4),
+        _ if code.starts_with('A') => ("git-added", 2),
+        _ => ("git-modified", 3),
+    }
+}
+
+impl EmblemProvider for GitEmblems {
+    fn annotate(&self, dir: &Path, entries: &mut [FileEntry]) {
+        let Some(root) = repo_root(dir) else {
+            return;
+        };
+        let Ok(prefix) = dir.strip_prefix(&root) else {
+            return;
+        };
+        let Ok(output) = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["status", "--porcelain=v1", "-z", "--", "."])
+            .output()
+        else {
+            return;
+        };
+        if !output.status.success() {
+            return;
+        }
+
+        // Porcelain paths are relative to the repository root; renames and
+        // copies are followed by their source path as an extra record.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut records = stdout.split('\0');
+        let mut states: HashMap<String, (&'static str, u8)> = HashMap::new();
+        while let Some(record) = records.next() {
+            if record.len() < 4 || !record.is_char_boundary(3) {
+                continue;
+            }
+            let (code, path) = record.split_at(2);
+            if code.starts_with('R') || code.starts_with('C') {
+                records.next();
+            }
+            let Ok(relative) = Path::new(&path[1..]).strip_prefix(prefix) else {
+                continue;
+            };
+            let Some(name) = relative.components().next() else {
+                continue;
+            };
+            let name = name.as_os_str().to_string_lossy().to_string();
+            let state = git_emblem(code);
+            let current = states.entry(name).or_insert(state);
+            if state.1 > current.1 {
+                *current = state;
+            }
+        }
+
+        for entry in entries.iter_mut() {
+            if let Some((emblem, _)) = states.get(&entry.name) {
+                add(entry, emblem);
+            }
+        }
+    }
+}
+
+static SHARED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+// Called whenever the LAN share starts or stops.
+pub fn set_shared_paths(paths: &[String]) {
+    *SHARED.lock().unwrap() = Some(paths.iter().cloned().collect());
+}
+
+struct SharedEmblems;
+
+impl EmblemProvider for SharedEmblems {
+    fn annotate(&self, _dir: &Path, entries: &mut [FileEntry]) {
+        let shared = SHARED.lock().unwrap();
+        let Some(shared) = shared.as_ref().filter(|s| !s.is_empty()) else {
+            return;
+        };
+        for entry in entries.iter_mut().filter(|e| shared.contains(&e.path)) {
+            add(entry, "shared");
+        }
+    }
+}
+
+// Cloud placeholders are already detected while reading the entry.
+struct SyncedEmblems;
+
+impl EmblemProvider for SyncedEmblems {
+    fn annotate(&self, _dir: &Path, entries: &mut [FileEntry]) {
+        for entry in entries.iter_mut().filter(|e| e.placeholder) {
+            add(entry, "synced");
+        }
+    }
+}
+
+struct EncryptedEmblems;
+
+const ENCRYPTED_EXTENSIONS: &[&str] = &["gpg", "pgp", "age", "aes"];
+
+#[cfg(target_os = "linux")]
+fn has_encrypted_attribute(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+    let result = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+            0,
+            &mut stx,
+        )
+    };
+    result == 0
+        && stx.stx_attributes_mask & libc::STATX_ATTR_ENCRYPTED as u64 != 0
+        && stx.stx_attributes & libc::STATX_ATTR_ENCRYPTED as u64 != 0
+}
+
+#[cfg(windows)]
+fn has_encrypted_attribute(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_ENCRYPTED: u32 = 0x4000;
+
+    std::fs::symlink_metadata(path)
+        .map(|m| m.file_attributes() & FILE_ATTRIBUTE_ENCRYPTED != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn has_encrypted_attribute(_path: &Path) -> bool {
+    false
+}
+
+impl EmblemProvider for EncryptedEmblems {
+    fn annotate(&self, _dir: &Path, entries: &mut [FileEntry]) {
+        entries.par_iter_mut().for_each(|entry| {
+            let by_extension = entry
+                .extension
+                .as_deref()
+                .is_some_and(|ext| ENCRYPTED_EXTENSIONS.contains(&ext));
+            if by_extension || has_encrypted_attribute(Path::new(&entry.path)) {
+                add(entry, "encrypted");
+            }
+        });
+    }
+}
+
+struct TaggedEmblems;
+
+// Tags set by other file managers: the freedesktop `user.xdg.tags` attribute
+// and Finder tags on macOS.
+#[cfg(target_os = "linux")]
+fn has_tags(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let name = b"user.xdg.tags\0";
+    unsafe { libc::lgetxattr(c_path.as_ptr(), name.as_ptr().cast(), std::ptr::null_mut(), 0) > 0 }
+}
+
+#[cfg(target_os = "macos")]
+fn has_tags(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let name = b"com.apple.metadata:_kMDItemUserTags\0";
+    unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            name.as_ptr().cast(),
+            std::ptr::null_mut(),
+            0,
+            0,
+            libc::XATTR_NOFOLLOW,
+        ) > 0
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn has_tags(_path: &Path) -> bool {
+    false
+}
+
+impl EmblemProvider for TaggedEmblems {
+    fn annotate(&self, _dir: &Path, entries: &mut [FileEntry]) {
+        entries.par_iter_mut().for_each(|entry| {
+            if has_tags(Path::new(&entry.path)) {
+                add(entry, "tagged");
+            }
+        });
+    }
+}
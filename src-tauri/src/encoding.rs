@@ -0,0 +1,68 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+pub struct Decoded {
+    pub text: String,
+    // The WHATWG name, e.g. "UTF-8", "Shift_JIS" or "windows-1252".
+    pub encoding: String,
+    pub bom: bool,
+}
+
+// UTF-16 text is full of NUL bytes, so it needs a BOM to be told from binary.
+pub fn has_utf16_bom(bytes: &[u8]) -> bool {
+    matches!(Encoding::for_bom(bytes), Some((enc, _)) if enc == UTF_16LE || enc == UTF_16BE)
+}
+
+// `complete` is false when `bytes` is a prefix of the file, in which case a
+// multibyte sequence cut off at the end isn't treated as an error.
+fn is_utf8(bytes: &[u8], complete: bool) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => !complete && e.error_len().is_none(),
+    }
+}
+
+fn detect(bytes: &[u8], complete: bool) -> &'static Encoding {
+    if is_utf8(bytes, complete) {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, complete);
+    detector.guess(None, true)
+}
+
+// Picks `label` when given, otherwise the BOM's encoding or the best guess
+// from the content. Also returns the length of the BOM to skip.
+pub fn resolve(bytes: &[u8], label: Option<&str>, complete: bool) -> Result<(&'static Encoding, usize), String> {
+    Ok(match label {
+        Some(label) => {
+            let encoding = Encoding::for_label(label.trim().as_bytes())
+                .ok_or_else(|| format!("Unknown encoding: {}", label))?;
+            let bom_len = Encoding::for_bom(bytes).filter(|(enc, _)| *enc == encoding).map_or(0, |(_, len)| len);
+            (encoding, bom_len)
+        }
+        None => match Encoding::for_bom(bytes) {
+            Some((encoding, bom_len)) => (encoding, bom_len),
+            None => (detect(bytes, complete), 0),
+        },
+    })
+}
+
+pub fn decode(bytes: &[u8], label: Option<&str>, complete: bool) -> Result<Decoded, String> {
+    let (encoding, bom_len) = resolve(bytes, label, complete)?;
+
+    let mut body = &bytes[bom_len..];
+    if !complete && encoding == UTF_8 {
+        if let Err(e) = std::str::from_utf8(body) {
+            if e.error_len().is_none() {
+                body = &body[..e.valid_up_to()];
+            }
+        }
+    }
+    let (text, _) = encoding.decode_without_bom_handling(body);
+    Ok(Decoded {
+        text: text.into_owned(),
+        encoding: encoding.name().to_string(),
+        bom: bom_len > 0,
+    })
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter};
+
+use crate::{dircache, rawpath};
+
+// Every window hears about changes to the filesystem, whether one of them
+// made it or a change journal saw it, so a folder open in two windows stays
+// the same in both. Journals running in the indexing daemon have no windows
+// to tell; only the app's own operations are broadcast then.
+pub const EVENT: &str = "fs-changed";
+
+static APP: RwLock<Option<AppHandle>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    // A file operation run by one of the windows.
+    App,
+    // A change journal, i.e. anything outside the app.
+    Watcher,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsChange {
+    pub origin: Origin,
+    // Created, modified or deleted; a folder stands for all it contains.
+    pub paths: Vec<String>,
+    pub moves: Vec<(String, String)>,
+    // The folders whose listings changed: the parents of all of the above.
+    pub dirs: Vec<String>,
+}
+
+pub fn init(app_handle: &AppHandle) {
+    *APP.write().unwrap() = Some(app_handle.clone());
+}
+
+fn parent(path: &str) -> Option<String> {
+    rawpath::decode(path).parent().map(rawpath::encode_path)
+}
+
+pub fn publish(origin: Origin, paths: &[String], moves: &[(String, String)]) {
+    if paths.is_empty() && moves.is_empty() {
+        return;
+    }
+    // Drops a changed folder's cached listing along with its parent's.
+    for path in paths.iter().chain(moves.iter().flat_map(|(from, to)| [from, to])) {
+        dircache::invalidate(&rawpath::decode(path));
+    }
+    let Some(app_handle) = APP.read().unwrap().clone() else {
+        return;
+    };
+    let dirs: BTreeSet<String> = paths
+        .iter()
+        .chain(moves.iter().flat_map(|(from, to)| [from, to]))
+        .filter_map(|p| parent(p))
+        .collect();
+    let change = FsChange {
+        origin,
+        paths: paths.to_vec(),
+        moves: moves.to_vec(),
+        dirs: dirs.into_iter().collect(),
+    };
+    if let Err(e) = app_handle.emit(EVENT, change) {
+        tracing::warn!("Failed to broadcast file changes: {}", e);
+    }
+}
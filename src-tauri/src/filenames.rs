@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::fs_engine::containing_mount;
+
+const MAX_POSIX_BYTES: usize = 255;
+const MAX_UTF16_UNITS: usize = 255;
+// An extension longer than this is more likely part of the name, so it isn't
+// kept intact when a long name is shortened.
+const MAX_KEPT_EXTENSION: usize = 16;
+const WINDOWS_RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+const WINDOWS_RESERVED_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TargetFs {
+    // ext4, btrfs, xfs and the like: only '/' and NUL are off limits.
+    Posix,
+    Apfs,
+    Ntfs,
+    Fat,
+    Exfat,
+}
+
+impl TargetFs {
+    // Maps a type as reported by the mount table or GetVolumeInformationW.
+    pub fn from_fs_type(fs_type: &str) -> Self {
+        match fs_type.to_ascii_lowercase().as_str() {
+            "ntfs" | "ntfs3" | "fuseblk" | "refs" => TargetFs::Ntfs,
+            "vfat" | "msdos" | "fat" | "fat12" | "fat16" | "fat32" => TargetFs::Fat,
+            "exfat" => TargetFs::Exfat,
+            "apfs" | "hfs" => TargetFs::Apfs,
+            _ => TargetFs::Posix,
+        }
+    }
+
+    fn windows_rules(self) -> bool {
+        matches!(self, TargetFs::Ntfs | TargetFs::Fat | TargetFs::Exfat)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NameIssue {
+    Empty,
+    // "." or "..".
+    DotName,
+    ReservedCharacters,
+    // CON, NUL, COM1 and friends, with or without an extension.
+    ReservedName,
+    TrailingDotOrSpace,
+    TooLong,
+    // Not valid Unicode, so it can't be stored as UTF-16 or APFS's UTF-8.
+    InvalidUnicode,
+}
+
+impl NameIssue {
+    pub fn describe(self) -> &'static str {
+        match self {
+            NameIssue::Empty => "is empty",
+            NameIssue::DotName => "is . or ..",
+            NameIssue::ReservedCharacters => "contains reserved characters",
+            NameIssue::ReservedName => "is a reserved name",
+            NameIssue::TrailingDotOrSpace => "ends with a dot or space",
+            NameIssue::TooLong => "is too long",
+            NameIssue::InvalidUnicode => "is not valid Unicode",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameCheck {
+    pub target_fs: TargetFs,
+    pub issues: Vec<NameIssue>,
+    // Equal to the input when there are no issues.
+    pub sanitized: String,
+}
+
+// The filesystem `dir` lives on, falling back to the platform's usual one.
+pub fn target_fs_for(dir: &Path) -> TargetFs {
+    let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(dir);
+    match containing_mount(existing) {
+        Some((_, fs_type)) => TargetFs::from_fs_type(&fs_type),
+        None if cfg!(windows) => TargetFs::Ntfs,
+        None if cfg!(target_os = "macos") => TargetFs::Apfs,
+        None => TargetFs::Posix,
+    }
+}
+
+fn is_reserved_char(c: char, target: TargetFs) -> bool {
+    match target.windows_rules() {
+        true => WINDOWS_RESERVED_CHARS.contains(&c) || (c as u32) < 0x20,
+        false => c == '/' || c == '\0',
+    }
+}
+
+// Windows checks the part before the first dot, ignoring trailing spaces,
+// so "con.txt" and "COM1 .log" are reserved too.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    let upper = stem.to_ascii_uppercase();
+    WINDOWS_RESERVED_NAMES.contains(&upper.as_str())
+        || (upper.len() == 4
+            && (upper.starts_with("COM") || upper.starts_with("LPT"))
+            && upper.as_bytes()[3].is_ascii_digit())
+}
+
+fn is_too_long(name: &str, target: TargetFs) -> bool {
+    match target {
+        TargetFs::Posix => name.len() > MAX_POSIX_BYTES,
+        _ => name.encode_utf16().count() > MAX_UTF16_UNITS,
+    }
+}
+
+pub fn validate_filename(name: &OsStr, target: TargetFs) -> Vec<NameIssue> {
+    let mut issues = Vec::new();
+    let text = match name.to_str() {
+        Some(text) => text.to_string(),
+        None => {
+            if target != TargetFs::Posix {
+                issues.push(NameIssue::InvalidUnicode);
+            }
+            name.to_string_lossy().to_string()
+        }
+    };
+    if text.is_empty() {
+        issues.push(NameIssue::Empty);
+        return issues;
+    }
+    if text == "." || text == ".." {
+        issues.push(NameIssue::DotName);
+    }
+    if text.chars().any(|c| is_reserved_char(c, target)) {
+        issues.push(NameIssue::ReservedCharacters);
+    }
+    if target.windows_rules() {
+        if is_reserved_name(&text) {
+            issues.push(NameIssue::ReservedName);
+        }
+        if text.ends_with(['.', ' ']) && text != "." && text != ".." {
+            issues.push(NameIssue::TrailingDotOrSpace);
+        }
+    }
+    if is_too_long(&text, target) {
+        issues.push(NameIssue::TooLong);
+    }
+    issues
+}
+
+// Shortens the stem and keeps a short extension intact.
+fn shorten(name: &str, target: TargetFs) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(i) if i > 0 && name.len() - i <= MAX_KEPT_EXTENSION => name.split_at(i),
+        _ => (name, ""),
+    };
+    let mut stem: Vec<char> = stem.chars().collect();
+    let mut shortened = name.to_string();
+    while is_too_long(&shortened, target) && !stem.is_empty() {
+        stem.pop();
+        shortened = stem.iter().collect::<String>() + extension;
+    }
+    shortened
+}
+
+// Rewrites `name` into one `target` accepts: reserved characters become '_',
+// trailing dots and spaces are dropped, reserved names get a '_' after the
+// stem and overlong names are shortened.
+pub fn sanitize_filename(name: &OsStr, target: TargetFs) -> String {
+    let mut text: String = name
+        .to_string_lossy()
+        .chars()
+        .map(|c| if is_reserved_char(c, target) { '_' } else { c })
+        .collect();
+    if target.windows_rules() {
+        text.truncate(text.trim_end_matches(['.', ' ']).len());
+        if is_reserved_name(&text) {
+            let stem_len = text.find('.').unwrap_or(text.len());
+            text.insert(stem_len, '_');
+        }
+    }
+    if text.is_empty() || text == "." || text == ".." {
+        text = text.replace('.', "_");
+        if text.is_empty() {
+            text.push('_');
+        }
+    }
+    shorten(&text, target)
+}
+
+pub fn check_filename(name: &OsStr, target: TargetFs) -> NameCheck {
+    let issues = validate_filename(name, target);
+    let sanitized = match issues.is_empty() {
+        true => name.to_string_lossy().to_string(),
+        false => sanitize_filename(name, target),
+    };
+    NameCheck {
+        target_fs: target,
+        issues,
+        sanitized,
+    }
+}
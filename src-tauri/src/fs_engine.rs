@@ -1,6 +1,6 @@
-use jwalk::WalkDir;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 
 #[cfg(unix)]
@@ -14,19 +14,55 @@ use std::os::windows::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::classify::{classify_entry, FileCategory};
+use crate::contentcache;
+use crate::emblems;
+use crate::encoding;
+use crate::humanize::{format_date, format_size};
+use crate::markdown;
+use crate::notebook::{notebook_preview, NotebookPreview};
+use crate::rawpath;
+use crate::shortcuts::{read_shortcut, LaunchTarget};
+use crate::sqlite::{preview_sqlite, SqlitePreview};
+use crate::structured::{json_preview, table_preview, JsonPreview, TablePreview};
+use crate::textinfo::{text_stats, TextStats};
+use crate::torrent::{read_torrent, TorrentInfo};
+use crate::users::{group_name, user_name};
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
     pub is_dir: bool,
     pub is_symlink: bool,
     pub size: u64,
+    pub disk_size: u64,
     pub modified: i64,
     pub permissions: String,
     pub owner: u32,
     pub group: u32,
+    pub owner_name: Option<String>,
+    pub group_name: Option<String>,
     pub extension: Option<String>,
     pub hidden: bool,
+    pub placeholder: bool,
+    #[serde(default)]
+    pub category: FileCategory,
+    #[serde(default)]
+    pub emblems: Vec<String>,
+    // Set for .desktop and .lnk files, which are opened by launching this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<LaunchTarget>,
+    // Extra columns requested by id; see `columns`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub columns: BTreeMap<String, serde_json::Value>,
+    // Size and modification time as the user's settings and locale render
+    // them; the size is empty for directories.
+    #[serde(default)]
+    pub size_text: String,
+    #[serde(default)]
+    pub modified_text: String,
 }
 
 
@@ -37,6 +73,9 @@ pub struct DirectoryContents {
     pub entries: Vec<FileEntry>,
     pub total_items: usize,
     pub total_size: u64,
+    pub total_disk_size: u64,
+    #[serde(default)]
+    pub total_size_text: String,
 }
 
 #[cfg(unix)]
@@ -69,9 +108,208 @@ fn mode_to_string(_mode: u32, is_dir: bool) -> String {
     }
 }
 
-fn get_file_entry(path: &Path) -> Option<FileEntry> {
+// Sparse files, transparently compressed files and cloud placeholders occupy less
+// than their logical size; this is what they actually take up on disk.
+#[cfg(unix)]
+pub(crate) fn allocated_size(_path: &Path, metadata: &fs::Metadata) -> u64 {
+    metadata.blocks() * 512
+}
+
+#[cfg(windows)]
+pub(crate) fn allocated_size(path: &Path, metadata: &fs::Metadata) -> u64 {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCompressedFileSizeW(file_name: *const u16, file_size_high: *mut u32) -> u32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut high = 0u32;
+    let low = unsafe { GetCompressedFileSizeW(wide.as_ptr(), &mut high) };
+    if low == u32::MAX && std::io::Error::last_os_error().raw_os_error() != Some(0) {
+        return metadata.len();
+    }
+    ((high as u64) << 32) | low as u64
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn allocated_size(_path: &Path, metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+// /proc/mounts escapes whitespace and backslashes in paths as octal.
+#[cfg(target_os = "linux")]
+pub(crate) fn unescape_mount_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u8::from_str_radix(d, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// Mount point and filesystem type of the mount holding `path`: the longest
+// mount point that prefixes it, and the last one mounted when several are stacked.
+#[cfg(target_os = "linux")]
+pub(crate) fn containing_mount(path: &Path) -> Option<(String, String)> {
+    let canonical = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let _device = parts.next()?;
+            let mount_point = unescape_mount_path(parts.next()?);
+            let fs_type = parts.next()?;
+            canonical.starts_with(&mount_point).then(|| (mount_point, fs_type.to_string()))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.len())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn containing_mount(path: &Path) -> Option<(String, String)> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let text = |chars: &[libc::c_char]| unsafe { CStr::from_ptr(chars.as_ptr()) }.to_string_lossy().to_string();
+    Some((text(&stat.f_mntonname), text(&stat.f_fstypename)))
+}
+
+#[cfg(windows)]
+pub(crate) fn containing_mount(path: &Path) -> Option<(String, String)> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetVolumePathNameW(file_name: *const u16, volume_path: *mut u16, length: u32) -> i32;
+        fn GetVolumeInformationW(
+            root_path: *const u16,
+            volume_name: *mut u16,
+            volume_name_size: u32,
+            serial_number: *mut u32,
+            max_component_length: *mut u32,
+            flags: *mut u32,
+            fs_name: *mut u16,
+            fs_name_size: u32,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut volume = [0u16; 261];
+    if unsafe { GetVolumePathNameW(wide.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) } == 0 {
+        return None;
+    }
+    let mut fs_name = [0u16; 261];
+    let ok = unsafe {
+        GetVolumeInformationW(
+            volume.as_ptr(),
+            null_mut(),
+            0,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            fs_name.as_mut_ptr(),
+            fs_name.len() as u32,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+    let text = |buf: &[u16]| String::from_utf16_lossy(&buf[..buf.iter().position(|&c| c == 0).unwrap_or(buf.len())]);
+    Some((text(&volume), text(&fs_name)))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub(crate) fn containing_mount(_path: &Path) -> Option<(String, String)> {
+    None
+}
+
+// Dehydrated cloud files (OneDrive/iCloud/Nextcloud) are fetched from the network
+// the moment their contents are read, so callers check this before hashing or previewing.
+#[cfg(windows)]
+pub(crate) fn is_placeholder(path: &Path, metadata: &fs::Metadata) -> bool {
+    const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+
+    let attrs = metadata.file_attributes();
+    attrs & (FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS) != 0
+        || has_placeholder_suffix(path)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn is_placeholder(path: &Path, metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt as MacMetadataExt;
+    const SF_DATALESS: u32 = 0x40000000;
+
+    metadata.st_flags() & SF_DATALESS != 0 || has_placeholder_suffix(path)
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub(crate) fn is_placeholder(path: &Path, _metadata: &fs::Metadata) -> bool {
+    has_placeholder_suffix(path)
+}
+
+// Nextcloud/ownCloud "suffix" virtual files are empty stand-ins next to where the
+// real file will be downloaded.
+fn has_placeholder_suffix(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("nextcloud" | "owncloud")
+    )
+}
+
+// Explorer hides whatever carries the hidden or system attribute and pays no
+// attention to a leading dot; everywhere else the dot is what hides a file.
+// The .DS_Store and ._ files Finder leaves on shared drives are hidden too.
+#[cfg(windows)]
+pub(crate) fn is_hidden(name: &str, metadata: &fs::Metadata) -> bool {
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+    metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+        || name == ".DS_Store"
+        || name.starts_with("._")
+}
+
+// Finder also hides anything with the UF_HIDDEN flag (`chflags hidden`).
+#[cfg(target_os = "macos")]
+pub(crate) fn is_hidden(name: &str, metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt as MacMetadataExt;
+    const UF_HIDDEN: u32 = 0x8000;
+
+    name.starts_with('.') || metadata.st_flags() & UF_HIDDEN != 0
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub(crate) fn is_hidden(name: &str, _metadata: &fs::Metadata) -> bool {
+    name.starts_with('.')
+}
+
+pub(crate) fn get_file_entry(path: &Path) -> Option<FileEntry> {
     let metadata = fs::symlink_metadata(path).ok()?;
-    let name = path.file_name()?.to_string_lossy().to_string();
+    let name = rawpath::encode(path.file_name()?);
     let is_symlink = metadata.is_symlink();
 
     let real_metadata = if is_symlink {
@@ -94,6 +332,15 @@ fn get_file_entry(path: &Path) -> Option<FileEntry> {
             .unwrap_or(metadata.len())
     };
 
+    let disk_size = if is_dir {
+        0
+    } else {
+        real_metadata
+            .as_ref()
+            .map(|m| allocated_size(path, m))
+            .unwrap_or(0)
+    };
+
     let modified = metadata
         .modified()
         .ok()
@@ -115,12 +362,18 @@ fn get_file_entry(path: &Path) -> Option<FileEntry> {
             .map(|e| e.to_string_lossy().to_lowercase())
     };
 
+    let hidden = is_hidden(&name, &metadata);
+
+    let placeholder = !is_dir && is_placeholder(path, &metadata);
+
     #[cfg(unix)]
-    let hidden = name.starts_with('.');
-    #[cfg(windows)]
-    let hidden = metadata.file_attributes() & 0x2 != 0;
-    #[cfg(not(any(unix, windows)))]
-    let hidden = name.starts_with('.');
+    let executable = real_metadata
+        .as_ref()
+        .is_some_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0);
+    #[cfg(not(unix))]
+    let executable = false;
+    let category = classify_entry(extension.as_deref(), is_dir, executable);
+    let launch = if is_dir { None } else { read_shortcut(path) };
 
     #[cfg(unix)]
     let (owner, group) = (metadata.uid(), metadata.gid());
@@ -129,21 +382,31 @@ fn get_file_entry(path: &Path) -> Option<FileEntry> {
 
     Some(FileEntry {
         name,
-        path: path.to_string_lossy().to_string(),
+        path: rawpath::encode_path(path),
         is_dir,
         is_symlink,
         size,
+        disk_size,
         modified,
         permissions,
         owner,
         group,
+        owner_name: user_name(owner),
+        group_name: group_name(group),
         extension,
         hidden,
+        placeholder,
+        category,
+        emblems: Vec::new(),
+        launch,
+        columns: BTreeMap::new(),
+        size_text: if is_dir { String::new() } else { format_size(size) },
+        modified_text: format_date(modified),
     })
 }
 
 pub fn read_directory(path: &str, show_hidden: bool) -> Result<DirectoryContents, String> {
-    let dir_path = PathBuf::from(path);
+    let dir_path = rawpath::decode(path);
 
     if !dir_path.exists() {
         return Err(format!("Path does not exist: {}", path));
@@ -167,6 +430,8 @@ pub fn read_directory(path: &str, show_hidden: bool) -> Result<DirectoryContents
         .filter(|entry| show_hidden || !entry.hidden)
         .collect();
 
+    emblems::annotate(&dir_path, &mut entries);
+
     entries.sort_by(|a, b| {
         match (a.is_dir, b.is_dir) {
             (true, false) => std::cmp::Ordering::Less,
@@ -177,8 +442,9 @@ pub fn read_directory(path: &str, show_hidden: bool) -> Result<DirectoryContents
 
     let total_items = entries.len();
     let total_size: u64 = entries.iter().map(|e| e.size).sum();
+    let total_disk_size: u64 = entries.iter().map(|e| e.disk_size).sum();
 
-    let parent = dir_path.parent().map(|p| p.to_string_lossy().to_string());
+    let parent = dir_path.parent().map(rawpath::encode_path);
 
     Ok(DirectoryContents {
         path: path.to_string(),
@@ -186,24 +452,147 @@ pub fn read_directory(path: &str, show_hidden: bool) -> Result<DirectoryContents
         entries,
         total_items,
         total_size,
+        total_disk_size,
+        total_size_text: format_size(total_size),
     })
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrawlOptions {
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub same_filesystem: bool,
+    #[serde(default)]
+    pub background: bool,
+}
+
 pub fn crawl_directory(root: &str, max_depth: Option<usize>) -> Vec<FileEntry> {
-    let walker = WalkDir::new(root)
-        .skip_hidden(false)
-        .max_depth(max_depth.unwrap_or(usize::MAX))
-        .parallelism(jwalk::Parallelism::RayonNewPool(num_cpus::get()));
+    crawl_directory_with_options(
+        root,
+        &CrawlOptions {
+            max_depth,
+            ..Default::default()
+        },
+    )
+}
 
-    walker
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter_map(|entry| get_file_entry(&entry.path()))
-        .collect()
+pub fn crawl_directory_with_options(root: &str, options: &CrawlOptions) -> Vec<FileEntry> {
+    crate::crawler::crawl(root, options).flatten().collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeCount {
+    pub kind: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiProperties {
+    pub count: usize,
+    pub file_count: usize,
+    pub dir_count: usize,
+    pub total_size: u64,
+    pub permissions: Option<String>,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    pub types: Vec<TypeCount>,
+    pub oldest_modified: Option<i64>,
+    pub newest_modified: Option<i64>,
+    pub errors: Vec<String>,
+    // Folders on slow mounts whose contents were not walked; the counts and
+    // total size leave them out.
+    #[serde(default)]
+    pub skipped_dirs: Vec<String>,
+}
+
+fn common_value<T: PartialEq + Clone>(values: &[T]) -> Option<T> {
+    let first = values.first()?;
+    values.iter().all(|v| v == first).then(|| first.clone())
+}
+
+pub fn get_multi_properties(paths: &[String]) -> MultiProperties {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in paths {
+        match get_file_entry(&rawpath::decode(path)) {
+            Some(entry) => entries.push(entry),
+            None => errors.push(format!("{}: Failed to get metadata", path)),
+        }
+    }
+
+    let mut file_count = 0;
+    let mut dir_count = 0;
+    let mut total_size = 0;
+    let mut skipped_dirs = Vec::new();
+
+    for entry in &entries {
+        if entry.is_dir && !entry.is_symlink && !crate::mounts::behavior_for(&entry.path).recursive_sizes {
+            dir_count += 1;
+            skipped_dirs.push(entry.path.clone());
+        } else if entry.is_dir && !entry.is_symlink {
+            let descendants = crawl_directory(&entry.path, None);
+            dir_count += 1;
+            for child in descendants.iter().skip(1) {
+                if child.is_dir {
+                    dir_count += 1;
+                } else {
+                    file_count += 1;
+                    total_size += child.size;
+                }
+            }
+        } else if entry.is_dir {
+            dir_count += 1;
+        } else {
+            file_count += 1;
+            total_size += entry.size;
+        }
+    }
+
+    let mut types: Vec<TypeCount> = Vec::new();
+    for entry in &entries {
+        let kind = if entry.is_dir {
+            "folder".to_string()
+        } else {
+            entry.extension.clone().unwrap_or_else(|| "file".to_string())
+        };
+        match types.iter_mut().find(|t| t.kind == kind) {
+            Some(t) => t.count += 1,
+            None => types.push(TypeCount { kind, count: 1 }),
+        }
+    }
+    types.sort_by_key(|t| std::cmp::Reverse(t.count));
+
+    // The leading type character differs between files and folders, so only the
+    // rwx part is compared.
+    let permissions: Vec<String> = entries.iter().map(|e| e.permissions[1..].to_string()).collect();
+    let owners: Vec<u32> = entries.iter().map(|e| e.owner).collect();
+    let groups: Vec<u32> = entries.iter().map(|e| e.group).collect();
+
+    MultiProperties {
+        count: entries.len(),
+        file_count,
+        dir_count,
+        total_size,
+        permissions: common_value(&permissions),
+        owner: common_value(&owners),
+        group: common_value(&groups),
+        types,
+        oldest_modified: entries.iter().map(|e| e.modified).min(),
+        newest_modified: entries.iter().map(|e| e.modified).max(),
+        errors,
+        skipped_dirs,
+    }
 }
 
-pub fn get_file_preview(path: &str, max_bytes: usize) -> Result<FilePreview, String> {
-    let file_path = PathBuf::from(path);
+// `encoding` overrides the detected text encoding, e.g. "Shift_JIS".
+pub fn get_file_preview(
+    path: &str,
+    max_bytes: usize,
+    allow_download: bool,
+    encoding: Option<&str>,
+) -> Result<FilePreview, String> {
+    let file_path = rawpath::decode(path);
 
     if !file_path.exists() {
         return Err("File does not exist".to_string());
@@ -220,46 +609,178 @@ pub fn get_file_preview(path: &str, max_bytes: usize) -> Result<FilePreview, Str
         .extension()
         .map(|e| e.to_string_lossy().to_lowercase());
 
+    if !allow_download && is_placeholder(&file_path, &metadata) {
+        return Ok(FilePreview {
+            path: path.to_string(),
+            preview_type: PreviewType::Auto,
+            size: metadata.len(),
+            text_content: None,
+            hex_content: None,
+            html_content: None,
+            encoding: None,
+            text_stats: None,
+            truncated: false,
+            extension,
+            placeholder: true,
+            torrent: None,
+            json: None,
+            table: None,
+            notebook: None,
+            database: None,
+        });
+    }
+
     let preview_type = match extension.as_deref() {
         Some("rs" | "py" | "js" | "ts" | "jsx" | "tsx" | "svelte" | "vue" |
-             "html" | "css" | "scss" | "sass" | "json" | "yaml" | "yml" |
-             "toml" | "xml" | "md" | "txt" | "sh" | "bash" | "zsh" |
+             "html" | "css" | "scss" | "sass" | "yaml" | "yml" |
+             "toml" | "xml" | "txt" | "sh" | "bash" | "zsh" |
              "c" | "cpp" | "h" | "hpp" | "go" | "java" | "kt" | "swift" |
              "rb" | "php" | "sql" | "lua" | "vim" | "conf" | "ini" |
              "dockerfile" | "makefile" | "cmake") => PreviewType::Code,
         Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "ico" | "bmp" | "tiff" | "avif") => PreviewType::Image,
         Some("exe" | "bin" | "so" | "dylib" | "dll" | "o" | "a") => PreviewType::Hex,
+        Some("torrent" | "magnet") => PreviewType::Torrent,
+        Some("json" | "jsonl" | "ndjson" | "geojson") => PreviewType::Json,
+        Some("csv" | "tsv") => PreviewType::Table,
+        Some("md" | "markdown" | "mdown" | "mkd") => PreviewType::Markdown,
+        Some("ipynb") => PreviewType::Notebook,
+        Some("db" | "sqlite" | "sqlite3" | "db3") => PreviewType::Database,
         _ => PreviewType::Auto,
     };
 
-    let content = fs::read(&file_path)
+    // These parse or render the whole file, so they're kept by content;
+    // plain previews are quicker to redo than to look up. Databases are left
+    // out, as their journal lives beside them.
+    if !matches!(
+        preview_type,
+        PreviewType::Json | PreviewType::Table | PreviewType::Markdown | PreviewType::Notebook | PreviewType::Torrent
+    ) {
+        return read_preview(path, &file_path, &metadata, extension, preview_type, max_bytes, encoding);
+    }
+    // Markdown links, in notebook cells too, resolve against the file's folder.
+    let base = match preview_type {
+        PreviewType::Markdown | PreviewType::Notebook => file_path.parent().map(|p| p.display().to_string()).unwrap_or_default(),
+        _ => String::new(),
+    };
+    let variant = format!(
+        "preview:{}:{}:{}:{}",
+        extension.as_deref().unwrap_or(""),
+        max_bytes,
+        encoding.unwrap_or(""),
+        base
+    );
+    contentcache::cached_json(&file_path, &variant, || {
+        read_preview(path, &file_path, &metadata, extension, preview_type, max_bytes, encoding)
+    })
+    .map(|preview| FilePreview {
+        path: path.to_string(),
+        ..preview
+    })
+}
+
+fn read_preview(
+    path: &str,
+    file_path: &Path,
+    metadata: &fs::Metadata,
+    extension: Option<String>,
+    preview_type: PreviewType,
+    max_bytes: usize,
+    encoding: Option<&str>,
+) -> Result<FilePreview, String> {
+    // Structured previews fall back to the plain text/hex preview below when
+    // the file doesn't parse.
+    let (mut torrent, mut json, mut table, mut notebook, mut database) = (None, None, None, None, None);
+    let (mut text_content, mut truncated) = (None, false);
+    match preview_type {
+        PreviewType::Torrent => torrent = read_torrent(file_path).ok(),
+        PreviewType::Json => {
+            if let Ok((tree, pretty, text_truncated)) = json_preview(file_path, max_bytes) {
+                truncated = text_truncated || tree.truncated;
+                text_content = Some(pretty);
+                json = Some(tree);
+            }
+        }
+        PreviewType::Table => {
+            table = table_preview(file_path).ok();
+            truncated = table.as_ref().is_some_and(|t| t.truncated);
+        }
+        PreviewType::Notebook => {
+            notebook = notebook_preview(file_path).ok();
+            truncated = notebook.as_ref().is_some_and(|n| n.truncated);
+        }
+        PreviewType::Database => {
+            database = preview_sqlite(file_path, None, None).ok();
+            truncated = database.as_ref().is_some_and(|d| d.truncated);
+        }
+        _ => {}
+    }
+    let structured = torrent.is_some() || json.is_some() || table.is_some() || notebook.is_some() || database.is_some();
+    if structured {
+        return Ok(FilePreview {
+            path: path.to_string(),
+            preview_type,
+            size: metadata.len(),
+            text_content,
+            hex_content: None,
+            html_content: None,
+            encoding: None,
+            text_stats: None,
+            truncated,
+            extension,
+            placeholder: false,
+            torrent,
+            json,
+            table,
+            notebook,
+            database,
+        });
+    }
+
+    let content = fs::read(file_path)
         .map_err(|e| format!("Failed to read file: {}", e))?;
 
     let truncated = content.len() > max_bytes;
     let preview_bytes: Vec<u8> = content.into_iter().take(max_bytes).collect();
 
-    let is_text = !preview_bytes.iter().take(8192).any(|&b| b == 0);
+    // An explicit encoding means the user knows it's text, NUL bytes or not.
+    let is_text = encoding.is_some()
+        || encoding::has_utf16_bom(&preview_bytes)
+        || !preview_bytes.iter().take(8192).any(|&b| b == 0);
+    let decode = || encoding::decode(&preview_bytes, encoding, !truncated);
 
-    let (final_type, text_content, hex_content) = match preview_type {
-        PreviewType::Code => {
-            let text = String::from_utf8_lossy(&preview_bytes).to_string();
-            (PreviewType::Code, Some(text), None)
+    let (final_type, decoded, hex_content) = match preview_type {
+        PreviewType::Code | PreviewType::Json | PreviewType::Table | PreviewType::Notebook => {
+            (PreviewType::Code, Some(decode()?), None)
         }
+        PreviewType::Markdown => (PreviewType::Markdown, Some(decode()?), None),
         PreviewType::Image => (PreviewType::Image, None, None),
         PreviewType::Hex => {
             let hex = bytes_to_hex(&preview_bytes);
             (PreviewType::Hex, None, Some(hex))
         }
-        PreviewType::Auto => {
+        PreviewType::Auto | PreviewType::Torrent | PreviewType::Database => {
             if is_text {
-                let text = String::from_utf8_lossy(&preview_bytes).to_string();
-                (PreviewType::Code, Some(text), None)
+                (PreviewType::Code, Some(decode()?), None)
             } else {
                 let hex = bytes_to_hex(&preview_bytes);
                 (PreviewType::Hex, None, Some(hex))
             }
         }
     };
+    let (text_content, encoding, text_stats) = match decoded {
+        Some(decoded) => {
+            let stats = text_stats(&decoded.text, decoded.bom);
+            (Some(decoded.text), Some(decoded.encoding), Some(stats))
+        }
+        None => (None, None, None),
+    };
+
+    let html_content = match final_type {
+        PreviewType::Markdown => text_content
+            .as_deref()
+            .map(|text| markdown::render(text, file_path.parent().unwrap_or(Path::new("")))),
+        _ => None,
+    };
 
     Ok(FilePreview {
         path: path.to_string(),
@@ -267,8 +788,17 @@ pub fn get_file_preview(path: &str, max_bytes: usize) -> Result<FilePreview, Str
         size: metadata.len(),
         text_content,
         hex_content,
+        html_content,
+        encoding,
+        text_stats,
         truncated,
         extension,
+        placeholder: false,
+        torrent: None,
+        json: None,
+        table: None,
+        notebook: None,
+        database: None,
     })
 }
 
@@ -311,6 +841,12 @@ pub enum PreviewType {
     Image,
     Hex,
     Auto,
+    Torrent,
+    Json,
+    Table,
+    Markdown,
+    Notebook,
+    Database,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -320,27 +856,29 @@ pub struct FilePreview {
     pub size: u64,
     pub text_content: Option<String>,
     pub hex_content: Option<String>,
+    // Sanitized HTML rendered from Markdown.
+    #[serde(default)]
+    pub html_content: Option<String>,
+    // Detected or requested encoding of `text_content`.
+    #[serde(default)]
+    pub encoding: Option<String>,
+    #[serde(default)]
+    pub text_stats: Option<TextStats>,
     pub truncated: bool,
     pub extension: Option<String>,
+    pub placeholder: bool,
+    #[serde(default)]
+    pub torrent: Option<TorrentInfo>,
+    #[serde(default)]
+    pub json: Option<JsonPreview>,
+    #[serde(default)]
+    pub table: Option<TablePreview>,
+    #[serde(default)]
+    pub notebook: Option<NotebookPreview>,
+    #[serde(default)]
+    pub database: Option<SqlitePreview>,
 }
 
 pub fn get_home_dir() -> Option<String> {
     dirs::home_dir().map(|p| p.to_string_lossy().to_string())
 }
-
-pub fn format_size(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size = bytes as f64;
-    let mut unit_idx = 0;
-
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_idx += 1;
-    }
-
-    if unit_idx == 0 {
-        format!("{} {}", bytes, UNITS[unit_idx])
-    } else {
-        format!("{:.1} {}", size, UNITS[unit_idx])
-    }
-}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Datelike, Local, Locale, TimeZone};
+use pure_rust_locales::locale_match;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+pub const SIZE_UNITS_SETTING: &str = "size_units";
+pub const DATE_STYLE_SETTING: &str = "date_style";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SizeUnits {
+    // Powers of 1024, labelled KB/MB/GB as most file managers do.
+    #[default]
+    Iec,
+    // Powers of 1000, matching what drive makers print on the box.
+    Si,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DateStyle {
+    // "Mar 04 13:37" this year, "Mar 04, 2023" before.
+    #[default]
+    Short,
+    // The locale's own date and time representation.
+    Locale,
+    // "5 minutes ago", falling back to Short past a month.
+    Relative,
+    Iso,
+}
+
+struct Config {
+    size_units: SizeUnits,
+    date_style: DateStyle,
+    locale: Locale,
+}
+
+static CONFIG: RwLock<Config> = RwLock::new(Config {
+    size_units: SizeUnits::Iec,
+    date_style: DateStyle::Short,
+    locale: Locale::POSIX,
+});
+
+// "de-DE" from the OS becomes chrono's "de_DE"; unknown locales stay POSIX.
+fn system_locale() -> Locale {
+    sys_locale::get_locale()
+        .and_then(|name| Locale::try_from(name.replace('-', "_").as_str()).ok())
+        .unwrap_or(Locale::POSIX)
+}
+
+pub fn init(size_units: Option<SizeUnits>, date_style: Option<DateStyle>) {
+    let mut config = CONFIG.write().unwrap();
+    config.size_units = size_units.unwrap_or_default();
+    config.date_style = date_style.unwrap_or_default();
+    config.locale = system_locale();
+}
+
+pub fn set_size_units(size_units: SizeUnits) {
+    CONFIG.write().unwrap().size_units = size_units;
+}
+
+pub fn set_date_style(date_style: DateStyle) {
+    CONFIG.write().unwrap().date_style = date_style;
+}
+
+pub fn format_size_with(bytes: u64, units: SizeUnits, locale: Locale) -> String {
+    const LABELS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
+    let step = match units {
+        SizeUnits::Iec => 1024.0,
+        SizeUnits::Si => 1000.0,
+    };
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= step && unit < LABELS.len() - 1 {
+        size /= step;
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{} {}", bytes, LABELS[0]);
+    }
+    let decimal_point = locale_match!(locale => LC_NUMERIC::DECIMAL_POINT);
+    format!("{:.1} {}", size, LABELS[unit]).replacen('.', decimal_point, 1)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let config = CONFIG.read().unwrap();
+    format_size_with(bytes, config.size_units, config.locale)
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    match count {
+        1 => format!("1 {} ago", unit),
+        n => format!("{} {}s ago", n, unit),
+    }
+}
+
+fn format_short(date: &DateTime<Local>, now: &DateTime<Local>, locale: Locale) -> String {
+    let pattern = match date.year() == now.year() {
+        true => "%b %d %H:%M",
+        false => "%b %d, %Y",
+    };
+    date.format_localized(pattern, locale).to_string()
+}
+
+pub fn format_date_with(timestamp: i64, style: DateStyle, locale: Locale, now: DateTime<Local>) -> String {
+    let Some(date) = Local.timestamp_opt(timestamp, 0).single() else {
+        return String::new();
+    };
+    match style {
+        DateStyle::Short => format_short(&date, &now, locale),
+        DateStyle::Locale => date.format_localized("%x %X", locale).to_string(),
+        DateStyle::Iso => date.format("%Y-%m-%d %H:%M").to_string(),
+        DateStyle::Relative => {
+            let seconds = (now - date).num_seconds();
+            match seconds {
+                // Clock skew or a file from the future.
+                ..0 => format_short(&date, &now, locale),
+                0..60 => "just now".to_string(),
+                60..3600 => plural(seconds / 60, "minute"),
+                3600..86_400 => plural(seconds / 3600, "hour"),
+                86_400..172_800 => "yesterday".to_string(),
+                172_800..2_592_000 => plural(seconds / 86_400, "day"),
+                _ => format_short(&date, &now, locale),
+            }
+        }
+    }
+}
+
+pub fn format_date(timestamp: i64) -> String {
+    let config = CONFIG.read().unwrap();
+    format_date_with(timestamp, config.date_style, config.locale, Local::now())
+}
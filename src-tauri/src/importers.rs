@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::downloads::decode_percent;
+
+// Bookmarks and a couple of view settings taken over from the file manager
+// someone used before. Reading only builds a preview; favorites are added and
+// the view changed once it has been confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileManager {
+    Nautilus,
+    Dolphin,
+    Thunar,
+    DoubleCommander,
+    TotalCommander,
+}
+
+// Field and direction use the names of the view's sort settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedSort {
+    pub field: String,
+    pub direction: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPreview {
+    pub app: FileManager,
+    // The configuration files that were found and read.
+    pub sources: Vec<String>,
+    // Folders not yet among the favorites.
+    pub favorites: Vec<String>,
+    // Bookmarked folders that don't exist on this machine, left out.
+    pub missing: Vec<String>,
+    pub show_hidden: Option<bool>,
+    pub sort: Option<ImportedSort>,
+}
+
+#[derive(Default)]
+struct Found {
+    sources: Vec<PathBuf>,
+    folders: Vec<String>,
+    show_hidden: Option<bool>,
+    sort: Option<ImportedSort>,
+}
+
+impl Found {
+    // The file's content, noting it as a source when it could be read.
+    fn read(&mut self, path: &Path) -> Option<String> {
+        let content = fs::read_to_string(path).ok()?;
+        self.sources.push(path.to_path_buf());
+        Some(content)
+    }
+}
+
+pub fn import_from(app: FileManager, existing: &[String]) -> Result<ImportPreview, String> {
+    let found = match app {
+        FileManager::Nautilus => nautilus(),
+        FileManager::Dolphin => dolphin(),
+        FileManager::Thunar => thunar(),
+        FileManager::DoubleCommander => double_commander(),
+        FileManager::TotalCommander => total_commander(),
+    };
+    if found.sources.is_empty() && found.show_hidden.is_none() && found.sort.is_none() {
+        return Err(format!("No {} configuration found", name(app)));
+    }
+
+    let mut seen: HashSet<String> = existing.iter().map(|p| normalize(p)).collect();
+    let (mut favorites, mut missing) = (Vec::new(), Vec::new());
+    for folder in found.folders {
+        let key = normalize(&folder);
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        if Path::new(&key).is_dir() {
+            favorites.push(key);
+        } else {
+            missing.push(key);
+        }
+    }
+    Ok(ImportPreview {
+        app,
+        sources: found.sources.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+        favorites,
+        missing,
+        show_hidden: found.show_hidden,
+        sort: found.sort,
+    })
+}
+
+fn name(app: FileManager) -> &'static str {
+    match app {
+        FileManager::Nautilus => "Nautilus",
+        FileManager::Dolphin => "Dolphin",
+        FileManager::Thunar => "Thunar",
+        FileManager::DoubleCommander => "Double Commander",
+        FileManager::TotalCommander => "Total Commander",
+    }
+}
+
+// Bookmarks often end in a slash; favorites don't.
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() || trimmed.ends_with(':') {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn sort(field: &str, descending: bool) -> ImportedSort {
+    ImportedSort {
+        field: field.to_string(),
+        direction: if descending { "desc" } else { "asc" }.to_string(),
+    }
+}
+
+fn local_path(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix("file://")?;
+    // file://host/path names a local file only when the host is empty or us.
+    let path = &rest[rest.find('/')?..];
+    Some(decode_percent(path))
+}
+
+// GTK's bookmarks, shared by Nautilus and Thunar: one `URI [label]` per line.
+fn gtk_bookmarks(found: &mut Found) {
+    let mut files = Vec::new();
+    if let Some(config) = dirs::config_dir() {
+        files.push(config.join("gtk-3.0").join("bookmarks"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        files.push(home.join(".gtk-bookmarks"));
+    }
+    for file in files {
+        let Some(content) = found.read(&file) else {
+            continue;
+        };
+        let uris = content.lines().filter_map(|l| l.split_whitespace().next());
+        found.folders.extend(uris.filter_map(local_path));
+    }
+}
+
+fn gsettings(schema: &str, key: &str) -> Option<String> {
+    Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().trim_matches('\'').to_string())
+}
+
+fn nautilus() -> Found {
+    let mut found = Found::default();
+    gtk_bookmarks(&mut found);
+    // Nautilus follows the GTK file chooser's hidden-files toggle.
+    found.show_hidden = ["org.gtk.gtk4.Settings.FileChooser", "org.gtk.Settings.FileChooser"]
+        .iter()
+        .find_map(|schema| gsettings(schema, "show-hidden"))
+        .map(|v| v == "true");
+    let descending = gsettings("org.gnome.nautilus.preferences", "default-sort-in-reverse-order").as_deref() == Some("true");
+    found.sort = gsettings("org.gnome.nautilus.preferences", "default-sort-order").and_then(|order| {
+        let field = match order.as_str() {
+            "name" => "name",
+            "size" => "size",
+            "type" => "extension",
+            "mtime" => "modified",
+            _ => return None,
+        };
+        Some(sort(field, descending))
+    });
+    found
+}
+
+// The value of every `attribute="..."` in the document, in order.
+fn xml_attributes<'a>(content: &'a str, attribute: &str) -> Vec<&'a str> {
+    let needle = format!("{}=\"", attribute);
+    content
+        .match_indices(&needle)
+        .filter_map(|(i, _)| {
+            let rest = &content[i + needle.len()..];
+            rest.find('"').map(|end| &rest[..end])
+        })
+        .collect()
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// `key=value` lines of one `[section]` of an INI or KDE config file.
+fn ini_section<'a>(content: &'a str, section: &str) -> Vec<(&'a str, &'a str)> {
+    let header = format!("[{}]", section);
+    let mut in_section = false;
+    let mut entries = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line.eq_ignore_ascii_case(&header);
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_section) {
+            entries.push((key.trim(), value.trim()));
+        }
+    }
+    entries
+}
+
+fn ini_value<'a>(content: &'a str, section: &str, key: &str) -> Option<&'a str> {
+    ini_section(content, section)
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+fn dolphin() -> Found {
+    let mut found = Found::default();
+    if let Some(places) = dirs::data_dir().map(|d| d.join("user-places.xbel")) {
+        if let Some(content) = found.read(&places) {
+            let hrefs = xml_attributes(&content, "href");
+            found.folders.extend(hrefs.into_iter().filter_map(|h| local_path(&xml_unescape(h))));
+        }
+    }
+
+    // The view every folder starts with unless it has its own.
+    let global = dirs::data_dir().map(|d| d.join("dolphin").join("view_properties").join("global").join(".directory"));
+    if let Some(content) = global.and_then(|g| found.read(&g)) {
+        found.show_hidden = ini_value(&content, "Settings", "HiddenFilesShown").map(|v| v == "true");
+        let descending = ini_value(&content, "Dolphin", "SortOrder") == Some("1");
+        found.sort = ini_value(&content, "Dolphin", "SortRole")
+            .and_then(|role| match role {
+                "text" => Some("name"),
+                "size" => Some("size"),
+                "modificationtime" => Some("modified"),
+                "type" => Some("extension"),
+                _ => None,
+            })
+            .map(|field| sort(field, descending));
+    }
+    found
+}
+
+// The value of the property `name` in an xfconf channel file.
+fn xfconf_value<'a>(content: &'a str, name: &str) -> Option<&'a str> {
+    let needle = format!("name=\"{}\"", name);
+    let line = content.lines().find(|l| l.contains(&needle))?;
+    xml_attributes(line, "value").into_iter().next()
+}
+
+fn thunar() -> Found {
+    let mut found = Found::default();
+    gtk_bookmarks(&mut found);
+
+    let channel = dirs::config_dir().map(|c| c.join("xfce4").join("xfconf").join("xfce-perchannel-xml").join("thunar.xml"));
+    if let Some(content) = channel.and_then(|c| found.read(&c)) {
+        found.show_hidden = xfconf_value(&content, "last-show-hidden").map(|v| v == "true");
+        let descending = xfconf_value(&content, "last-sort-order") == Some("GTK_SORT_DESCENDING");
+        found.sort = xfconf_value(&content, "last-sort-column")
+            .and_then(|column| match column {
+                "THUNAR_COLUMN_NAME" => Some("name"),
+                "THUNAR_COLUMN_SIZE" => Some("size"),
+                "THUNAR_COLUMN_DATE_MODIFIED" => Some("modified"),
+                "THUNAR_COLUMN_TYPE" => Some("extension"),
+                _ => None,
+            })
+            .map(|field| sort(field, descending));
+    }
+    found
+}
+
+// `~` and $HOME are the only variables worth resolving; the rest point into
+// the program's own folder.
+fn expand_home(path: &str) -> Option<String> {
+    let home = dirs::home_dir()?;
+    let home = home.to_string_lossy();
+    if let Some(rest) = path.strip_prefix('~') {
+        return Some(format!("{}{}", home, rest));
+    }
+    if let Some(rest) = path.strip_prefix("$HOME").or_else(|| path.strip_prefix("%$HOME%")) {
+        return Some(format!("{}{}", home, rest));
+    }
+    Some(path.to_string())
+}
+
+fn double_commander() -> Found {
+    let mut found = Found::default();
+    let Some(config) = dirs::config_dir().map(|c| c.join("doublecmd").join("doublecmd.xml")) else {
+        return found;
+    };
+    let Some(content) = found.read(&config) else {
+        return found;
+    };
+
+    // Hotlist entries look like <HotDir Name="..." Path="/some/where/" .../>.
+    let hotlist = content
+        .find("<DirectoryHotList")
+        .map(|start| &content[start..])
+        .map(|rest| &rest[..rest.find("</DirectoryHotList>").unwrap_or(rest.len())])
+        .unwrap_or_default();
+    for line in hotlist.lines().filter(|l| l.contains("<HotDir")) {
+        let Some(path) = xml_attributes(line, "Path").into_iter().next() else {
+            continue;
+        };
+        if let Some(path) = expand_home(&xml_unescape(path)).filter(|p| Path::new(p).is_absolute()) {
+            found.folders.push(path);
+        }
+    }
+
+    found.show_hidden = content
+        .find("<ShowSystemFiles>")
+        .map(|start| &content[start + "<ShowSystemFiles>".len()..])
+        .and_then(|rest| rest.split('<').next())
+        .map(|v| v.trim().eq_ignore_ascii_case("true"));
+    found
+}
+
+// wincmd.ini, where $COMMANDER_INI says or in the roaming profile; under
+// Wine, in the prefix's profile.
+fn total_commander_ini() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = std::env::var_os("COMMANDER_INI").map(PathBuf::from).into_iter().collect();
+    if cfg!(windows) {
+        candidates.extend(dirs::config_dir().map(|c| c.join("GHISLER").join("wincmd.ini")));
+    } else if let (Some(prefix), Ok(user)) = (wine_prefix(), std::env::var("USER")) {
+        let profile = prefix.join("drive_c").join("users").join(user);
+        candidates.push(profile.join("AppData").join("Roaming").join("GHISLER").join("wincmd.ini"));
+        candidates.push(profile.join("Application Data").join("GHISLER").join("wincmd.ini"));
+    }
+    candidates
+}
+
+fn wine_prefix() -> Option<PathBuf> {
+    std::env::var_os("WINEPREFIX")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".wine")))
+}
+
+// A `C:\dir` path from Total Commander, as seen from here. Under Wine the
+// drive letters are links in the prefix's dosdevices.
+fn windows_path(path: &str) -> Option<String> {
+    if cfg!(windows) {
+        return Some(path.to_string());
+    }
+    let (drive, rest) = path.split_once(":\\").or_else(|| path.split_once(':'))?;
+    if drive.len() != 1 {
+        return None;
+    }
+    let device = wine_prefix()?.join("dosdevices").join(format!("{}:", drive.to_ascii_lowercase()));
+    let root = fs::canonicalize(device).ok()?;
+    let local = rest.split('\\').filter(|p| !p.is_empty()).fold(root, |dir, part| dir.join(part));
+    Some(local.to_string_lossy().to_string())
+}
+
+fn total_commander() -> Found {
+    let mut found = Found::default();
+    let Some(content) = total_commander_ini().iter().find_map(|ini| found.read(ini)) else {
+        return found;
+    };
+
+    // The Ctrl+D menu: cdN is a `cd <dir>` command, or just the dir.
+    for (key, value) in ini_section(&content, "DirMenu") {
+        if !key.to_ascii_lowercase().starts_with("cd") {
+            continue;
+        }
+        let dir = value.strip_prefix("cd ").unwrap_or(value).trim().trim_matches('"');
+        if dir.is_empty() || dir.contains('%') {
+            continue;
+        }
+        if let Some(path) = windows_path(dir) {
+            found.folders.push(path);
+        }
+    }
+
+    found.show_hidden = ini_value(&content, "Configuration", "ShowHiddenSystem").map(|v| v == "1");
+    found
+}
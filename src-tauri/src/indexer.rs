@@ -1,13 +1,31 @@
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use rusqlite::{Connection, Result as SqliteResult};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection, ErrorCode, OptionalExtension, Result as SqliteResult, TransactionBehavior};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::fs_engine::crawl_directory;
+use crate::activity::{self, ActivityEvent, ActivityKind};
+use crate::classify::FileCategory;
+use crate::crawler;
+use crate::fs_engine::{containing_mount, crawl_directory_with_options, get_file_entry, CrawlOptions, FileEntry};
+use crate::jobs::JobHandle;
+use crate::metrics;
+use crate::rawpath;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SearchSource {
+    #[default]
+    Index,
+    Locate,
+    Spotlight,
+    Everything,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -16,6 +34,11 @@ pub struct SearchResult {
     pub is_dir: bool,
     pub hidden: bool,
     pub score: i64,
+    #[serde(default)]
+    pub source: SearchSource,
+    // Listed in the index but gone from the disk when the search ran.
+    #[serde(default)]
+    pub stale: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,9 +49,406 @@ pub struct IndexerStatus {
     pub elapsed_ms: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakdownEntry {
+    pub key: Option<String>,
+    pub count: usize,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexBreakdown {
+    pub root: String,
+    pub total_count: usize,
+    pub total_size: u64,
+    pub by_extension: Vec<BreakdownEntry>,
+    pub by_category: Vec<BreakdownEntry>,
+    pub by_directory: Vec<BreakdownEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum IndexOrder {
+    Largest,
+    Smallest,
+    Newest,
+    Oldest,
+    Name,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexFilter {
+    pub root: Option<String>,
+    pub extension: Option<String>,
+    #[serde(default)]
+    pub category: Option<FileCategory>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+    #[serde(default)]
+    pub include_dirs: bool,
+    #[serde(default)]
+    pub include_hidden: bool,
+    #[serde(default)]
+    pub empty_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub hidden: bool,
+    pub extension: Option<String>,
+    pub size: u64,
+    pub modified: i64,
+    pub category: Option<FileCategory>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReclaimCategory {
+    BuildArtifacts,
+    Dependencies,
+    Caches,
+    OldLargeFiles,
+    Duplicates,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReclaimItem {
+    pub path: String,
+    pub size: u64,
+    pub file_count: usize,
+    pub modified: i64,
+    pub related: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReclaimGroup {
+    pub category: ReclaimCategory,
+    pub total_size: u64,
+    pub items: Vec<ReclaimItem>,
+}
+
+pub const RECLAIMABLE_DIRS: &[(&str, ReclaimCategory)] = &[
+    ("node_modules", ReclaimCategory::Dependencies),
+    (".venv", ReclaimCategory::Dependencies),
+    ("target", ReclaimCategory::BuildArtifacts),
+    ("__pycache__", ReclaimCategory::BuildArtifacts),
+    (".pytest_cache", ReclaimCategory::BuildArtifacts),
+    (".mypy_cache", ReclaimCategory::BuildArtifacts),
+    (".gradle", ReclaimCategory::BuildArtifacts),
+    (".next", ReclaimCategory::BuildArtifacts),
+    (".parcel-cache", ReclaimCategory::BuildArtifacts),
+    (".cache", ReclaimCategory::Caches),
+];
+
+const OLD_FILE_AGE_SECS: i64 = 180 * 24 * 60 * 60;
+const OLD_FILE_MIN_SIZE: i64 = 50 * 1024 * 1024;
+const DUPLICATE_MIN_SIZE: i64 = 1024 * 1024;
+const RECLAIM_GROUP_LIMIT: usize = 50;
+
+// Rows under `root` are selected with `path > prefix AND path < upper` so the
+// UNIQUE index on `path` is used; '0' is the byte right after '/'.
+pub(crate) fn descendant_range(root: &str) -> (String, String) {
+    let trimmed = root.trim_end_matches('/');
+    let prefix = format!("{}/", trimmed);
+    let upper = format!("{}0", trimmed);
+    (prefix, upper)
+}
+
+pub const VALIDATE_SETTING: &str = "validate_search_results";
+
+// Results checked against the disk, from the top; nobody scrolls past these.
+const VALIDATED_RESULTS: usize = 100;
+
+// Off by default: on a slow network drive every search would wait on it.
+static VALIDATE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_validate_results(enabled: bool) {
+    VALIDATE.store(enabled, Ordering::Relaxed);
+}
+
+// Flags index results whose path no longer exists, so a search run after
+// files were deleted outside the app doesn't lead to them.
+pub fn flag_stale(results: &mut [SearchResult]) {
+    if !VALIDATE.load(Ordering::Relaxed) {
+        return;
+    }
+    for result in results
+        .iter_mut()
+        .filter(|r| r.source == SearchSource::Index)
+        .take(VALIDATED_RESULTS)
+    {
+        result.stale = std::fs::symlink_metadata(rawpath::decode(&result.path)).is_err();
+    }
+}
+
+// How long a connection waits on another's lock before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+// SQLite attaches at most this many databases to one connection.
+const MAX_SHARDS: usize = 10;
+
+// How often writes fold the write-ahead log back and return free pages to the
+// disk. Every index run finishes with this too.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Free pages a database keeps before maintenance gives them back.
+const FREE_PAGES_KEPT: i64 = 1024;
+
+// Columns of `files`, listed so shards created at different times line up.
+const FILE_COLUMNS: &str = "id, path, name, is_dir, hidden, parent_path, extension, size, modified, category";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexShard {
+    pub mount: String,
+    pub file_size: u64,
+    // An unplugged drive keeps its shard, left out of searches until it's back.
+    pub mounted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactReport {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+}
+
+// The tables each shard holds, created in `schema`.
+fn files_schema(schema: &str) -> String {
+    format!(
+        "
+        CREATE TABLE IF NOT EXISTS {s}.files (
+            id INTEGER PRIMARY KEY,
+            path TEXT UNIQUE NOT NULL,
+            name TEXT NOT NULL,
+            is_dir INTEGER NOT NULL,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            parent_path TEXT,
+            extension TEXT,
+            size INTEGER,
+            modified INTEGER,
+            category TEXT
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS {s}.files_fts USING fts5(
+            name,
+            path,
+            content='files',
+            content_rowid='id',
+            tokenize='trigram'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS {s}.files_ai AFTER INSERT ON files BEGIN
+            INSERT INTO files_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS {s}.files_ad AFTER DELETE ON files BEGIN
+            INSERT INTO files_fts(files_fts, rowid, name, path) VALUES('delete', old.id, old.name, old.path);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS {s}.files_au AFTER UPDATE ON files BEGIN
+            INSERT INTO files_fts(files_fts, rowid, name, path) VALUES('delete', old.id, old.name, old.path);
+            INSERT INTO files_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
+        END;
+
+        CREATE INDEX IF NOT EXISTS {s}.idx_files_parent ON files(parent_path);
+        CREATE INDEX IF NOT EXISTS {s}.idx_files_is_dir ON files(is_dir);
+        CREATE INDEX IF NOT EXISTS {s}.idx_files_extension ON files(extension);
+        ",
+        s = schema
+    )
+}
+
+// Databases on the connection holding a `files` table: main, then the shards.
+fn members(conn: &Connection) -> SqliteResult<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .filter(|name| name != "temp")
+        .collect();
+    Ok(names)
+}
+
+// Reads go through a `files` view over every attached shard. It's per
+// connection, so it's rebuilt whenever a shard is attached.
+fn refresh_view(conn: &Connection) -> SqliteResult<()> {
+    let union = members(conn)?
+        .iter()
+        .map(|s| format!("SELECT {} FROM {}.files", FILE_COLUMNS, s))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    conn.execute_batch(&format!(
+        "DROP VIEW IF EXISTS temp.files; CREATE TEMP VIEW files AS {};",
+        union
+    ))
+}
+
+// The mount holding `path`, or its nearest existing ancestor. Rows whose
+// mount can't be told share the "" shard.
+fn mount_of(path: &Path) -> String {
+    path.ancestors()
+        .find(|p| p.exists())
+        .and_then(containing_mount)
+        .map(|(mount, _)| mount)
+        .unwrap_or_default()
+}
+
+fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
+fn is_mounted(mount: &str) -> bool {
+    mount.is_empty() || containing_mount(Path::new(mount)).is_some_and(|(m, _)| m == mount)
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+// Includes the write-ahead log, which can hold a large share of recent writes.
+fn database_file_size(path: &Path) -> u64 {
+    let mut wal = path.to_path_buf().into_os_string();
+    wal.push("-wal");
+    [path.to_path_buf(), PathBuf::from(wal)]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn parent_of(path: &str) -> Option<String> {
+    PathBuf::from(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+// An entry and the index of the shard it's written to.
+type Routed = (FileEntry, usize);
+
+// Picks the shard for each row of one write. A folder on another device than
+// its parent starts a new mount; everything else follows its parent. Shards
+// can't be attached inside a transaction, so everything is routed first.
+struct Router<'a> {
+    indexer: &'a Indexer,
+    conn: &'a Connection,
+    schemas: Vec<String>,
+    by_mount: HashMap<String, usize>,
+    dirs: HashMap<String, (usize, Option<u64>)>,
+}
+
+impl<'a> Router<'a> {
+    fn new(indexer: &'a Indexer, conn: &'a Connection) -> Self {
+        Self {
+            indexer,
+            conn,
+            schemas: Vec::new(),
+            by_mount: HashMap::new(),
+            dirs: HashMap::new(),
+        }
+    }
+
+    fn shard(&mut self, mount: String) -> SqliteResult<usize> {
+        if let Some(&index) = self.by_mount.get(&mount) {
+            return Ok(index);
+        }
+        self.conn
+            .execute("INSERT OR IGNORE INTO main.shards (mount) VALUES (?1)", [&mount])?;
+        let id: i64 = self
+            .conn
+            .query_row("SELECT id FROM main.shards WHERE mount = ?1", [&mount], |row| row.get(0))?;
+        // Past the attach limit, rows stay in the main database.
+        let schema = self
+            .indexer
+            .attach(self.conn, id)?
+            .unwrap_or_else(|| "main".to_string());
+        let index = match self.schemas.iter().position(|s| *s == schema) {
+            Some(index) => index,
+            None => {
+                self.schemas.push(schema);
+                self.schemas.len() - 1
+            }
+        };
+        self.by_mount.insert(mount, index);
+        Ok(index)
+    }
+
+    fn route(&mut self, path: &str, is_dir: bool) -> SqliteResult<usize> {
+        let parent = parent_of(path);
+        let inherited = parent.as_ref().and_then(|p| self.dirs.get(p)).copied();
+        let (index, device) = match inherited {
+            Some((index, _)) if !is_dir => return Ok(index),
+            Some((index, parent_device)) => {
+                let device = device_of(&rawpath::decode(path));
+                if device.is_some() && parent_device.is_some() && device != parent_device {
+                    (self.shard(mount_of(&rawpath::decode(path)))?, device)
+                } else {
+                    (index, device.or(parent_device))
+                }
+            }
+            None => {
+                let decoded = rawpath::decode(path);
+                let index = self.shard(mount_of(&decoded))?;
+                if !is_dir {
+                    if let Some(parent) = parent {
+                        let device = device_of(&rawpath::decode(&parent));
+                        self.dirs.insert(parent, (index, device));
+                    }
+                    return Ok(index);
+                }
+                (index, device_of(&decoded))
+            }
+        };
+        self.dirs.insert(path.to_string(), (index, device));
+        Ok(index)
+    }
+
+    // Rebuilds the `files` view once routing has attached new shards.
+    fn refresh(&self) -> SqliteResult<()> {
+        refresh_view(self.conn)
+    }
+
+    // The schema for each index `route` returned.
+    fn finish(self) -> SqliteResult<Vec<String>> {
+        refresh_view(self.conn)?;
+        Ok(self.schemas)
+    }
+}
+
+// The index in use, for file operations that keep it current without a
+// handle to the app's state.
+static ACTIVE: RwLock<Option<Indexer>> = RwLock::new(None);
+
+pub fn set_active(indexer: Option<Indexer>) {
+    *ACTIVE.write().unwrap() = indexer;
+}
+
+pub fn active() -> Option<Indexer> {
+    ACTIVE.read().unwrap().clone()
+}
+
+// Clones share the database and status, e.g. for a thread applying changes.
+// Rows live in one shard database per mount under `index/`, so a drive or a
+// whole-disk root goes away by dropping a file rather than DELETE and VACUUM
+// over every row.
+#[derive(Clone)]
 pub struct Indexer {
     db_path: PathBuf,
     status: Arc<Mutex<IndexerStatus>>,
+    maintained: Arc<Mutex<Instant>>,
+    crawling: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Indexer {
@@ -42,18 +462,33 @@ impl Indexer {
                 current_path: None,
                 elapsed_ms: 0,
             })),
+            maintained: Arc::new(Mutex::new(Instant::now())),
+            crawling: Arc::new(Mutex::new(HashSet::new())),
         };
 
         indexer.init_db()?;
+        indexer.migrate_legacy_rows()?;
         indexer.restore_status();
 
         Ok(indexer)
     }
 
-    fn get_connection(&self) -> SqliteResult<Connection> {
+    fn shard_dir(&self) -> PathBuf {
+        self.db_path.with_file_name("index")
+    }
+
+    fn shard_path(&self, id: i64) -> PathBuf {
+        self.shard_dir().join(format!("shard-{}.db", id))
+    }
+
+    fn open_main(&self) -> SqliteResult<Connection> {
         let conn = Connection::open(&self.db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // auto_vacuum only takes on a new database; older ones switch over in
+        // `compact`.
         conn.execute_batch(
             "
+            PRAGMA auto_vacuum = INCREMENTAL;
             PRAGMA journal_mode = WAL;
             PRAGMA synchronous = NORMAL;
             PRAGMA cache_size = -64000;
@@ -64,48 +499,91 @@ impl Indexer {
         Ok(conn)
     }
 
-    fn init_db(&self) -> SqliteResult<()> {
-        let conn = self.get_connection()?;
-
-        conn.execute_batch(
-            "
-            CREATE TABLE IF NOT EXISTS files (
-                id INTEGER PRIMARY KEY,
-                path TEXT UNIQUE NOT NULL,
-                name TEXT NOT NULL,
-                is_dir INTEGER NOT NULL,
-                hidden INTEGER NOT NULL DEFAULT 0,
-                parent_path TEXT,
-                extension TEXT,
-                size INTEGER,
-                modified INTEGER
-            );
-
-            CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
-                name,
-                path,
-                content='files',
-                content_rowid='id',
-                tokenize='trigram'
-            );
+    // The main database with the shard of every present mount attached.
+    fn get_connection(&self) -> SqliteResult<Connection> {
+        let conn = self.open_main()?;
+        let shards: Vec<(i64, String)> = {
+            let mut stmt = conn.prepare("SELECT id, mount FROM shards ORDER BY id")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        };
+        for (id, mount) in shards {
+            if self.shard_path(id).exists() && is_mounted(&mount) {
+                self.attach(&conn, id)?;
+            }
+        }
+        refresh_view(&conn)?;
+        Ok(conn)
+    }
 
-            CREATE TRIGGER IF NOT EXISTS files_ai AFTER INSERT ON files BEGIN
-                INSERT INTO files_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
-            END;
+    // Attaches shard `id`, creating it if needed, and returns its schema name.
+    fn attach(&self, conn: &Connection, id: i64) -> SqliteResult<Option<String>> {
+        let schema = format!("shard{}", id);
+        let attached = members(conn)?;
+        if attached.contains(&schema) {
+            return Ok(Some(schema));
+        }
+        if attached.len() > MAX_SHARDS {
+            tracing::warn!(shard = id, "too many index shards to attach");
+            return Ok(None);
+        }
+        let path = self.shard_path(id);
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        // Created on its own connection: here, `files` would name the view.
+        let shard = Connection::open(&path)?;
+        shard.busy_timeout(BUSY_TIMEOUT)?;
+        shard.execute_batch(&format!(
+            "PRAGMA auto_vacuum = INCREMENTAL;
+             PRAGMA journal_mode = WAL;
+             {}
+             CREATE INDEX IF NOT EXISTS idx_files_category ON files(category);",
+            files_schema("main")
+        ))?;
+        drop(shard);
+        conn.execute(&format!("ATTACH DATABASE ?1 AS {}", schema), [path.to_string_lossy()])?;
+        conn.execute_batch(&format!("PRAGMA {}.synchronous = NORMAL;", schema))?;
+        Ok(Some(schema))
+    }
 
-            CREATE TRIGGER IF NOT EXISTS files_ad AFTER DELETE ON files BEGIN
-                INSERT INTO files_fts(files_fts, rowid, name, path) VALUES('delete', old.id, old.name, old.path);
-            END;
+    // Runs `f` on one read snapshot of every shard. WAL lets reads go on while
+    // a crawl writes; a lock the busy timeout couldn't wait out, as during a
+    // checkpoint, is retried rather than turned into empty results.
+    fn read<T>(&self, f: impl Fn(&Connection) -> SqliteResult<T>) -> SqliteResult<T> {
+        let mut attempt = 0;
+        loop {
+            let result = self.get_connection().and_then(|conn| {
+                let tx = conn.unchecked_transaction()?;
+                let value = f(&tx)?;
+                tx.commit()?;
+                Ok(value)
+            });
+            match result {
+                Err(ref e) if is_busy(e) && attempt < READ_RETRIES => {
+                    attempt += 1;
+                    tracing::debug!(attempt, "index busy, retrying read");
+                    thread::sleep(RETRY_DELAY * attempt);
+                }
+                result => return result,
+            }
+        }
+    }
 
-            CREATE TRIGGER IF NOT EXISTS files_au AFTER UPDATE ON files BEGIN
-                INSERT INTO files_fts(files_fts, rowid, name, path) VALUES('delete', old.id, old.name, old.path);
-                INSERT INTO files_fts(rowid, name, path) VALUES (new.id, new.name, new.path);
-            END;
+    fn init_db(&self) -> SqliteResult<()> {
+        let conn = self.open_main()?;
 
-            CREATE INDEX IF NOT EXISTS idx_files_parent ON files(parent_path);
-            CREATE INDEX IF NOT EXISTS idx_files_is_dir ON files(is_dir);
-            CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension);
-            ",
+        // `files` in the main database only holds rows from before sharding,
+        // or ones that didn't fit in an attached shard.
+        conn.execute_batch(&files_schema("main"))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS shards (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                mount TEXT UNIQUE NOT NULL
+            );",
         )?;
 
         let _ = conn.execute_batch(
@@ -114,13 +592,89 @@ impl Indexer {
             ",
         );
 
+        conn.execute_batch(activity::SCHEMA)?;
+
+        // Rows indexed before categories existed stay NULL until re-indexed.
+        let _ = conn.execute_batch("ALTER TABLE files ADD COLUMN category TEXT;");
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_files_category ON files(category);")?;
+
+        Ok(())
+    }
+
+    // Indexes from before sharding kept every row in the main database; they
+    // move to their mount's shard once.
+    fn migrate_legacy_rows(&self) -> SqliteResult<()> {
+        let mut conn = self.get_connection()?;
+        let rows: Vec<(String, bool)> = {
+            let mut stmt = conn.prepare("SELECT path, is_dir FROM main.files ORDER BY path")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get::<_, i32>(1)? != 0)))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        };
+        if rows.is_empty() {
+            return Ok(());
+        }
+        tracing::info!(rows = rows.len(), "moving index rows into shards");
+
+        let mut router = Router::new(self, &conn);
+        let routed: Vec<usize> = rows
+            .iter()
+            .map(|(path, is_dir)| router.route(path, *is_dir))
+            .collect::<SqliteResult<_>>()?;
+        let schemas = router.finish()?;
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for ((path, _), index) in rows.iter().zip(routed) {
+            let schema = &schemas[index];
+            if schema == "main" {
+                continue;
+            }
+            let columns = FILE_COLUMNS.trim_start_matches("id, ");
+            tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO {}.files ({c}) SELECT {c} FROM main.files WHERE path = ?1",
+                schema,
+                c = columns
+            ))?
+            .execute([path])?;
+            tx.prepare_cached("DELETE FROM main.files WHERE path = ?1")?.execute([path])?;
+        }
+        tx.commit()?;
+        drop(conn);
+        // Not on a connection with the view: VACUUM would index it.
+        self.open_main()?.execute_batch("VACUUM;")?;
         Ok(())
     }
 
     pub fn index_directory(&self, root: &str, max_depth: Option<usize>) {
+        self.index_directory_with_options(
+            root,
+            CrawlOptions {
+                max_depth,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn index_directory_with_options(&self, root: &str, options: CrawlOptions) {
+        self.spawn_index(root, options, None);
+    }
+
+    pub fn index_directory_as_job(&self, root: &str, options: CrawlOptions, job: JobHandle) {
+        self.spawn_index(root, options, Some(job));
+    }
+
+    // Set from before the crawl thread starts until it has written its last row.
+    pub fn is_crawling(&self, root: &str) -> bool {
+        self.crawling.lock().unwrap().contains(root)
+    }
+
+    fn spawn_index(&self, root: &str, mut options: CrawlOptions, job: Option<JobHandle>) {
         let root = root.to_string();
-        let db_path = self.db_path.clone();
+        let indexer = self.clone();
         let status = self.status.clone();
+        self.crawling.lock().unwrap().insert(root.clone());
 
         thread::spawn(move || {
             let start = Instant::now();
@@ -132,74 +686,151 @@ impl Indexer {
                 s.indexed_count = 0;
             }
 
-            let entries = crawl_directory(&root, max_depth);
+            if let Some(ref job) = job {
+                let _ = job.start();
+                options.background = job.is_background();
+            }
+            let mut interrupted = None;
+            let mut indexed = 0;
+            tracing::info!(root = %root, "indexing started");
 
-            if let Ok(conn) = Connection::open(&db_path) {
-                let _ = conn.execute_batch(
-                    "PRAGMA synchronous = OFF;
-                     PRAGMA journal_mode = MEMORY;
-                     PRAGMA temp_store = MEMORY;"
-                );
+            let conn = indexer.get_connection();
+            if let Err(ref e) = conn {
+                tracing::error!("Failed to open index database: {}", e);
+            }
+            if let Ok(conn) = conn {
+                let _ = conn.execute_batch("PRAGMA temp_store = MEMORY;");
+                let mut router = Router::new(&indexer, &conn);
+                // Everything under a hidden folder counts as hidden, so search
+                // leaves out the contents of .git or AppData along with the folder.
+                // The crawl lists every folder before what it contains.
+                let mut hidden_dirs: HashSet<String> = HashSet::new();
+
+                // Entries are written as the crawl finds them, one transaction per
+                // chunk; a slow disk for the database slows the crawl down instead
+                // of piling up entries in memory.
+                const BATCH_SIZE: usize = 10000;
+                let mut crawl = crawler::crawl(&root, &options);
+                let mut chunk: Vec<FileEntry> = Vec::with_capacity(BATCH_SIZE);
+                let mut finished = false;
+                while !finished && interrupted.is_none() {
+                    while chunk.len() < BATCH_SIZE {
+                        match crawl.next() {
+                            Some(batch) => chunk.extend(batch),
+                            None => {
+                                finished = true;
+                                break;
+                            }
+                        }
+                    }
 
-                let mut stmt = conn.prepare(
-                    "INSERT OR REPLACE INTO files (path, name, is_dir, hidden, parent_path, extension, size, modified)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
-                ).ok();
-
-                if let Some(ref mut prepared_stmt) = stmt {
-                    const BATCH_SIZE: usize = 10000;
-                    let _ = conn.execute_batch("BEGIN TRANSACTION;");
-
-                    for (i, entry) in entries.iter().enumerate() {
-                        let parent = PathBuf::from(&entry.path)
-                            .parent()
-                            .map(|p| p.to_string_lossy().to_string());
-
-                        let _ = prepared_stmt.execute((
-                            &entry.path,
-                            &entry.name,
-                            entry.is_dir as i32,
-                            entry.hidden as i32,
-                            &parent,
-                            &entry.extension,
-                            entry.size as i64,
-                            entry.modified,
-                        ));
-
-                        if (i + 1) % BATCH_SIZE == 0 {
-                            let _ = conn.execute_batch("COMMIT; BEGIN TRANSACTION;");
-                            
-                            let mut s = status.lock().unwrap();
-                            s.indexed_count = i + 1;
-                            s.elapsed_ms = start.elapsed().as_millis() as u64;
+                    // Routing can attach shards, which can't happen inside the
+                    // transaction.
+                    let routed: SqliteResult<Vec<usize>> =
+                        chunk.iter().map(|e| router.route(&e.path, e.is_dir)).collect();
+                    let routed = match routed.and_then(|routed| router.refresh().map(|_| routed)) {
+                        Ok(routed) => routed,
+                        Err(e) => {
+                            tracing::error!("Failed to open index shards: {}", e);
+                            break;
                         }
+                    };
+                    // The journal stays in WAL mode so searches keep reading
+                    // their snapshot while the chunk is written.
+                    for schema in &router.schemas {
+                        let _ = conn.execute_batch(&format!("PRAGMA {}.synchronous = OFF;", schema));
                     }
+                    let inserts: Vec<String> = router
+                        .schemas
+                        .iter()
+                        .map(|s| {
+                            format!(
+                                "INSERT OR REPLACE INTO {}.files (path, name, is_dir, hidden, parent_path, extension, size, modified, category)
+                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                                s
+                            )
+                        })
+                        .collect();
 
+                    // Immediate, so a writer applying changes at the same time is
+                    // waited for instead of failing the chunk.
+                    let _ = conn.execute_batch("BEGIN IMMEDIATE;");
+                    for (entry, &shard) in chunk.iter().zip(&routed) {
+                        // A cancelled index keeps whatever was written so far.
+                        if let Some(ref job) = job {
+                            if let Err(e) = job.checkpoint() {
+                                interrupted = Some(e);
+                                break;
+                            }
+                            job.progress(indexed as u64, Some(&entry.path));
+                        }
+
+                        let parent = parent_of(&entry.path);
+                        let hidden = entry.hidden || parent.as_ref().is_some_and(|p| hidden_dirs.contains(p));
+                        // A hidden folder indexed on purpose still has its contents found.
+                        if hidden && entry.is_dir && entry.path != root {
+                            hidden_dirs.insert(entry.path.clone());
+                        }
+
+                        if let Ok(mut stmt) = conn.prepare_cached(&inserts[shard]) {
+                            let _ = stmt.execute((
+                                &entry.path,
+                                &entry.name,
+                                entry.is_dir as i32,
+                                hidden as i32,
+                                &parent,
+                                &entry.extension,
+                                entry.size as i64,
+                                entry.modified,
+                                entry.category.as_str(),
+                            ));
+                        }
+                        indexed += 1;
+                    }
                     let _ = conn.execute_batch("COMMIT;");
+                    chunk.clear();
+
+                    let mut s = status.lock().unwrap();
+                    s.indexed_count = indexed;
+                    s.elapsed_ms = start.elapsed().as_millis() as u64;
                 }
 
-                let _ = conn.execute_batch(
-                    "PRAGMA synchronous = NORMAL;
-                     PRAGMA journal_mode = WAL;"
-                );
+                for schema in &router.schemas {
+                    let _ = conn.execute_batch(&format!("PRAGMA {}.synchronous = NORMAL;", schema));
+                }
+                indexer.maintain(&conn);
             }
 
             {
                 let mut s = status.lock().unwrap();
                 s.is_running = false;
-                s.indexed_count = entries.len();
+                s.indexed_count = indexed;
                 s.elapsed_ms = start.elapsed().as_millis() as u64;
                 s.current_path = None;
+                tracing::info!(root = %root, indexed = s.indexed_count, elapsed_ms = s.elapsed_ms, "indexing finished");
+            }
+            indexer.crawling.lock().unwrap().remove(&root);
+
+            if let Some(job) = job {
+                job.finish(interrupted.map_or(Ok(()), Err));
             }
         });
     }
 
-    pub fn search_fts(&self, query: &str, limit: usize) -> Vec<SearchResult> {
-        let conn = match self.get_connection() {
-            Ok(c) => c,
-            Err(_) => return vec![],
-        };
+    // Full-text matches, or fuzzy ones when full-text finds nothing.
+    pub fn search(&self, query: &str, limit: usize, include_hidden: bool) -> Vec<SearchResult> {
+        let start = Instant::now();
+        let results = self.search_fts(query, limit, include_hidden);
+        if !results.is_empty() {
+            metrics::record_latency("search_fts", start.elapsed());
+            return results;
+        }
+        let results = self.search_fuzzy(query, limit, include_hidden);
+        metrics::record_latency("search_fuzzy", start.elapsed());
+        results
+    }
 
+    pub fn search_fts(&self, query: &str, limit: usize, include_hidden: bool) -> Vec<SearchResult> {
         let escaped_query = query
             .chars()
             .map(|c| match c {
@@ -210,63 +841,72 @@ impl Indexer {
 
         let fts_query = format!("\"{}\"*", escaped_query);
 
-        let mut stmt = match conn.prepare(
-            "SELECT f.name, f.path, f.is_dir, f.hidden
-             FROM files_fts fts
-             JOIN files f ON fts.rowid = f.id
-             WHERE files_fts MATCH ?1
-             ORDER BY bm25(files_fts) 
-             LIMIT ?2",
-        ) {
-            Ok(s) => s,
-            Err(_) => return vec![],
-        };
-
-        let results: Vec<SearchResult> = stmt
-            .query_map([&fts_query, &limit.to_string()], |row| {
-                Ok(SearchResult {
-                    name: row.get(0)?,
-                    path: row.get(1)?,
-                    is_dir: row.get::<_, i32>(2)? != 0,
-                    hidden: row.get::<_, i32>(3)? != 0,
-                    score: 0,
+        let results = self.read(|conn| {
+            // Ranks come from each shard's own FTS table and are merged here.
+            let arms: Vec<String> = members(conn)?
+                .iter()
+                .map(|s| {
+                    format!(
+                        "SELECT f.name, f.path, f.is_dir, f.hidden, bm25(files_fts) AS rank
+                         FROM {s}.files_fts
+                         JOIN {s}.files f ON files_fts.rowid = f.id
+                         WHERE files_fts MATCH ?1 AND (?3 OR f.hidden = 0)",
+                        s = s
+                    )
                 })
-            })
-            .ok()
-            .map(|iter| iter.filter_map(|r| r.ok()).collect())
-            .unwrap_or_default();
+                .collect();
+            let mut stmt = conn.prepare(&format!(
+                "SELECT name, path, is_dir, hidden FROM ({})
+                 ORDER BY rank
+                 LIMIT ?2",
+                arms.join(" UNION ALL ")
+            ))?;
+            let results = stmt
+                .query_map((&fts_query, limit as i64, include_hidden), |row| {
+                    Ok(SearchResult {
+                        name: row.get(0)?,
+                        path: row.get(1)?,
+                        is_dir: row.get::<_, i32>(2)? != 0,
+                        hidden: row.get::<_, i32>(3)? != 0,
+                        score: 0,
+                        source: SearchSource::Index,
+                        stale: false,
+                    })
+                })?
+                .collect();
+            results
+        });
 
-        results
+        results.unwrap_or_else(|e| {
+            tracing::warn!(query = %query, error = %e, "index search failed");
+            vec![]
+        })
     }
 
-    pub fn search_fuzzy(&self, query: &str, limit: usize) -> Vec<SearchResult> {
-        let conn = match self.get_connection() {
-            Ok(c) => c,
-            Err(_) => return vec![],
-        };
-
-        let mut stmt = match conn.prepare(
-            "SELECT name, path, is_dir, hidden FROM files 
-             WHERE name LIKE ?1 OR path LIKE ?1
-             LIMIT 5000",
-        ) {
-            Ok(s) => s,
-            Err(_) => return vec![],
-        };
-
+    pub fn search_fuzzy(&self, query: &str, limit: usize, include_hidden: bool) -> Vec<SearchResult> {
         let pattern = format!("%{}%", query);
-        let candidates: Vec<(String, String, bool, bool)> = stmt
-            .query_map([&pattern], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, i32>(2)? != 0,
-                    row.get::<_, i32>(3)? != 0,
-                ))
-            })
-            .ok()
-            .map(|iter| iter.filter_map(|r| r.ok()).collect())
-            .unwrap_or_default();
+        let candidates = self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, path, is_dir, hidden FROM files 
+                 WHERE (name LIKE ?1 OR path LIKE ?1) AND (?2 OR hidden = 0)
+                 LIMIT 5000",
+            )?;
+            let candidates: SqliteResult<Vec<(String, String, bool, bool)>> = stmt
+                .query_map((&pattern, include_hidden), |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i32>(2)? != 0,
+                        row.get::<_, i32>(3)? != 0,
+                    ))
+                })?
+                .collect();
+            candidates
+        });
+        let candidates = candidates.unwrap_or_else(|e| {
+            tracing::warn!(query = %query, error = %e, "index search failed");
+            vec![]
+        });
 
         let matcher = SkimMatcherV2::default();
         let mut results: Vec<SearchResult> = candidates
@@ -280,6 +920,8 @@ impl Indexer {
                     is_dir,
                     hidden,
                     score,
+                    source: SearchSource::Index,
+                    stale: false,
                 })
             })
             .collect();
@@ -289,32 +931,761 @@ impl Indexer {
         results
     }
 
+    // Brings the rows for `paths` in line with the disk: paths that exist are
+    // written, with the whole subtree when a folder is new to the index, and
+    // the rest are removed along with anything below them. Returns the number
+    // of rows touched.
+    pub fn apply_changes(&self, paths: &[String]) -> SqliteResult<usize> {
+        let mut conn = self.get_connection()?;
+
+        let mut removed: Vec<&String> = Vec::new();
+        let mut written: Vec<(Routed, Vec<Routed>)> = Vec::new();
+        // Folders change whenever their contents do, so only files count as modified.
+        let mut events: Vec<(&String, ActivityKind, bool)> = Vec::new();
+        let schemas = {
+            let mut router = Router::new(self, &conn);
+            let mut known = conn.prepare("SELECT is_dir FROM files WHERE path = ?1")?;
+            for path in paths {
+                let stored: Option<bool> = known.query_row([path], |row| row.get::<_, i32>(0)).optional()?.map(|d| d != 0);
+                let Some(entry) = get_file_entry(&rawpath::decode(path)) else {
+                    if let Some(is_dir) = stored {
+                        events.push((path, ActivityKind::Deleted, is_dir));
+                    }
+                    removed.push(path);
+                    continue;
+                };
+                match stored {
+                    None => events.push((path, ActivityKind::Created, entry.is_dir)),
+                    Some(_) if !entry.is_dir => events.push((path, ActivityKind::Modified, false)),
+                    Some(_) => {}
+                }
+                let shard = router.route(&entry.path, entry.is_dir)?;
+                // A folder moved in from outside arrives as a single event.
+                let mut subtree = Vec::new();
+                if entry.is_dir && stored.is_none() {
+                    for child in crawl_directory_with_options(path, &CrawlOptions::default()) {
+                        if child.path != entry.path {
+                            let child_shard = router.route(&child.path, child.is_dir)?;
+                            subtree.push((child, child_shard));
+                        }
+                    }
+                }
+                written.push(((entry, shard), subtree));
+            }
+            drop(known);
+            router.finish()?
+        };
+        let members = members(&conn)?;
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut touched = 0;
+        {
+            let mut parent_hidden = tx.prepare("SELECT hidden FROM files WHERE path = ?1")?;
+            let write = |entry: &FileEntry, shard: usize, hidden: bool| {
+                tx.prepare_cached(&format!(
+                    "INSERT INTO {}.files (path, name, is_dir, hidden, parent_path, extension, size, modified, category)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                     ON CONFLICT(path) DO UPDATE SET
+                        name = excluded.name, is_dir = excluded.is_dir, hidden = excluded.hidden,
+                        parent_path = excluded.parent_path, extension = excluded.extension,
+                        size = excluded.size, modified = excluded.modified, category = excluded.category",
+                    schemas[shard]
+                ))?
+                .execute((
+                    &entry.path,
+                    &entry.name,
+                    entry.is_dir as i32,
+                    hidden as i32,
+                    parent_of(&entry.path),
+                    &entry.extension,
+                    entry.size as i64,
+                    entry.modified,
+                    entry.category.as_str(),
+                ))
+            };
+
+            // A removed path's rows may sit in any shard.
+            for path in removed {
+                let (prefix, upper) = descendant_range(path);
+                for schema in &members {
+                    touched += tx
+                        .prepare_cached(&format!(
+                            "DELETE FROM {}.files WHERE path = ?1 OR (path > ?2 AND path < ?3)",
+                            schema
+                        ))?
+                        .execute((path, &prefix, &upper))?;
+                }
+            }
+
+            for ((entry, shard), subtree) in &written {
+                let inherited = parent_of(&entry.path)
+                    .and_then(|p| parent_hidden.query_row([p], |row| row.get::<_, i32>(0)).ok())
+                    .is_some_and(|h| h != 0);
+                let hidden = entry.hidden || inherited;
+                touched += write(entry, *shard, hidden)?;
+
+                let mut hidden_dirs: HashSet<String> = HashSet::new();
+                if hidden {
+                    hidden_dirs.insert(entry.path.clone());
+                }
+                for (child, child_shard) in subtree {
+                    let child_hidden =
+                        child.hidden || parent_of(&child.path).is_some_and(|p| hidden_dirs.contains(&p));
+                    if child_hidden && child.is_dir {
+                        hidden_dirs.insert(child.path.clone());
+                    }
+                    touched += write(child, *child_shard, child_hidden)?;
+                }
+            }
+        }
+        for (path, kind, is_dir) in events {
+            activity::record(&tx, path, kind, is_dir, None)?;
+        }
+        tx.commit()?;
+        if self.maintained.lock().unwrap().elapsed() >= MAINTENANCE_INTERVAL {
+            self.maintain(&conn);
+        }
+        Ok(touched)
+    }
+
+    // Moves the rows of renamed paths and everything below them to their new
+    // names in one transaction, instead of dropping them and crawling the
+    // folder again. Moves are applied together, so swaps and chains from one
+    // batch rename land where they should. Rows already at a target are
+    // replaced. Returns the number of rows moved.
+    pub fn rebase_paths(&self, moves: &[(String, String)]) -> SqliteResult<usize> {
+        let moves: Vec<&(String, String)> = moves.iter().filter(|(from, to)| from != to).collect();
+        if moves.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.get_connection()?;
+        let members = members(&conn)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut moved = 0;
+        // Rows being moved are parked under a leading \x01, which no real path
+        // starts with, so a target can be freed before anything moves into it.
+        for (from, _) in &moves {
+            let (prefix, upper) = descendant_range(from);
+            for schema in &members {
+                moved += tx.execute(
+                    &format!(
+                        "UPDATE {}.files SET path = char(1) || path
+                         WHERE path = ?1 OR (path > ?2 AND path < ?3)",
+                        schema
+                    ),
+                    (from, &prefix, &upper),
+                )?;
+            }
+        }
+        for (_, to) in &moves {
+            let (prefix, upper) = descendant_range(to);
+            for schema in &members {
+                tx.execute(
+                    &format!("DELETE FROM {}.files WHERE path = ?1 OR (path > ?2 AND path < ?3)", schema),
+                    (to, &prefix, &upper),
+                )?;
+            }
+        }
+        for (from, to) in &moves {
+            let (prefix, upper) = descendant_range(from);
+            let name = Path::new(to)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| to.clone());
+            for schema in &members {
+                tx.execute(
+                    &format!(
+                        "UPDATE {}.files SET
+                            path = ?1 || substr(path, length(?2) + 2),
+                            parent_path = CASE WHEN path = char(1) || ?2 THEN ?3
+                                ELSE ?1 || substr(parent_path, length(?2) + 1) END,
+                            name = CASE WHEN path = char(1) || ?2 THEN ?4 ELSE name END
+                         WHERE path = char(1) || ?2 OR (path > char(1) || ?5 AND path < char(1) || ?6)",
+                        schema
+                    ),
+                    (to, from, parent_of(to), &name, &prefix, &upper),
+                )?;
+            }
+        }
+
+        for (from, to) in &moves {
+            activity::record(&tx, to, ActivityKind::Moved, rawpath::decode(to).is_dir(), Some(from))?;
+        }
+
+        // A rename into or out of a dot name changes what's hidden below it;
+        // those folders are read again rather than patched row by row.
+        let mut reread = Vec::new();
+        {
+            let mut stored = tx.prepare("SELECT hidden FROM files WHERE path = ?1")?;
+            for (_, to) in &moves {
+                let Some(entry) = get_file_entry(&rawpath::decode(to)) else {
+                    continue;
+                };
+                let was_hidden = stored.query_row([to], |row| row.get::<_, i32>(0)).ok().map(|h| h != 0);
+                let inherited = parent_of(to)
+                    .and_then(|p| stored.query_row([p], |row| row.get::<_, i32>(0)).ok())
+                    .is_some_and(|h| h != 0);
+                if was_hidden.is_some_and(|h| h != (entry.hidden || inherited)) {
+                    reread.push(to.clone());
+                }
+            }
+        }
+        for to in &reread {
+            let (prefix, upper) = descendant_range(to);
+            for schema in &members {
+                tx.execute(
+                    &format!("DELETE FROM {}.files WHERE path = ?1 OR (path > ?2 AND path < ?3)", schema),
+                    (to, &prefix, &upper),
+                )?;
+            }
+        }
+        tx.commit()?;
+        drop(conn);
+        if !reread.is_empty() {
+            self.apply_changes(&reread)?;
+        }
+        Ok(moved)
+    }
+
+    // Brings the index in line after the app itself changed `paths`. A path
+    // that exists is only added when its folder is indexed already, so copying
+    // into a folder outside the index doesn't start indexing it.
+    pub fn record_changes(&self, paths: &[String]) -> SqliteResult<usize> {
+        let parents: Vec<String> = paths.iter().filter_map(|p| parent_of(p)).collect();
+        let indexed = self.indexed_dirs(&parents);
+        let paths: Vec<String> = paths
+            .iter()
+            .filter(|p| {
+                std::fs::symlink_metadata(rawpath::decode(p)).is_err()
+                    || parent_of(p).is_some_and(|parent| indexed.contains(&parent))
+            })
+            .cloned()
+            .collect();
+        if paths.is_empty() {
+            return Ok(0);
+        }
+        self.apply_changes(&paths)
+    }
+
+    // Which of `dirs` have been crawled, i.e. are listed in the index.
+    pub fn indexed_dirs(&self, dirs: &[String]) -> HashSet<String> {
+        let indexed = self.read(|conn| {
+            let mut stmt = conn.prepare("SELECT 1 FROM files WHERE path = ?1 AND is_dir = 1")?;
+            let mut indexed = HashSet::new();
+            for dir in dirs {
+                if stmt.exists([dir])? {
+                    indexed.insert(dir.clone());
+                }
+            }
+            Ok(indexed)
+        });
+        indexed.unwrap_or_default()
+    }
+
     pub fn get_status(&self) -> IndexerStatus {
         self.status.lock().unwrap().clone()
     }
 
     pub fn clear_index(&self) -> SqliteResult<()> {
-        let conn = self.get_connection()?;
+        let conn = self.open_main()?;
+        let ids = self.shard_ids(&conn, "SELECT id FROM shards", [])?;
         conn.execute_batch(
             "
+            DELETE FROM shards;
             DELETE FROM files;
             DELETE FROM files_fts;
+            DELETE FROM activity;
             VACUUM;
             ",
         )?;
+        drop(conn);
+        for id in ids {
+            self.remove_shard_file(id);
+        }
         Ok(())
     }
 
-    pub fn get_indexed_count(&self) -> usize {
-        let conn = match self.get_connection() {
-            Ok(c) => c,
-            Err(_) => return 0,
+    fn shard_ids<P: rusqlite::Params>(&self, conn: &Connection, sql: &str, params: P) -> SqliteResult<Vec<i64>> {
+        let mut stmt = conn.prepare(sql)?;
+        let ids = stmt.query_map(params, |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+        Ok(ids)
+    }
+
+    fn remove_shard_file(&self, id: i64) {
+        let path = self.shard_path(id);
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+
+    // Forgets everything indexed under `root`. Mounts at or below it lose
+    // their shard file; rows in the shards above are deleted.
+    pub fn remove_root(&self, root: &str) -> SqliteResult<()> {
+        let conn = self.get_connection()?;
+        let (prefix, upper) = descendant_range(root);
+        let dropped = self.shard_ids(
+            &conn,
+            "SELECT id FROM main.shards WHERE mount = ?1 OR (mount > ?2 AND mount < ?3)",
+            (root, &prefix, &upper),
+        )?;
+        let dropped_schemas: Vec<String> = dropped.iter().map(|id| format!("shard{}", id)).collect();
+        conn.execute(
+            "DELETE FROM main.activity WHERE path = ?1 OR (path > ?2 AND path < ?3)",
+            (root, &prefix, &upper),
+        )?;
+
+        for schema in members(&conn)?.iter().filter(|s| !dropped_schemas.contains(s)) {
+            conn.execute(
+                &format!("DELETE FROM {}.files WHERE path = ?1 OR (path > ?2 AND path < ?3)", schema),
+                (root, &prefix, &upper),
+            )?;
+        }
+        for id in &dropped {
+            conn.execute("DELETE FROM main.shards WHERE id = ?1", [id])?;
+        }
+        drop(conn);
+        for id in dropped {
+            self.remove_shard_file(id);
+        }
+        Ok(())
+    }
+
+    pub fn shards(&self) -> SqliteResult<Vec<IndexShard>> {
+        let conn = self.open_main()?;
+        let mut stmt = conn.prepare("SELECT id, mount FROM shards ORDER BY mount")?;
+        let shards = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .filter_map(|r| r.ok())
+            .map(|(id, mount)| IndexShard {
+                file_size: database_file_size(&self.shard_path(id)),
+                mounted: is_mounted(&mount),
+                mount,
+            })
+            .collect();
+        Ok(shards)
+    }
+
+    // Folds the write-ahead logs back into their databases and truncates them,
+    // and hands free pages past a small reserve back to the disk. Without it
+    // re-indexing the same roots grows the files on every run. Failures only
+    // leave the files larger, so they're logged and otherwise ignored.
+    fn maintain(&self, conn: &Connection) {
+        *self.maintained.lock().unwrap() = Instant::now();
+        if let Err(e) = activity::prune(conn) {
+            tracing::warn!("failed to prune the activity log: {}", e);
+        }
+        let Ok(schemas) = members(conn) else {
+            return;
         };
+        for schema in schemas {
+            let result = conn
+                .query_row(&format!("PRAGMA {}.freelist_count", schema), [], |row| row.get::<_, i64>(0))
+                .and_then(|free| {
+                    if free > FREE_PAGES_KEPT {
+                        conn.execute_batch(&format!(
+                            "PRAGMA {}.incremental_vacuum({});",
+                            schema,
+                            free - FREE_PAGES_KEPT
+                        ))?;
+                    }
+                    conn.execute_batch(&format!("PRAGMA {}.wal_checkpoint(TRUNCATE);", schema))
+                });
+            if let Err(e) = result {
+                tracing::warn!(schema = %schema, "index maintenance failed: {}", e);
+            }
+        }
+    }
+
+    // Rebuilds every database file to its smallest size, switching older ones
+    // over to incremental auto-vacuum on the way. Slow on a large index, and
+    // waits for a running crawl to finish writing.
+    pub fn compact(&self) -> SqliteResult<CompactReport> {
+        let before_bytes = self.database_size();
+        let ids = self.shard_ids(&self.open_main()?, "SELECT id FROM shards", [])?;
+        // Each file on its own connection: with the view in place, VACUUM
+        // would try to index it.
+        let mut files = vec![self.open_main()?];
+        for id in ids {
+            let path = self.shard_path(id);
+            if path.exists() {
+                let conn = Connection::open(&path)?;
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                files.push(conn);
+            }
+        }
+        // Merging the full-text index's segments first frees the pages that
+        // re-indexing left behind in it.
+        for conn in files {
+            conn.execute_batch(
+                "INSERT INTO files_fts(files_fts) VALUES('optimize');
+                 PRAGMA auto_vacuum = INCREMENTAL;
+                 VACUUM;
+                 PRAGMA wal_checkpoint(TRUNCATE);",
+            )?;
+        }
+        *self.maintained.lock().unwrap() = Instant::now();
+        Ok(CompactReport {
+            before_bytes,
+            after_bytes: self.database_size(),
+        })
+    }
 
-        conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
+    pub fn get_indexed_count(&self) -> usize {
+        self.read(|conn| conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0)))
             .unwrap_or(0)
     }
 
+    // Whether `path` is one of the index's databases or their journal files.
+    pub fn owns(&self, path: &Path) -> bool {
+        let main = path.parent() == self.db_path.parent()
+            && path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("hardbore_index.db"));
+        main || path.parent() == Some(self.shard_dir().as_path())
+    }
+
+    pub fn database_size(&self) -> u64 {
+        let shards = std::fs::read_dir(self.shard_dir())
+            .map(|dir| {
+                dir.filter_map(|e| e.ok())
+                    .filter_map(|e| e.metadata().ok())
+                    .map(|m| m.len())
+                    .sum()
+            })
+            .unwrap_or(0);
+        database_file_size(&self.db_path) + shards
+    }
+
+    // What changed under `root` since `since`, newest first.
+    pub fn activity_timeline(&self, root: &str, since: i64, limit: usize) -> SqliteResult<Vec<ActivityEvent>> {
+        self.read(|conn| activity::timeline(conn, root, since, limit))
+    }
+
+    pub fn get_breakdown(&self, root: &str) -> SqliteResult<IndexBreakdown> {
+        self.read(|conn| {
+            let (prefix, upper) = descendant_range(root);
+
+            let (total_count, total_size): (i64, i64) = conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0",
+                [&prefix, &upper],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+
+            let mut stmt = conn.prepare(
+                "SELECT extension, COUNT(*), COALESCE(SUM(size), 0) FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0
+                 GROUP BY extension
+                 ORDER BY SUM(size) DESC",
+            )?;
+            let by_extension = stmt
+                .query_map([&prefix, &upper], |row| {
+                    Ok(BreakdownEntry {
+                        key: row.get(0)?,
+                        count: row.get::<_, i64>(1)? as usize,
+                        total_size: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            let mut stmt = conn.prepare(
+                "SELECT category, COUNT(*), COALESCE(SUM(size), 0) FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0
+                 GROUP BY category
+                 ORDER BY SUM(size) DESC",
+            )?;
+            let by_category = stmt
+                .query_map([&prefix, &upper], |row| {
+                    Ok(BreakdownEntry {
+                        key: row.get(0)?,
+                        count: row.get::<_, i64>(1)? as usize,
+                        total_size: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            let mut stmt = conn.prepare(
+                "SELECT CASE WHEN instr(rest, '/') > 0 THEN substr(rest, 1, instr(rest, '/') - 1) END AS top,
+                        COUNT(*), COALESCE(SUM(size), 0)
+                 FROM (SELECT substr(path, ?3) AS rest, size FROM files
+                       WHERE path > ?1 AND path < ?2 AND is_dir = 0)
+                 GROUP BY top
+                 ORDER BY SUM(size) DESC",
+            )?;
+            let by_directory = stmt
+                .query_map(
+                    (&prefix, &upper, prefix.chars().count() as i64 + 1),
+                    |row| {
+                        Ok(BreakdownEntry {
+                            key: row.get(0)?,
+                            count: row.get::<_, i64>(1)? as usize,
+                            total_size: row.get::<_, i64>(2)? as u64,
+                        })
+                    },
+                )?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            Ok(IndexBreakdown {
+                root: root.to_string(),
+                total_count: total_count as usize,
+                total_size: total_size as u64,
+                by_extension,
+                by_category,
+                by_directory,
+            })
+        })
+    }
+
+    pub fn query(&self, order_by: IndexOrder, filter: &IndexFilter, limit: usize) -> SqliteResult<Vec<IndexedFile>> {
+        self.read(|conn| {
+
+            let mut conditions: Vec<&str> = Vec::new();
+            let mut params: Vec<SqlValue> = Vec::new();
+
+            if let Some(root) = &filter.root {
+                let (prefix, upper) = descendant_range(root);
+                conditions.push("f.path > ? AND f.path < ?");
+                params.push(SqlValue::Text(prefix));
+                params.push(SqlValue::Text(upper));
+            }
+            if let Some(ext) = &filter.extension {
+                conditions.push("f.extension = ?");
+                params.push(SqlValue::Text(ext.trim_start_matches('.').to_lowercase()));
+            }
+            if let Some(category) = filter.category {
+                conditions.push("f.category = ?");
+                params.push(SqlValue::Text(category.as_str().to_string()));
+            }
+            if let Some(min) = filter.min_size {
+                conditions.push("f.size >= ?");
+                params.push(SqlValue::Integer(min as i64));
+            }
+            if let Some(max) = filter.max_size {
+                conditions.push("f.size <= ?");
+                params.push(SqlValue::Integer(max as i64));
+            }
+            if let Some(after) = filter.modified_after {
+                conditions.push("f.modified >= ?");
+                params.push(SqlValue::Integer(after));
+            }
+            if let Some(before) = filter.modified_before {
+                conditions.push("f.modified < ?");
+                params.push(SqlValue::Integer(before));
+            }
+            if !filter.include_dirs {
+                conditions.push("f.is_dir = 0");
+            }
+            if !filter.include_hidden {
+                conditions.push("f.hidden = 0");
+            }
+            if filter.empty_only {
+                conditions.push(
+                    "((f.is_dir = 0 AND f.size = 0) OR
+                      (f.is_dir = 1 AND NOT EXISTS (SELECT 1 FROM files c WHERE c.parent_path = f.path)))",
+                );
+            }
+
+            let where_clause = if conditions.is_empty() {
+                String::new()
+            } else {
+                format!("WHERE {}", conditions.join(" AND "))
+            };
+
+            let order_clause = match order_by {
+                IndexOrder::Largest => "f.size DESC",
+                IndexOrder::Smallest => "f.size ASC",
+                IndexOrder::Newest => "f.modified DESC",
+                IndexOrder::Oldest => "f.modified ASC",
+                IndexOrder::Name => "f.name COLLATE NOCASE ASC",
+            };
+
+            params.push(SqlValue::Integer(limit as i64));
+
+            let sql = format!(
+                "SELECT f.name, f.path, f.is_dir, f.hidden, f.extension, f.size, f.modified, f.category
+                 FROM files f
+                 {}
+                 ORDER BY {}
+                 LIMIT ?",
+                where_clause, order_clause
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let results = stmt
+                .query_map(params_from_iter(params), |row| {
+                    Ok(IndexedFile {
+                        name: row.get(0)?,
+                        path: row.get(1)?,
+                        is_dir: row.get::<_, i32>(2)? != 0,
+                        hidden: row.get::<_, i32>(3)? != 0,
+                        extension: row.get(4)?,
+                        size: row.get::<_, Option<i64>>(5)?.unwrap_or(0) as u64,
+                        modified: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+                        category: row.get::<_, Option<String>>(7)?.as_deref().and_then(FileCategory::parse),
+                    })
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            Ok(results)
+        })
+    }
+
+    pub fn suggest_reclaim(&self, root: &str) -> SqliteResult<Vec<ReclaimGroup>> {
+        self.read(|conn| {
+            let (prefix, upper) = descendant_range(root);
+            let mut groups: Vec<ReclaimGroup> = Vec::new();
+
+            let names: Vec<&str> = RECLAIMABLE_DIRS.iter().map(|(n, _)| *n).collect();
+            let placeholders = vec!["?"; names.len()].join(", ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT f.path, f.name, f.modified FROM files f
+                 WHERE f.path > ? AND f.path < ? AND f.is_dir = 1 AND f.name IN ({})
+                   AND (f.name != 'target' OR EXISTS (
+                       SELECT 1 FROM files c WHERE c.path = f.parent_path || '/Cargo.toml'))
+                 ORDER BY f.path",
+                placeholders
+            ))?;
+
+            let mut params: Vec<SqlValue> = vec![SqlValue::Text(prefix.clone()), SqlValue::Text(upper.clone())];
+            params.extend(names.iter().map(|n| SqlValue::Text(n.to_string())));
+
+            let dirs: Vec<(String, String, i64)> = stmt
+                .query_map(params_from_iter(params), |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<i64>>(2)?.unwrap_or(0)))
+                })?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            // Nested matches (node_modules inside node_modules) are covered by their
+            // outermost ancestor, and rows are ordered by path so ancestors come first.
+            let mut kept: Vec<(String, String, i64)> = Vec::new();
+            for dir in dirs {
+                if kept.iter().any(|(p, _, _)| dir.0.starts_with(&format!("{}/", p))) {
+                    continue;
+                }
+                kept.push(dir);
+            }
+
+            let mut size_stmt = conn.prepare(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0",
+            )?;
+
+            for (path, name, modified) in kept {
+                let category = RECLAIMABLE_DIRS
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, c)| *c)
+                    .unwrap_or(ReclaimCategory::Caches);
+                let (dir_prefix, dir_upper) = descendant_range(&path);
+                let (count, size): (i64, i64) =
+                    size_stmt.query_row([&dir_prefix, &dir_upper], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                if count == 0 {
+                    continue;
+                }
+
+                let item = ReclaimItem {
+                    path,
+                    size: size as u64,
+                    file_count: count as usize,
+                    modified,
+                    related: vec![],
+                };
+                match groups.iter_mut().find(|g| g.category == category) {
+                    Some(group) => group.items.push(item),
+                    None => groups.push(ReclaimGroup {
+                        category,
+                        total_size: 0,
+                        items: vec![item],
+                    }),
+                }
+            }
+
+            let cutoff = chrono::Utc::now().timestamp() - OLD_FILE_AGE_SECS;
+            let mut stmt = conn.prepare(
+                "SELECT path, size, modified FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0 AND size >= ?3 AND modified < ?4
+                 ORDER BY size DESC
+                 LIMIT ?5",
+            )?;
+            let old_items: Vec<ReclaimItem> = stmt
+                .query_map(
+                    (&prefix, &upper, OLD_FILE_MIN_SIZE, cutoff, RECLAIM_GROUP_LIMIT as i64),
+                    |row| {
+                        Ok(ReclaimItem {
+                            path: row.get(0)?,
+                            size: row.get::<_, i64>(1)? as u64,
+                            file_count: 1,
+                            modified: row.get(2)?,
+                            related: vec![],
+                        })
+                    },
+                )?
+                .filter_map(|r| r.ok())
+                .collect();
+            if !old_items.is_empty() {
+                groups.push(ReclaimGroup {
+                    category: ReclaimCategory::OldLargeFiles,
+                    total_size: 0,
+                    items: old_items,
+                });
+            }
+
+            // Same name and size is only a hint; the frontend confirms with a hash
+            // before anything is removed.
+            let mut stmt = conn.prepare(
+                "SELECT name, size, COUNT(*), group_concat(path, char(10)), MAX(modified) FROM files
+                 WHERE path > ?1 AND path < ?2 AND is_dir = 0 AND size >= ?3
+                 GROUP BY name, size
+                 HAVING COUNT(*) > 1
+                 ORDER BY size * (COUNT(*) - 1) DESC
+                 LIMIT ?4",
+            )?;
+            let duplicate_items: Vec<ReclaimItem> = stmt
+                .query_map(
+                    (&prefix, &upper, DUPLICATE_MIN_SIZE, RECLAIM_GROUP_LIMIT as i64),
+                    |row| {
+                        let size = row.get::<_, i64>(1)? as u64;
+                        let count = row.get::<_, i64>(2)? as usize;
+                        let paths: String = row.get(3)?;
+                        let mut paths: Vec<String> = paths.split('\n').map(|p| p.to_string()).collect();
+                        paths.sort();
+                        let first = paths.remove(0);
+                        Ok(ReclaimItem {
+                            path: first,
+                            size: size * (count as u64 - 1),
+                            file_count: count,
+                            modified: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                            related: paths,
+                        })
+                    },
+                )?
+                .filter_map(|r| r.ok())
+                .collect();
+            if !duplicate_items.is_empty() {
+                groups.push(ReclaimGroup {
+                    category: ReclaimCategory::Duplicates,
+                    total_size: 0,
+                    items: duplicate_items,
+                });
+            }
+
+            for group in &mut groups {
+                group.items.sort_by_key(|i| std::cmp::Reverse(i.size));
+                group.total_size = group.items.iter().map(|i| i.size).sum();
+            }
+            groups.sort_by_key(|g| std::cmp::Reverse(g.total_size));
+
+            Ok(groups)
+        })
+    }
+
     fn restore_status(&self) {
         let count = self.get_indexed_count();
         if count > 0 {
@@ -0,0 +1,62 @@
+// Lowers or restores the I/O priority of the calling thread. Every platform
+// scopes this per thread, so a background job never slows down the threads
+// serving foreground directory reads.
+
+#[cfg(target_os = "linux")]
+pub fn set_current_thread_background(background: bool) {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    const IOPRIO_CLASS_BE: libc::c_int = 2;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const DEFAULT_LEVEL: libc::c_int = 4;
+
+    let priority = if background {
+        IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT
+    } else {
+        (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | DEFAULT_LEVEL
+    };
+    // `who == 0` targets the calling thread, the same as `ionice -c3 -p <tid>`.
+    unsafe {
+        libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority);
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn set_current_thread_background(background: bool) {
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_THREAD: libc::c_int = 1;
+    const IOPOL_DEFAULT: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+
+    extern "C" {
+        fn setiopolicy_np(iotype: libc::c_int, scope: libc::c_int, policy: libc::c_int) -> libc::c_int;
+    }
+
+    let policy = if background { IOPOL_THROTTLE } else { IOPOL_DEFAULT };
+    unsafe {
+        setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_THREAD, policy);
+    }
+}
+
+#[cfg(windows)]
+pub fn set_current_thread_background(background: bool) {
+    const THREAD_MODE_BACKGROUND_BEGIN: i32 = 0x0001_0000;
+    const THREAD_MODE_BACKGROUND_END: i32 = 0x0002_0000;
+
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn SetThreadPriority(thread: isize, priority: i32) -> i32;
+    }
+
+    let mode = if background {
+        THREAD_MODE_BACKGROUND_BEGIN
+    } else {
+        THREAD_MODE_BACKGROUND_END
+    };
+    unsafe {
+        SetThreadPriority(GetCurrentThread(), mode);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn set_current_thread_background(_background: bool) {}
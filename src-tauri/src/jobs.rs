@@ -0,0 +1,459 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::iopriority;
+use crate::rawpath;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JobKind {
+    Indexing,
+    Copy,
+    Move,
+    Download,
+}
+
+impl JobKind {
+    // Indexing is never something the user is waiting on.
+    pub fn default_background(self) -> bool {
+        self == JobKind::Indexing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+    // Left over from a previous run that ended without finishing the job.
+    Interrupted,
+}
+
+impl JobState {
+    pub fn is_active(self) -> bool {
+        matches!(self, JobState::Queued | JobState::Running | JobState::Paused)
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub title: String,
+    pub state: JobState,
+    pub processed: u64,
+    pub total: Option<u64>,
+    pub current: Option<String>,
+    pub error: Option<String>,
+    pub background: bool,
+    // Files and directories the job reads or writes, used to find jobs touching a device.
+    pub paths: Vec<String>,
+}
+
+pub type JobCallback = Arc<dyn Fn(&JobInfo) + Send + Sync>;
+
+#[derive(Default)]
+struct Flags {
+    paused: bool,
+    cancelled: bool,
+    background: bool,
+}
+
+#[derive(Default)]
+struct Control {
+    flags: Mutex<Flags>,
+    changed: Condvar,
+}
+
+type JobList = Arc<Mutex<Vec<JobInfo>>>;
+
+// Shared byte budget for all background jobs, refilled every second.
+#[derive(Default)]
+struct Throttle {
+    limit: Option<u64>,
+    window_start: Option<Instant>,
+    used: u64,
+}
+
+impl Throttle {
+    // Returns how long the caller has to wait before its bytes fit the budget.
+    fn reserve(&mut self, bytes: u64) -> Option<Duration> {
+        let limit = self.limit?;
+        let now = Instant::now();
+        let window_start = *self.window_start.get_or_insert(now);
+        let elapsed = now.duration_since(window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.window_start = Some(now);
+            self.used = 0;
+        }
+        // A single chunk larger than the whole budget still gets through on its own.
+        if self.used == 0 || self.used + bytes <= limit {
+            self.used += bytes;
+            None
+        } else {
+            Some(Duration::from_secs(1).saturating_sub(elapsed))
+        }
+    }
+}
+
+// Handed to the worker that performs a job. Workers report progress through it
+// and call `checkpoint` between units of work so pause and cancel take effect.
+pub struct JobHandle {
+    id: u64,
+    jobs: JobList,
+    control: Arc<Control>,
+    on_update: JobCallback,
+    last_update: Mutex<Instant>,
+    throttle: Arc<Mutex<Throttle>>,
+    applied_background: Mutex<Option<bool>>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut().find(|j| j.id == self.id)?;
+        f(job);
+        Some(job.clone())
+    }
+
+    fn notify(&self, info: Option<JobInfo>) {
+        if let Some(info) = info {
+            (self.on_update)(&info);
+        }
+    }
+
+    pub fn set_total(&self, total: Option<u64>) {
+        let info = self.modify(|j| j.total = total);
+        self.notify(info);
+    }
+
+    // Progress events are throttled; the final state is always reported by `finish`.
+    pub fn progress(&self, processed: u64, current: Option<&str>) {
+        let info = self.modify(|j| {
+            j.processed = processed;
+            j.current = current.map(|c| c.to_string());
+        });
+        let mut last_update = self.last_update.lock().unwrap();
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            *last_update = Instant::now();
+            drop(last_update);
+            self.notify(info);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.flags.lock().unwrap().cancelled
+    }
+
+    pub fn is_background(&self) -> bool {
+        self.control.flags.lock().unwrap().background
+    }
+
+    // Priority is applied to whichever thread runs the checkpoint, which is
+    // always the job's own worker.
+    fn apply_priority(&self, background: bool) {
+        let mut applied = self.applied_background.lock().unwrap();
+        if *applied != Some(background) {
+            iopriority::set_current_thread_background(background);
+            *applied = Some(background);
+        }
+    }
+
+    // Background jobs share the global byte budget; foreground jobs are never held back.
+    pub fn throttle(&self, bytes: u64) {
+        if !self.is_background() {
+            return;
+        }
+        loop {
+            let wait = self.throttle.lock().unwrap().reserve(bytes);
+            match wait {
+                Some(wait) if !self.is_cancelled() => std::thread::sleep(wait),
+                _ => return,
+            }
+        }
+    }
+
+    // Blocks while the job is paused and fails once it has been cancelled.
+    pub fn checkpoint(&self) -> Result<(), String> {
+        let mut flags = self.control.flags.lock().unwrap();
+        if flags.paused && !flags.cancelled {
+            let info = self.modify(|j| j.state = JobState::Paused);
+            self.notify(info);
+            while flags.paused && !flags.cancelled {
+                flags = self.control.changed.wait(flags).unwrap();
+            }
+            let info = self.modify(|j| j.state = JobState::Running);
+            self.notify(info);
+        }
+        if flags.cancelled {
+            return Err(CANCELLED.to_string());
+        }
+        let background = flags.background;
+        drop(flags);
+        self.apply_priority(background);
+        Ok(())
+    }
+
+    pub fn start(&self) -> Result<(), String> {
+        let info = self.modify(|j| j.state = JobState::Running);
+        self.notify(info);
+        self.checkpoint()
+    }
+
+    pub fn finish(self, result: Result<(), String>) {
+        let cancelled = self.is_cancelled();
+        // Worker threads may be reused, so leave them at normal priority.
+        if self.applied_background.lock().unwrap().is_some() {
+            iopriority::set_current_thread_background(false);
+        }
+        let info = self.modify(|j| {
+            j.current = None;
+            match result {
+                Ok(()) => j.state = JobState::Completed,
+                Err(_) if cancelled => j.state = JobState::Cancelled,
+                Err(e) => {
+                    tracing::warn!(id = j.id, kind = ?j.kind, "job failed: {}", e);
+                    j.state = JobState::Failed;
+                    j.error = Some(e);
+                }
+            }
+        });
+        self.notify(info);
+    }
+}
+
+#[derive(Clone)]
+pub struct JobManager {
+    jobs: JobList,
+    controls: Arc<Mutex<HashMap<u64, Arc<Control>>>>,
+    next_id: Arc<AtomicU64>,
+    throttle: Arc<Mutex<Throttle>>,
+    // Holds every background job, including ones started while it's set.
+    background_paused: Arc<AtomicBool>,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            controls: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+            throttle: Arc::new(Mutex::new(Throttle::default())),
+            background_paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn create(&self, kind: JobKind, title: &str, paths: Vec<String>, on_update: JobCallback) -> JobHandle {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let info = JobInfo {
+            id,
+            kind,
+            title: title.to_string(),
+            state: JobState::Queued,
+            processed: 0,
+            total: None,
+            current: None,
+            error: None,
+            background: kind.default_background(),
+            paths,
+        };
+        self.jobs.lock().unwrap().push(info);
+        self.handle(id, on_update)
+    }
+
+    // Lists a job from a previous run as interrupted. Its id is kept so that
+    // `reopen` can pick it up again, and new jobs are numbered after it.
+    pub fn restore(
+        &self,
+        id: u64,
+        kind: JobKind,
+        title: &str,
+        paths: Vec<String>,
+        processed: u64,
+        total: Option<u64>,
+    ) {
+        self.next_id.fetch_max(id + 1, Ordering::SeqCst);
+        self.jobs.lock().unwrap().push(JobInfo {
+            id,
+            kind,
+            title: title.to_string(),
+            state: JobState::Interrupted,
+            processed,
+            total,
+            current: None,
+            error: None,
+            background: kind.default_background(),
+            paths,
+        });
+    }
+
+    pub fn reopen(&self, id: u64, on_update: JobCallback) -> Result<JobHandle, String> {
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let job = jobs
+                .iter_mut()
+                .find(|j| j.id == id && j.state == JobState::Interrupted)
+                .ok_or("Job was not interrupted")?;
+            job.state = JobState::Queued;
+        }
+        Ok(self.handle(id, on_update))
+    }
+
+    // Marks an interrupted job as cancelled once its leftovers are cleaned up.
+    pub fn discard(&self, id: u64) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|j| j.id == id) {
+            job.state = JobState::Cancelled;
+        }
+    }
+
+    fn handle(&self, id: u64, on_update: JobCallback) -> JobHandle {
+        let info = self.get(id);
+        let control = Arc::new(Control::default());
+        {
+            let mut flags = control.flags.lock().unwrap();
+            flags.background = info.as_ref().is_some_and(|j| j.background);
+            flags.paused = flags.background && self.background_paused();
+        }
+        self.controls.lock().unwrap().insert(id, control.clone());
+        if let Some(info) = info {
+            on_update(&info);
+        }
+
+        JobHandle {
+            id,
+            jobs: self.jobs.clone(),
+            control,
+            on_update,
+            last_update: Mutex::new(Instant::now()),
+            throttle: self.throttle.clone(),
+            applied_background: Mutex::new(None),
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobInfo> {
+        self.jobs.lock().unwrap().iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn active(&self) -> Vec<JobInfo> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|j| j.state.is_active())
+            .cloned()
+            .collect()
+    }
+
+    // Active jobs with any path on or below `root`.
+    pub fn active_under(&self, root: &Path) -> Vec<JobInfo> {
+        self.active()
+            .into_iter()
+            .filter(|j| j.paths.iter().any(|p| rawpath::decode(p).starts_with(root)))
+            .collect()
+    }
+
+    pub fn has_active(&self) -> bool {
+        self.jobs.lock().unwrap().iter().any(|j| j.state.is_active())
+    }
+
+    fn with_control(&self, id: u64, f: impl FnOnce(&mut Flags)) -> Result<(), String> {
+        if self.get(id).is_none_or(|j| !j.state.is_active()) {
+            return Err("Job is not running".to_string());
+        }
+        let control = self
+            .controls
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or("Job is not running")?;
+        f(&mut control.flags.lock().unwrap());
+        control.changed.notify_all();
+        Ok(())
+    }
+
+    pub fn pause(&self, id: u64) -> Result<(), String> {
+        self.with_control(id, |f| f.paused = true)
+    }
+
+    pub fn resume(&self, id: u64) -> Result<(), String> {
+        self.with_control(id, |f| f.paused = false)
+    }
+
+    pub fn cancel(&self, id: u64) -> Result<(), String> {
+        self.with_control(id, |f| f.cancelled = true)
+    }
+
+    pub fn set_background(&self, id: u64, background: bool) -> Result<(), String> {
+        self.with_control(id, |f| f.background = background)?;
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|j| j.id == id) {
+            job.background = background;
+        }
+        Ok(())
+    }
+
+    pub fn background_paused(&self) -> bool {
+        self.background_paused.load(Ordering::SeqCst)
+    }
+
+    pub fn pause_background(&self, paused: bool) {
+        self.background_paused.store(paused, Ordering::SeqCst);
+        for job in self.active().into_iter().filter(|j| j.background) {
+            let _ = self.with_control(job.id, |f| f.paused = paused);
+        }
+    }
+
+    pub fn set_throttle(&self, bytes_per_second: Option<u64>) {
+        let mut throttle = self.throttle.lock().unwrap();
+        throttle.limit = bytes_per_second.filter(|l| *l > 0);
+        throttle.used = 0;
+    }
+
+    // Lets every paused job carry on, background ones included.
+    pub fn resume_all(&self) {
+        self.pause_background(false);
+        for job in self.active() {
+            let _ = self.resume(job.id);
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for job in self.active() {
+            let _ = self.cancel(job.id);
+        }
+    }
+
+    pub fn clear_finished(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|j| !j.state.is_finished());
+        let active: Vec<u64> = jobs.iter().map(|j| j.id).collect();
+        self.controls.lock().unwrap().retain(|id, _| active.contains(id));
+    }
+}
@@ -0,0 +1,74 @@
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::indexer::{self, Indexer, SearchResult};
+
+// The quick-search window that `--background` mode pops up on a global
+// shortcut, a file-only launcher over the shared index.
+pub const LABEL: &str = "launcher";
+pub const SHORTCUT_SETTING: &str = "launcher_shortcut";
+pub const DEFAULT_SHORTCUT: &str = "CommandOrControl+Alt+Space";
+
+pub fn register(app_handle: &AppHandle, shortcut: &str) -> Result<(), String> {
+    app_handle
+        .global_shortcut()
+        .on_shortcut(shortcut, |app_handle, _, event| {
+            if event.state() != ShortcutState::Pressed {
+                return;
+            }
+            if let Err(e) = toggle(app_handle) {
+                tracing::warn!("Failed to show the launcher: {}", e);
+            }
+        })
+        .map_err(|e| format!("Failed to register {}: {}", shortcut, e))
+}
+
+// The window is built on first use and only hidden afterwards, so it comes
+// back instantly.
+pub fn toggle(app_handle: &AppHandle) -> Result<(), String> {
+    let window = match app_handle.get_webview_window(LABEL) {
+        Some(window) if window.is_visible().unwrap_or(false) => return hide(app_handle),
+        Some(window) => window,
+        None => WebviewWindowBuilder::new(app_handle, LABEL, WebviewUrl::App("launcher".into()))
+            .title("HardBore Search")
+            .inner_size(640.0, 420.0)
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .resizable(false)
+            .visible(false)
+            .build()
+            .map_err(|e| format!("Failed to create the launcher: {}", e))?,
+    };
+    let _ = window.center();
+    window.show().map_err(|e| e.to_string())?;
+    let _ = window.set_focus();
+    let _ = app_handle.emit_to(LABEL, "launcher-shown", ());
+    Ok(())
+}
+
+pub fn hide(app_handle: &AppHandle) -> Result<(), String> {
+    match app_handle.get_webview_window(LABEL) {
+        Some(window) => window.hide().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+// Files only, and none the index knows to be gone: the launcher opens
+// things rather than browsing folders.
+pub fn search(indexer: &Indexer, query: &str, limit: usize) -> Vec<SearchResult> {
+    let mut results = indexer.search(query, limit * 2, false);
+    results.retain(|r| !r.is_dir);
+    indexer::flag_stale(&mut results);
+    results.retain(|r| !r.stale);
+    results.truncate(limit);
+    results
+}
+
+// Brings up the main window with `path` selected in its folder.
+pub fn reveal(app_handle: &AppHandle, path: &str) -> Result<(), String> {
+    let window = app_handle.get_webview_window("main").ok_or("The main window is gone")?;
+    window.show().map_err(|e| e.to_string())?;
+    let _ = window.set_focus();
+    app_handle.emit_to("main", "reveal-path", path).map_err(|e| e.to_string())
+}
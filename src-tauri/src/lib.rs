@@ -1,43 +1,154 @@
+pub mod acl;
+pub mod activity;
+pub mod applications;
+pub mod backups;
+pub mod benchmark;
+pub mod bulkrename;
+pub mod changejournal;
+pub mod classify;
+pub mod cleanup;
+pub mod clipboard;
+pub mod columnar;
+pub mod columns;
+pub mod contentcache;
+pub mod contextmenu;
+pub mod copyio;
+pub mod crawler;
+pub mod daemon;
+pub mod delta;
+pub mod dircache;
+pub mod diff;
+pub mod downloads;
+pub mod durability;
+pub mod eject;
+pub mod emblems;
+pub mod encoding;
+pub mod events;
+pub mod filenames;
 pub mod fs_engine;
+pub mod humanize;
+pub mod icons;
+pub mod importers;
 pub mod indexer;
+pub mod iopriority;
+pub mod jobs;
+pub mod launcher;
+pub mod locations;
+pub mod logging;
+pub mod markdown;
+pub mod metrics;
+pub mod mime;
+pub mod mounts;
+pub mod notebook;
+pub mod openfiles;
+pub mod packaging;
+pub mod picker;
+pub mod placement;
+pub mod plugins;
+pub mod portable;
+pub mod portal;
+pub mod preflight;
+pub mod profile;
+pub mod pty;
+pub mod rawpath;
+pub mod scripting;
+pub mod sendto;
+pub mod settings;
+pub mod share;
+pub mod shortcuts;
+pub mod snapshots;
+pub mod sqlite;
+pub mod statefile;
+pub mod structured;
+pub mod syssearch;
+pub mod tags;
+pub mod textinfo;
+pub mod textrange;
+pub mod torrent;
+pub mod transfer;
+pub mod tray;
+pub mod tree;
+pub mod users;
+pub mod userscripts;
+pub mod workspaces;
 
-use fs_engine::{read_directory, get_file_preview, get_home_dir, DirectoryContents, FilePreview};
-use indexer::{Indexer, IndexerStatus, SearchResult};
-use serde::Serialize;
-use std::sync::Mutex;
+use acl::{AclEntry, FileAcl};
+use activity::ActivityEvent;
+use applications::{Application, ChooserRequest};
+use backups::{BackupEntry, BackupSnapshot, BackupTool};
+use benchmark::BenchmarkReport;
+use bulkrename::RenameOp;
+use changejournal::{ChangeJournals, JournalBackend, JournalStatus};
+use cleanup::{CleanupCandidate, CleanupReport};
+use clipboard::PathFormat;
+use columnar::ListingPayload;
+use columns::ColumnInfo;
+use contentcache::ClearReport;
+use contextmenu::ContextMenuEntry;
+use delta::{DeltaTracker, DirectoryDelta};
+use diff::{DiffMode, FileDiff};
+use downloads::{DownloadManager, DownloadRequest, DownloadStatus};
+use eject::EjectReport;
+use filenames::{NameCheck, TargetFs};
+use fs_engine::{
+    get_file_preview, get_home_dir, get_multi_properties, CrawlOptions, DirectoryContents, FilePreview,
+    MultiProperties,
+};
+use importers::{FileManager, ImportPreview};
+use indexer::{
+    CompactReport, IndexBreakdown, IndexFilter, IndexOrder, IndexShard, IndexedFile, Indexer, IndexerStatus, ReclaimCategory,
+    ReclaimGroup, SearchResult,
+};
+use jobs::{JobCallback, JobInfo, JobKind, JobManager, JobState};
+use locations::{QuickBookmark, SavedSearch};
+use logging::LogRecord;
+use metrics::{IndexMetrics, LocalMetrics};
+use mounts::{MountBehavior, MountInfo, MountProfile};
+use openfiles::FileHolder;
+use packaging::PackageInfo;
+use picker::{PickerConfig, PickerMode, SaveNameCheck, SaveTarget, Selection};
+use placement::WindowHints;
+use plugins::{PluginCapability, PluginHost, PluginInfo, PluginMenuItem, PluginPreview, PluginVfsEntry};
+use portal::{InstallReport, PortalStatus};
+use preflight::PreflightError;
+use pty::{PtyEvent, PtyManager};
+use scripting::{ScriptEvent, ScriptHost, ScriptInfo, ScriptResult};
+use sendto::SendTarget;
+use share::{ShareInfo, ShareServer};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use std::path::Path;
+use snapshots::PreviousVersion;
+use sqlite::SqlitePreview;
+use textrange::{FileSearch, TextRange};
+use transfer::{SkipIdentical, TransferMode};
+use tree::TreeOptions;
+use users::{SystemGroup, SystemUser};
+use userscripts::UserScript;
+use workspaces::Workspace;
 use std::process::Command;
-use tauri::{Manager, State};
-
-#[derive(Debug, Clone, Serialize)]
-pub struct PickerConfig {
-    pub mode: PickerMode,
-    pub allow_multiple: bool,
-    pub file_types: Option<Vec<String>>,
-    pub start_dir: Option<String>,
-    pub current_name: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, PartialEq)]
-pub enum PickerMode {
-    Disabled,
-    Files,
-    Directories,
-    Both,
-    Save,
-}
+use tauri::{Emitter, Manager, State};
 
 struct AppState {
     indexer: Mutex<Option<Indexer>>,
     picker_config: Mutex<PickerConfig>,
+    app_chooser: Mutex<Option<ChooserRequest>>,
+    jobs: JobManager,
+    listings: DeltaTracker,
+    downloads: DownloadManager,
+    sharing: Mutex<Option<ShareServer>>,
+    terminals: PtyManager,
+    plugins: Arc<Mutex<Option<PluginHost>>>,
+    scripts: Arc<ScriptHost>,
+    journals: ChangeJournals,
+    // Started with `--background`: the main window only hides when closed and
+    // the launcher's shortcut is registered.
+    background: bool,
 }
 
 #[tauri::command]
 fn init_indexer(app_handle: tauri::AppHandle, state: State<AppState>) -> Result<usize, String> {
-    let data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e: tauri::Error| e.to_string())?;
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
 
     std::fs::create_dir_all(&data_dir)
         .map_err(|e| format!("Failed to create data directory: {}", e))?;
@@ -47,20 +158,202 @@ fn init_indexer(app_handle: tauri::AppHandle, state: State<AppState>) -> Result<
 
     let count = indexer.get_indexed_count();
 
+    indexer::set_active(Some(indexer.clone()));
     let mut state_indexer = state.indexer.lock().unwrap();
     *state_indexer = Some(indexer);
+    // With a daemon running, searches, crawls and index writes go to it and
+    // the local handle is only read from.
+    daemon::set_client(daemon::Client::connect());
 
     Ok(count)
 }
 
+fn list_directory(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    path: &str,
+    show_hidden: bool,
+    columns: &[String],
+    force_refresh: bool,
+) -> Result<DirectoryContents, String> {
+    let mut contents = if locations::is_virtual(path) {
+        let data_dir = profile::data_dir(app_handle).map_err(|e: tauri::Error| e.to_string())?;
+        let indexer = state.indexer.lock().unwrap();
+        locations::read_virtual(path, &data_dir, show_hidden, indexer.as_ref())?
+    } else {
+        let start = std::time::Instant::now();
+        let contents = dircache::read(path, show_hidden, force_refresh)?;
+        metrics::record_latency("read_dir", start.elapsed());
+        contents
+    };
+    state.picker_config.lock().unwrap().filter_listing(&mut contents)?;
+    columns::fill(&mut contents.entries, columns);
+    Ok(contents)
+}
+
+#[tauri::command]
+fn read_dir(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    path: String,
+    show_hidden: bool,
+    columns: Option<Vec<String>>,
+    force_refresh: Option<bool>,
+) -> Result<DirectoryContents, String> {
+    metrics::record_operation("read_dir");
+    list_directory(
+        &app_handle,
+        &state,
+        &path,
+        show_hidden,
+        &columns.unwrap_or_default(),
+        force_refresh.unwrap_or(false),
+    )
+}
+
+// Like read_dir, but listings of at least `min_entries` come back column by
+// column; the frontend rebuilds the entries.
+#[tauri::command]
+fn read_dir_packed(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    path: String,
+    show_hidden: bool,
+    columns: Option<Vec<String>>,
+    force_refresh: Option<bool>,
+    min_entries: usize,
+) -> Result<ListingPayload, String> {
+    metrics::record_operation("read_dir");
+    let contents = list_directory(
+        &app_handle,
+        &state,
+        &path,
+        show_hidden,
+        &columns.unwrap_or_default(),
+        force_refresh.unwrap_or(false),
+    )?;
+    Ok(ListingPayload::pack(contents, min_entries))
+}
+
+#[tauri::command]
+fn list_columns() -> Vec<ColumnInfo> {
+    columns::available()
+}
+
+// Icons are the same whatever the profile, so their cache is shared, as is
+// the content cache.
+fn icon_cache_dir(app_handle: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let data_dir = portable::app_data_dir(app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(data_dir.join("icon_cache"))
+}
+
+// Empties the preview and icon caches; everything in them is made again
+// when next needed.
+#[tauri::command]
+async fn clear_caches(app_handle: tauri::AppHandle) -> Result<ClearReport, String> {
+    let icon_cache = icon_cache_dir(&app_handle)?;
+    let report = tauri::async_runtime::spawn_blocking(move || contentcache::clear(&[icon_cache]))
+        .await
+        .map_err(|e| e.to_string())?;
+    icons::forget();
+    Ok(report)
+}
+
+// The icon bytes go back as a raw ArrayBuffer rather than a JSON number array.
+#[tauri::command]
+async fn get_icon_for(
+    app_handle: tauri::AppHandle,
+    path_or_mime: String,
+    size: u32,
+) -> Result<tauri::ipc::Response, String> {
+    let cache_dir = icon_cache_dir(&app_handle)?;
+    let data = tauri::async_runtime::spawn_blocking(move || icons::icon_for(&path_or_mime, size, &cache_dir))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(tauri::ipc::Response::new(data))
+}
+
+// Auto-refresh calls this with the token of its last result and only gets
+// back what changed, instead of the whole listing. It always reads from disk,
+// which also keeps the cached listing current.
+#[tauri::command]
+fn read_dir_delta(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    path: String,
+    since_token: Option<u64>,
+    show_hidden: bool,
+    columns: Option<Vec<String>>,
+) -> Result<DirectoryDelta, String> {
+    metrics::record_operation("read_dir_delta");
+    let contents = list_directory(&app_handle, &state, &path, show_hidden, &columns.unwrap_or_default(), true)?;
+    Ok(state.listings.diff(contents, show_hidden, since_token))
+}
+
+#[tauri::command]
+fn preview_file(
+    path: String,
+    max_bytes: Option<usize>,
+    allow_download: Option<bool>,
+    encoding: Option<String>,
+) -> Result<FilePreview, String> {
+    get_file_preview(&path, max_bytes.unwrap_or(65536), allow_download.unwrap_or(false), encoding.as_deref())
+}
+
+#[tauri::command]
+async fn preview_text_range(
+    path: String,
+    start_line: usize,
+    line_count: usize,
+    encoding: Option<String>,
+) -> Result<TextRange, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        textrange::preview_text_range(&rawpath::decode(&path), start_line, line_count, encoding.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
-fn read_dir(path: String, show_hidden: bool) -> Result<DirectoryContents, String> {
-    read_directory(&path, show_hidden)
+async fn search_in_file(
+    path: String,
+    pattern: String,
+    max_matches: Option<usize>,
+    encoding: Option<String>,
+) -> Result<FileSearch, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        textrange::search_in_file(&rawpath::decode(&path), &pattern, max_matches.unwrap_or(1000), encoding.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// The text is returned either way; `output` also saves it to that file.
+#[tauri::command]
+async fn generate_tree(
+    path: String,
+    depth: Option<usize>,
+    options: Option<TreeOptions>,
+    output: Option<String>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let text = tree::generate_tree(&rawpath::decode(&path), depth.unwrap_or(0), &options.unwrap_or_default())?;
+        if let Some(output) = output {
+            tree::write_tree(&text, &rawpath::decode(&output))?;
+        }
+        Ok(text)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-fn preview_file(path: String, max_bytes: Option<usize>) -> Result<FilePreview, String> {
-    get_file_preview(&path, max_bytes.unwrap_or(65536))
+async fn diff_files(a: String, b: String, mode: Option<DiffMode>) -> Result<FileDiff, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        diff::diff_files(&rawpath::decode(&a), &rawpath::decode(&b), mode.unwrap_or(DiffMode::Auto))
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -76,33 +369,117 @@ fn get_current_dir() -> Option<String> {
 }
 
 #[tauri::command]
-fn start_indexing(path: String, max_depth: Option<usize>, state: State<AppState>) -> Result<(), String> {
+fn start_indexing(
+    path: String,
+    max_depth: Option<usize>,
+    follow_symlinks: Option<bool>,
+    same_filesystem: Option<bool>,
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let options = CrawlOptions {
+        max_depth,
+        follow_symlinks: follow_symlinks.unwrap_or(false),
+        same_filesystem: same_filesystem.unwrap_or(false),
+        ..Default::default()
+    };
+    if let Some(client) = daemon::client() {
+        return client.call(&daemon::Request::Index { root: path, options });
+    }
     let indexer = state.indexer.lock().unwrap();
     if let Some(ref idx) = *indexer {
-        idx.index_directory(&path, max_depth);
+        let job = state
+            .jobs
+            .create(
+                JobKind::Indexing,
+                &format!("Indexing {}", path),
+                vec![path.clone()],
+                job_events(app_handle),
+            );
+        idx.index_directory_as_job(&path, options, job);
         Ok(())
     } else {
         Err("Indexer not initialized".to_string())
     }
 }
 
+// The external search may shell out to locate or mdfind, so it runs off the
+// main thread on a copy of the indexer handle rather than under its lock.
+#[tauri::command]
+async fn search_files(
+    query: String,
+    limit: Option<usize>,
+    include_hidden: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchResult>, String> {
+    metrics::record_operation("search_files");
+    let indexer = state.indexer.lock().unwrap().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let start = std::time::Instant::now();
+        let limit = limit.unwrap_or(50);
+        let include_hidden = include_hidden.unwrap_or(true);
+        let from_daemon = daemon::client().and_then(|client| {
+            client
+                .call(&daemon::Request::Search { query: query.clone(), limit, include_hidden })
+                .ok()
+        });
+        let mut results = match from_daemon {
+            Some(results) => {
+                metrics::record_latency("search_daemon", start.elapsed());
+                results
+            }
+            None => indexer.as_ref().map(|idx| idx.search(&query, limit, include_hidden)).unwrap_or_default(),
+        };
+        indexer::flag_stale(&mut results);
+
+        if results.len() < limit && syssearch::enabled() {
+            let start = std::time::Instant::now();
+            let mut extra = syssearch::search(&query, limit, include_hidden);
+            metrics::record_latency("search_system", start.elapsed());
+            // Inside folders the index has crawled, its answer stands.
+            if let Some(ref idx) = indexer {
+                let parents: Vec<String> = extra
+                    .iter()
+                    .filter_map(|r| Path::new(&r.path).parent().map(|p| p.to_string_lossy().to_string()))
+                    .collect();
+                let indexed = idx.indexed_dirs(&parents);
+                extra.retain(|r| {
+                    Path::new(&r.path)
+                        .parent()
+                        .is_none_or(|p| !indexed.contains(p.to_string_lossy().as_ref()))
+                });
+            }
+            let seen: HashSet<String> = results.iter().map(|r| r.path.clone()).collect();
+            extra.retain(|r| !seen.contains(&r.path));
+            extra.truncate(limit - results.len());
+            results.extend(extra);
+        }
+        results
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Drops the rows of search results found to be gone from the disk.
 #[tauri::command]
-fn search_files(query: String, limit: Option<usize>, state: State<AppState>) -> Vec<SearchResult> {
+fn remove_stale_entries(paths: Vec<String>, state: State<AppState>) -> Result<usize, String> {
     let indexer = state.indexer.lock().unwrap();
     if let Some(ref idx) = *indexer {
-        let results = idx.search_fts(&query, limit.unwrap_or(50));
-        if results.is_empty() {
-            idx.search_fuzzy(&query, limit.unwrap_or(50))
-        } else {
-            results
-        }
+        let gone: Vec<String> = paths
+            .into_iter()
+            .filter(|p| std::fs::symlink_metadata(rawpath::decode(p)).is_err())
+            .collect();
+        idx.apply_changes(&gone).map_err(|e| e.to_string())
     } else {
-        vec![]
+        Err("Indexer not initialized".to_string())
     }
 }
 
 #[tauri::command]
 fn get_indexer_status(state: State<AppState>) -> Option<IndexerStatus> {
+    if let Some(status) = daemon::client().and_then(|c| c.call(&daemon::Request::Status).ok()) {
+        return Some(status);
+    }
     let indexer = state.indexer.lock().unwrap();
     indexer.as_ref().map(|idx| idx.get_status())
 }
@@ -113,6 +490,86 @@ fn get_indexed_count(state: State<AppState>) -> usize {
     indexer.as_ref().map(|idx| idx.get_indexed_count()).unwrap_or(0)
 }
 
+#[tauri::command]
+fn get_index_breakdown(root: String, state: State<AppState>) -> Result<IndexBreakdown, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.get_breakdown(&root).map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+// Creations, modifications, deletions and moves under `path`, newest first;
+// the last week unless `since` (Unix seconds) says otherwise.
+#[tauri::command]
+fn get_activity_timeline(
+    path: String,
+    since: Option<i64>,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<Vec<ActivityEvent>, String> {
+    let since = since.unwrap_or_else(|| chrono::Utc::now().timestamp() - 7 * 24 * 60 * 60);
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.activity_timeline(&path, since, limit.unwrap_or(500))
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+fn query_index(
+    order_by: IndexOrder,
+    filter: Option<IndexFilter>,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<Vec<IndexedFile>, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.query(order_by, &filter.unwrap_or_default(), limit.unwrap_or(100))
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+fn suggest_reclaim(root: String, state: State<AppState>) -> Result<Vec<ReclaimGroup>, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.suggest_reclaim(&root).map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+// Only folders the index still suggests are removed, so a path from
+// anywhere else can't be passed in.
+#[tauri::command]
+fn reclaim_directories(root: String, paths: Vec<String>, state: State<AppState>) -> Result<CleanupReport, String> {
+    let groups = {
+        let indexer = state.indexer.lock().unwrap();
+        let idx = indexer.as_ref().ok_or("Indexer not initialized")?;
+        idx.suggest_reclaim(&root).map_err(|e| e.to_string())?
+    };
+    let suggested: HashSet<String> = groups
+        .into_iter()
+        .filter(|g| {
+            matches!(
+                g.category,
+                ReclaimCategory::BuildArtifacts | ReclaimCategory::Dependencies | ReclaimCategory::Caches
+            )
+        })
+        .flat_map(|g| g.items)
+        .map(|item| item.path)
+        .collect();
+    let report = cleanup::reclaim_directories(&rawpath::decode(&root), &paths, &suggested);
+    update_index(report.removed.clone());
+    Ok(report)
+}
+
 #[tauri::command]
 fn clear_index(state: State<AppState>) -> Result<(), String> {
     let indexer = state.indexer.lock().unwrap();
@@ -124,35 +581,96 @@ fn clear_index(state: State<AppState>) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn delete_path(path: String, is_dir: bool) -> Result<(), String> {
-    if is_dir {
-        std::fs::remove_dir_all(&path)
-            .map_err(|e| format!("Failed to delete directory: {}", e))
+fn compact_index(state: State<AppState>) -> Result<CompactReport, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.compact().map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+fn list_index_shards(state: State<AppState>) -> Result<Vec<IndexShard>, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.shards().map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+fn remove_index_root(root: String, state: State<AppState>) -> Result<(), String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.remove_root(&root).map_err(|e| e.to_string())
     } else {
-        std::fs::remove_file(&path)
-            .map_err(|e| format!("Failed to delete file: {}", e))
+        Err("Indexer not initialized".to_string())
+    }
+}
+
+// Follows a whole-volume index from the filesystem's change feed instead of
+// recrawling it. Needs elevated rights; the error says which.
+#[tauri::command]
+fn start_change_journal(root: String, state: State<AppState>) -> Result<JournalBackend, String> {
+    if let Some(client) = daemon::client() {
+        return client.call(&daemon::Request::Watch { root });
     }
+    let indexer = state.indexer.lock().unwrap();
+    let Some(ref idx) = *indexer else {
+        return Err("Indexer not initialized".to_string());
+    };
+    state.journals.start(&root, idx.clone())
+}
+
+#[tauri::command]
+fn stop_change_journal(root: String, state: State<AppState>) {
+    state.journals.stop(&root);
+}
+
+#[tauri::command]
+fn list_change_journals(state: State<AppState>) -> Vec<JournalStatus> {
+    state.journals.list()
+}
+
+#[tauri::command]
+fn delete_path(path: String, is_dir: bool) -> Result<(), String> {
+    metrics::record_operation("delete_path");
+    let target = rawpath::decode(&path);
+    dircache::invalidate(&target);
+    let result = if is_dir {
+        std::fs::remove_dir_all(&target)
+            .map_err(|e| format!("Failed to delete directory: {}{}", e, openfiles::in_use_hint(&target)))
+    } else {
+        std::fs::remove_file(&target)
+            .map_err(|e| format!("Failed to delete file: {}{}", e, openfiles::in_use_hint(&target)))
+    };
+    // A folder that was only partly deleted still loses the rows it no
+    // longer has.
+    update_index(vec![path]);
+    result
 }
 
 #[tauri::command]
 fn copy_path(source: String, destination: String) -> Result<(), String> {
-    use std::fs;
-    use std::path::Path;
+    metrics::record_operation("copy_path");
     
-    let src = Path::new(&source);
-    let dst = Path::new(&destination);
+    let src = rawpath::decode(&source);
+    let dst = rawpath::decode(&destination);
     
     if !src.exists() {
         return Err("Source does not exist".to_string());
     }
+    dircache::invalidate(&dst);
     
-    if src.is_dir() {
-        copy_dir_recursive(src, dst)
+    let result = if src.is_dir() {
+        copy_dir_recursive(&src, &dst)
     } else {
-        fs::copy(src, dst)
-            .map(|_| ())
-            .map_err(|e| format!("Failed to copy file: {}", e))
-    }
+        durability::copy_file(&src, &dst)
+    };
+    update_index(vec![destination]);
+    result
 }
 
 fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
@@ -161,6 +679,9 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
     if !dst.exists() {
         fs::create_dir_all(dst)
             .map_err(|e| format!("Failed to create directory: {}", e))?;
+        if durability::enabled() {
+            durability::sync_parent(dst)?;
+        }
     }
     
     for entry in fs::read_dir(src)
@@ -173,8 +694,7 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
         if src_path.is_dir() {
             copy_dir_recursive(&src_path, &dst_path)?;
         } else {
-            fs::copy(&src_path, &dst_path)
-                .map_err(|e| format!("Failed to copy file: {}", e))?;
+            durability::copy_file(&src_path, &dst_path)?;
         }
     }
     
@@ -184,27 +704,31 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), String> {
 #[tauri::command]
 fn move_path(source: String, destination: String) -> Result<(), String> {
     use std::fs;
-    use std::path::Path;
+    metrics::record_operation("move_path");
     
-    let src = Path::new(&source);
-    let dst = Path::new(&destination);
+    let src = rawpath::decode(&source);
+    let dst = rawpath::decode(&destination);
     
     if !src.exists() {
         return Err("Source does not exist".to_string());
     }
+    dircache::invalidate(&src);
+    dircache::invalidate(&dst);
     
-    if let Err(_) = fs::rename(src, dst) {
+    if durability::rename(&src, &dst).is_ok() {
+        rebase_index(vec![(source.clone(), destination.clone())]);
+    } else {
         if src.is_dir() {
-            copy_dir_recursive(src, dst)?;
-            fs::remove_dir_all(src)
-                .map_err(|e| format!("Failed to remove source directory: {}", e))?;
+            copy_dir_recursive(&src, &dst)?;
+            fs::remove_dir_all(&src)
+                .map_err(|e| format!("Failed to remove source directory: {}{}", e, openfiles::in_use_hint(&src)))?;
         } else {
-            fs::copy(src, dst)
-                .map_err(|e| format!("Failed to copy file: {}", e))?;
-            fs::remove_file(src)
-                .map_err(|e| format!("Failed to remove source file: {}", e))?;
+            durability::copy_file(&src, &dst)?;
+            fs::remove_file(&src)
+                .map_err(|e| format!("Failed to remove source file: {}{}", e, openfiles::in_use_hint(&src)))?;
         }
     }
+    update_index(vec![source, destination]);
     
     Ok(())
 }
@@ -212,9 +736,11 @@ fn move_path(source: String, destination: String) -> Result<(), String> {
 #[tauri::command]
 fn batch_copy_paths(sources: Vec<String>, destination_dir: String) -> Result<Vec<String>, String> {
     use std::path::Path;
+    metrics::record_operation("batch_copy_paths");
     
+    // Names are joined in encoded form; copy_path and move_path decode them.
     let dest_dir = Path::new(&destination_dir);
-    if !dest_dir.is_dir() {
+    if !rawpath::decode(&destination_dir).is_dir() {
         return Err("Destination must be a directory".to_string());
     }
     
@@ -241,9 +767,11 @@ fn batch_copy_paths(sources: Vec<String>, destination_dir: String) -> Result<Vec
 #[tauri::command]
 fn batch_move_paths(sources: Vec<String>, destination_dir: String) -> Result<Vec<String>, String> {
     use std::path::Path;
+    metrics::record_operation("batch_move_paths");
     
+    // Names are joined in encoded form; copy_path and move_path decode them.
     let dest_dir = Path::new(&destination_dir);
-    if !dest_dir.is_dir() {
+    if !rawpath::decode(&destination_dir).is_dir() {
         return Err("Destination must be a directory".to_string());
     }
     
@@ -269,6 +797,7 @@ fn batch_move_paths(sources: Vec<String>, destination_dir: String) -> Result<Vec
 
 #[tauri::command]
 fn batch_delete_paths(items: Vec<(String, bool)>) -> Result<(), String> {
+    metrics::record_operation("batch_delete_paths");
     let mut errors = Vec::new();
 
     for (path, is_dir) in items {
@@ -284,48 +813,201 @@ fn batch_delete_paths(items: Vec<(String, bool)>) -> Result<(), String> {
     }
 }
 
+#[tauri::command]
+fn find_cleanup_candidates(root: String) -> Result<Vec<CleanupCandidate>, String> {
+    cleanup::find_cleanup_candidates(&rawpath::decode(&root))
+}
+
+#[tauri::command]
+fn remove_cleanup_candidates(root: String, paths: Vec<String>) -> CleanupReport {
+    let report = cleanup::remove_cleanup_candidates(&rawpath::decode(&root), &paths);
+    update_index(report.removed.clone());
+    report
+}
+
+// Keeps the index current after the app's own file operations, so search
+// doesn't list what was just deleted. Failing to update it doesn't fail the
+// operation. Every window is told as well, so other views of the same folders
+// follow.
+fn update_index(paths: Vec<String>) {
+    events::publish(events::Origin::App, &paths, &[]);
+    if let Some(client) = daemon::client() {
+        if let Err(e) = client.call::<usize>(&daemon::Request::RecordChanges { paths }) {
+            tracing::warn!("Failed to update the index: {}", e);
+        }
+    } else if let Some(idx) = indexer::active() {
+        if let Err(e) = idx.record_changes(&paths) {
+            tracing::warn!("Failed to update the index: {}", e);
+        }
+    }
+}
+
+// Indexed rows follow a rename made here instead of going stale until the
+// next crawl. Failing to update them doesn't fail the rename.
+fn rebase_index(moves: Vec<(String, String)>) {
+    events::publish(events::Origin::App, &[], &moves);
+    if let Some(client) = daemon::client() {
+        if let Err(e) = client.call::<usize>(&daemon::Request::Rebase { moves }) {
+            tracing::warn!("Failed to move renamed paths in the index: {}", e);
+        }
+    } else if let Some(idx) = indexer::active() {
+        if let Err(e) = idx.rebase_paths(&moves) {
+            tracing::warn!("Failed to move renamed paths in the index: {}", e);
+        }
+    }
+}
+
 #[tauri::command]
 fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
+    metrics::record_operation("rename_path");
     let path = Path::new(&old_path);
     let parent = path.parent()
         .ok_or("Cannot get parent directory")?;
     let new_path = parent.join(&new_name);
     
-    std::fs::rename(&old_path, &new_path)
-        .map_err(|e| format!("Failed to rename: {}", e))?;
-    
-    Ok(new_path.to_string_lossy().to_string())
+    // Goes through the batch path so a case-only rename works on
+    // case-insensitive filesystems and an existing file is never replaced.
+    let op = RenameOp {
+        from: old_path,
+        to: new_path.to_string_lossy().to_string(),
+    };
+    dircache::invalidate(&rawpath::decode(&op.from));
+    let renamed = bulkrename::apply_bulk_rename(std::slice::from_ref(&op), false)?;
+    let new_path = renamed.into_iter().next().unwrap_or(op.to);
+    rebase_index(vec![(op.from, new_path.clone())]);
+    Ok(new_path)
 }
 
 #[tauri::command]
-fn open_path(path: String) -> Result<(), String> {
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open: {}", e))?;
+async fn apply_bulk_rename(renames: Vec<RenameOp>, sanitize: Option<bool>) -> Result<Vec<String>, String> {
+    metrics::record_operation("apply_bulk_rename");
+    for op in &renames {
+        dircache::invalidate(&rawpath::decode(&op.from));
     }
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open: {}", e))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let renamed = bulkrename::apply_bulk_rename(&renames, sanitize.unwrap_or(false))?;
+        let moves = renames.into_iter().map(|op| op.from).zip(renamed.iter().cloned()).collect();
+        rebase_index(moves);
+        Ok(renamed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Moves indexed rows from `old_prefix` to `new_prefix`, for a rename the app
+// didn't make itself.
+#[tauri::command]
+fn rebase_index_paths(old_prefix: String, new_prefix: String, state: State<AppState>) -> Result<usize, String> {
+    let indexer = state.indexer.lock().unwrap();
+    if let Some(ref idx) = *indexer {
+        idx.rebase_paths(&[(old_prefix, new_prefix)]).map_err(|e| e.to_string())
+    } else {
+        Err("Indexer not initialized".to_string())
     }
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("explorer")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open: {}", e))?;
+}
+
+// Checks `name` for the filesystem `directory` is on, unless `target_fs` names one.
+#[tauri::command]
+fn validate_filename(name: String, directory: Option<String>, target_fs: Option<TargetFs>) -> NameCheck {
+    let target = target_fs.unwrap_or_else(|| match &directory {
+        Some(dir) => filenames::target_fs_for(&rawpath::decode(dir)),
+        None => filenames::target_fs_for(Path::new("/")),
+    });
+    filenames::check_filename(name.as_ref(), target)
+}
+
+#[tauri::command]
+fn open_path(app_handle: tauri::AppHandle, path: String) -> Result<(), String> {
+    // Explorer already follows .lnk files; everywhere else a shortcut would just
+    // be opened as a text file, so it is launched from its parsed target.
+    let target = rawpath::decode(&path);
+    let shortcut = if cfg!(windows) {
+        None
+    } else {
+        shortcuts::read_shortcut(&target)
+    };
+    if let Some(shortcut) = shortcut {
+        shortcuts::launch(&target, &shortcut)?;
+    } else {
+        #[cfg(target_os = "linux")]
+        {
+            Command::new("xdg-open")
+                .arg(&target)
+                .spawn()
+                .map_err(|e| format!("Failed to open: {}", e))?;
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("open")
+                .arg(&target)
+                .spawn()
+                .map_err(|e| format!("Failed to open: {}", e))?;
+        }
+        #[cfg(target_os = "windows")]
+        {
+            Command::new("explorer")
+                .arg(&target)
+                .spawn()
+                .map_err(|e| format!("Failed to open: {}", e))?;
+        }
+    }
+    if let Ok(data_dir) = profile::data_dir(&app_handle) {
+        let _ = locations::record_access(&data_dir, &path);
     }
     Ok(())
 }
 
+#[tauri::command]
+fn get_profile() -> Option<String> {
+    profile::name().map(str::to_string)
+}
+
+#[tauri::command]
+fn is_background_mode(state: State<AppState>) -> bool {
+    state.background
+}
+
+#[tauri::command]
+fn launcher_search(query: String, limit: Option<usize>, state: State<AppState>) -> Vec<SearchResult> {
+    let indexer = state.indexer.lock().unwrap();
+    match *indexer {
+        Some(ref idx) => launcher::search(idx, &query, limit.unwrap_or(20)),
+        None => vec![],
+    }
+}
+
+// Enter opens the file, Ctrl+Enter reveals it in the main window; either way
+// the launcher gets out of the way.
+#[tauri::command]
+fn launcher_open(path: String, reveal: bool, app_handle: tauri::AppHandle) -> Result<(), String> {
+    launcher::hide(&app_handle)?;
+    if reveal {
+        launcher::reveal(&app_handle, &path)
+    } else {
+        open_path(app_handle, path)
+    }
+}
+
+#[tauri::command]
+fn hide_launcher(app_handle: tauri::AppHandle) -> Result<(), String> {
+    launcher::hide(&app_handle)
+}
+
+#[tauri::command]
+fn open_in_torrent_client(path: String) -> Result<(), String> {
+    torrent::open_in_client(&rawpath::decode(&path))
+}
+
+#[tauri::command]
+async fn preview_sqlite(path: String, table: Option<String>, limit: Option<usize>) -> Result<SqlitePreview, String> {
+    tauri::async_runtime::spawn_blocking(move || sqlite::preview_sqlite(&rawpath::decode(&path), table.as_deref(), limit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn show_in_folder(path: String) -> Result<(), String> {
-    let target_path = Path::new(&path);
+    let target_path = &rawpath::decode(&path);
     let folder = if target_path.is_dir() {
         target_path
     } else {
@@ -343,7 +1025,7 @@ fn show_in_folder(path: String) -> Result<(), String> {
     {
         Command::new("open")
             .arg("-R")
-            .arg(&path)
+            .arg(target_path)
             .spawn()
             .map_err(|e| format!("Failed to open folder: {}", e))?;
     }
@@ -351,7 +1033,7 @@ fn show_in_folder(path: String) -> Result<(), String> {
     {
         Command::new("explorer")
             .arg("/select,")
-            .arg(&path)
+            .arg(target_path)
             .spawn()
             .map_err(|e| format!("Failed to open folder: {}", e))?;
     }
@@ -360,7 +1042,7 @@ fn show_in_folder(path: String) -> Result<(), String> {
 
 #[tauri::command]
 fn open_terminal(path: String) -> Result<(), String> {
-    let target_path = Path::new(&path);
+    let target_path = rawpath::decode(&path);
     let folder = if target_path.is_dir() {
         target_path.to_path_buf()
     } else {
@@ -421,8 +1103,13 @@ fn open_terminal(path: String) -> Result<(), String> {
 
 #[tauri::command]
 fn create_directory(path: String) -> Result<(), String> {
-    std::fs::create_dir_all(&path)
-        .map_err(|e| format!("Failed to create directory: {}", e))
+    metrics::record_operation("create_directory");
+    let dir = rawpath::decode(&path);
+    dircache::invalidate(&dir);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+    update_index(vec![path]);
+    Ok(())
 }
 
 #[tauri::command]
@@ -464,6 +1151,7 @@ fn get_mount_points() -> Vec<MountPoint> {
                         path: mount_point.to_string(),
                         device: device.to_string(),
                         fs_type: fs_type.to_string(),
+                        profile: mounts::mount_profile(mount_point, fs_type),
                     });
                 }
             }
@@ -490,6 +1178,7 @@ fn get_mount_points() -> Vec<MountPoint> {
                                 path: mount_point.to_string(),
                                 device: device.to_string(),
                                 fs_type: String::new(),
+                                profile: mounts::mount_profile(mount_point, ""),
                             });
                         }
                     }
@@ -505,6 +1194,7 @@ fn get_mount_points() -> Vec<MountPoint> {
             if Path::new(&drive).exists() {
                 mounts.push(MountPoint {
                     name: format!("Drive {}", letter as char),
+                    profile: mounts::mount_profile(&drive, ""),
                     path: drive.clone(),
                     device: drive,
                     fs_type: String::new(),
@@ -518,138 +1208,328 @@ fn get_mount_points() -> Vec<MountPoint> {
 
 #[tauri::command]
 fn add_favorite(path: String, app_handle: tauri::AppHandle) -> Result<(), String> {
-    let data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e: tauri::Error| e.to_string())?;
-    
-    let favorites_file = data_dir.join("favorites.json");
-    
-    let mut favorites: Vec<String> = if favorites_file.exists() {
-        let content = std::fs::read_to_string(&favorites_file)
-            .map_err(|e| format!("Failed to read favorites: {}", e))?;
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        Vec::new()
-    };
-    
-    if !favorites.contains(&path) {
-        favorites.push(path);
-        let json = serde_json::to_string_pretty(&favorites)
-            .map_err(|e| format!("Failed to serialize favorites: {}", e))?;
-        std::fs::write(&favorites_file, json)
-            .map_err(|e| format!("Failed to write favorites: {}", e))?;
-    }
-    
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    locations::add_favorites(&data_dir, &[path])?;
+    tray::refresh(&app_handle);
     Ok(())
 }
 
 #[tauri::command]
 fn remove_favorite(path: String, app_handle: tauri::AppHandle) -> Result<(), String> {
-    let data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e: tauri::Error| e.to_string())?;
-    
-    let favorites_file = data_dir.join("favorites.json");
-    
-    if favorites_file.exists() {
-        let content = std::fs::read_to_string(&favorites_file)
-            .map_err(|e| format!("Failed to read favorites: {}", e))?;
-        let mut favorites: Vec<String> = serde_json::from_str(&content).unwrap_or_default();
-        
-        favorites.retain(|f| f != &path);
-        
-        let json = serde_json::to_string_pretty(&favorites)
-            .map_err(|e| format!("Failed to serialize favorites: {}", e))?;
-        std::fs::write(&favorites_file, json)
-            .map_err(|e| format!("Failed to write favorites: {}", e))?;
-    }
-    
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    locations::remove_favorite(&data_dir, &path)?;
+    tray::refresh(&app_handle);
     Ok(())
 }
 
+// A preview only; nothing changes until import_favorites.
+#[tauri::command]
+fn import_from(app: FileManager, app_handle: tauri::AppHandle) -> Result<ImportPreview, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    importers::import_from(app, &locations::favorites(&data_dir))
+}
+
+#[tauri::command]
+fn import_favorites(paths: Vec<String>, app_handle: tauri::AppHandle) -> Result<usize, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    let added = locations::add_favorites(&data_dir, &paths)?;
+    tray::refresh(&app_handle);
+    Ok(added)
+}
+
 #[tauri::command]
 fn get_favorites(app_handle: tauri::AppHandle) -> Vec<String> {
-    let data_dir = match app_handle.path().app_data_dir() {
-        Ok(dir) => dir,
-        Err(_) => return Vec::new(),
-    };
-    
-    let favorites_file = data_dir.join("favorites.json");
-    
-    if favorites_file.exists() {
-        if let Ok(content) = std::fs::read_to_string(&favorites_file) {
-            return serde_json::from_str(&content).unwrap_or_default();
-        }
+    match profile::data_dir(&app_handle) {
+        Ok(data_dir) => locations::favorites(&data_dir),
+        Err(_) => Vec::new(),
     }
-    
-    Vec::new()
 }
 
 #[tauri::command]
-fn path_exists(path: String) -> bool {
-    Path::new(&path).exists()
+fn get_quick_bookmarks(app_handle: tauri::AppHandle) -> Vec<QuickBookmark> {
+    match profile::data_dir(&app_handle) {
+        Ok(data_dir) => locations::get_quick_bookmarks(&data_dir),
+        Err(_) => Vec::new(),
+    }
 }
 
 #[tauri::command]
-fn get_picker_config(state: State<AppState>) -> PickerConfig {
-    state.picker_config.lock().unwrap().clone()
+fn set_quick_bookmark(app_handle: tauri::AppHandle, slot: u8, path: Option<String>) -> Result<Vec<QuickBookmark>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    locations::set_quick_bookmark(&data_dir, slot, path.as_deref())
 }
 
 #[tauri::command]
-fn select_files(paths: Vec<String>, app_handle: tauri::AppHandle) -> Result<(), String> {
-    use std::io::Write;
-    for path in &paths {
-        println!("HARDBORE_SELECTED:{}", path);
+fn create_search_location(app_handle: tauri::AppHandle, query: String, save: Option<bool>) -> Result<SavedSearch, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    locations::create_search(&data_dir, &query, save.unwrap_or(false))
+}
+
+#[tauri::command]
+fn list_saved_searches(app_handle: tauri::AppHandle) -> Result<Vec<SavedSearch>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(locations::list_saved_searches(&data_dir))
+}
+
+#[tauri::command]
+fn delete_saved_search(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    locations::delete_saved_search(&data_dir, &id)
+}
+
+#[tauri::command]
+fn list_workspaces(app_handle: tauri::AppHandle) -> Result<Vec<Workspace>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(workspaces::list_workspaces(&data_dir))
+}
+
+#[tauri::command]
+fn save_workspace(app_handle: tauri::AppHandle, workspace: Workspace) -> Result<Workspace, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    workspaces::save_workspace(&data_dir, workspace)
+}
+
+#[tauri::command]
+fn open_workspace(app_handle: tauri::AppHandle, name: String) -> Result<Workspace, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    workspaces::open_workspace(&data_dir, &name)
+}
+
+#[tauri::command]
+fn delete_workspace(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    workspaces::delete_workspace(&data_dir, &name)
+}
+
+#[tauri::command]
+fn path_exists(path: String) -> bool {
+    if locations::is_virtual(&path) {
+        return locations::VirtualLocation::parse(&path).is_some();
+    }
+    rawpath::decode(&path).exists()
+}
+
+#[tauri::command]
+fn get_picker_config(state: State<AppState>) -> PickerConfig {
+    state.picker_config.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn select_files(paths: Vec<String>, app_handle: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    state.picker_config.lock().unwrap().validate(&paths)?;
+    print_selection(&paths);
+    placement::remember(&app_handle);
+    app_handle.exit(0);
+    Ok(())
+}
+
+// Each path is followed by its details as JSON; readers that only know the
+// first line skip the second.
+fn print_selection(paths: &[String]) {
+    use std::io::Write;
+    for path in paths {
+        println!("HARDBORE_SELECTED:{}", path);
+        if let Ok(details) = serde_json::to_string(&Selection::of(path)) {
+            println!("HARDBORE_SELECTION:{}", details);
+        }
     }
     let _ = std::io::stdout().flush();
-    
+}
+
+// `--headless` answers a picker request with its start folder and no window,
+// so the portal's self-test can run the whole chain on a machine without a display.
+fn select_headless(config: &PickerConfig) -> i32 {
+    let paths: Vec<String> = config.start_dir.iter().cloned().collect();
+    match config.validate(&paths) {
+        Ok(()) => {
+            print_selection(&paths);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            println!("HARDBORE_CANCELLED");
+            1
+        }
+    }
+}
+
+#[tauri::command]
+fn get_app_chooser(state: State<AppState>) -> Option<ChooserRequest> {
+    state.app_chooser.lock().unwrap().clone()
+}
+
+#[tauri::command]
+fn chooser_applications(state: State<AppState>) -> Vec<Application> {
+    state.app_chooser.lock().unwrap().as_ref().map(ChooserRequest::applications).unwrap_or_default()
+}
+
+#[tauri::command]
+fn choose_application(app_id: String, app_handle: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    use std::io::Write;
+    let offered = chooser_applications(state);
+    if !offered.iter().any(|a| a.id == app_id) {
+        return Err(format!("{} is not one of the applications offered", app_id));
+    }
+    println!("HARDBORE_APP:{}", app_id);
+    let _ = std::io::stdout().flush();
+    placement::remember(&app_handle);
     app_handle.exit(0);
     Ok(())
 }
 
+// The portal sends new choices on stdin while the chooser is open, one
+// `HARDBORE_CHOICES:` line each time.
+fn watch_app_choices(app_handle: &tauri::AppHandle) {
+    if app_handle.state::<AppState>().app_chooser.lock().unwrap().is_none() {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            let Some(choices) = line.strip_prefix("HARDBORE_CHOICES:") else {
+                continue;
+            };
+            if let Some(request) = app_handle.state::<AppState>().app_chooser.lock().unwrap().as_mut() {
+                request.choices = choices.split(',').filter(|c| !c.is_empty()).map(str::to_string).collect();
+            }
+            let _ = app_handle.emit("app-choices-changed", ());
+        }
+    });
+}
+
+#[tauri::command]
+async fn install_portal_service() -> Result<InstallReport, String> {
+    tauri::async_runtime::spawn_blocking(portal::install)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn portal_status() -> Result<PortalStatus, String> {
+    tauri::async_runtime::spawn_blocking(portal::status)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// `--daemon` serves the index until killed, logging where the app would.
+fn daemon_cli() -> i32 {
+    let Some(data_dir) = daemon::data_dir() else {
+        eprintln!("No data directory");
+        return 1;
+    };
+    if let Err(e) = logging::init(&data_dir, settings::get(&data_dir, logging::SETTING).as_ref().and_then(|v| v.as_str())) {
+        eprintln!("{}", e);
+    }
+    let threads = settings::get(&data_dir, crawler::THREADS_SETTING).and_then(|v| v.as_u64());
+    crawler::set_threads(threads.map(|t| t as usize));
+    match daemon::serve(&data_dir) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// `--install-portal` sets the portal up from a terminal and exits.
+fn install_portal_cli() -> i32 {
+    let report = match portal::install() {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    for path in &report.written {
+        println!("Wrote {}", path);
+    }
+    let status = &report.status;
+    println!("Backend activatable: {}", if status.activatable { "yes" } else { "no" });
+    println!("xdg-desktop-portal running: {}", if status.frontend_running { "yes" } else { "no" });
+    for problem in &status.problems {
+        println!("Problem: {}", problem);
+    }
+    if status.problems.is_empty() { 0 } else { 2 }
+}
+
+#[tauri::command]
+fn applications_for(path: String) -> Vec<Application> {
+    applications::for_mime(&mime::mime_type(&rawpath::decode(&path)))
+}
+
+#[tauri::command]
+fn open_with(app_id: String, paths: Vec<String>) -> Result<(), String> {
+    let app = applications::find(&app_id).ok_or_else(|| format!("{} is not installed", app_id))?;
+    let files: Vec<String> = paths
+        .iter()
+        .map(|p| rawpath::decode(p).to_string_lossy().to_string())
+        .collect();
+    applications::launch(&app, &files)
+}
+
+#[tauri::command]
+fn check_save_name(name: String, file_type: Option<String>, state: State<AppState>) -> SaveNameCheck {
+    state.picker_config.lock().unwrap().check_save_name(&name, file_type.as_deref())
+}
+
+#[tauri::command]
+fn check_save_target(path: String, state: State<AppState>) -> SaveTarget {
+    state.picker_config.lock().unwrap().check_save_target(&path)
+}
+
 #[tauri::command]
 fn cancel_picker(app_handle: tauri::AppHandle) -> Result<(), String> {
     use std::io::Write;
     println!("HARDBORE_CANCELLED");
     let _ = std::io::stdout().flush();
+    placement::remember(&app_handle);
     app_handle.exit(1);
     Ok(())
 }
 
 #[tauri::command]
 fn duplicate_path(path: String) -> Result<String, String> {
-    let src = Path::new(&path);
+    metrics::record_operation("duplicate_path");
+    let src = &rawpath::decode(&path);
     if !src.exists() {
         return Err("Source does not exist".to_string());
     }
 
     let parent = src.parent().ok_or("Cannot get parent directory")?;
-    let stem = src.file_stem().unwrap_or_default().to_string_lossy();
-    let ext = src.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let stem = src.file_stem().unwrap_or_default();
     let is_dir = src.is_dir();
+    // Built as an OsString so a name that isn't valid UTF-8 keeps its bytes.
+    let copy_name = |suffix: String| {
+        let mut name = stem.to_os_string();
+        name.push(suffix);
+        if let Some(ext) = src.extension() {
+            name.push(".");
+            name.push(ext);
+        }
+        parent.join(name)
+    };
 
-    let mut dest = parent.join(format!("{stem} (copy){ext}"));
+    let mut dest = copy_name(" (copy)".to_string());
     let mut counter = 2u32;
     while dest.exists() {
-        dest = parent.join(format!("{stem} (copy {counter}){ext}"));
+        dest = copy_name(format!(" (copy {counter})"));
         counter += 1;
     }
 
-    let dest_str = dest.to_string_lossy().to_string();
+    let dest_str = rawpath::encode_path(&dest);
     if is_dir {
         copy_dir_recursive(src, &dest)?;
     } else {
-        std::fs::copy(src, &dest)
-            .map_err(|e| format!("Failed to duplicate file: {e}"))?;
+        durability::copy_file(src, &dest)?;
     }
+    update_index(vec![dest_str.clone()]);
 
     Ok(dest_str)
 }
 
 #[tauri::command]
 fn batch_duplicate_paths(paths: Vec<String>) -> Result<Vec<String>, String> {
+    metrics::record_operation("batch_duplicate_paths");
     let mut results = Vec::new();
     let mut errors = Vec::new();
 
@@ -669,21 +1549,56 @@ fn batch_duplicate_paths(paths: Vec<String>) -> Result<Vec<String>, String> {
 
 #[tauri::command]
 fn get_properties(path: String) -> Result<FileProperties, String> {
-    let metadata = std::fs::metadata(&path)
+    let path_obj = &rawpath::decode(&path);
+    let metadata = std::fs::metadata(path_obj)
         .map_err(|e| format!("Failed to get metadata: {}", e))?;
     
-    let path_obj = Path::new(&path);
     let name = path_obj.file_name()
-        .map(|n| n.to_string_lossy().to_string())
+        .map(rawpath::encode)
         .unwrap_or_default();
+
+    #[cfg(unix)]
+    let (owner, group, mode) = {
+        use std::os::unix::fs::MetadataExt;
+        (Some(metadata.uid()), Some(metadata.gid()), Some(metadata.mode() & 0o7777))
+    };
+    #[cfg(not(unix))]
+    let (owner, group, mode): (Option<u32>, Option<u32>, Option<u32>) = (None, None, None);
+
+    #[cfg(unix)]
+    let (inode, hard_links, device, block_size) = {
+        use std::os::unix::fs::MetadataExt;
+        (Some(metadata.ino()), Some(metadata.nlink()), Some(metadata.dev()), Some(metadata.blksize()))
+    };
+    #[cfg(not(unix))]
+    let (inode, hard_links, device, block_size): (Option<u64>, Option<u64>, Option<u64>, Option<u64>) =
+        (None, None, None, None);
+    let (mount_point, fs_type) = fs_engine::containing_mount(path_obj).unzip();
+
+    let disk_size = fs_engine::allocated_size(path_obj, &metadata);
+    let compression_ratio = (metadata.is_file() && disk_size > 0 && disk_size < metadata.len())
+        .then(|| metadata.len() as f64 / disk_size as f64);
     
     Ok(FileProperties {
         name,
         path: path.clone(),
         size: metadata.len(),
+        disk_size,
+        compression_ratio,
         is_dir: metadata.is_dir(),
         is_symlink: metadata.is_symlink(),
         readonly: metadata.permissions().readonly(),
+        owner,
+        group,
+        owner_name: owner.and_then(users::user_name),
+        group_name: group.and_then(users::group_name),
+        mode,
+        inode,
+        hard_links,
+        device,
+        block_size,
+        mount_point,
+        fs_type,
         created: metadata.created()
             .ok()
             .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
@@ -699,14 +1614,862 @@ fn get_properties(path: String) -> Result<FileProperties, String> {
     })
 }
 
+#[tauri::command]
+async fn get_properties_multi(paths: Vec<String>) -> Result<MultiProperties, String> {
+    tauri::async_runtime::spawn_blocking(move || get_multi_properties(&paths))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_system_users() -> Vec<SystemUser> {
+    users::list_users()
+}
+
+#[tauri::command]
+fn list_system_groups() -> Vec<SystemGroup> {
+    users::list_groups()
+}
+
+#[tauri::command]
+fn get_acl(path: String) -> Result<FileAcl, String> {
+    acl::get_acl(&rawpath::decode(&path))
+}
+
+// Edits POSIX ACLs only; elsewhere this fails and `get_acl` reports `editable: false`.
+#[tauri::command]
+fn set_acl(path: String, entries: Vec<AclEntry>, default_entries: Option<Vec<AclEntry>>) -> Result<(), String> {
+    acl::set_acl(&rawpath::decode(&path), &entries, default_entries.as_deref())
+}
+
+#[tauri::command]
+fn list_previous_versions(path: String) -> Result<Vec<PreviousVersion>, String> {
+    snapshots::list_previous_versions(&rawpath::decode(&path))
+}
+
+#[tauri::command]
+fn restore_previous_version(path: String, version_path: String, as_copy: Option<bool>) -> Result<String, String> {
+    let restored = snapshots::restore_previous_version(&rawpath::decode(&path), &version_path, as_copy.unwrap_or(false))?;
+    update_index(vec![restored.clone()]);
+    Ok(restored)
+}
+
+#[tauri::command]
+async fn list_backup_snapshots(
+    tool: BackupTool,
+    repository: String,
+    password: Option<String>,
+) -> Result<Vec<BackupSnapshot>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        backups::list_snapshots(tool, &repository, password.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn list_backup_dir(
+    tool: BackupTool,
+    repository: String,
+    snapshot: String,
+    path: String,
+    password: Option<String>,
+) -> Result<Vec<BackupEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        backups::list_dir(tool, &repository, &snapshot, &path, password.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn extract_from_backup(
+    tool: BackupTool,
+    repository: String,
+    snapshot: String,
+    path: String,
+    dest_dir: String,
+    password: Option<String>,
+    expected_size: Option<u64>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        backups::extract(
+            tool,
+            &repository,
+            &snapshot,
+            &path,
+            &dest_dir,
+            password.as_deref(),
+            expected_size.unwrap_or(0),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn download_urls(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    urls: Vec<DownloadRequest>,
+    dest_dir: String,
+) -> Result<Vec<u64>, String> {
+    metrics::record_operation("download_urls");
+    let on_job = job_events(app_handle.clone());
+    let on_update = Arc::new(move |status: &DownloadStatus| {
+        let _ = app_handle.emit("download-progress", status);
+    });
+    state
+        .downloads
+        .download_urls(urls, &dest_dir, &state.jobs, on_update, on_job)
+}
+
+#[tauri::command]
+fn get_downloads(state: State<AppState>) -> Vec<DownloadStatus> {
+    state.downloads.list()
+}
+
+#[tauri::command]
+fn cancel_download(state: State<AppState>, id: u64) -> bool {
+    state.jobs.cancel(id).is_ok()
+}
+
+#[tauri::command]
+fn clear_finished_downloads(state: State<AppState>) {
+    state.downloads.clear_finished()
+}
+
+fn job_events(app_handle: tauri::AppHandle) -> JobCallback {
+    Arc::new(move |job: &JobInfo| {
+        let _ = app_handle.emit("job-progress", job);
+    })
+}
+
+#[tauri::command]
+fn list_jobs(state: State<AppState>) -> Vec<JobInfo> {
+    state.jobs.list()
+}
+
+#[tauri::command]
+fn pause_job(state: State<AppState>, id: u64) -> Result<(), String> {
+    state.jobs.pause(id)
+}
+
+#[tauri::command]
+fn resume_job(app_handle: tauri::AppHandle, state: State<AppState>, id: u64) -> Result<(), String> {
+    if state.jobs.get(id).is_some_and(|j| j.state == JobState::Interrupted) {
+        let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+        let plan = transfer::load_transfer(&data_dir, id)?;
+        let job = state.jobs.reopen(id, job_events(app_handle))?;
+        std::thread::spawn(move || {
+            let result = transfer::run_transfer(&data_dir, &plan, &job);
+            update_index(transfer::changed_paths(&plan));
+            job.finish(result);
+        });
+        return Ok(());
+    }
+    state.jobs.resume(id)
+}
+
+#[tauri::command]
+fn cancel_job(app_handle: tauri::AppHandle, state: State<AppState>, id: u64) -> Result<(), String> {
+    if state.jobs.get(id).is_some_and(|j| j.state == JobState::Interrupted) {
+        let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+        transfer::discard_transfer(&data_dir, id)?;
+        state.jobs.discard(id);
+        return Ok(());
+    }
+    state.jobs.cancel(id)
+}
+
+#[tauri::command]
+fn clear_finished_jobs(state: State<AppState>) {
+    state.jobs.clear_finished()
+}
+
+#[tauri::command]
+fn set_job_background(state: State<AppState>, id: u64, background: bool) -> Result<(), String> {
+    state.jobs.set_background(id, background)
+}
+
+#[tauri::command]
+fn set_job_throttle(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    bytes_per_second: Option<u64>,
+) -> Result<(), String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    settings::set(&data_dir, JOB_THROTTLE_SETTING, serde_json::json!(bytes_per_second))?;
+    state.jobs.set_throttle(bytes_per_second);
+    Ok(())
+}
+
+const JOB_THROTTLE_SETTING: &str = "job_throttle_bytes_per_second";
+
+fn init_logging(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let levels = settings::get(&data_dir, logging::SETTING);
+    if let Err(e) = logging::init(&data_dir, levels.as_ref().and_then(|v| v.as_str())) {
+        eprintln!("{}", e);
+    }
+}
+
+#[tauri::command]
+fn get_recent_logs(level: Option<String>, limit: Option<usize>) -> Result<Vec<LogRecord>, String> {
+    logging::recent_logs(level.as_deref(), limit.unwrap_or(500))
+}
+
+fn init_formatting(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let size_units = settings::get(&data_dir, humanize::SIZE_UNITS_SETTING).and_then(|v| serde_json::from_value(v).ok());
+    let date_style = settings::get(&data_dir, humanize::DATE_STYLE_SETTING).and_then(|v| serde_json::from_value(v).ok());
+    humanize::init(size_units, date_style);
+}
+
+fn init_mount_profiles(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let overrides = settings::get(&data_dir, mounts::PROFILES_SETTING).and_then(|v| serde_json::from_value(v).ok());
+    mounts::set_profile_overrides(overrides.unwrap_or_default());
+    let concurrency = settings::get(&data_dir, mounts::CONCURRENCY_SETTING).and_then(|v| serde_json::from_value(v).ok());
+    mounts::set_concurrency_overrides(concurrency.unwrap_or_default());
+}
+
+fn init_metrics(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let enabled = settings::get(&data_dir, metrics::SETTING).and_then(|v| v.as_bool());
+    metrics::init(&data_dir, enabled.unwrap_or(false));
+}
+
+fn init_content_cache(app_handle: &tauri::AppHandle) {
+    if let Ok(dir) = portable::app_data_dir(app_handle) {
+        contentcache::init(dir.join("content_cache"));
+    }
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let limit_mb = settings::get(&data_dir, contentcache::LIMIT_SETTING).and_then(|v| v.as_u64());
+    contentcache::set_limit_mb(limit_mb);
+}
+
+fn init_crawler(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let threads = settings::get(&data_dir, crawler::THREADS_SETTING).and_then(|v| v.as_u64());
+    crawler::set_threads(threads.map(|t| t as usize));
+    let memory_mb = settings::get(&data_dir, crawler::MEMORY_SETTING).and_then(|v| v.as_u64());
+    crawler::set_memory_mb(memory_mb.map(|m| m as usize));
+}
+
+// Every window hears about changes to favorites, settings and the like, made
+// by another window or process, as `state-changed` with the file's name.
+fn watch_state_files(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let app_handle = app_handle.clone();
+    statefile::watch(data_dir, move |file| {
+        if file == "favorites.json" {
+            tray::refresh(&app_handle);
+        }
+        let _ = app_handle.emit("state-changed", file);
+    });
+}
+
+// Windows of another profile say so, so they aren't mistaken for the main one.
+fn show_profile(app_handle: &tauri::AppHandle) {
+    if let (Some(name), Some(window)) = (profile::name(), app_handle.get_webview_window("main")) {
+        let _ = window.set_title(&format!("HARDBORE [{}]", name));
+    }
+}
+
+// `--background` mode sits in the tray and listens for the launcher's shortcut.
+fn init_background_mode(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    if !state.background {
+        return;
+    }
+    if let Err(e) = tray::create(app_handle, state.jobs.clone()) {
+        eprintln!("{}", e);
+    }
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let shortcut = settings::get(&data_dir, launcher::SHORTCUT_SETTING).and_then(|v| v.as_str().map(str::to_string));
+    if let Err(e) = launcher::register(app_handle, shortcut.as_deref().unwrap_or(launcher::DEFAULT_SHORTCUT)) {
+        eprintln!("{}", e);
+    }
+}
+
+fn init_system_search(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let enabled = settings::get(&data_dir, syssearch::SETTING).and_then(|v| v.as_bool());
+    syssearch::set_enabled(enabled.unwrap_or(false));
+    let everything_url = settings::get(&data_dir, syssearch::EVERYTHING_URL_SETTING);
+    syssearch::set_everything_url(everything_url.and_then(|v| v.as_str().map(str::to_string)));
+    let validate = settings::get(&data_dir, indexer::VALIDATE_SETTING).and_then(|v| v.as_bool());
+    indexer::set_validate_results(validate.unwrap_or(false));
+}
+
+#[tauri::command]
+fn get_local_metrics(state: State<AppState>) -> LocalMetrics {
+    let indexer = state.indexer.lock().unwrap();
+    let index = indexer.as_ref().map(|idx| IndexMetrics {
+        indexed_files: idx.get_indexed_count(),
+        database_bytes: idx.database_size(),
+    });
+    metrics::snapshot(index)
+}
+
+#[tauri::command]
+fn reset_local_metrics() -> Result<(), String> {
+    metrics::reset()
+}
+
+fn restore_jobs(app_handle: &tauri::AppHandle) {
+    let Ok(data_dir) = profile::data_dir(app_handle) else {
+        return;
+    };
+    let state = app_handle.state::<AppState>();
+    let throttle = settings::get(&data_dir, JOB_THROTTLE_SETTING).and_then(|v| v.as_u64());
+    state.jobs.set_throttle(throttle);
+    let durable = settings::get(&data_dir, durability::SETTING).and_then(|v| v.as_bool());
+    durability::set_enabled(durable.unwrap_or(false));
+    let backend = settings::get(&data_dir, copyio::BACKEND_SETTING).and_then(|v| serde_json::from_value(v).ok());
+    let buffer_size = settings::get(&data_dir, copyio::BUFFER_SETTING).and_then(|v| v.as_u64());
+    copyio::init(backend, buffer_size.map(|b| b as usize));
+    for interrupted in transfer::interrupted_transfers(&data_dir) {
+        let plan = interrupted.plan;
+        tracing::info!(id = plan.id, "restoring interrupted transfer: {}", plan.title);
+        state.jobs.restore(
+            plan.id,
+            plan.mode.job_kind(),
+            &plan.title,
+            transfer::transfer_paths(&plan.sources, &plan.destination_dir),
+            interrupted.processed,
+            Some(plan.total),
+        );
+    }
+}
+
+const DRAIN_POLL: std::time::Duration = std::time::Duration::from_millis(500);
+const FORCE_QUIT_GRACE: std::time::Duration = std::time::Duration::from_secs(2);
+
+// Closing the window while jobs are running is deferred to the frontend, which
+// answers with finish_jobs_in_background or force_quit.
+fn handle_close_request(window: &tauri::Window, api: &tauri::CloseRequestApi) {
+    let state = window.state::<AppState>();
+    if window.label() == launcher::LABEL || state.background {
+        api.prevent_close();
+        let _ = window.hide();
+        return;
+    }
+    if state.picker_config.lock().unwrap().is_active() || state.app_chooser.lock().unwrap().is_some() {
+        placement::remember(window.app_handle());
+    }
+    let active = state.jobs.active();
+    if active.is_empty() {
+        return;
+    }
+    api.prevent_close();
+    let _ = window.emit_to(window.label(), "close-requested", active);
+}
+
+#[tauri::command]
+fn finish_jobs_in_background(window: tauri::Window, state: State<AppState>) -> Result<(), String> {
+    window.hide().map_err(|e| format!("Failed to hide window: {}", e))?;
+
+    // With the window gone nothing could resume a paused job, and the app
+    // would never exit.
+    state.jobs.resume_all();
+    let jobs = state.jobs.clone();
+    let app_handle = window.app_handle().clone();
+    std::thread::spawn(move || {
+        while jobs.has_active() {
+            std::thread::sleep(DRAIN_POLL);
+        }
+        app_handle.exit(0);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn force_quit(app_handle: tauri::AppHandle, state: State<AppState>) {
+    state.jobs.cancel_all();
+
+    // Give cancelled jobs a moment to remove their partial output.
+    let jobs = state.jobs.clone();
+    std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        while jobs.has_active() && start.elapsed() < FORCE_QUIT_GRACE {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        app_handle.exit(0);
+    });
+}
+
+#[tauri::command]
+async fn who_has_open(path: String) -> Result<Vec<FileHolder>, String> {
+    tauri::async_runtime::spawn_blocking(move || openfiles::who_has_open(&rawpath::decode(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn package_cache_dir(app_handle: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let data_dir = profile::data_dir(app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(data_dir.join("package_cache"))
+}
+
+#[tauri::command]
+async fn get_package_info(app_handle: tauri::AppHandle, path: String) -> Result<PackageInfo, String> {
+    let cache_dir = package_cache_dir(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || packaging::package_info(&rawpath::decode(&path), &cache_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn integrate_appimage(app_handle: tauri::AppHandle, path: String) -> Result<PackageInfo, String> {
+    let cache_dir = package_cache_dir(&app_handle)?;
+    let info = tauri::async_runtime::spawn_blocking(move || packaging::integrate_appimage(&rawpath::decode(&path), &cache_dir))
+        .await
+        .map_err(|e| e.to_string())??;
+    tracing::info!(path = %info.path, "integrated AppImage");
+    Ok(info)
+}
+
+#[tauri::command]
+async fn run_self_benchmark(root: Option<String>) -> Result<BenchmarkReport, String> {
+    tauri::async_runtime::spawn_blocking(move || benchmark::run_self_benchmark(root))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn eject_device(state: State<'_, AppState>, path: String) -> Result<EjectReport, String> {
+    let jobs = state.jobs.clone();
+    tauri::async_runtime::spawn_blocking(move || eject::safe_eject(&rawpath::decode(&path), &jobs))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_mount_for_path(path: String) -> Result<MountInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || mounts::get_mount_for_path(&rawpath::decode(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Works for plugin VFS locations too, which count as remote.
+#[tauri::command]
+async fn get_mount_behavior(path: String) -> Result<MountBehavior, String> {
+    tauri::async_runtime::spawn_blocking(move || mounts::behavior_for(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Resolves to the first problem found, or None when the transfer can start.
+#[tauri::command]
+async fn preflight_transfer(
+    sources: Vec<String>,
+    destination_dir: String,
+    mode: TransferMode,
+) -> Result<Option<PreflightError>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Remote backends report no space or permissions up front.
+        if plugins::vfs_backend(&destination_dir).is_some() {
+            return None;
+        }
+        let required = transfer::required_space(&sources, &destination_dir, mode);
+        preflight::check_destination(&rawpath::decode(&destination_dir), required).err()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn start_transfer(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    sources: Vec<String>,
+    destination_dir: String,
+    mode: TransferMode,
+    skip_identical: Option<SkipIdentical>,
+) -> Result<u64, String> {
+    metrics::record_operation("start_transfer");
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    let title = transfer::transfer_title(&sources, mode);
+    let remote = transfer::remote_backend(&sources, &destination_dir)?;
+    let paths = transfer::transfer_paths(&sources, &destination_dir);
+    let job = state.jobs.create(mode.job_kind(), &title, paths, job_events(app_handle));
+    let id = job.id();
+    let plugins = state.plugins.clone();
+    std::thread::spawn(move || {
+        let skip_identical = skip_identical.unwrap_or_default();
+        let plan = transfer::plan_transfer(id, &title, sources, destination_dir, mode, skip_identical);
+        let result = match remote {
+            Some(_) => transfer::run_remote_transfer(&plan, &job, |source, destination| {
+                let mut host = plugins.lock().unwrap();
+                if host.is_none() {
+                    *host = Some(PluginHost::new(&data_dir)?);
+                }
+                let host = host.as_mut().unwrap();
+                match mode {
+                    TransferMode::Copy => host.vfs_copy(source, destination),
+                    TransferMode::Move => host.vfs_rename(source, destination),
+                }
+            }),
+            None => transfer::run_transfer(&data_dir, &plan, &job),
+        };
+        dircache::clear();
+        // Whatever got across before a failure or cancel is indexed too.
+        if remote.is_none() {
+            update_index(transfer::changed_paths(&plan));
+        }
+        job.finish(result);
+    });
+    Ok(id)
+}
+
+#[tauri::command]
+fn share_files(
+    state: State<AppState>,
+    paths: Vec<String>,
+    use_token: Option<bool>,
+    one_time: Option<bool>,
+) -> Result<ShareInfo, String> {
+    let mut sharing = state.sharing.lock().unwrap();
+    if let Some(previous) = sharing.take() {
+        previous.stop();
+    }
+    let server = share::share_files(&paths, use_token.unwrap_or(true), one_time.unwrap_or(false))?;
+    emblems::set_shared_paths(&paths);
+    let info = server.info.clone();
+    *sharing = Some(server);
+    Ok(info)
+}
+
+#[tauri::command]
+fn stop_sharing(state: State<AppState>) {
+    if let Some(server) = state.sharing.lock().unwrap().take() {
+        server.stop();
+    }
+    emblems::set_shared_paths(&[]);
+}
+
+#[tauri::command]
+fn get_share_status(state: State<AppState>) -> Option<ShareInfo> {
+    state
+        .sharing
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|s| s.is_running())
+        .map(|s| s.info.clone())
+}
+
+#[tauri::command]
+async fn list_send_targets() -> Result<Vec<SendTarget>, String> {
+    tauri::async_runtime::spawn_blocking(sendto::list_send_targets)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn send_to(target: SendTarget, paths: Vec<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || sendto::send_to(&target, &paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn copy_path_to_clipboard(paths: Vec<String>, format: Option<PathFormat>) -> Result<String, String> {
+    clipboard::copy_paths(&paths, format.unwrap_or(PathFormat::Plain))
+}
+
+#[tauri::command]
+fn pty_spawn(
+    app_handle: tauri::AppHandle,
+    state: State<AppState>,
+    cwd: String,
+    shell: Option<String>,
+    rows: Option<u16>,
+    cols: Option<u16>,
+) -> Result<u64, String> {
+    let on_event = Arc::new(move |event: PtyEvent| {
+        let _ = match event {
+            PtyEvent::Output(output) => app_handle.emit("pty-output", output),
+            PtyEvent::Cwd(cwd) => app_handle.emit("pty-cwd", cwd),
+            PtyEvent::Exit(exit) => app_handle.emit("pty-exit", exit),
+        };
+    });
+    state
+        .terminals
+        .spawn(&cwd, shell.as_deref(), rows.unwrap_or(24), cols.unwrap_or(80), on_event)
+}
+
+#[tauri::command]
+fn pty_write(state: State<AppState>, id: u64, data: String) -> Result<(), String> {
+    state.terminals.write(id, &data)
+}
+
+#[tauri::command]
+fn pty_cd(state: State<AppState>, id: u64, path: String) -> Result<(), String> {
+    state.terminals.change_directory(id, &path)
+}
+
+#[tauri::command]
+fn pty_resize(state: State<AppState>, id: u64, rows: u16, cols: u16) -> Result<(), String> {
+    state.terminals.resize(id, rows, cols)
+}
+
+#[tauri::command]
+fn pty_kill(state: State<AppState>, id: u64) -> Result<(), String> {
+    state.terminals.kill(id)
+}
+
+// Plugin calls block on a child process, so they run off the main thread with
+// the host loaded lazily on first use.
+async fn with_plugins<T: Send + 'static>(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    f: impl FnOnce(&mut PluginHost) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let data_dir = profile::data_dir(app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    let plugins = state.plugins.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut host = plugins.lock().unwrap();
+        if host.is_none() {
+            *host = Some(PluginHost::new(&data_dir)?);
+        }
+        f(host.as_mut().unwrap())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn list_plugins(app_handle: tauri::AppHandle, state: State<'_, AppState>) -> Result<Vec<PluginInfo>, String> {
+    with_plugins(&app_handle, &state, |host| Ok(host.list())).await
+}
+
+#[tauri::command]
+async fn reload_plugins(app_handle: tauri::AppHandle, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    with_plugins(&app_handle, &state, |host| host.reload()).await
+}
+
+#[tauri::command]
+async fn set_plugin_permissions(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+    granted: Vec<PluginCapability>,
+) -> Result<(), String> {
+    with_plugins(&app_handle, &state, move |host| host.set_permissions(&id, enabled, granted)).await
+}
+
+#[tauri::command]
+async fn plugin_preview(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Option<PluginPreview>, String> {
+    with_plugins(&app_handle, &state, move |host| host.preview(&path)).await
+}
+
+#[tauri::command]
+async fn plugin_context_actions(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<PluginMenuItem>, String> {
+    with_plugins(&app_handle, &state, move |host| Ok(host.context_actions(&paths))).await
+}
+
+#[tauri::command]
+async fn run_plugin_action(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    plugin_id: String,
+    action_id: String,
+    paths: Vec<String>,
+) -> Result<Option<String>, String> {
+    with_plugins(&app_handle, &state, move |host| host.run_action(&plugin_id, &action_id, &paths)).await
+}
+
+#[tauri::command]
+async fn plugin_vfs_list(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    uri: String,
+) -> Result<Vec<PluginVfsEntry>, String> {
+    with_plugins(&app_handle, &state, move |host| host.vfs_list(&uri)).await
+}
+
+#[tauri::command]
+async fn plugin_vfs_read(app_handle: tauri::AppHandle, state: State<'_, AppState>, uri: String) -> Result<String, String> {
+    with_plugins(&app_handle, &state, move |host| host.vfs_read(&uri)).await
+}
+
+#[tauri::command]
+fn get_settings(app_handle: tauri::AppHandle) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(settings::load(&data_dir))
+}
+
+#[tauri::command]
+fn set_setting(app_handle: tauri::AppHandle, key: String, value: serde_json::Value) -> Result<(), String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    if key == durability::SETTING {
+        durability::set_enabled(value.as_bool().unwrap_or(false));
+    }
+    if key == copyio::BACKEND_SETTING {
+        copyio::set_backend(serde_json::from_value(value.clone()).unwrap_or_default());
+    }
+    if key == copyio::BUFFER_SETTING {
+        copyio::set_buffer_size(value.as_u64().map(|b| b as usize));
+    }
+    if key == metrics::SETTING {
+        metrics::set_enabled(value.as_bool().unwrap_or(false));
+    }
+    if key == logging::SETTING {
+        logging::set_levels(value.as_str().unwrap_or(logging::DEFAULT_LEVELS))?;
+    }
+    if key == humanize::SIZE_UNITS_SETTING {
+        humanize::set_size_units(serde_json::from_value(value.clone()).unwrap_or_default());
+    }
+    if key == humanize::DATE_STYLE_SETTING {
+        humanize::set_date_style(serde_json::from_value(value.clone()).unwrap_or_default());
+    }
+    if key == mounts::PROFILES_SETTING {
+        mounts::set_profile_overrides(serde_json::from_value(value.clone()).unwrap_or_default());
+    }
+    if key == mounts::CONCURRENCY_SETTING {
+        mounts::set_concurrency_overrides(serde_json::from_value(value.clone()).unwrap_or_default());
+    }
+    if key == crawler::THREADS_SETTING {
+        crawler::set_threads(value.as_u64().map(|t| t as usize));
+    }
+    if key == crawler::MEMORY_SETTING {
+        crawler::set_memory_mb(value.as_u64().map(|m| m as usize));
+    }
+    if key == contentcache::LIMIT_SETTING {
+        contentcache::set_limit_mb(value.as_u64());
+    }
+    if key == syssearch::SETTING {
+        syssearch::set_enabled(value.as_bool().unwrap_or(false));
+    }
+    if key == syssearch::EVERYTHING_URL_SETTING {
+        syssearch::set_everything_url(value.as_str().map(str::to_string));
+    }
+    if key == indexer::VALIDATE_SETTING {
+        indexer::set_validate_results(value.as_bool().unwrap_or(false));
+    }
+    settings::set(&data_dir, &key, value)
+}
+
+#[tauri::command]
+fn list_scripts(app_handle: tauri::AppHandle) -> Result<Vec<ScriptInfo>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    scripting::list_scripts(&data_dir)
+}
+
+#[tauri::command]
+async fn run_script(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    selection: Vec<String>,
+    cwd: String,
+) -> Result<ScriptResult, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    let scripts = state.scripts.clone();
+    let jobs = state.jobs.clone();
+    let on_event = Arc::new(move |event: ScriptEvent| {
+        let _ = match event {
+            ScriptEvent::Output(output) => app_handle.emit("script-output", output),
+            ScriptEvent::Prompt(prompt) => app_handle.emit("script-prompt", prompt),
+            ScriptEvent::Job(job) => app_handle.emit("job-progress", job),
+        };
+    });
+
+    tauri::async_runtime::spawn_blocking(move || scripts.run(&data_dir, &jobs, &name, selection, &cwd, on_event))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn answer_script_prompt(state: State<AppState>, id: u64, value: Option<String>) -> Result<(), String> {
+    state.scripts.answer(id, value)
+}
+
+#[tauri::command]
+async fn get_context_menu(app_handle: tauri::AppHandle, paths: Vec<String>) -> Result<Vec<ContextMenuEntry>, String> {
+    let config_dir = portable::app_config_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || contextmenu::get_context_menu(&config_dir, &paths))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn run_context_menu_entry(app_handle: tauri::AppHandle, id: String, paths: Vec<String>) -> Result<(), String> {
+    let config_dir = portable::app_config_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || contextmenu::run_context_menu_entry(&config_dir, &id, &paths))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn list_user_scripts(app_handle: tauri::AppHandle) -> Result<Vec<UserScript>, String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    Ok(userscripts::list_user_scripts(&data_dir))
+}
+
+#[tauri::command]
+fn run_user_script(
+    app_handle: tauri::AppHandle,
+    path: String,
+    selection: Vec<String>,
+    current_dir: String,
+) -> Result<(), String> {
+    let data_dir = profile::data_dir(&app_handle).map_err(|e: tauri::Error| e.to_string())?;
+    userscripts::run_user_script(&data_dir, &path, &selection, &current_dir)
+}
+
 #[derive(serde::Serialize)]
 struct FileProperties {
     name: String,
     path: String,
     size: u64,
+    disk_size: u64,
+    compression_ratio: Option<f64>,
     is_dir: bool,
     is_symlink: bool,
     readonly: bool,
+    owner: Option<u32>,
+    group: Option<u32>,
+    owner_name: Option<String>,
+    group_name: Option<String>,
+    mode: Option<u32>,
+    inode: Option<u64>,
+    hard_links: Option<u64>,
+    // st_dev; equal on both ends means a move is a cheap rename.
+    device: Option<u64>,
+    block_size: Option<u64>,
+    mount_point: Option<String>,
+    fs_type: Option<String>,
     created: Option<u64>,
     modified: Option<u64>,
     accessed: Option<u64>,
@@ -718,16 +2481,36 @@ struct MountPoint {
     path: String,
     device: String,
     fs_type: String,
+    profile: MountProfile,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let args: Vec<String> = std::env::args().collect();
+    portable::init(args.iter().any(|a| a == "--portable"));
+    if let Some(i) = args.iter().position(|a| a == "--profile") {
+        let set = args.get(i + 1).ok_or("--profile needs a name".to_string()).and_then(|n| profile::set(n));
+        if let Err(e) = set {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    if args.iter().any(|a| a == "--install-portal") {
+        std::process::exit(install_portal_cli());
+    }
+    if args.iter().any(|a| a == "--daemon") {
+        std::process::exit(daemon_cli());
+    }
     let mut picker_mode = PickerMode::Disabled;
     let mut allow_multiple = false;
     let mut file_types: Option<Vec<String>> = None;
     let mut start_dir: Option<String> = None;
     let mut current_name: Option<String> = None;
+    let mut restrict_to_dir: Option<String> = None;
+    let mut app_chooser: Option<ChooserRequest> = None;
+    let mut window_hints = WindowHints::default();
+    let mut headless = false;
+    let mut background = false;
     
     let mut i = 1;
     while i < args.len() {
@@ -755,35 +2538,149 @@ pub fn run() {
                     i += 1;
                 }
             }
+            "--restrict-to-dir" if i + 1 < args.len() => {
+                restrict_to_dir = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--headless" => headless = true,
+            "--background" => background = true,
+            // Already applied before anything touched the data dir.
+            "--profile" => i += 1,
+            "--portable" => {}
+            "--window-size" if i + 1 < args.len() => {
+                window_hints.size = args[i + 1]
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                i += 1;
+            }
+            "--theme" if i + 1 < args.len() => {
+                window_hints.theme = match args[i + 1].as_str() {
+                    "dark" => Some(tauri::Theme::Dark),
+                    "light" => Some(tauri::Theme::Light),
+                    _ => None,
+                };
+                i += 1;
+            }
+            "--geometry" if i + 1 < args.len() => {
+                window_hints.geometry = placement::parse_geometry(&args[i + 1]);
+                i += 1;
+            }
+            "--parent-window" if i + 1 < args.len() => {
+                window_hints.parent = Some(args[i + 1].clone()).filter(|p| !p.is_empty());
+                i += 1;
+            }
+            "--choose-app" => {
+                app_chooser.get_or_insert_with(ChooserRequest::default);
+            }
+            "--choices" if i + 1 < args.len() => {
+                let request = app_chooser.get_or_insert_with(ChooserRequest::default);
+                request.choices = args[i + 1].split(',').filter(|c| !c.is_empty()).map(|c| c.to_string()).collect();
+                i += 1;
+            }
+            "--content-type" if i + 1 < args.len() => {
+                app_chooser.get_or_insert_with(ChooserRequest::default).content_type = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--target" if i + 1 < args.len() => {
+                app_chooser.get_or_insert_with(ChooserRequest::default).target = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--last-choice" if i + 1 < args.len() => {
+                app_chooser.get_or_insert_with(ChooserRequest::default).last_choice = Some(args[i + 1].clone());
+                i += 1;
+            }
             _ => {}
         }
         i += 1;
     }
     
-    let picker_config = PickerConfig {
+    let mut picker_config = PickerConfig {
         mode: picker_mode,
         allow_multiple,
         file_types,
         start_dir,
         current_name,
+        restrict_to_dir,
     };
+    picker_config.normalize();
+    if headless && picker_config.is_active() {
+        std::process::exit(select_headless(&picker_config));
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .manage(AppState {
             indexer: Mutex::new(None),
             picker_config: Mutex::new(picker_config),
+            app_chooser: Mutex::new(app_chooser),
+            jobs: JobManager::new(),
+            listings: DeltaTracker::new(),
+            downloads: DownloadManager::new(),
+            sharing: Mutex::new(None),
+            terminals: PtyManager::new(),
+            plugins: Arc::new(Mutex::new(None)),
+            scripts: Arc::new(ScriptHost::new()),
+            journals: ChangeJournals::new(),
+            background,
+        })
+        .setup(move |app| {
+            placement::apply(app.handle(), &window_hints);
+            show_profile(app.handle());
+            init_logging(app.handle());
+            events::init(app.handle());
+            init_metrics(app.handle());
+            init_formatting(app.handle());
+            init_mount_profiles(app.handle());
+            init_system_search(app.handle());
+            init_crawler(app.handle());
+            init_content_cache(app.handle());
+            restore_jobs(app.handle());
+            watch_app_choices(app.handle());
+            watch_state_files(app.handle());
+            init_background_mode(app.handle());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            match event {
+                tauri::WindowEvent::CloseRequested { api, .. } => handle_close_request(window, api),
+                tauri::WindowEvent::Focused(false) if window.label() == launcher::LABEL => {
+                    let _ = window.hide();
+                }
+                _ => {}
+            }
         })
         .invoke_handler(tauri::generate_handler![
             init_indexer,
             read_dir,
+            read_dir_delta,
+            read_dir_packed,
+            list_columns,
+            get_icon_for,
+            clear_caches,
             preview_file,
+            preview_text_range,
+            search_in_file,
+            diff_files,
+            generate_tree,
             get_home,
             get_current_dir,
             start_indexing,
+            compact_index,
+            list_index_shards,
+            remove_index_root,
+            start_change_journal,
+            stop_change_journal,
+            list_change_journals,
             search_files,
+            remove_stale_entries,
             get_indexer_status,
             get_indexed_count,
+            get_index_breakdown,
+            get_activity_timeline,
+            query_index,
+            suggest_reclaim,
+            reclaim_directories,
             clear_index,
             delete_path,
             copy_path,
@@ -792,21 +2689,116 @@ pub fn run() {
             batch_move_paths,
             batch_delete_paths,
             batch_duplicate_paths,
+            find_cleanup_candidates,
+            remove_cleanup_candidates,
             rename_path,
+            apply_bulk_rename,
+            rebase_index_paths,
+            validate_filename,
             duplicate_path,
             open_path,
+            get_profile,
+            is_background_mode,
+            launcher_search,
+            launcher_open,
+            hide_launcher,
+            open_in_torrent_client,
+            preview_sqlite,
             show_in_folder,
             open_terminal,
             get_properties,
+            get_properties_multi,
+            list_system_users,
+            list_system_groups,
+            get_acl,
+            set_acl,
+            list_previous_versions,
+            restore_previous_version,
+            list_backup_snapshots,
+            list_backup_dir,
+            extract_from_backup,
+            download_urls,
+            get_downloads,
+            cancel_download,
+            clear_finished_downloads,
+            list_jobs,
+            pause_job,
+            resume_job,
+            cancel_job,
+            clear_finished_jobs,
+            set_job_background,
+            set_job_throttle,
+            get_mount_for_path,
+            get_mount_behavior,
+            preflight_transfer,
+            start_transfer,
+            finish_jobs_in_background,
+            force_quit,
+            share_files,
+            stop_sharing,
+            get_share_status,
+            list_send_targets,
+            copy_path_to_clipboard,
+            pty_spawn,
+            pty_write,
+            pty_cd,
+            pty_resize,
+            pty_kill,
+            list_plugins,
+            reload_plugins,
+            set_plugin_permissions,
+            plugin_preview,
+            plugin_context_actions,
+            run_plugin_action,
+            plugin_vfs_list,
+            plugin_vfs_read,
+            get_settings,
+            set_setting,
+            list_scripts,
+            run_script,
+            answer_script_prompt,
+            get_context_menu,
+            run_context_menu_entry,
+            list_user_scripts,
+            run_user_script,
+            send_to,
             create_directory,
             get_mount_points,
+            eject_device,
+            who_has_open,
+            run_self_benchmark,
+            get_package_info,
+            integrate_appimage,
+            get_recent_logs,
+            get_local_metrics,
+            reset_local_metrics,
+            create_search_location,
+            list_saved_searches,
+            delete_saved_search,
+            list_workspaces,
+            save_workspace,
+            open_workspace,
+            delete_workspace,
             add_favorite,
             remove_favorite,
             get_favorites,
+            import_from,
+            import_favorites,
+            get_quick_bookmarks,
+            set_quick_bookmark,
             path_exists,
             get_picker_config,
             select_files,
+            check_save_name,
+            check_save_target,
             cancel_picker,
+            get_app_chooser,
+            chooser_applications,
+            choose_application,
+            applications_for,
+            open_with,
+            install_portal_service,
+            portal_status,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
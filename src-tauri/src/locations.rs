@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::fs_engine::{get_file_entry, DirectoryContents, FileEntry};
+use crate::humanize::format_size;
+use crate::indexer::Indexer;
+use crate::statefile;
+
+pub const SCHEME: &str = "hardbore://";
+
+const RECENT_FILE: &str = "recent.json";
+const FAVORITES_FILE: &str = "favorites.json";
+const SEARCHES_FILE: &str = "saved_searches.json";
+const QUICK_BOOKMARKS_FILE: &str = "quick_bookmarks.json";
+const RECENT_LIMIT: usize = 100;
+const SEARCH_LIMIT: usize = 1000;
+const QUICK_BOOKMARK_SLOTS: std::ops::RangeInclusive<u8> = 1..=9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub query: String,
+    pub uri: String,
+}
+
+// A directory bound to one of the number keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickBookmark {
+    pub slot: u8,
+    pub path: String,
+}
+
+// Live searches only exist for this session; saved ones are kept on disk.
+static LIVE_SEARCHES: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+static NEXT_LIVE_ID: AtomicU64 = AtomicU64::new(1);
+
+// Locations that list entries gathered from elsewhere. Each entry keeps its real
+// path, so every file operation on it acts on the underlying file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VirtualLocation {
+    Recent,
+    Starred,
+    Trash,
+    Search(String),
+}
+
+impl VirtualLocation {
+    pub fn parse(uri: &str) -> Option<Self> {
+        match uri.strip_prefix(SCHEME)?.trim_end_matches('/') {
+            "recent" => Some(Self::Recent),
+            "starred" => Some(Self::Starred),
+            "trash" => Some(Self::Trash),
+            rest => match rest.strip_prefix("search/") {
+                Some(id) if !id.is_empty() && !id.contains('/') => Some(Self::Search(id.to_string())),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn search_uri(id: &str) -> String {
+    format!("{}search/{}", SCHEME, id)
+}
+
+fn load_searches(data_dir: &Path) -> Vec<SavedSearch> {
+    statefile::read(&data_dir.join(SEARCHES_FILE))
+}
+
+fn update_searches<R>(data_dir: &Path, f: impl FnOnce(&mut Vec<SavedSearch>) -> R) -> Result<R, String> {
+    statefile::update(&data_dir.join(SEARCHES_FILE), "saved searches", f)
+}
+
+pub fn list_saved_searches(data_dir: &Path) -> Vec<SavedSearch> {
+    load_searches(data_dir)
+}
+
+// Returns the location to navigate to. Saving the same query twice reuses it.
+pub fn create_search(data_dir: &Path, query: &str, save: bool) -> Result<SavedSearch, String> {
+    if !save {
+        let id = format!("live-{}", NEXT_LIVE_ID.fetch_add(1, Ordering::SeqCst));
+        LIVE_SEARCHES
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id.clone(), query.to_string());
+        return Ok(SavedSearch {
+            uri: search_uri(&id),
+            id,
+            query: query.to_string(),
+        });
+    }
+
+    update_searches(data_dir, |searches| {
+        if let Some(existing) = searches.iter().find(|s| s.query == query) {
+            return existing.clone();
+        }
+        let id = format!("{:x}", chrono::Utc::now().timestamp_millis());
+        let search = SavedSearch {
+            uri: search_uri(&id),
+            id,
+            query: query.to_string(),
+        };
+        searches.push(search.clone());
+        search
+    })
+}
+
+pub fn delete_saved_search(data_dir: &Path, id: &str) -> Result<(), String> {
+    update_searches(data_dir, |searches| searches.retain(|s| s.id != id))
+}
+
+// Sorted by slot; an unreadable file counts as no bookmarks.
+pub fn get_quick_bookmarks(data_dir: &Path) -> Vec<QuickBookmark> {
+    let mut bookmarks = statefile::read(&data_dir.join(QUICK_BOOKMARKS_FILE));
+    tidy_quick_bookmarks(&mut bookmarks);
+    bookmarks
+}
+
+fn tidy_quick_bookmarks(bookmarks: &mut Vec<QuickBookmark>) {
+    bookmarks.retain(|b| QUICK_BOOKMARK_SLOTS.contains(&b.slot));
+    bookmarks.sort_by_key(|b| b.slot);
+    bookmarks.dedup_by_key(|b| b.slot);
+}
+
+// Binds the slot to a directory, replacing what it held; None clears it.
+pub fn set_quick_bookmark(data_dir: &Path, slot: u8, path: Option<&str>) -> Result<Vec<QuickBookmark>, String> {
+    if !QUICK_BOOKMARK_SLOTS.contains(&slot) {
+        return Err(format!("Invalid bookmark slot: {} (expected 1-9)", slot));
+    }
+    if let Some(path) = path {
+        if !is_virtual(path) && !crate::rawpath::decode(path).is_dir() {
+            return Err(format!("Not a directory: {}", path));
+        }
+    }
+
+    statefile::update(&data_dir.join(QUICK_BOOKMARKS_FILE), "quick bookmarks", |bookmarks: &mut Vec<QuickBookmark>| {
+        tidy_quick_bookmarks(bookmarks);
+        bookmarks.retain(|b| b.slot != slot);
+        if let Some(path) = path {
+            bookmarks.push(QuickBookmark { slot, path: path.to_string() });
+            bookmarks.sort_by_key(|b| b.slot);
+        }
+        bookmarks.clone()
+    })
+}
+
+fn search_query(data_dir: &Path, id: &str) -> Option<String> {
+    let live = LIVE_SEARCHES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|searches| searches.get(id).cloned());
+    live.or_else(|| load_searches(data_dir).into_iter().find(|s| s.id == id).map(|s| s.query))
+}
+
+// Re-run on every read, so the folder reflects the current index.
+fn search_paths(indexer: &Indexer, query: &str, include_hidden: bool) -> Vec<PathBuf> {
+    let mut results = indexer.search_fts(query, SEARCH_LIMIT, include_hidden);
+    if results.is_empty() {
+        results = indexer.search_fuzzy(query, SEARCH_LIMIT, include_hidden);
+    }
+    results.into_iter().map(|r| PathBuf::from(r.path)).collect()
+}
+
+pub fn is_virtual(path: &str) -> bool {
+    path.starts_with(SCHEME)
+}
+
+fn read_paths(file: &Path) -> Vec<String> {
+    statefile::read(file)
+}
+
+pub fn favorites(data_dir: &Path) -> Vec<String> {
+    read_paths(&data_dir.join(FAVORITES_FILE))
+}
+
+// Appends the paths not yet among the favorites, returning how many were new.
+pub fn add_favorites(data_dir: &Path, paths: &[String]) -> Result<usize, String> {
+    statefile::update(&data_dir.join(FAVORITES_FILE), "favorites", |favorites: &mut Vec<String>| {
+        let before = favorites.len();
+        for path in paths {
+            if !favorites.contains(path) {
+                favorites.push(path.clone());
+            }
+        }
+        favorites.len() - before
+    })
+}
+
+pub fn remove_favorite(data_dir: &Path, path: &str) -> Result<(), String> {
+    statefile::update(&data_dir.join(FAVORITES_FILE), "favorites", |favorites: &mut Vec<String>| {
+        favorites.retain(|f| f != path)
+    })
+}
+
+// Most recent first; reopening a path moves it back to the top.
+pub fn record_access(data_dir: &Path, path: &str) -> Result<(), String> {
+    statefile::update(&data_dir.join(RECENT_FILE), "recent files", |recent: &mut Vec<String>| {
+        recent.retain(|p| p != path);
+        recent.insert(0, path.to_string());
+        recent.truncate(RECENT_LIMIT);
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn trash_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("Trash").join("files"))
+}
+
+#[cfg(target_os = "macos")]
+fn trash_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|d| d.join(".Trash"))
+}
+
+// The Recycle Bin is not a plain directory and needs the shell API to list.
+#[cfg(windows)]
+fn trash_dir() -> Option<PathBuf> {
+    None
+}
+
+fn trash_paths() -> Vec<PathBuf> {
+    let Some(dir) = trash_dir() else {
+        return Vec::new();
+    };
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+fn entries_for(paths: Vec<PathBuf>) -> Vec<FileEntry> {
+    paths.iter().filter_map(|p| get_file_entry(p)).collect()
+}
+
+pub fn read_virtual(
+    uri: &str,
+    data_dir: &Path,
+    show_hidden: bool,
+    indexer: Option<&Indexer>,
+) -> Result<DirectoryContents, String> {
+    let location = VirtualLocation::parse(uri).ok_or_else(|| format!("Unknown location: {}", uri))?;
+
+    let entries = match location {
+        VirtualLocation::Recent => entries_for(read_paths(&data_dir.join(RECENT_FILE)).into_iter().map(PathBuf::from).collect()),
+        VirtualLocation::Starred => entries_for(read_paths(&data_dir.join(FAVORITES_FILE)).into_iter().map(PathBuf::from).collect()),
+        VirtualLocation::Search(id) => {
+            let query = search_query(data_dir, &id).ok_or_else(|| format!("Unknown search: {}", id))?;
+            let indexer = indexer.ok_or("Indexer not initialized")?;
+            entries_for(search_paths(indexer, &query, show_hidden))
+                .into_iter()
+                .filter(|e| show_hidden || !e.hidden)
+                .collect()
+        }
+        VirtualLocation::Trash => {
+            let mut entries: Vec<FileEntry> = entries_for(trash_paths())
+                .into_iter()
+                .filter(|e| show_hidden || !e.hidden)
+                .collect();
+            entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            });
+            entries
+        }
+    };
+
+    Ok(DirectoryContents {
+        path: uri.to_string(),
+        parent: None,
+        total_items: entries.len(),
+        total_size: entries.iter().map(|e| e.size).sum(),
+        total_disk_size: entries.iter().map(|e| e.disk_size).sum(),
+        total_size_text: format_size(entries.iter().map(|e| e.size).sum()),
+        entries,
+    })
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+// Directives in the `target=level` form, e.g. `info,hardbore_lib::indexer=debug`.
+pub const SETTING: &str = "log_levels";
+pub const DEFAULT_LEVELS: &str = "info";
+
+const LOG_DIR: &str = "logs";
+const LOG_FILES_KEPT: usize = 7;
+const RECENT_CAPACITY: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+static RECENT: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+// Keeps the latest events in memory for the debug console.
+struct RecentLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let record = LogRecord {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message + visitor.fields.as_str(),
+        };
+
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+}
+
+fn parse_levels(levels: &str) -> Result<Targets, String> {
+    Targets::from_str(levels).map_err(|e| format!("Failed to parse log levels: {}", e))
+}
+
+// Installs the global subscriber: stderr, a daily rolling file in
+// `<data_dir>/logs` and the in-memory buffer, all behind one reloadable filter.
+pub fn init(data_dir: &Path, levels: Option<&str>) -> Result<(), String> {
+    let targets = levels
+        .and_then(|l| parse_levels(l).ok())
+        .unwrap_or_else(|| parse_levels(DEFAULT_LEVELS).unwrap());
+    let (filter, handle) = reload::Layer::new(targets);
+
+    let log_dir = data_dir.join(LOG_DIR);
+    std::fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("hardbore")
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES_KEPT)
+        .build(log_dir)
+        .map_err(|e| format!("Failed to create log file: {}", e))?;
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(fmt::layer().with_ansi(false).with_writer(file))
+        .with(RecentLayer)
+        .try_init()
+        .map_err(|e| format!("Failed to initialize logging: {}", e))?;
+
+    let _ = FILTER.set(handle);
+    Ok(())
+}
+
+pub fn set_levels(levels: &str) -> Result<(), String> {
+    let targets = parse_levels(levels)?;
+    match FILTER.get() {
+        Some(handle) => handle
+            .reload(targets)
+            .map_err(|e| format!("Failed to update log levels: {}", e)),
+        None => Ok(()),
+    }
+}
+
+// Newest last. `level` is the least severe level included, so `warn` returns
+// warnings and errors.
+pub fn recent_logs(level: Option<&str>, limit: usize) -> Result<Vec<LogRecord>, String> {
+    let min = match level {
+        Some(level) => Level::from_str(level).map_err(|e| format!("Failed to parse log level: {}", e))?,
+        None => Level::TRACE,
+    };
+
+    let recent = RECENT.lock().unwrap();
+    let mut records: Vec<LogRecord> = recent
+        .iter()
+        .rev()
+        .filter(|r| Level::from_str(&r.level).map(|l| l <= min).unwrap_or(true))
+        .take(limit)
+        .cloned()
+        .collect();
+    records.reverse();
+    Ok(records)
+}
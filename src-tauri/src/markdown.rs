@@ -0,0 +1,73 @@
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::path::Path;
+
+use crate::clipboard::file_uri;
+use crate::share::percent_encode;
+
+// The frontend's `convertFileSrc`, so images load through the asset protocol.
+fn asset_url(path: &Path) -> String {
+    let encoded = percent_encode(&path.to_string_lossy());
+    if cfg!(windows) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+fn is_relative(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with('#') && !url.starts_with('/') && !url.contains(':')
+}
+
+// Drops any `?query` or `#fragment` and undoes percent-encoding, leaving a
+// path relative to the document.
+fn relative_path(url: &str) -> String {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    crate::downloads::decode_percent(&url[..end])
+}
+
+fn resolve<'a>(url: CowStr<'a>, base_dir: &Path, image: bool) -> CowStr<'a> {
+    if !is_relative(&url) {
+        return url;
+    }
+    let path = base_dir.join(relative_path(&url));
+    if image {
+        asset_url(&path).into()
+    } else {
+        file_uri(&path.to_string_lossy()).into()
+    }
+}
+
+// Renders to HTML that is safe to inject: scripts, event handlers and inline
+// styles are stripped. Relative images point at the asset protocol and relative
+// links become file:// URLs the frontend hands to `open_path`.
+pub fn render(markdown: &str, base_dir: &Path) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: resolve(dest_url, base_dir, true),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: resolve(dest_url, base_dir, false),
+            title,
+            id,
+        }),
+        other => other,
+    });
+
+    let mut unsafe_html = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, events);
+
+    ammonia::Builder::default()
+        .add_url_schemes(&["asset", "file"])
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .clean(&unsafe_html)
+        .to_string()
+}
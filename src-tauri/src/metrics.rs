@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Opt-in, and nothing recorded here is ever sent anywhere.
+pub const SETTING: &str = "local_metrics";
+
+const METRICS_FILE: &str = "metrics.json";
+const MAX_SAMPLES: usize = 1000;
+// Recording must stay cheap, so the file is rewritten at most this often.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MetricsData {
+    since: i64,
+    operations: BTreeMap<String, u64>,
+    // Latest samples per kind, in microseconds.
+    latencies: BTreeMap<String, Vec<u64>>,
+}
+
+struct Store {
+    path: PathBuf,
+    data: MetricsData,
+    last_flush: Instant,
+}
+
+impl Store {
+    fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&self.data) {
+            if let Err(e) = fs::write(&self.path, json) {
+                tracing::warn!("Failed to write metrics: {}", e);
+            }
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STORE: Mutex<Option<Store>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub kind: String,
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexMetrics {
+    pub indexed_files: usize,
+    pub database_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalMetrics {
+    pub enabled: bool,
+    pub since: Option<i64>,
+    pub operations: BTreeMap<String, u64>,
+    pub latencies: Vec<LatencySummary>,
+    pub index: Option<IndexMetrics>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub fn init(data_dir: &Path, enabled: bool) {
+    let path = data_dir.join(METRICS_FILE);
+    let data = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| MetricsData {
+            since: now(),
+            ..Default::default()
+        });
+    *STORE.lock().unwrap() = Some(Store {
+        path,
+        data,
+        last_flush: Instant::now(),
+    });
+    set_enabled(enabled);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    let was_enabled = ENABLED.swap(enabled, Ordering::Relaxed);
+    if was_enabled && !enabled {
+        if let Some(store) = STORE.lock().unwrap().as_mut() {
+            store.flush();
+        }
+    }
+}
+
+fn with_store(f: impl FnOnce(&mut MetricsData)) {
+    if !enabled() {
+        return;
+    }
+    if let Some(store) = STORE.lock().unwrap().as_mut() {
+        f(&mut store.data);
+        store.flush_if_due();
+    }
+}
+
+pub fn record_operation(name: &str) {
+    with_store(|data| *data.operations.entry(name.to_string()).or_insert(0) += 1);
+}
+
+pub fn record_latency(kind: &str, elapsed: Duration) {
+    with_store(|data| {
+        let samples = data.latencies.entry(kind.to_string()).or_default();
+        if samples.len() == MAX_SAMPLES {
+            samples.remove(0);
+        }
+        samples.push(elapsed.as_micros() as u64);
+    });
+}
+
+fn percentile(sorted: &[u64], p: f64) -> f64 {
+    let rank = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[rank] as f64 / 1000.0
+}
+
+fn summarize(kind: &str, samples: &[u64]) -> Option<LatencySummary> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    Some(LatencySummary {
+        kind: kind.to_string(),
+        samples: sorted.len(),
+        p50_ms: percentile(&sorted, 0.5),
+        p90_ms: percentile(&sorted, 0.9),
+        p99_ms: percentile(&sorted, 0.99),
+        max_ms: percentile(&sorted, 1.0),
+    })
+}
+
+pub fn snapshot(index: Option<IndexMetrics>) -> LocalMetrics {
+    let mut store = STORE.lock().unwrap();
+    let Some(store) = store.as_mut() else {
+        return LocalMetrics {
+            enabled: enabled(),
+            since: None,
+            operations: BTreeMap::new(),
+            latencies: Vec::new(),
+            index,
+        };
+    };
+    if enabled() {
+        store.flush();
+    }
+    LocalMetrics {
+        enabled: enabled(),
+        since: Some(store.data.since),
+        operations: store.data.operations.clone(),
+        latencies: store
+            .data
+            .latencies
+            .iter()
+            .filter_map(|(kind, samples)| summarize(kind, samples))
+            .collect(),
+        index,
+    }
+}
+
+pub fn reset() -> Result<(), String> {
+    let mut store = STORE.lock().unwrap();
+    if let Some(store) = store.as_mut() {
+        store.data = MetricsData {
+            since: now(),
+            ..Default::default()
+        };
+        match fs::remove_file(&store.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to remove metrics: {}", e));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const DEFAULT_MIME: &str = "application/octet-stream";
+
+static EXTENSIONS: OnceLock<HashMap<String, String>> = OnceLock::new();
+static PREFERRED: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// shared-mime-info's globs2 is the most complete source on Linux; mime.types
+// covers the BSDs and macOS installs that ship it.
+fn load_extensions() -> HashMap<String, String> {
+    let mut map = HashMap::new();
+
+    for line in fs::read_to_string("/etc/mime.types").unwrap_or_default().lines() {
+        let mut parts = line.split_whitespace();
+        let Some(mime) = parts.next().filter(|m| !m.starts_with('#')) else {
+            continue;
+        };
+        for ext in parts {
+            map.insert(ext.to_lowercase(), mime.to_string());
+        }
+    }
+
+    let mut weights: HashMap<String, u32> = HashMap::new();
+    for line in fs::read_to_string("/usr/share/mime/globs2").unwrap_or_default().lines() {
+        let mut parts = line.splitn(3, ':');
+        let (Some(weight), Some(mime), Some(glob)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some(ext) = glob.split(':').next().and_then(|g| g.strip_prefix("*.")) else {
+            continue;
+        };
+        if ext.contains(['*', '?', '[']) {
+            continue;
+        }
+        let weight: u32 = weight.parse().unwrap_or(50);
+        let ext = ext.to_lowercase();
+        if weights.get(&ext).is_none_or(|w| weight > *w) {
+            weights.insert(ext.clone(), weight);
+            map.insert(ext, mime.to_string());
+        }
+    }
+
+    map
+}
+
+// `ext` must already be lowercase.
+pub fn mime_for_extension(ext: &str) -> Option<&'static str> {
+    EXTENSIONS.get_or_init(load_extensions).get(ext).map(String::as_str)
+}
+
+// Several extensions can map to one type; the alphabetically first is stable.
+pub fn extension_for_mime(mime: &str) -> Option<&'static str> {
+    EXTENSIONS
+        .get_or_init(load_extensions)
+        .iter()
+        .filter(|(_, m)| m.eq_ignore_ascii_case(mime))
+        .map(|(ext, _)| ext.as_str())
+        .min()
+}
+
+// mime.types lists a type's usual extension first (`jpeg jpg jpe jfif`).
+fn load_preferred() -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    for line in fs::read_to_string("/etc/mime.types").unwrap_or_default().lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(mime), Some(ext)) = (parts.next().filter(|m| !m.starts_with('#')), parts.next()) else {
+            continue;
+        };
+        map.entry(mime.to_lowercase()).or_insert_with(|| ext.to_lowercase());
+    }
+    map
+}
+
+// The extension to give a new file of this type, such as one being saved.
+pub fn preferred_extension(mime: &str) -> Option<String> {
+    PREFERRED
+        .get_or_init(load_preferred)
+        .get(&mime.to_lowercase())
+        .cloned()
+        .or_else(|| extension_for_mime(mime).map(str::to_string))
+}
+
+pub fn mime_type(path: &Path) -> String {
+    if path.is_dir() {
+        return "inode/directory".to_string();
+    }
+    let Some(ext) = path.extension() else {
+        return DEFAULT_MIME.to_string();
+    };
+    mime_for_extension(&ext.to_string_lossy().to_lowercase())
+        .unwrap_or(DEFAULT_MIME)
+        .to_string()
+}
+
+// Accepts exact types, `type/*` wildcards and KDE's `all/all` / `all/allfiles`.
+pub fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.trim();
+    match pattern {
+        "" => false,
+        "*" | "*/*" | "all/all" => true,
+        "all/allfiles" => mime != "inode/directory",
+        _ => match pattern.strip_suffix("/*") {
+            Some(prefix) => mime.split('/').next() == Some(prefix),
+            None => pattern.eq_ignore_ascii_case(mime),
+        },
+    }
+}
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+use crate::fs_engine::containing_mount;
+
+// Mount point -> profile, for mounts the automatic guess gets wrong.
+pub const PROFILES_SETTING: &str = "mount_profiles";
+// Mount point -> number of files copied at once onto that mount.
+pub const CONCURRENCY_SETTING: &str = "copy_concurrency";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MountProfile {
+    LocalFast,
+    // Spinning disks, where parallel access means seeking back and forth.
+    LocalRotational,
+    // USB sticks, SD cards and optical media: local, but slow and often FAT.
+    Removable,
+    // Network shares and FUSE remotes, where every stat is a round trip.
+    NetworkSlow,
+}
+
+// What the rest of the app should do on a mount with a given profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountBehavior {
+    pub profile: MountProfile,
+    pub auto_thumbnails: bool,
+    pub recursive_sizes: bool,
+    pub copy_buffer_size: usize,
+    pub copy_concurrency: usize,
+    pub watch_debounce_ms: u64,
+}
+
+impl MountProfile {
+    pub fn behavior(self) -> MountBehavior {
+        let (auto_thumbnails, recursive_sizes, copy_buffer_size, copy_concurrency, watch_debounce_ms) = match self {
+            MountProfile::LocalFast => (true, true, 1 << 20, 8, 100),
+            MountProfile::LocalRotational => (true, true, 4 << 20, 1, 100),
+            MountProfile::Removable => (true, true, 4 << 20, 1, 500),
+            MountProfile::NetworkSlow => (false, false, 8 << 20, 1, 2000),
+        };
+        MountBehavior {
+            profile: self,
+            auto_thumbnails,
+            recursive_sizes,
+            copy_buffer_size,
+            copy_concurrency,
+            watch_debounce_ms,
+        }
+    }
+}
+
+static PROFILE_OVERRIDES: RwLock<Option<HashMap<String, MountProfile>>> = RwLock::new(None);
+static CONCURRENCY_OVERRIDES: RwLock<Option<HashMap<String, usize>>> = RwLock::new(None);
+
+pub fn set_profile_overrides(overrides: HashMap<String, MountProfile>) {
+    *PROFILE_OVERRIDES.write().unwrap() = Some(overrides);
+}
+
+pub fn set_concurrency_overrides(overrides: HashMap<String, usize>) {
+    *CONCURRENCY_OVERRIDES.write().unwrap() = Some(overrides);
+}
+
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "afpfs", "webdav", "davfs", "9p", "ceph", "glusterfs", "fuse.sshfs",
+    "fuse.rclone", "fuse.davfs2", "fuse.s3fs", "fuse.gvfsd-fuse",
+];
+
+const REMOVABLE_FS_TYPES: &[&str] = &["vfat", "msdos", "exfat", "iso9660", "udf", "cd9660"];
+
+#[cfg(windows)]
+fn drive_profile(mount_point: &str) -> Option<MountProfile> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDriveTypeW(root_path: *const u16) -> u32;
+    }
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_REMOTE: u32 = 4;
+    const DRIVE_CDROM: u32 = 5;
+
+    let wide: Vec<u16> = std::ffi::OsStr::new(mount_point).encode_wide().chain(Some(0)).collect();
+    match unsafe { GetDriveTypeW(wide.as_ptr()) } {
+        DRIVE_REMOTE => Some(MountProfile::NetworkSlow),
+        DRIVE_REMOVABLE | DRIVE_CDROM => Some(MountProfile::Removable),
+        _ => None,
+    }
+}
+
+#[cfg(not(windows))]
+fn drive_profile(_mount_point: &str) -> Option<MountProfile> {
+    None
+}
+
+// The kernel flags each block device as rotational or not. A partition has no
+// queue of its own, so its parent disk's is used; device-mapper volumes
+// report what they sit on.
+#[cfg(target_os = "linux")]
+fn is_rotational(mount_point: &str) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    // The last line wins when several mounts are stacked on one point.
+    let device = mounts.lines().rev().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let device = parts.next()?;
+        let point = crate::fs_engine::unescape_mount_path(parts.next()?);
+        (point == mount_point).then(|| device.to_string())
+    });
+    let Some(name) = device
+        .filter(|d| d.starts_with("/dev/"))
+        .and_then(|d| std::fs::canonicalize(d).ok())
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+    else {
+        return false;
+    };
+    let Ok(sys) = std::fs::canonicalize(Path::new("/sys/class/block").join(name)) else {
+        return false;
+    };
+    [sys.join("queue/rotational"), sys.join("../queue/rotational")]
+        .iter()
+        .find_map(|p| std::fs::read_to_string(p).ok())
+        .is_some_and(|flag| flag.trim() == "1")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_rotational(_mount_point: &str) -> bool {
+    false
+}
+
+// Guessed from the filesystem type first, then from where desktops put
+// hot-plugged media.
+fn classify_mount(mount_point: &str, fs_type: &str) -> MountProfile {
+    if let Some(profile) = drive_profile(mount_point) {
+        return profile;
+    }
+    let fs_type = fs_type.to_lowercase();
+    if NETWORK_FS_TYPES.contains(&fs_type.as_str()) || mount_point.contains("/gvfs") {
+        return MountProfile::NetworkSlow;
+    }
+    let hot_plugged = ["/media/", "/run/media/", "/Volumes/"].iter().any(|p| mount_point.starts_with(p));
+    if REMOVABLE_FS_TYPES.contains(&fs_type.as_str()) || hot_plugged {
+        return MountProfile::Removable;
+    }
+    if is_rotational(mount_point) {
+        return MountProfile::LocalRotational;
+    }
+    MountProfile::LocalFast
+}
+
+// A profile set by the user wins over the guess.
+pub fn mount_profile(mount_point: &str, fs_type: &str) -> MountProfile {
+    let overridden = PROFILE_OVERRIDES
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|o| o.get(mount_point).copied());
+    overridden.unwrap_or_else(|| classify_mount(mount_point, fs_type))
+}
+
+// Plugin VFS locations (`scheme://...`) are treated as remote; the app's own
+// virtual folders list local files.
+pub fn profile_for(path: &str) -> MountProfile {
+    if path.contains("://") && !crate::locations::is_virtual(path) {
+        return MountProfile::NetworkSlow;
+    }
+    match resolve_mount(path) {
+        Some((mount_point, fs_type)) => mount_profile(&mount_point, &fs_type),
+        None => MountProfile::LocalFast,
+    }
+}
+
+fn resolve_mount(path: &str) -> Option<(String, String)> {
+    let resolved = crate::rawpath::decode(path);
+    let existing = resolved.ancestors().find(|p| p.exists())?;
+    containing_mount(existing)
+}
+
+pub fn behavior_for(path: &str) -> MountBehavior {
+    profile_for(path).behavior()
+}
+
+// How many files to copy onto `path` at once; a configured value wins.
+pub fn copy_concurrency_for(path: &str) -> usize {
+    let configured = resolve_mount(path).and_then(|(mount_point, _)| {
+        CONCURRENCY_OVERRIDES
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|o| o.get(&mount_point).copied())
+    });
+    configured.unwrap_or_else(|| behavior_for(path).copy_concurrency).max(1)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountInfo {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub total_space: u64,
+    // Space usable by this process; on unix this leaves out blocks reserved for root.
+    pub available_space: u64,
+    pub profile: MountProfile,
+}
+
+#[cfg(unix)]
+fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+#[cfg(windows)]
+fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null_mut;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut available, mut total) = (0u64, 0u64);
+    if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, &mut total, null_mut()) } == 0 {
+        return None;
+    }
+    Some((total, available))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn disk_space(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+// Resolves the mount holding `path`. A path that doesn't exist yet, like a
+// copy destination, is looked up through its nearest existing ancestor.
+pub fn get_mount_for_path(path: &Path) -> Result<MountInfo, String> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| format!("No existing parent for {}", path.display()))?;
+    let (mount_point, fs_type) =
+        containing_mount(existing).ok_or_else(|| format!("Failed to find the mount for {}", path.display()))?;
+    let (total_space, available_space) =
+        disk_space(existing).ok_or_else(|| format!("Failed to read free space for {}", path.display()))?;
+    let profile = profile_for(&crate::rawpath::encode_path(existing));
+    Ok(MountInfo {
+        mount_point,
+        fs_type,
+        total_space,
+        available_space,
+        profile,
+    })
+}
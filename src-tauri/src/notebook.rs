@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::markdown;
+use crate::share::percent_encode;
+
+// Notebooks with inline plots get big; past this the plain preview is used.
+const MAX_NOTEBOOK_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_CELLS: usize = 200;
+const MAX_OUTPUTS: usize = 20;
+const MAX_SOURCE_LEN: usize = 10_000;
+const MAX_OUTPUT_LEN: usize = 5_000;
+// Images are sent to the frontend as data URLs, so they count against a budget.
+const MAX_IMAGE_LEN: usize = 2 * 1024 * 1024;
+const MAX_IMAGES_LEN: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CellKind {
+    Code,
+    Markdown,
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OutputKind {
+    Text,
+    Stderr,
+    Error,
+    Image,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellOutput {
+    pub kind: OutputKind,
+    // Text, stderr and error tracebacks; images carry a data URL instead.
+    pub text: Option<String>,
+    pub image: Option<String>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotebookCell {
+    pub kind: CellKind,
+    pub source: String,
+    // Sanitized HTML for markdown cells.
+    pub html: Option<String>,
+    pub execution_count: Option<u64>,
+    pub outputs: Vec<CellOutput>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotebookPreview {
+    pub language: Option<String>,
+    pub kernel: Option<String>,
+    pub nbformat: Option<u64>,
+    pub cell_count: usize,
+    pub cells: Vec<NotebookCell>,
+    pub truncated: bool,
+}
+
+// Multiline fields are stored either as one string or as a list of lines.
+fn joined(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn cut(s: String, max: usize) -> (String, bool) {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => (format!("{}…", &s[..end]), true),
+        None => (s, false),
+    }
+}
+
+// Tracebacks are colored with ANSI escapes meant for a terminal.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn text_output(kind: OutputKind, text: &str) -> CellOutput {
+    let (text, truncated) = cut(text.to_string(), MAX_OUTPUT_LEN);
+    CellOutput {
+        kind,
+        text: Some(text),
+        image: None,
+        truncated,
+    }
+}
+
+fn image_url(data: &Value, image_budget: &mut usize) -> Option<String> {
+    let url = if let Some(png) = data.get("image/png") {
+        format!("data:image/png;base64,{}", joined(Some(png)).replace(['\n', ' '], ""))
+    } else if let Some(jpeg) = data.get("image/jpeg") {
+        format!("data:image/jpeg;base64,{}", joined(Some(jpeg)).replace(['\n', ' '], ""))
+    } else if let Some(svg) = data.get("image/svg+xml") {
+        format!("data:image/svg+xml,{}", percent_encode(&joined(Some(svg))))
+    } else {
+        return None;
+    };
+    if url.len() > MAX_IMAGE_LEN || url.len() > *image_budget {
+        return None;
+    }
+    *image_budget -= url.len();
+    Some(url)
+}
+
+fn parse_output(output: &Value, image_budget: &mut usize) -> Option<CellOutput> {
+    match output.get("output_type")?.as_str()? {
+        "stream" => {
+            let kind = match output.get("name").and_then(Value::as_str) {
+                Some("stderr") => OutputKind::Stderr,
+                _ => OutputKind::Text,
+            };
+            Some(text_output(kind, &joined(output.get("text"))))
+        }
+        "error" => {
+            let traceback: Vec<String> = output
+                .get("traceback")
+                .and_then(Value::as_array)
+                .map(|lines| lines.iter().filter_map(Value::as_str).map(strip_ansi).collect())
+                .unwrap_or_default();
+            let text = if traceback.is_empty() {
+                format!("{}: {}", joined(output.get("ename")), joined(output.get("evalue")))
+            } else {
+                traceback.join("\n")
+            };
+            Some(text_output(OutputKind::Error, &text))
+        }
+        // `display_data` and `execute_result` hold a mime bundle; the richest
+        // type we can show wins, falling back to the plain text repr.
+        _ => {
+            let data = output.get("data")?;
+            if let Some(image) = image_url(data, image_budget) {
+                return Some(CellOutput {
+                    kind: OutputKind::Image,
+                    text: None,
+                    image: Some(image),
+                    truncated: false,
+                });
+            }
+            data.get("text/plain").map(|text| text_output(OutputKind::Text, &joined(Some(text))))
+        }
+    }
+}
+
+fn parse_cell(cell: &Value, base_dir: &Path, image_budget: &mut usize) -> NotebookCell {
+    let kind = match cell.get("cell_type").and_then(Value::as_str) {
+        Some("code") => CellKind::Code,
+        Some("markdown") => CellKind::Markdown,
+        _ => CellKind::Raw,
+    };
+    let (source, mut truncated) = cut(joined(cell.get("source")), MAX_SOURCE_LEN);
+    let html = (kind == CellKind::Markdown).then(|| markdown::render(&source, base_dir));
+
+    let raw_outputs = cell.get("outputs").and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]);
+    truncated |= raw_outputs.len() > MAX_OUTPUTS;
+    let outputs: Vec<CellOutput> = raw_outputs
+        .iter()
+        .take(MAX_OUTPUTS)
+        .filter_map(|o| parse_output(o, image_budget))
+        .collect();
+    truncated |= outputs.iter().any(|o| o.truncated);
+
+    NotebookCell {
+        kind,
+        source,
+        html,
+        execution_count: cell.get("execution_count").and_then(Value::as_u64),
+        outputs,
+        truncated,
+    }
+}
+
+pub fn notebook_preview(path: &Path) -> Result<NotebookPreview, String> {
+    let size = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?.len();
+    if size > MAX_NOTEBOOK_SIZE {
+        return Err("Notebook is too large to parse".to_string());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let notebook: Value = serde_json::from_str(&content).map_err(|e| format!("Invalid notebook: {}", e))?;
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or("Notebook has no cells")?;
+
+    let metadata = notebook.get("metadata");
+    let language = metadata
+        .and_then(|m| m.get("language_info"))
+        .and_then(|l| l.get("name"))
+        .or_else(|| metadata.and_then(|m| m.get("kernelspec")).and_then(|k| k.get("language")))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let kernel = metadata
+        .and_then(|m| m.get("kernelspec"))
+        .and_then(|k| k.get("display_name"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut image_budget = MAX_IMAGES_LEN;
+    let parsed: Vec<NotebookCell> = cells
+        .iter()
+        .take(MAX_CELLS)
+        .map(|c| parse_cell(c, base_dir, &mut image_budget))
+        .collect();
+
+    Ok(NotebookPreview {
+        language,
+        kernel,
+        nbformat: notebook.get("nbformat").and_then(Value::as_u64),
+        cell_count: cells.len(),
+        truncated: cells.len() > MAX_CELLS || parsed.iter().any(|c| c.truncated),
+        cells: parsed,
+    })
+}
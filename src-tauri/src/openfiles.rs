@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+#[cfg(not(target_os = "linux"))]
+use std::process::Command;
+
+#[cfg(target_os = "linux")]
+use crate::rawpath;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHolder {
+    pub pid: u32,
+    pub command: String,
+    pub path: Option<String>,
+}
+
+// `lsof -F pcn` prints one field per line: `p<pid>`, `c<command>`, `n<name>`.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn parse_lsof(output: &str) -> Vec<FileHolder> {
+    let mut holders: Vec<FileHolder> = Vec::new();
+    for line in output.lines() {
+        let (field, value) = line.split_at(line.len().min(1));
+        match field {
+            "p" => {
+                if let Ok(pid) = value.parse() {
+                    holders.push(FileHolder {
+                        pid,
+                        command: String::new(),
+                        path: None,
+                    });
+                }
+            }
+            "c" => {
+                if let Some(holder) = holders.last_mut() {
+                    holder.command = value.to_string();
+                }
+            }
+            // One entry per open file, so the caller can say what is in use.
+            "n" => match holders.last_mut() {
+                Some(holder) if holder.path.is_none() => holder.path = Some(value.to_string()),
+                Some(holder) => {
+                    let next = FileHolder {
+                        pid: holder.pid,
+                        command: holder.command.clone(),
+                        path: Some(value.to_string()),
+                    };
+                    holders.push(next);
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+    holders
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn process_name(pid: u32) -> String {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|c| c.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn process_name(_pid: u32) -> String {
+    String::new()
+}
+
+// Checks every process's open descriptors, working directory and memory-mapped
+// files. Processes of other users are unreadable without privileges and skipped.
+#[cfg(target_os = "linux")]
+fn scan(target: &Path, recursive: bool) -> Vec<FileHolder> {
+    use std::fs;
+    use std::path::PathBuf;
+
+    let own_pid = std::process::id();
+    let matches = |p: &Path| p == target || (recursive && p.starts_with(target));
+    let mut holders: Vec<FileHolder> = Vec::new();
+
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return holders;
+    };
+    for entry in procs.filter_map(|e| e.ok()) {
+        let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+            continue;
+        };
+        if pid == own_pid {
+            continue;
+        }
+        let proc_dir = entry.path();
+
+        let mut paths: Vec<PathBuf> = Vec::new();
+        if let Ok(fds) = fs::read_dir(proc_dir.join("fd")) {
+            paths.extend(fds.filter_map(|e| e.ok()).filter_map(|e| fs::read_link(e.path()).ok()));
+        }
+        paths.extend(fs::read_link(proc_dir.join("cwd")).ok());
+        if let Ok(maps) = fs::read_to_string(proc_dir.join("maps")) {
+            paths.extend(
+                maps.lines()
+                    .filter_map(|l| l.split_whitespace().nth(5))
+                    .filter(|p| p.starts_with('/'))
+                    .map(PathBuf::from),
+            );
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for path in paths.into_iter().filter(|p| matches(p)) {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            holders.push(FileHolder {
+                pid,
+                command: process_name(pid),
+                path: Some(rawpath::encode_path(&path)),
+            });
+        }
+    }
+    holders
+}
+
+#[cfg(target_os = "linux")]
+pub fn who_has_open(path: &Path) -> Result<Vec<FileHolder>, String> {
+    let target = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve path: {}", e))?;
+    Ok(scan(&target, target.is_dir()))
+}
+
+#[cfg(target_os = "macos")]
+pub fn who_has_open(path: &Path) -> Result<Vec<FileHolder>, String> {
+    // `+D` makes lsof report everything open below a directory.
+    let mut command = Command::new("lsof");
+    command.args(["-F", "pcn"]);
+    if path.is_dir() {
+        command.arg("+D");
+    } else {
+        command.arg("--");
+    }
+    let output = command
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run lsof: {}", e))?;
+    let own_pid = std::process::id();
+    Ok(parse_lsof(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|h| h.pid != own_pid)
+        .collect())
+}
+
+#[cfg(windows)]
+mod restart_manager {
+    use super::FileHolder;
+    use crate::rawpath;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+
+    const CCH_RM_SESSION_KEY: usize = 32;
+    const CCH_RM_MAX_APP_NAME: usize = 255;
+    const CCH_RM_MAX_SVC_NAME: usize = 63;
+    const ERROR_SUCCESS: u32 = 0;
+    const ERROR_MORE_DATA: u32 = 234;
+
+    // Field layouts mirror the Win32 structs; not every field is read.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct RmUniqueProcess {
+        process_id: u32,
+        start_time: FileTime,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct RmProcessInfo {
+        process: RmUniqueProcess,
+        app_name: [u16; CCH_RM_MAX_APP_NAME + 1],
+        service_short_name: [u16; CCH_RM_MAX_SVC_NAME + 1],
+        application_type: u32,
+        app_status: u32,
+        ts_session_id: u32,
+        restartable: i32,
+    }
+
+    #[link(name = "rstrtmgr")]
+    extern "system" {
+        fn RmStartSession(session: *mut u32, flags: u32, key: *mut u16) -> u32;
+        fn RmEndSession(session: u32) -> u32;
+        fn RmRegisterResources(
+            session: u32,
+            n_files: u32,
+            files: *const *const u16,
+            n_apps: u32,
+            apps: *const RmUniqueProcess,
+            n_services: u32,
+            services: *const *const u16,
+        ) -> u32;
+        fn RmGetList(
+            session: u32,
+            needed: *mut u32,
+            count: *mut u32,
+            apps: *mut RmProcessInfo,
+            reboot_reasons: *mut u32,
+        ) -> u32;
+    }
+
+    fn wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    fn from_wide(s: &[u16]) -> String {
+        let len = s.iter().position(|c| *c == 0).unwrap_or(s.len());
+        String::from_utf16_lossy(&s[..len])
+    }
+
+    pub fn query(path: &Path) -> Result<Vec<FileHolder>, String> {
+        let mut session = 0u32;
+        let mut key = [0u16; CCH_RM_SESSION_KEY + 1];
+        let status = unsafe { RmStartSession(&mut session, 0, key.as_mut_ptr()) };
+        if status != ERROR_SUCCESS {
+            return Err(format!("Failed to start Restart Manager session: error {}", status));
+        }
+
+        let result = (|| {
+            let file = wide(path.as_os_str());
+            let files = [file.as_ptr()];
+            let status = unsafe {
+                RmRegisterResources(session, 1, files.as_ptr(), 0, std::ptr::null(), 0, std::ptr::null())
+            };
+            if status != ERROR_SUCCESS {
+                return Err(format!("Failed to register file: error {}", status));
+            }
+
+            // The list can grow between the sizing call and the real one, so retry.
+            let mut infos: Vec<RmProcessInfo> = Vec::new();
+            loop {
+                let mut needed = 0u32;
+                let mut count = infos.len() as u32;
+                let mut reasons = 0u32;
+                let status = unsafe {
+                    RmGetList(session, &mut needed, &mut count, infos.as_mut_ptr(), &mut reasons)
+                };
+                match status {
+                    ERROR_SUCCESS => {
+                        infos.truncate(count as usize);
+                        break;
+                    }
+                    ERROR_MORE_DATA => {
+                        let empty: RmProcessInfo = unsafe { std::mem::zeroed() };
+                        infos = vec![empty; needed as usize];
+                    }
+                    _ => return Err(format!("Failed to list processes: error {}", status)),
+                }
+            }
+
+            Ok(infos
+                .iter()
+                .map(|info| FileHolder {
+                    pid: info.process.process_id,
+                    command: from_wide(&info.app_name),
+                    path: Some(rawpath::encode_path(path)),
+                })
+                .collect())
+        })();
+
+        unsafe { RmEndSession(session) };
+        result
+    }
+}
+
+#[cfg(windows)]
+pub fn who_has_open(path: &Path) -> Result<Vec<FileHolder>, String> {
+    restart_manager::query(path)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn who_has_open(path: &Path) -> Result<Vec<FileHolder>, String> {
+    let output = Command::new("lsof")
+        .args(["-F", "pcn", "--"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run lsof: {}", e))?;
+    Ok(parse_lsof(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Appended to error messages of failed deletes, moves and renames.
+pub fn in_use_hint(path: &Path) -> String {
+    let holders = who_has_open(path).unwrap_or_default();
+    if holders.is_empty() {
+        return String::new();
+    }
+    let mut names: Vec<String> = holders
+        .iter()
+        .map(|h| {
+            if h.command.is_empty() {
+                format!("pid {}", h.pid)
+            } else {
+                format!("{} (pid {})", h.command, h.pid)
+            }
+        })
+        .collect();
+    names.dedup();
+    format!(" (in use by {})", names.join(", "))
+}
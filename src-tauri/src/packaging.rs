@@ -0,0 +1,465 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::rawpath;
+use crate::shortcuts::desktop_entry;
+
+const INFO_FILE: &str = "info.json";
+const ICON_SIZES: &[&str] = &["scalable", "512x512", "256x256", "128x128", "96x96", "64x64", "48x48"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PackageFormat {
+    AppImage,
+    Flatpak,
+    Snap,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    pub format: PackageFormat,
+    pub path: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub summary: Option<String>,
+    // Absolute path of the icon file, extracted into the cache for images.
+    pub icon: Option<String>,
+    // AppImages only: a desktop entry launching this file is installed.
+    #[serde(default)]
+    pub integrated: bool,
+}
+
+fn read_head(path: &Path, len: usize) -> Option<Vec<u8>> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut buf = vec![0u8; len];
+    let n = file.read(&mut buf).ok()?;
+    buf.truncate(n);
+    Some(buf)
+}
+
+// Type 2 AppImages mark the ELF header padding with `AI\x02`.
+fn is_appimage(path: &Path) -> bool {
+    let by_extension = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("appimage"));
+    let by_magic = read_head(path, 11).is_some_and(|h| h.starts_with(b"\x7fELF") && h[8..11] == *b"AI\x02");
+    by_extension || by_magic
+}
+
+// An installed flatpak app directory holds `current/active`, which points at
+// the deployed commit containing `metadata`, `export` and `files`.
+fn flatpak_root(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("current").join("active"), dir.join("active")]
+        .into_iter()
+        .find(|d| d.join("metadata").is_file() && d.join("export").is_dir())
+}
+
+fn snap_root(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("current")]
+        .into_iter()
+        .find(|d| d.join("meta").join("snap.yaml").is_file())
+}
+
+pub fn detect(path: &Path) -> Option<PackageFormat> {
+    if path.is_dir() {
+        if snap_root(path).is_some() {
+            return Some(PackageFormat::Snap);
+        }
+        if flatpak_root(path).is_some() {
+            return Some(PackageFormat::Flatpak);
+        }
+        return None;
+    }
+    if is_appimage(path) {
+        return Some(PackageFormat::AppImage);
+    }
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("snap")) {
+        return Some(PackageFormat::Snap);
+    }
+    None
+}
+
+// The squashfs payload of an AppImage starts right after its ELF runtime, which
+// ends with the section header table.
+fn elf_size(path: &Path) -> Option<u64> {
+    let h = read_head(path, 64)?;
+    if !h.starts_with(b"\x7fELF") || h.get(5) != Some(&1) {
+        return None;
+    }
+    let u16_at = |o: usize| u16::from_le_bytes([h[o], h[o + 1]]) as u64;
+    match h.get(4)? {
+        1 => {
+            let shoff = u32::from_le_bytes(h.get(0x20..0x24)?.try_into().ok()?) as u64;
+            Some(shoff + u16_at(0x2E) * u16_at(0x30))
+        }
+        2 => {
+            let shoff = u64::from_le_bytes(h.get(0x28..0x30)?.try_into().ok()?);
+            Some(shoff + u16_at(0x3A) * u16_at(0x3C))
+        }
+        _ => None,
+    }
+}
+
+// unsquashfs reads the image without running it, unlike `--appimage-extract`.
+// Patterns that match nothing make it exit non-zero, so only spawn errors count.
+fn unsquash(image: &Path, offset: u64, dest: &Path, patterns: &[String]) -> Result<(), String> {
+    Command::new("unsquashfs")
+        .args(["-n", "-f", "-o", &offset.to_string(), "-d"])
+        .arg(dest)
+        .arg(image)
+        .args(patterns)
+        .output()
+        .map_err(|e| format!("Failed to run unsquashfs (is squashfs-tools installed?): {}", e))?;
+    Ok(())
+}
+
+// Top-level files in an AppImage are usually symlinks into `usr/share`; the
+// targets are extracted in a second pass.
+fn extract_link_targets(image: &Path, offset: u64, dest: &Path) -> Result<(), String> {
+    let mut targets = Vec::new();
+    for entry in fs::read_dir(dest).map_err(|e| format!("Failed to read extracted files: {}", e))?.flatten() {
+        let Ok(link) = fs::read_link(entry.path()) else {
+            continue;
+        };
+        let mut resolved = PathBuf::new();
+        for component in link.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                _ => {}
+            }
+        }
+        if link.is_relative() && !resolved.as_os_str().is_empty() {
+            targets.push(resolved.to_string_lossy().to_string());
+        }
+    }
+    if targets.is_empty() {
+        return Ok(());
+    }
+    unsquash(image, offset, dest, &targets)
+}
+
+fn cache_key(path: &Path) -> String {
+    let metadata = fs::metadata(path).ok();
+    let modified = metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let size = metadata.map(|m| m.len()).unwrap_or(0);
+    let digest = Sha256::digest(format!("{}:{}:{}", path.display(), size, modified).as_bytes());
+    digest.iter().take(12).map(|b| format!("{:02x}", b)).collect()
+}
+
+fn first_match(dir: &Path, ext: &str) -> Option<PathBuf> {
+    let mut matches: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case(ext)))
+        .collect();
+    matches.sort();
+    matches.into_iter().next()
+}
+
+fn find_icon(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    for dir in dirs {
+        for ext in ["png", "svg"] {
+            let file = dir.join(format!("{}.{}", name, ext));
+            if file.is_file() {
+                return Some(file);
+            }
+        }
+    }
+    None
+}
+
+fn hicolor_dirs(share: &Path) -> Vec<PathBuf> {
+    ICON_SIZES
+        .iter()
+        .map(|size| share.join("icons").join("hicolor").join(size).join("apps"))
+        .collect()
+}
+
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..start + end].trim().to_string())
+}
+
+// AppStream metainfo lists releases newest first.
+fn metainfo(dir: &Path) -> (Option<String>, Option<String>) {
+    let xml = fs::read_dir(dir)
+        .ok()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.to_string_lossy().ends_with(".xml"))
+        .and_then(|p| fs::read_to_string(p).ok());
+    let Some(xml) = xml else {
+        return (None, None);
+    };
+    let version = xml
+        .find("<release ")
+        .and_then(|i| xml[i..].split_once("version=\""))
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(v, _)| v.to_string());
+    (version, xml_text(&xml, "summary"))
+}
+
+fn appimage_info(path: &Path, cache_dir: &Path) -> Result<PackageInfo, String> {
+    let dest = cache_dir.join(cache_key(path));
+    let info_file = dest.join(INFO_FILE);
+    if let Some(info) = fs::read_to_string(&info_file)
+        .ok()
+        .and_then(|json| serde_json::from_str::<PackageInfo>(&json).ok())
+    {
+        return Ok(info);
+    }
+
+    let offset = elf_size(path).ok_or("Not a type 2 AppImage")?;
+    let _ = fs::remove_dir_all(&dest);
+    fs::create_dir_all(&dest).map_err(|e| format!("Failed to create package cache: {}", e))?;
+    let patterns = ["*.desktop", ".DirIcon", "*.png", "*.svg", "usr/share/metainfo"].map(String::from);
+    unsquash(path, offset, &dest, &patterns)?;
+    extract_link_targets(path, offset, &dest)?;
+
+    let desktop_file = first_match(&dest, "desktop");
+    let content = desktop_file.as_ref().and_then(|f| fs::read_to_string(f).ok()).unwrap_or_default();
+    let entry = desktop_entry(&content);
+    let (meta_version, meta_summary) = metainfo(&dest.join("usr/share/metainfo"));
+
+    let dir_icon = dest.join(".DirIcon");
+    let icon = entry
+        .get("Icon")
+        .and_then(|name| find_icon(std::slice::from_ref(&dest), name))
+        .or_else(|| fs::metadata(&dir_icon).is_ok().then_some(dir_icon))
+        .map(|p| fs::canonicalize(&p).unwrap_or(p));
+
+    let info = PackageInfo {
+        format: PackageFormat::AppImage,
+        path: rawpath::encode_path(path),
+        id: desktop_file.and_then(|f| f.file_stem().map(|s| s.to_string_lossy().to_string())),
+        name: entry.get("Name").map(|s| s.to_string()),
+        version: entry.get("X-AppImage-Version").map(|s| s.to_string()).or(meta_version),
+        summary: entry.get("Comment").map(|s| s.to_string()).or(meta_summary),
+        icon: icon.map(|p| rawpath::encode_path(&p)),
+        integrated: false,
+    };
+    if let Ok(json) = serde_json::to_string(&info) {
+        let _ = fs::write(info_file, json);
+    }
+    Ok(info)
+}
+
+fn flatpak_info(path: &Path) -> Result<PackageInfo, String> {
+    let root = flatpak_root(path).ok_or("Not a flatpak app directory")?;
+    let metadata = fs::read_to_string(root.join("metadata")).unwrap_or_default();
+    let id = desktop_entry(&metadata.replace("[Application]", "[Desktop Entry]"))
+        .get("name")
+        .map(|s| s.to_string())
+        .or_else(|| path.file_name().map(|n| n.to_string_lossy().to_string()));
+
+    let share = root.join("export").join("share");
+    let desktop = id
+        .as_ref()
+        .and_then(|id| fs::read_to_string(share.join("applications").join(format!("{}.desktop", id))).ok())
+        .unwrap_or_default();
+    let entry = desktop_entry(&desktop);
+    let (version, meta_summary) = metainfo(&root.join("files").join("share").join("metainfo"));
+    let icon_name = entry.get("Icon").map(|s| s.to_string()).or_else(|| id.clone());
+
+    Ok(PackageInfo {
+        format: PackageFormat::Flatpak,
+        path: rawpath::encode_path(path),
+        name: entry.get("Name").map(|s| s.to_string()),
+        version,
+        summary: entry.get("Comment").map(|s| s.to_string()).or(meta_summary),
+        icon: icon_name
+            .and_then(|name| find_icon(&hicolor_dirs(&share), &name))
+            .map(|p| rawpath::encode_path(&p)),
+        id,
+        integrated: false,
+    })
+}
+
+// snap.yaml is YAML, but the fields needed here are plain top-level scalars.
+fn yaml_scalar(yaml: &str, key: &str) -> Option<String> {
+    yaml.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?.trim();
+        (!value.is_empty() && value != "|" && value != ">")
+            .then(|| value.trim_matches(|c| c == '\'' || c == '"').to_string())
+    })
+}
+
+fn snap_info(path: &Path, cache_dir: &Path) -> Result<PackageInfo, String> {
+    let root = if path.is_dir() {
+        snap_root(path).ok_or("Not a snap directory")?
+    } else {
+        let dest = cache_dir.join(cache_key(path));
+        if !dest.join("meta").join("snap.yaml").is_file() {
+            fs::create_dir_all(&dest).map_err(|e| format!("Failed to create package cache: {}", e))?;
+            unsquash(path, 0, &dest, &["meta/snap.yaml", "meta/gui"].map(String::from))?;
+        }
+        dest
+    };
+    let meta = root.join("meta");
+    let yaml = fs::read_to_string(meta.join("snap.yaml")).map_err(|e| format!("Failed to read snap.yaml: {}", e))?;
+    let gui = meta.join("gui");
+    let icon = find_icon(std::slice::from_ref(&gui), "icon").or_else(|| first_match(&gui, "png"));
+
+    Ok(PackageInfo {
+        format: PackageFormat::Snap,
+        path: rawpath::encode_path(path),
+        id: yaml_scalar(&yaml, "name"),
+        name: yaml_scalar(&yaml, "title").or_else(|| yaml_scalar(&yaml, "name")),
+        version: yaml_scalar(&yaml, "version"),
+        summary: yaml_scalar(&yaml, "summary"),
+        icon: icon.map(|p| rawpath::encode_path(&p)),
+        integrated: false,
+    })
+}
+
+fn desktop_id(info: &PackageInfo, path: &Path) -> String {
+    let base = info
+        .name
+        .clone()
+        .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "app".to_string());
+    let slug: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("appimage-{}", slug.trim_matches('-'))
+}
+
+fn applications_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("applications"))
+}
+
+fn is_integrated(info: &PackageInfo, path: &Path) -> bool {
+    let Some(dir) = applications_dir() else {
+        return false;
+    };
+    fs::read_to_string(dir.join(format!("{}.desktop", desktop_id(info, path))))
+        .is_ok_and(|content| desktop_entry(&content).get("TryExec") == Some(&path.to_string_lossy().as_ref()))
+}
+
+// Images are extracted into `cache_dir` once per file version.
+pub fn package_info(path: &Path, cache_dir: &Path) -> Result<PackageInfo, String> {
+    let format = detect(path).ok_or_else(|| format!("Not an AppImage, flatpak or snap: {}", path.display()))?;
+    match format {
+        PackageFormat::AppImage => {
+            let mut info = appimage_info(path, cache_dir)?;
+            info.integrated = is_integrated(&info, path);
+            Ok(info)
+        }
+        PackageFormat::Flatpak => flatpak_info(path),
+        PackageFormat::Snap => snap_info(path, cache_dir),
+    }
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+    fs::remove_file(from).map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+// Moves the AppImage into ~/Applications and installs a desktop entry and icon
+// for it, the way AppImageLauncher does.
+pub fn integrate_appimage(source: &Path, cache_dir: &Path) -> Result<PackageInfo, String> {
+    let info = package_info(source, cache_dir)?;
+    if info.format != PackageFormat::AppImage {
+        return Err("Only AppImages can be integrated".to_string());
+    }
+
+    // Keep the categories and extra Exec arguments of the embedded entry. The
+    // extraction is keyed by path, so read it before the file moves.
+    let embedded = first_match(&cache_dir.join(cache_key(source)), "desktop")
+        .and_then(|f| fs::read_to_string(f).ok())
+        .unwrap_or_default();
+    let entry = desktop_entry(&embedded);
+
+    let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
+    let apps = home.join("Applications");
+    fs::create_dir_all(&apps).map_err(|e| format!("Failed to create {}: {}", apps.display(), e))?;
+    let file_name = source.file_name().ok_or("Invalid AppImage path")?;
+    let target = apps.join(file_name);
+    if target != source {
+        if target.exists() {
+            return Err(format!("{} already exists", target.display()));
+        }
+        move_file(source, &target)?;
+    }
+    make_executable(&target)?;
+
+    let target_str = target.to_string_lossy().to_string();
+    let id = desktop_id(&info, source);
+    let data_dir = dirs::data_dir().ok_or("Cannot determine data directory")?;
+
+    let icon = match info.icon.as_deref().map(rawpath::decode) {
+        Some(extracted) => {
+            let ext = extracted.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "png".to_string());
+            let icons = data_dir.join("icons");
+            fs::create_dir_all(&icons).map_err(|e| format!("Failed to create icon directory: {}", e))?;
+            let installed = icons.join(format!("{}.{}", id, ext));
+            fs::copy(&extracted, &installed).map_err(|e| format!("Failed to install icon: {}", e))?;
+            Some(installed.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+
+    let exec_args = entry
+        .get("Exec")
+        .and_then(|exec| exec.split_once(' '))
+        .map(|(_, args)| args.to_string())
+        .unwrap_or_else(|| "%U".to_string());
+
+    let mut desktop = String::from("[Desktop Entry]\nType=Application\n");
+    desktop.push_str(&format!("Name={}\n", info.name.as_deref().unwrap_or(&id)));
+    if let Some(summary) = &info.summary {
+        desktop.push_str(&format!("Comment={}\n", summary));
+    }
+    desktop.push_str(&format!("Exec=\"{}\" {}\n", target_str.replace('"', "\\\""), exec_args));
+    desktop.push_str(&format!("TryExec={}\n", target_str));
+    if let Some(icon) = &icon {
+        desktop.push_str(&format!("Icon={}\n", icon));
+    }
+    if let Some(categories) = entry.get("Categories") {
+        desktop.push_str(&format!("Categories={}\n", categories));
+    }
+    if let Some(version) = &info.version {
+        desktop.push_str(&format!("X-AppImage-Version={}\n", version));
+    }
+    desktop.push_str("Terminal=false\n");
+
+    let applications = data_dir.join("applications");
+    fs::create_dir_all(&applications).map_err(|e| format!("Failed to create applications directory: {}", e))?;
+    let desktop_file = applications.join(format!("{}.desktop", id));
+    fs::write(&desktop_file, desktop).map_err(|e| format!("Failed to write desktop entry: {}", e))?;
+    make_executable(&desktop_file)?;
+    let _ = Command::new("update-desktop-database").arg(&applications).output();
+
+    package_info(&target, cache_dir)
+}
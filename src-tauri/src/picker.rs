@@ -0,0 +1,297 @@
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::fs_engine::DirectoryContents;
+use crate::humanize::format_size;
+use crate::mime;
+use crate::preflight::probe_writable;
+use crate::rawpath;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PickerConfig {
+    pub mode: PickerMode,
+    pub allow_multiple: bool,
+    pub file_types: Option<Vec<String>>,
+    pub start_dir: Option<String>,
+    pub current_name: Option<String>,
+    // Navigation and selections stay inside this folder.
+    pub restrict_to_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum PickerMode {
+    Disabled,
+    Files,
+    Directories,
+    Both,
+    Save,
+}
+
+// What the picker reports for each selected path besides the path itself, so
+// the portal can answer without statting the files again. A file about to be
+// saved has no size or time yet; its type comes from the name.
+#[derive(Debug, Clone, Serialize)]
+pub struct Selection {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<i64>,
+    pub mime: String,
+}
+
+impl Selection {
+    pub fn of(path: &str) -> Self {
+        let target = rawpath::decode(path);
+        let metadata = fs::metadata(&target).ok();
+        Self {
+            path: path.to_string(),
+            is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+            size: metadata.as_ref().filter(|m| !m.is_dir()).map(|m| m.len()),
+            modified: metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64),
+            mime: mime::mime_type(&target),
+        }
+    }
+}
+
+// How a name typed in Save mode fits the file type it's saved as.
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveNameCheck {
+    // The name to save under: as typed, or with the type's extension added
+    // when it had none.
+    pub name: String,
+    pub appended: bool,
+    // Set when the name's own extension isn't one of the type's; holds the
+    // extension that was expected, or the type itself when it has no single one.
+    pub expected: Option<String>,
+}
+
+// Whether a file can be saved at a path, checked before the picker answers so
+// the app isn't the one to find out.
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveTarget {
+    pub path: String,
+    pub dir_writable: bool,
+    pub exists: bool,
+    // Whether the file can be written: the existing one, or a new one in the
+    // folder.
+    pub writable: bool,
+    // Why the file can't be saved there, if it can't.
+    pub problem: Option<String>,
+}
+
+// The one extension a type stands for, if any: `*.png` and `image/png` both
+// give `png`, while `image/*` gives none.
+fn type_extension(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim();
+    if pattern.contains('/') {
+        return mime::preferred_extension(pattern);
+    }
+    let ext = pattern.trim_start_matches('*').trim_start_matches('.');
+    (!ext.is_empty() && !ext.contains(['*', '?'])).then(|| ext.to_string())
+}
+
+// Symlinks are resolved, so a link inside the folder can't lead out of it.
+// A path that doesn't exist yet, like a save target, is judged by its folder.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path)
+        .ok()
+        .or_else(|| Some(fs::canonicalize(path.parent()?).ok()?.join(path.file_name()?)))
+}
+
+// `pattern` is an extension, with or without a leading `*.` or `.`, or a MIME
+// type such as `image/*`.
+fn type_matches(pattern: &str, path: &Path) -> bool {
+    let pattern = pattern.trim();
+    if pattern.contains('/') {
+        return mime::mime_matches(pattern, &mime::mime_type(path));
+    }
+    let ext = pattern.trim_start_matches('*').trim_start_matches('.');
+    if ext == "*" || ext.is_empty() {
+        return true;
+    }
+    // Compared against the end of the name, so `tar.gz` works too.
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    name.ends_with(&format!(".{}", ext.to_lowercase()))
+}
+
+impl PickerConfig {
+    pub fn is_active(&self) -> bool {
+        self.mode != PickerMode::Disabled
+    }
+
+    // Resolves the restriction once so later checks compare like with like,
+    // and starts inside it when no start folder inside it was given.
+    pub fn normalize(&mut self) {
+        let Some(root) = self.restrict_to_dir.as_deref().map(rawpath::decode) else {
+            return;
+        };
+        let Some(root) = fs::canonicalize(root).ok() else {
+            return;
+        };
+        self.restrict_to_dir = Some(rawpath::encode_path(&root));
+        if !self.start_dir.as_deref().is_some_and(|d| self.allows(&rawpath::decode(d))) {
+            self.start_dir = self.restrict_to_dir.clone();
+        }
+    }
+
+    // Whether `path` is inside the folder the picker is restricted to.
+    pub fn allows(&self, path: &Path) -> bool {
+        let Some(root) = &self.restrict_to_dir else {
+            return true;
+        };
+        resolve(path).is_some_and(|p| p.starts_with(rawpath::decode(root)))
+    }
+
+    // Whether a file has one of the requested types. Folders always pass, so
+    // they can still be opened.
+    pub fn accepts(&self, path: &Path, is_dir: bool) -> bool {
+        match &self.file_types {
+            Some(types) if !is_dir && !types.is_empty() => types.iter().any(|t| type_matches(t, path)),
+            _ => true,
+        }
+    }
+
+    // Leaves out files of other types and anything outside the restricted
+    // folder: symlinks that point out of it, or entries of listings such as
+    // search results.
+    pub fn filter_listing(&self, contents: &mut DirectoryContents) -> Result<(), String> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let dir = rawpath::decode(&contents.path);
+        let listed_dir = dir.is_dir();
+        if listed_dir && !self.allows(&dir) {
+            return Err("This picker is limited to another folder".to_string());
+        }
+        let before = contents.entries.len();
+        contents.entries.retain(|e| {
+            let path = rawpath::decode(&e.path);
+            self.accepts(&path, e.is_dir) && ((listed_dir && !e.is_symlink) || self.allows(&path))
+        });
+        if contents.entries.len() != before {
+            contents.total_items = contents.entries.len();
+            contents.total_size = contents.entries.iter().map(|e| e.size).sum();
+            contents.total_disk_size = contents.entries.iter().map(|e| e.disk_size).sum();
+            contents.total_size_text = format_size(contents.total_size);
+        }
+        // No way up from the restricted folder itself.
+        if contents.parent.as_deref().is_some_and(|p| !self.allows(&rawpath::decode(p))) {
+            contents.parent = None;
+        }
+        Ok(())
+    }
+
+    // Adds the chosen type's extension to a name typed without one, and
+    // flags one that has another, like GTK's and Qt's save dialogs. Without a
+    // choice, the first type given is the one used.
+    pub fn check_save_name(&self, name: &str, file_type: Option<&str>) -> SaveNameCheck {
+        let chosen = file_type.or_else(|| self.file_types.as_ref()?.first().map(String::as_str));
+        let mut check = SaveNameCheck {
+            name: name.to_string(),
+            appended: false,
+            expected: None,
+        };
+        let Some(chosen) = chosen else {
+            return check;
+        };
+        let extension = type_extension(chosen);
+        let path = Path::new(name);
+        if path.extension().is_none() {
+            if let Some(ext) = &extension {
+                check.name = format!("{}.{}", name, ext);
+                check.appended = true;
+                return check;
+            }
+        }
+        if !type_matches(chosen, path) {
+            check.expected = Some(extension.unwrap_or_else(|| chosen.to_string()));
+        }
+        check
+    }
+
+    // Like copies, judged by trying rather than by permission bits: a new
+    // file is probed for in the folder, and an existing one is opened for
+    // writing without truncating it, which leaves it as it was.
+    pub fn check_save_target(&self, path: &str) -> SaveTarget {
+        let target = rawpath::decode(path);
+        let dir = target.parent().filter(|d| d.is_dir());
+        let dir_writable = dir.is_some_and(|d| probe_writable(d).is_ok());
+        let metadata = fs::metadata(&target).ok();
+        let exists = metadata.is_some();
+        // Only regular files are probed: opening a FIFO for writing blocks
+        // until something reads from it, and devices aren't saved over.
+        let writable = match &metadata {
+            Some(m) if m.is_file() => OpenOptions::new().write(true).open(&target).is_ok(),
+            Some(_) => false,
+            None => dir_writable,
+        };
+        let problem = if !self.allows(&target) {
+            Some(format!("{} is outside the folder this picker is limited to", path))
+        } else if dir.is_none() {
+            Some(format!("{} is not in an existing folder", path))
+        } else if metadata.as_ref().is_some_and(|m| m.is_dir()) {
+            Some(format!("{} is a folder", path))
+        } else if metadata.as_ref().is_some_and(|m| !m.is_file()) {
+            Some(format!("{} is not a regular file", path))
+        } else if exists && !writable {
+            Some(format!("{} exists and can't be overwritten", path))
+        } else if !dir_writable && !exists {
+            Some(format!("Cannot create files in {}", dir.unwrap_or(&target).display()))
+        } else {
+            None
+        };
+        SaveTarget {
+            path: path.to_string(),
+            dir_writable,
+            exists,
+            writable,
+            problem,
+        }
+    }
+
+    // Checks a selection against the mode, types and count the picker was
+    // started with; the frontend's own checks can't be relied on.
+    pub fn validate(&self, paths: &[String]) -> Result<(), String> {
+        if !self.is_active() {
+            return Err("Not running as a file picker".to_string());
+        }
+        if paths.is_empty() {
+            return Err("Nothing selected".to_string());
+        }
+        let single = self.mode == PickerMode::Save || !self.allow_multiple;
+        if single && paths.len() > 1 {
+            return Err("Only one item can be selected".to_string());
+        }
+        for path in paths {
+            let target = rawpath::decode(path);
+            if !self.allows(&target) {
+                return Err(format!("{} is outside the folder this picker is limited to", path));
+            }
+            if self.mode == PickerMode::Save {
+                if let Some(problem) = self.check_save_target(path).problem {
+                    return Err(problem);
+                }
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&target) else {
+                return Err(format!("{} does not exist", path));
+            };
+            let is_dir = metadata.is_dir();
+            match self.mode {
+                PickerMode::Files if is_dir => return Err(format!("{} is a folder", path)),
+                PickerMode::Directories if !is_dir => return Err(format!("{} is not a folder", path)),
+                _ => {}
+            }
+            if !self.accepts(&target, is_dir) {
+                return Err(format!("{} is not one of the accepted file types", path));
+            }
+        }
+        Ok(())
+    }
+}
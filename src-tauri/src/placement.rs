@@ -0,0 +1,177 @@
+use serde_json::{Map, Value};
+use std::process::Command;
+use tauri::{Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+use crate::{profile, settings};
+
+// Picker window sizes by monitor scale factor, so a size picked on a HiDPI
+// screen isn't reused as-is on a regular one.
+pub const SIZES_SETTING: &str = "picker_window_sizes";
+
+// How a caller such as the portal asked the window to open.
+#[derive(Debug, Clone, Default)]
+pub struct WindowHints {
+    // `--window-size`, in logical pixels.
+    pub size: Option<(f64, f64)>,
+    pub geometry: Option<Geometry>,
+    // The portal's parent window, `x11:<xid>` or `wayland:<handle>`.
+    pub parent: Option<String>,
+    pub theme: Option<tauri::Theme>,
+}
+
+// An X11-style `WxH+X+Y` geometry in physical pixels, either part optional.
+// Offsets are desktop coordinates, so negative ones reach monitors to the
+// left of or above the primary one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub size: Option<(u32, u32)>,
+    pub position: Option<(i32, i32)>,
+}
+
+pub fn parse_geometry(spec: &str) -> Option<Geometry> {
+    let spec = spec.trim();
+    let split = spec.find(['+', '-']).unwrap_or(spec.len());
+    let (size, offsets) = spec.split_at(split);
+    let size = if size.is_empty() {
+        None
+    } else {
+        let (w, h) = size.split_once(['x', 'X'])?;
+        Some((w.parse().ok()?, h.parse().ok()?))
+    };
+    // Each offset is `+N`, `-N` or `+-N`.
+    let mut numbers = Vec::new();
+    let mut rest = offsets;
+    while !rest.is_empty() {
+        let body = rest.strip_prefix('+').unwrap_or(rest);
+        let end = body
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '+' || *c == '-')
+            .map_or(body.len(), |(i, _)| i);
+        numbers.push(body[..end].parse::<i32>().ok()?);
+        rest = &body[end..];
+    }
+    let position = match numbers[..] {
+        [] => None,
+        [x, y] => Some((x, y)),
+        _ => return None,
+    };
+    (size.is_some() || position.is_some()).then_some(Geometry { size, position })
+}
+
+// The parent's outer rectangle in root coordinates. Wayland hands out opaque
+// handles that say nothing about where a window is, so only X11 parents help.
+fn parent_rect(parent: &str) -> Option<(i32, i32, u32, u32)> {
+    let xid = parent.strip_prefix("x11:")?;
+    let output = Command::new("xwininfo").args(["-id", &format!("0x{}", xid.trim_start_matches("0x"))]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let field = |name: &str| -> Option<i64> {
+        text.lines()
+            .find_map(|l| l.trim().strip_prefix(name))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some((
+        field("Absolute upper-left X:")? as i32,
+        field("Absolute upper-left Y:")? as i32,
+        field("Width:")? as u32,
+        field("Height:")? as u32,
+    ))
+}
+
+fn monitor_at(window: &WebviewWindow, x: i32, y: i32) -> Option<Monitor> {
+    window.available_monitors().ok()?.into_iter().find(|m| {
+        let (pos, size) = (m.position(), m.size());
+        x >= pos.x && y >= pos.y && x < pos.x + size.width as i32 && y < pos.y + size.height as i32
+    })
+}
+
+fn scale_key(scale: f64) -> String {
+    format!("{:.2}", scale)
+}
+
+fn remembered_size(app_handle: &tauri::AppHandle, scale: f64) -> Option<(f64, f64)> {
+    let data_dir = profile::data_dir(app_handle).ok()?;
+    let sizes = settings::get(&data_dir, SIZES_SETTING)?;
+    let size = sizes.get(scale_key(scale))?;
+    Some((size.get(0)?.as_f64()?, size.get(1)?.as_f64()?))
+}
+
+// Sizes and places the window before it's shown: an explicit geometry wins,
+// then a requested size, then the size last used at the target monitor's
+// scale. Without a position it opens centred over the caller, on its monitor.
+pub fn apply(app_handle: &tauri::AppHandle, hints: &WindowHints) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    if hints.theme.is_some() {
+        let _ = window.set_theme(hints.theme);
+    }
+
+    let parent = hints.parent.as_deref().and_then(parent_rect);
+    let monitor = parent
+        .and_then(|(x, y, w, h)| monitor_at(&window, x + w as i32 / 2, y + h as i32 / 2))
+        .or_else(|| window.current_monitor().ok().flatten());
+    let scale = monitor.as_ref().map(Monitor::scale_factor).unwrap_or(1.0);
+
+    let size = match hints.geometry.and_then(|g| g.size) {
+        Some((w, h)) => Some(PhysicalSize::new(w, h)),
+        None => hints
+            .size
+            .or_else(|| remembered_size(app_handle, scale))
+            .map(|(w, h)| PhysicalSize::new((w * scale).round() as u32, (h * scale).round() as u32)),
+    };
+    if let Some(size) = size {
+        let _ = window.set_size(size);
+    }
+
+    if let Some((x, y)) = hints.geometry.and_then(|g| g.position) {
+        let _ = window.set_position(PhysicalPosition::new(x, y));
+        return;
+    }
+    let Some(monitor) = monitor else {
+        if size.is_some() {
+            let _ = window.center();
+        }
+        return;
+    };
+    let outer = size.or_else(|| window.outer_size().ok());
+    let Some(outer) = outer else {
+        return;
+    };
+    let (mpos, msize) = (monitor.position(), monitor.size());
+    let (cx, cy) = match parent {
+        Some((x, y, w, h)) => (x + w as i32 / 2, y + h as i32 / 2),
+        None => (mpos.x + msize.width as i32 / 2, mpos.y + msize.height as i32 / 2),
+    };
+    // Kept on the monitor even when the caller hangs off its edge.
+    let max_x = mpos.x + (msize.width as i32 - outer.width as i32).max(0);
+    let max_y = mpos.y + (msize.height as i32 - outer.height as i32).max(0);
+    let x = (cx - outer.width as i32 / 2).clamp(mpos.x, max_x);
+    let y = (cy - outer.height as i32 / 2).clamp(mpos.y, max_y);
+    let _ = window.set_position(PhysicalPosition::new(x, y));
+}
+
+// Records the window's size for its monitor's scale, in logical pixels.
+pub fn remember(app_handle: &tauri::AppHandle) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+    let (Ok(size), Ok(scale), Ok(data_dir)) = (window.inner_size(), window.scale_factor(), profile::data_dir(app_handle)) else {
+        return;
+    };
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
+    let logical = |v: u32| (v as f64 / scale).round();
+    let _ = settings::update(&data_dir, |settings| {
+        if !settings.get(SIZES_SETTING).is_some_and(Value::is_object) {
+            settings.insert(SIZES_SETTING.to_string(), Value::Object(Map::new()));
+        }
+        if let Some(Value::Object(sizes)) = settings.get_mut(SIZES_SETTING) {
+            sizes.insert(scale_key(scale), serde_json::json!([logical(size.width), logical(size.height)]));
+        }
+    });
+}
@@ -0,0 +1,445 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::statefile;
+
+const MANIFEST_FILE: &str = "plugin.toml";
+const STATE_FILE: &str = "plugins.json";
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginCapability {
+    Preview,
+    ContextMenu,
+    Vfs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginAction {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub directories: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<PluginCapability>,
+    #[serde(default)]
+    pub preview_extensions: Vec<String>,
+    #[serde(default)]
+    pub actions: Vec<PluginAction>,
+    #[serde(default)]
+    pub vfs_schemes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginInfo {
+    pub manifest: PluginManifest,
+    pub dir: String,
+    pub enabled: bool,
+    pub granted: Vec<PluginCapability>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PluginSettings {
+    enabled: bool,
+    granted: Vec<PluginCapability>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginPreview {
+    pub plugin_id: String,
+    pub content_type: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginMenuItem {
+    pub plugin_id: String,
+    pub action_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginVfsEntry {
+    pub name: String,
+    pub uri: String,
+    pub is_dir: bool,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified: i64,
+}
+
+// Plugins speak line-delimited JSON-RPC 2.0 over stdin/stdout; a reader thread
+// forwards every line so calls can time out instead of hanging the host.
+struct PluginProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<Value>,
+    next_id: u64,
+}
+
+impl PluginProcess {
+    fn spawn(manifest: &PluginManifest, dir: &Path, granted: &[PluginCapability]) -> Result<Self, String> {
+        let (program, args) = manifest.command.split_first().ok_or("Plugin has no command")?;
+        let program = if program.contains('/') && !Path::new(program).is_absolute() {
+            dir.join(program)
+        } else {
+            PathBuf::from(program)
+        };
+
+        let mut child = Command::new(&program)
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start plugin {}: {}", manifest.id, e))?;
+
+        let stdin = child.stdin.take().ok_or("Failed to open plugin input")?;
+        let stdout = child.stdout.take().ok_or("Failed to open plugin output")?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Ok(value) = serde_json::from_str::<Value>(&line) {
+                    if tx.send(value).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut process = Self {
+            child,
+            stdin,
+            lines: rx,
+            next_id: 1,
+        };
+        process.call(
+            "initialize",
+            json!({ "host": "hardbore", "version": env!("CARGO_PKG_VERSION"), "capabilities": granted }),
+        )?;
+        Ok(process)
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.stdin, "{}", request)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Failed to talk to plugin: {}", e))?;
+
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let message = self
+                .lines
+                .recv_timeout(remaining)
+                .map_err(|_| format!("Plugin did not answer {}", method))?;
+
+            // Anything without our id is a notification or a stale reply.
+            if message.get("id").and_then(|v| v.as_u64()) != Some(id) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error");
+                return Err(format!("Plugin error: {}", text));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct PluginHost {
+    plugins_dir: PathBuf,
+    state_file: PathBuf,
+    plugins: Vec<PluginInfo>,
+    processes: HashMap<String, PluginProcess>,
+}
+
+fn matches_extension(path: &str, extensions: &[String]) -> bool {
+    let ext = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    extensions.iter().any(|e| e == "*" || e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+}
+
+// `scheme://authority` of a plugin VFS URI; None for local paths and the
+// app's own locations.
+pub fn vfs_backend(uri: &str) -> Option<&str> {
+    if crate::locations::is_virtual(uri) {
+        return None;
+    }
+    let (scheme, rest) = uri.split_once("://")?;
+    let authority = rest.split('/').next().unwrap_or_default();
+    Some(&uri[..scheme.len() + 3 + authority.len()])
+}
+
+fn method_capability(method: &str) -> Option<PluginCapability> {
+    match method {
+        "preview" => Some(PluginCapability::Preview),
+        "context_action" => Some(PluginCapability::ContextMenu),
+        m if m.starts_with("vfs.") => Some(PluginCapability::Vfs),
+        _ => None,
+    }
+}
+
+impl PluginHost {
+    pub fn new(data_dir: &Path) -> Result<Self, String> {
+        let plugins_dir = data_dir.join("plugins");
+        fs::create_dir_all(&plugins_dir).map_err(|e| format!("Failed to create plugins directory: {}", e))?;
+
+        let mut host = Self {
+            plugins_dir,
+            state_file: data_dir.join(STATE_FILE),
+            plugins: Vec::new(),
+            processes: HashMap::new(),
+        };
+        host.reload()?;
+        Ok(host)
+    }
+
+    fn load_settings(&self) -> HashMap<String, PluginSettings> {
+        statefile::read(&self.state_file)
+    }
+
+    fn save_settings(&self) -> Result<(), String> {
+        let settings: HashMap<String, PluginSettings> = self
+            .plugins
+            .iter()
+            .map(|p| {
+                let s = PluginSettings {
+                    enabled: p.enabled,
+                    granted: p.granted.clone(),
+                };
+                (p.manifest.id.clone(), s)
+            })
+            .collect();
+        statefile::write(&self.state_file, "plugin settings", &settings)
+    }
+
+    pub fn reload(&mut self) -> Result<Vec<String>, String> {
+        self.processes.clear();
+        let settings = self.load_settings();
+        let mut errors = Vec::new();
+        let mut plugins = Vec::new();
+
+        let read_dir = fs::read_dir(&self.plugins_dir).map_err(|e| format!("Failed to read plugins: {}", e))?;
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let manifest_path = entry.path().join(MANIFEST_FILE);
+            if !manifest_path.is_file() {
+                continue;
+            }
+            let manifest = fs::read_to_string(&manifest_path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| toml::from_str::<PluginManifest>(&content).map_err(|e| e.to_string()));
+            let manifest = match manifest {
+                Ok(m) => m,
+                Err(e) => {
+                    errors.push(format!("{}: {}", manifest_path.display(), e));
+                    continue;
+                }
+            };
+
+            // New plugins start disabled with nothing granted until the user approves them.
+            let saved = settings.get(&manifest.id).cloned().unwrap_or_default();
+            let granted = saved
+                .granted
+                .into_iter()
+                .filter(|c| manifest.capabilities.contains(c))
+                .collect();
+            plugins.push(PluginInfo {
+                dir: entry.path().to_string_lossy().to_string(),
+                enabled: saved.enabled,
+                granted,
+                manifest,
+            });
+        }
+
+        plugins.sort_by_key(|p| p.manifest.name.to_lowercase());
+        self.plugins = plugins;
+        Ok(errors)
+    }
+
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.plugins.clone()
+    }
+
+    pub fn set_permissions(&mut self, id: &str, enabled: bool, granted: Vec<PluginCapability>) -> Result<(), String> {
+        let plugin = self
+            .plugins
+            .iter_mut()
+            .find(|p| p.manifest.id == id)
+            .ok_or("Plugin not found")?;
+        plugin.enabled = enabled;
+        plugin.granted = granted
+            .into_iter()
+            .filter(|c| plugin.manifest.capabilities.contains(c))
+            .collect();
+        // Restart with the new grants on next use.
+        self.processes.remove(id);
+        self.save_settings()
+    }
+
+    fn active(&self, capability: PluginCapability) -> impl Iterator<Item = &PluginInfo> {
+        self.plugins
+            .iter()
+            .filter(move |p| p.enabled && p.granted.contains(&capability))
+    }
+
+    fn call(&mut self, id: &str, method: &str, params: Value) -> Result<Value, String> {
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|p| p.manifest.id == id)
+            .ok_or("Plugin not found")?;
+        if !plugin.enabled {
+            return Err(format!("Plugin {} is disabled", id));
+        }
+        if let Some(capability) = method_capability(method) {
+            if !plugin.granted.contains(&capability) {
+                return Err(format!("Plugin {} lacks the {:?} permission", id, capability));
+            }
+        }
+
+        if !self.processes.contains_key(id) {
+            let process = PluginProcess::spawn(&plugin.manifest, Path::new(&plugin.dir), &plugin.granted)?;
+            self.processes.insert(id.to_string(), process);
+        }
+        let process = self.processes.get_mut(id).unwrap();
+        let result = process.call(method, params);
+        // A plugin that timed out or crashed is restarted on the next call.
+        if result.is_err() {
+            self.processes.remove(id);
+        }
+        result
+    }
+
+    pub fn preview(&mut self, path: &str) -> Result<Option<PluginPreview>, String> {
+        let ids: Vec<String> = self
+            .active(PluginCapability::Preview)
+            .filter(|p| matches_extension(path, &p.manifest.preview_extensions))
+            .map(|p| p.manifest.id.clone())
+            .collect();
+
+        for id in ids {
+            let result = self.call(&id, "preview", json!({ "path": path }))?;
+            if result.is_null() {
+                continue;
+            }
+            let (content_type, content) = if let Some(html) = result.get("html").and_then(|v| v.as_str()) {
+                ("html", html)
+            } else if let Some(text) = result.get("text").and_then(|v| v.as_str()) {
+                ("text", text)
+            } else {
+                continue;
+            };
+            return Ok(Some(PluginPreview {
+                plugin_id: id,
+                content_type: content_type.to_string(),
+                content: content.to_string(),
+            }));
+        }
+        Ok(None)
+    }
+
+    pub fn context_actions(&self, paths: &[String]) -> Vec<PluginMenuItem> {
+        if paths.is_empty() {
+            return Vec::new();
+        }
+        self.active(PluginCapability::ContextMenu)
+            .flat_map(|p| {
+                p.manifest.actions.iter().filter_map(move |action| {
+                    let applies = paths.iter().all(|path| {
+                        if Path::new(path).is_dir() {
+                            action.directories
+                        } else {
+                            action.extensions.is_empty() || matches_extension(path, &action.extensions)
+                        }
+                    });
+                    applies.then(|| PluginMenuItem {
+                        plugin_id: p.manifest.id.clone(),
+                        action_id: action.id.clone(),
+                        title: action.title.clone(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    pub fn run_action(&mut self, plugin_id: &str, action_id: &str, paths: &[String]) -> Result<Option<String>, String> {
+        let result = self.call(plugin_id, "context_action", json!({ "action": action_id, "paths": paths }))?;
+        Ok(result.get("message").and_then(|m| m.as_str()).map(|s| s.to_string()))
+    }
+
+    fn vfs_plugin(&self, uri: &str) -> Result<String, String> {
+        let scheme = uri.split_once("://").map(|(s, _)| s).ok_or("Invalid URI")?;
+        self.active(PluginCapability::Vfs)
+            .find(|p| p.manifest.vfs_schemes.iter().any(|s| s == scheme))
+            .map(|p| p.manifest.id.clone())
+            .ok_or_else(|| format!("No plugin handles {}://", scheme))
+    }
+
+    pub fn vfs_list(&mut self, uri: &str) -> Result<Vec<PluginVfsEntry>, String> {
+        let id = self.vfs_plugin(uri)?;
+        let result = self.call(&id, "vfs.list", json!({ "uri": uri }))?;
+        serde_json::from_value(result).map_err(|e| format!("Invalid plugin response: {}", e))
+    }
+
+    // Copies or moves within one backend without the data passing through
+    // here; the plugin maps these to SFTP copy/rename, SMB server-side copy,
+    // S3 CopyObject and the like.
+    pub fn vfs_copy(&mut self, source: &str, destination: &str) -> Result<(), String> {
+        let id = self.vfs_plugin(source)?;
+        self.call(&id, "vfs.copy", json!({ "source": source, "destination": destination }))?;
+        Ok(())
+    }
+
+    pub fn vfs_rename(&mut self, source: &str, destination: &str) -> Result<(), String> {
+        let id = self.vfs_plugin(source)?;
+        self.call(&id, "vfs.rename", json!({ "source": source, "destination": destination }))?;
+        Ok(())
+    }
+
+    // Returns the file contents base64-encoded, as sent by the plugin.
+    pub fn vfs_read(&mut self, uri: &str) -> Result<String, String> {
+        let id = self.vfs_plugin(uri)?;
+        let result = self.call(&id, "vfs.read", json!({ "uri": uri }))?;
+        result
+            .get("data")
+            .and_then(|d| d.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| "Invalid plugin response".to_string())
+    }
+}
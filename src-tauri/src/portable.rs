@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::Manager;
+
+// A `portable` file beside the executable, or `--portable`, keeps every bit
+// of state in a folder next to it instead of the user's profile, so HardBore
+// can live on a USB stick together with its catalog.
+pub const MARKER: &str = "portable";
+const DATA_FOLDER: &str = "HardBoreData";
+
+static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// The folder the executable was started from. An AppImage runs from a
+// temporary mount, so the image's own location counts instead.
+fn executable_dir() -> Option<PathBuf> {
+    let exe = std::env::var_os("APPIMAGE")
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_exe().ok())?;
+    exe.parent().map(|p| p.to_path_buf())
+}
+
+pub fn init(forced: bool) {
+    let root = executable_dir()
+        .filter(|dir| forced || dir.join(MARKER).is_file())
+        .map(|dir| dir.join(DATA_FOLDER));
+    let _ = ROOT.set(root);
+}
+
+pub fn root() -> Option<PathBuf> {
+    ROOT.get().cloned().flatten()
+}
+
+pub fn app_data_dir(app_handle: &tauri::AppHandle) -> tauri::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root),
+        None => app_handle.path().app_data_dir(),
+    }
+}
+
+pub fn app_config_dir(app_handle: &tauri::AppHandle) -> tauri::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.join("config")),
+        None => app_handle.path().app_config_dir(),
+    }
+}
@@ -0,0 +1,304 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::applications::data_dirs;
+
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.hardbore";
+const FRONTEND_BUS_NAME: &str = "org.freedesktop.portal.Desktop";
+const UNIT_NAME: &str = "hardbore-portal.service";
+const PORTAL_FILE: &str = include_str!("../../portal/hardbore.portal");
+// What portals.conf is told to use HardBore for; everything else stays with
+// the desktop's own backend.
+const INTERFACES: [&str; 2] = [
+    "org.freedesktop.impl.portal.FileChooser",
+    "org.freedesktop.impl.portal.AppChooser",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortalStatus {
+    // The backend binary the installed files start.
+    pub binary: Option<String>,
+    pub portal_file: Option<String>,
+    pub dbus_service: Option<String>,
+    pub systemd_unit: Option<String>,
+    // portals.conf picks HardBore for every interface it serves.
+    pub preferred: bool,
+    // The session bus can start the backend when a dialog is asked for.
+    pub activatable: bool,
+    pub running: bool,
+    // xdg-desktop-portal itself, which forwards apps' requests to backends.
+    pub frontend_running: bool,
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallReport {
+    pub written: Vec<String>,
+    pub status: PortalStatus,
+}
+
+fn user_portal_file() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("xdg-desktop-portal/portals/hardbore.portal"))
+}
+
+fn user_dbus_service() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("dbus-1/services").join(format!("{}.service", BUS_NAME)))
+}
+
+fn user_unit() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("systemd/user").join(UNIT_NAME))
+}
+
+fn user_portals_conf() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("xdg-desktop-portal/portals.conf"))
+}
+
+// The backend is built as `portal` and installed as `hardbore-portal`; a
+// copy next to HardBore itself wins over one on PATH.
+fn find_binary() -> Option<PathBuf> {
+    let beside = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .into_iter()
+        .flat_map(|dir| [dir.join("hardbore-portal"), dir.join("portal")]);
+    let installed = ["/usr/local/bin/hardbore-portal", "/usr/bin/hardbore-portal"].map(PathBuf::from);
+    beside.chain(installed).find(|p| p.is_file()).or_else(|| {
+        let output = Command::new("which").arg("hardbore-portal").output().ok()?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+    })
+}
+
+fn first_existing(candidates: impl IntoIterator<Item = PathBuf>) -> Option<String> {
+    candidates
+        .into_iter()
+        .find(|p| p.is_file())
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+// The `Exec=` or `ExecStart=` of a service file.
+fn exec_of(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("ExecStart=")
+            .or_else(|| line.strip_prefix("Exec="))
+            .and_then(|cmd| cmd.split_whitespace().next())
+            .map(str::to_string)
+    })
+}
+
+fn gdbus_bool(method: &str, arg: &str) -> Option<bool> {
+    let output = Command::new("gdbus")
+        .args(["call", "--session", "--timeout", "2", "--dest", "org.freedesktop.DBus"])
+        .args(["--object-path", "/org/freedesktop/DBus", "--method"])
+        .arg(format!("org.freedesktop.DBus.{}", method))
+        .arg(arg)
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).contains("true"))
+}
+
+fn activatable_names() -> Option<String> {
+    let output = Command::new("gdbus")
+        .args(["call", "--session", "--timeout", "2", "--dest", "org.freedesktop.DBus"])
+        .args(["--object-path", "/org/freedesktop/DBus"])
+        .args(["--method", "org.freedesktop.DBus.ListActivatableNames"])
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// The desktop-specific file is read before the generic one, per portals.conf(5).
+fn portals_conf_files() -> Vec<PathBuf> {
+    let Some(dir) = dirs::config_dir().map(|d| d.join("xdg-desktop-portal")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|d| !d.is_empty())
+        .map(|d| dir.join(format!("{}-portals.conf", d.to_lowercase())))
+        .collect();
+    files.push(dir.join("portals.conf"));
+    files
+}
+
+// Whether the first portals.conf that exists prefers HardBore, directly or
+// as its default. Without any, the .portal file's UseIn decides.
+fn preferred() -> Option<bool> {
+    let content = portals_conf_files().into_iter().find_map(|f| fs::read_to_string(f).ok())?;
+    let mut default = None;
+    let mut chosen = Vec::new();
+    let mut in_preferred = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_preferred = line == "[preferred]";
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_preferred) {
+            let uses_us = value.split(';').next().is_some_and(|b| b.trim() == "hardbore");
+            match key.trim() {
+                "default" => default = Some(uses_us),
+                key if INTERFACES.contains(&key) => chosen.push(uses_us),
+                _ => {}
+            }
+        }
+    }
+    Some((chosen.len() == INTERFACES.len() && chosen.iter().all(|c| *c)) || (chosen.is_empty() && default == Some(true)))
+}
+
+pub fn status() -> PortalStatus {
+    let binary = find_binary().map(|p| p.to_string_lossy().to_string());
+    let portal_file = first_existing(data_dirs().into_iter().map(|d| d.join("xdg-desktop-portal/portals/hardbore.portal")));
+    let dbus_service = first_existing(
+        data_dirs()
+            .into_iter()
+            .map(|d| d.join("dbus-1/services").join(format!("{}.service", BUS_NAME))),
+    );
+    let systemd_unit = first_existing(user_unit());
+    let names = activatable_names();
+    let activatable = names.as_deref().is_some_and(|n| n.contains(&format!("'{}'", BUS_NAME)));
+    let running = gdbus_bool("NameHasOwner", BUS_NAME).unwrap_or(false);
+    let frontend_running = gdbus_bool("NameHasOwner", FRONTEND_BUS_NAME).unwrap_or(false);
+    let preferred = preferred();
+
+    let mut problems = Vec::new();
+    if binary.is_none() {
+        problems.push("hardbore-portal was not found next to HardBore or on PATH".to_string());
+    }
+    if portal_file.is_none() {
+        problems.push("No hardbore.portal file where xdg-desktop-portal looks for backends".to_string());
+    }
+    match &dbus_service {
+        None => problems.push("No D-Bus service file, so the backend can't be started on demand".to_string()),
+        Some(service) => {
+            if let Some(exec) = exec_of(service).filter(|e| !Path::new(e).is_file()) {
+                problems.push(format!("{} starts {}, which does not exist", service, exec));
+            }
+        }
+    }
+    if let Some(exec) = systemd_unit.as_deref().and_then(exec_of).filter(|e| !Path::new(e).is_file()) {
+        problems.push(format!("{} starts {}, which does not exist", UNIT_NAME, exec));
+    }
+    if names.is_none() {
+        problems.push("Could not ask the session bus about the backend; is gdbus installed?".to_string());
+    } else if !activatable && dbus_service.is_some() {
+        problems.push("The session bus doesn't list the backend yet; log out and back in".to_string());
+    }
+    if preferred == Some(false) {
+        problems.push("portals.conf picks another backend for the file chooser".to_string());
+    }
+    if !frontend_running {
+        problems.push("xdg-desktop-portal is not running".to_string());
+    }
+
+    PortalStatus {
+        binary,
+        portal_file,
+        dbus_service,
+        systemd_unit,
+        preferred: preferred.unwrap_or_else(portal_file_used_in_desktop),
+        activatable,
+        running,
+        frontend_running,
+        problems,
+    }
+}
+
+// Without a portals.conf, older xdg-desktop-portal releases go by UseIn.
+fn portal_file_used_in_desktop() -> bool {
+    let desktops = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    PORTAL_FILE
+        .lines()
+        .find_map(|l| l.strip_prefix("UseIn="))
+        .is_some_and(|used| used.split(';').any(|d| !d.is_empty() && desktops.split(':').any(|c| c == d)))
+}
+
+// Added at the end of the section, before the blank lines that close it.
+fn push_preferences(lines: &mut Vec<String>) {
+    let blank = lines.iter().rev().take_while(|l| l.trim().is_empty()).count();
+    let at = lines.len() - blank;
+    lines.splice(at..at, INTERFACES.map(|interface| format!("{}=hardbore", interface)));
+}
+
+// Sets HardBore as the backend for its interfaces in portals.conf, keeping
+// whatever else the file says.
+fn prefer_in(content: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_preferred = false;
+    let mut saw_preferred = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_preferred {
+                push_preferences(&mut lines);
+            }
+            in_preferred = trimmed == "[preferred]";
+            saw_preferred |= in_preferred;
+        } else if in_preferred && trimmed.split_once('=').is_some_and(|(k, _)| INTERFACES.contains(&k.trim())) {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if in_preferred {
+        push_preferences(&mut lines);
+    } else if !saw_preferred {
+        if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push("[preferred]".to_string());
+        push_preferences(&mut lines);
+    }
+    lines.join("\n") + "\n"
+}
+
+fn write(path: &Path, content: &str, written: &mut Vec<String>) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    written.push(path.to_string_lossy().to_string());
+    Ok(())
+}
+
+// Registers the backend for the current user: the .portal file, a D-Bus
+// service that starts it through a systemd user unit, and the portals.conf
+// preference. xdg-desktop-portal is restarted to pick it up.
+pub fn install() -> Result<InstallReport, String> {
+    if !cfg!(target_os = "linux") {
+        return Err("The portal backend is only used on Linux".to_string());
+    }
+    let binary = find_binary().ok_or("hardbore-portal was not found; build it in portal/ and put it next to HardBore or on PATH")?;
+    let binary = binary.to_string_lossy();
+    let home = "Cannot determine the home directory";
+    let mut written = Vec::new();
+
+    write(&user_portal_file().ok_or(home)?, PORTAL_FILE, &mut written)?;
+    write(
+        &user_dbus_service().ok_or(home)?,
+        &format!("[D-BUS Service]\nName={}\nExec={}\nSystemdService={}\n", BUS_NAME, binary, UNIT_NAME),
+        &mut written,
+    )?;
+    write(
+        &user_unit().ok_or(home)?,
+        &format!(
+            "[Unit]\nDescription=HardBore portal backend\nPartOf=graphical-session.target\n\n\
+             [Service]\nType=dbus\nBusName={}\nExecStart={}\n",
+            BUS_NAME, binary
+        ),
+        &mut written,
+    )?;
+    let conf = user_portals_conf().ok_or(home)?;
+    write(&conf, &prefer_in(&fs::read_to_string(&conf).unwrap_or_default()), &mut written)?;
+
+    // Best effort: without systemd the D-Bus service starts the binary itself.
+    let _ = Command::new("systemctl").args(["--user", "daemon-reload"]).output();
+    let _ = Command::new("systemctl")
+        .args(["--user", "try-restart", "xdg-desktop-portal.service"])
+        .output();
+
+    Ok(InstallReport {
+        written,
+        status: status(),
+    })
+}
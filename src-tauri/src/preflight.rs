@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+use crate::humanize::format_size;
+use crate::mounts::get_mount_for_path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PreflightProblem {
+    NotADirectory,
+    NotWritable,
+    InsufficientSpace,
+}
+
+// Structured so the frontend can tell a full disk from a permission problem;
+// jobs report just the message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightError {
+    pub problem: PreflightProblem,
+    pub destination: String,
+    pub message: String,
+    pub required: Option<u64>,
+    pub available: Option<u64>,
+}
+
+impl PreflightError {
+    fn new(problem: PreflightProblem, destination: &Path, message: String) -> Self {
+        Self {
+            problem,
+            destination: destination.to_string_lossy().to_string(),
+            message,
+            required: None,
+            available: None,
+        }
+    }
+}
+
+// Permission bits don't cover ACLs, read-only mounts or sandboxing, so the only
+// reliable answer is to create a file and remove it again.
+pub(crate) fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".hardbore-preflight-{}", std::process::id()));
+    OpenOptions::new().write(true).create_new(true).open(&probe)?;
+    fs::remove_file(&probe)
+}
+
+// Checks that `destination` is a writable directory with room for `required`
+// more bytes. Free space that can't be read is not treated as a failure.
+pub fn check_destination(destination: &Path, required: u64) -> Result<(), PreflightError> {
+    if !destination.is_dir() {
+        return Err(PreflightError::new(
+            PreflightProblem::NotADirectory,
+            destination,
+            "Destination must be a directory".to_string(),
+        ));
+    }
+    if let Err(e) = probe_writable(destination) {
+        return Err(PreflightError::new(
+            PreflightProblem::NotWritable,
+            destination,
+            format!("Cannot write to {}: {}", destination.display(), e),
+        ));
+    }
+    if required == 0 {
+        return Ok(());
+    }
+    let Ok(mount) = get_mount_for_path(destination) else {
+        return Ok(());
+    };
+    if required > mount.available_space {
+        return Err(PreflightError {
+            required: Some(required),
+            available: Some(mount.available_space),
+            ..PreflightError::new(
+                PreflightProblem::InsufficientSpace,
+                destination,
+                format!(
+                    "Not enough space on {}: {} needed, {} available",
+                    mount.mount_point,
+                    format_size(required),
+                    format_size(mount.available_space)
+                ),
+            )
+        });
+    }
+    Ok(())
+}
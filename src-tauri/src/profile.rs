@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::portable;
+
+// `--profile NAME` keeps its own index, favorites, settings and workspaces
+// under `profiles/NAME` in the data dir, so a work, personal or throwaway
+// profile never touches the main one.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+// Names end up as a folder and in a socket name, so only plain ones pass.
+pub fn validate(name: &str) -> Result<(), String> {
+    let plain = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if name.is_empty() || name.len() > 64 || name.starts_with('.') || !plain {
+        return Err(format!(
+            "Invalid profile name {:?}: use up to 64 letters, digits, '-', '_' or '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+pub fn set(name: &str) -> Result<(), String> {
+    validate(name)?;
+    PROFILE.set(name.to_string()).map_err(|_| "The profile is already set".to_string())
+}
+
+pub fn name() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+// `base` itself for the default profile.
+pub fn within(base: PathBuf) -> PathBuf {
+    match name() {
+        Some(name) => base.join("profiles").join(name),
+        None => base,
+    }
+}
+
+// What the rest of the app uses in place of the app data dir.
+pub fn data_dir(app_handle: &tauri::AppHandle) -> tauri::Result<PathBuf> {
+    portable::app_data_dir(app_handle).map(within)
+}
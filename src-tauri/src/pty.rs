@@ -0,0 +1,235 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::clipboard::shell_quote;
+use crate::downloads::decode_percent;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyOutput {
+    pub id: u64,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyExit {
+    pub id: u64,
+    pub code: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyCwd {
+    pub id: u64,
+    pub path: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum PtyEvent {
+    Output(PtyOutput),
+    Cwd(PtyCwd),
+    Exit(PtyExit),
+}
+
+pub type PtyCallback = Arc<dyn Fn(PtyEvent) + Send + Sync>;
+
+struct PtySession {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+pub struct PtyManager {
+    sessions: Arc<Mutex<HashMap<u64, PtySession>>>,
+    next_id: AtomicU64,
+}
+
+impl Default for PtyManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+// Reads arrive in arbitrary chunks; hold back a trailing partial UTF-8 sequence
+// until the rest of it shows up instead of emitting replacement characters.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).to_string();
+    *pending = rest;
+    text
+}
+
+// Shells announce their directory with OSC 7: `ESC ] 7 ; file://host/path` ended
+// by BEL or ST. Only the last one in a chunk matters.
+fn osc7_path(data: &str) -> Option<String> {
+    let start = data.rfind("\x1b]7;")? + 4;
+    let rest = &data[start..];
+    let end = rest.find(['\x07', '\x1b']).unwrap_or(rest.len());
+    let uri = rest[..end].strip_prefix("file://")?;
+    let path = &uri[uri.find('/')?..];
+    Some(decode_percent(path))
+}
+
+// Fallback for shells that don't emit OSC 7: ask the kernel where the shell is.
+#[cfg(target_os = "linux")]
+fn process_cwd(pid: Option<u32>) -> Option<String> {
+    std::fs::read_link(format!("/proc/{}/cwd", pid?))
+        .ok()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cwd(_pid: Option<u32>) -> Option<String> {
+    None
+}
+
+impl PtyManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn spawn(
+        &self,
+        cwd: &str,
+        shell: Option<&str>,
+        rows: u16,
+        cols: u16,
+        on_event: PtyCallback,
+    ) -> Result<u64, String> {
+        if !Path::new(cwd).is_dir() {
+            return Err("Working directory must be a directory".to_string());
+        }
+
+        let pair = native_pty_system()
+            .openpty(size(rows, cols))
+            .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+        let mut cmd = match shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new_default_prog(),
+        };
+        cmd.cwd(cwd);
+        cmd.env("TERM", "xterm-256color");
+        // The shell's own config is left alone: overriding PROMPT_COMMAND would
+        // be undone by .bashrc or would replace the user's. Shells that emit
+        // OSC 7 are followed that way, the rest through the process cwd.
+
+        let child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to spawn shell: {}", e))?;
+        // Keeping the slave open here would stop the reader from ever seeing EOF.
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to open pty: {}", e))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+        let pid = child.process_id();
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.sessions.lock().unwrap().insert(
+            id,
+            PtySession {
+                master: pair.master,
+                writer,
+                child,
+            },
+        );
+
+        let sessions = self.sessions.clone();
+        let mut last_cwd = cwd.to_string();
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            let mut pending = Vec::new();
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        pending.extend_from_slice(&buf[..n]);
+                        let data = take_utf8(&mut pending);
+                        if data.is_empty() {
+                            continue;
+                        }
+                        let cwd = osc7_path(&data).or_else(|| process_cwd(pid));
+                        on_event(PtyEvent::Output(PtyOutput { id, data }));
+                        if let Some(path) = cwd.filter(|c| *c != last_cwd) {
+                            last_cwd = path.clone();
+                            on_event(PtyEvent::Cwd(PtyCwd { id, path }));
+                        }
+                    }
+                }
+            }
+
+            let session = sessions.lock().unwrap().remove(&id);
+            let code = session
+                .and_then(|mut s| s.child.wait().ok())
+                .map(|status| status.exit_code());
+            on_event(PtyEvent::Exit(PtyExit { id, code }));
+        });
+
+        Ok(id)
+    }
+
+    pub fn write(&self, id: u64, data: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(&id).ok_or("Terminal session not found")?;
+        session
+            .writer
+            .write_all(data.as_bytes())
+            .and_then(|_| session.writer.flush())
+            .map_err(|e| format!("Failed to write to terminal: {}", e))
+    }
+
+    // Ctrl+U first so a half-typed command line doesn't get glued to the cd; the
+    // leading space keeps it out of history with HISTCONTROL=ignorespace.
+    pub fn change_directory(&self, id: u64, path: &str) -> Result<(), String> {
+        if !Path::new(path).is_dir() {
+            return Err("Not a directory".to_string());
+        }
+        self.write(id, &format!("\x15 cd -- {}\r", shell_quote(path)))
+    }
+
+    pub fn resize(&self, id: u64, rows: u16, cols: u16) -> Result<(), String> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(&id).ok_or("Terminal session not found")?;
+        session
+            .master
+            .resize(size(rows, cols))
+            .map_err(|e| format!("Failed to resize terminal: {}", e))
+    }
+
+    pub fn kill(&self, id: u64) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(&id).ok_or("Terminal session not found")?;
+        session
+            .child
+            .kill()
+            .map_err(|e| format!("Failed to close terminal: {}", e))
+    }
+}
@@ -0,0 +1,112 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+// Paths cross the IPC boundary as JSON strings, which can't hold the raw bytes
+// of a non-UTF-8 unix name or the lone surrogates of a broken Windows one.
+// Each such unit is mapped to a code point in the supplementary private use
+// area instead. A genuine name character that falls in that range is escaped
+// the same way, so the mapping stays reversible. Valid names are unchanged.
+#[cfg(unix)]
+const ESCAPE_BASE: u32 = 0x10_FF00;
+#[cfg(unix)]
+const ESCAPE_RANGE: std::ops::RangeInclusive<u32> = 0x10_FF80..=0x10_FFFF;
+
+#[cfg(windows)]
+const ESCAPE_BASE: u32 = 0x10_0000;
+#[cfg(windows)]
+const ESCAPE_RANGE: std::ops::RangeInclusive<u32> = 0x10_0000..=0x10_07FF;
+
+fn is_escape(c: char) -> bool {
+    ESCAPE_RANGE.contains(&(c as u32))
+}
+
+#[cfg(unix)]
+fn escape_unit(out: &mut String, byte: u8) {
+    out.extend(char::from_u32(ESCAPE_BASE + byte as u32));
+}
+
+#[cfg(windows)]
+fn escape_unit(out: &mut String, unit: u16) {
+    out.extend(char::from_u32(ESCAPE_BASE + (unit as u32 - 0xD800)));
+}
+
+#[cfg(unix)]
+pub fn encode(os: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut out = String::new();
+    for chunk in os.as_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if is_escape(c) {
+                c.encode_utf8(&mut [0; 4]).bytes().for_each(|b| escape_unit(&mut out, b));
+            } else {
+                out.push(c);
+            }
+        }
+        chunk.invalid().iter().for_each(|&b| escape_unit(&mut out, b));
+    }
+    out
+}
+
+#[cfg(windows)]
+pub fn encode(os: &OsStr) -> String {
+    use std::os::windows::ffi::OsStrExt;
+
+    let mut out = String::new();
+    for unit in char::decode_utf16(os.encode_wide()) {
+        match unit {
+            Ok(c) if is_escape(c) => c.encode_utf16(&mut [0; 2]).iter().for_each(|&u| escape_unit(&mut out, u)),
+            Ok(c) => out.push(c),
+            Err(e) => escape_unit(&mut out, e.unpaired_surrogate()),
+        }
+    }
+    out
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn encode(os: &OsStr) -> String {
+    os.to_string_lossy().to_string()
+}
+
+#[cfg(unix)]
+pub fn decode(text: &str) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+
+    if !text.chars().any(is_escape) {
+        return PathBuf::from(text);
+    }
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match is_escape(c) {
+            true => bytes.push((c as u32 - ESCAPE_BASE) as u8),
+            false => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(windows)]
+pub fn decode(text: &str) -> PathBuf {
+    use std::os::windows::ffi::OsStringExt;
+
+    if !text.chars().any(is_escape) {
+        return PathBuf::from(text);
+    }
+    let mut units = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match is_escape(c) {
+            true => units.push((c as u32 - ESCAPE_BASE + 0xD800) as u16),
+            false => units.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+        }
+    }
+    PathBuf::from(OsString::from_wide(&units))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn decode(text: &str) -> PathBuf {
+    PathBuf::from(text)
+}
+
+pub fn encode_path(path: &Path) -> String {
+    encode(path.as_os_str())
+}
@@ -0,0 +1,308 @@
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::jobs::{JobCallback, JobInfo, JobManager};
+use crate::transfer::{self, SkipIdentical, TransferMode};
+use crate::{fs_engine, rawpath, settings};
+
+const SCRIPT_EXTENSION: &str = "rhai";
+const MAX_OPERATIONS: u64 = 50_000_000;
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptInfo {
+    pub name: String,
+    pub path: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptOutput {
+    pub run_id: u64,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptPrompt {
+    pub id: u64,
+    pub run_id: u64,
+    pub message: String,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptResult {
+    pub run_id: u64,
+    pub output: Vec<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScriptEvent {
+    Output(ScriptOutput),
+    Prompt(ScriptPrompt),
+    // Progress of a copy or move the script started.
+    Job(JobInfo),
+}
+
+pub type ScriptCallback = Arc<dyn Fn(ScriptEvent) + Send + Sync>;
+
+pub struct ScriptHost {
+    pending: Arc<Mutex<HashMap<u64, Sender<Option<String>>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+// Copies and moves run as jobs like any other transfer, so they can be
+// followed, paused and cancelled, and resume after a crash; the script waits
+// for the job to end.
+fn run_transfer(
+    data_dir: &Path,
+    jobs: &JobManager,
+    on_job: JobCallback,
+    source: &str,
+    dest_dir: &str,
+    mode: TransferMode,
+) -> Result<(), String> {
+    let sources = vec![source.to_string()];
+    let title = transfer::transfer_title(&sources, mode);
+    let job = jobs.create(mode.job_kind(), &title, transfer::transfer_paths(&sources, dest_dir), on_job);
+    let plan = transfer::plan_transfer(job.id(), &title, sources, dest_dir.to_string(), mode, SkipIdentical::Never);
+    let result = transfer::run_transfer(data_dir, &plan, &job);
+    crate::dircache::clear();
+    crate::update_index(transfer::changed_paths(&plan));
+    job.finish(result.clone());
+    result
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn json_to_dynamic(value: Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(b) => Dynamic::from(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Dynamic::from(i),
+            None => Dynamic::from(n.as_f64().unwrap_or(0.0)),
+        },
+        Value::String(s) => Dynamic::from(s),
+        Value::Array(items) => Dynamic::from(items.into_iter().map(json_to_dynamic).collect::<Array>()),
+        Value::Object(map) => {
+            let map: Map = map
+                .into_iter()
+                .map(|(k, v)| (k.into(), json_to_dynamic(v)))
+                .collect();
+            Dynamic::from(map)
+        }
+    }
+}
+
+fn runtime_error(e: String) -> Box<EvalAltResult> {
+    e.into()
+}
+
+fn scripts_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("scripts")
+}
+
+// The first `//` comment line of a script doubles as its description.
+fn script_description(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let first = content.lines().find(|l| !l.trim().is_empty())?;
+    first
+        .trim()
+        .strip_prefix("//")
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+}
+
+pub fn list_scripts(data_dir: &Path) -> Result<Vec<ScriptInfo>, String> {
+    let dir = scripts_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create scripts directory: {}", e))?;
+
+    let mut scripts: Vec<ScriptInfo> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read scripts: {}", e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == SCRIPT_EXTENSION))
+        .map(|p| ScriptInfo {
+            name: p.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            path: p.to_string_lossy().to_string(),
+            description: script_description(&p),
+        })
+        .collect();
+
+    scripts.sort_by_key(|s| s.name.to_lowercase());
+    Ok(scripts)
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn answer(&self, id: u64, value: Option<String>) -> Result<(), String> {
+        let sender = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or("No script is waiting for this prompt")?;
+        sender.send(value).map_err(|_| "Script is no longer running".to_string())
+    }
+
+    fn build_engine(
+        &self,
+        run_id: u64,
+        data_dir: &Path,
+        jobs: &JobManager,
+        output: Arc<Mutex<Vec<String>>>,
+        on_event: ScriptCallback,
+    ) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        // The default resolver would let `import` load any file on disk,
+        // outside the functions registered below.
+        engine.set_module_resolver(DummyModuleResolver::new());
+
+        let print_event = on_event.clone();
+        engine.on_print(move |line: &str| {
+            output.lock().unwrap().push(line.to_string());
+            print_event(ScriptEvent::Output(ScriptOutput {
+                run_id,
+                line: line.to_string(),
+            }));
+        });
+
+        engine.register_fn("list_dir", |path: &str| -> Result<Array, Box<EvalAltResult>> {
+            let contents = fs_engine::read_directory(path, false).map_err(runtime_error)?;
+            Ok(contents
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    let mut map = Map::new();
+                    map.insert("name".into(), Dynamic::from(entry.name));
+                    map.insert("path".into(), Dynamic::from(entry.path));
+                    map.insert("is_dir".into(), Dynamic::from(entry.is_dir));
+                    map.insert("size".into(), Dynamic::from(entry.size as i64));
+                    map.insert("modified".into(), Dynamic::from(entry.modified));
+                    Dynamic::from(map)
+                })
+                .collect())
+        });
+
+        engine.register_fn("exists", |path: &str| rawpath::decode(path).exists());
+
+        engine.register_fn("mkdir", |path: &str| -> Result<(), Box<EvalAltResult>> {
+            crate::create_directory(path.to_string()).map_err(runtime_error)
+        });
+
+        let job_event = on_event.clone();
+        let on_job: JobCallback = Arc::new(move |job: &JobInfo| job_event(ScriptEvent::Job(job.clone())));
+        let (transfer_dir, transfer_jobs, transfer_events) = (data_dir.to_path_buf(), jobs.clone(), on_job.clone());
+        engine.register_fn("copy_to", move |source: &str, dest_dir: &str| -> Result<(), Box<EvalAltResult>> {
+            run_transfer(&transfer_dir, &transfer_jobs, transfer_events.clone(), source, dest_dir, TransferMode::Copy)
+                .map_err(runtime_error)
+        });
+
+        let (transfer_dir, transfer_jobs) = (data_dir.to_path_buf(), jobs.clone());
+        engine.register_fn("move_to", move |source: &str, dest_dir: &str| -> Result<(), Box<EvalAltResult>> {
+            run_transfer(&transfer_dir, &transfer_jobs, on_job.clone(), source, dest_dir, TransferMode::Move)
+                .map_err(runtime_error)
+        });
+
+        engine.register_fn("rename", |path: &str, new_name: &str| -> Result<String, Box<EvalAltResult>> {
+            crate::rename_path(path.to_string(), new_name.to_string()).map_err(runtime_error)
+        });
+
+        let settings_dir = data_dir.to_path_buf();
+        engine.register_fn("setting", move |key: &str| {
+            settings::get(&settings_dir, key)
+                .map(json_to_dynamic)
+                .unwrap_or(Dynamic::UNIT)
+        });
+
+        // prompt() blocks the script until the frontend answers through
+        // answer_script_prompt; a cancelled or unanswered prompt returns ().
+        let prompt = {
+            let pending = self.pending.clone();
+            let next_id = self.next_id.clone();
+            move |message: &str, default: Option<String>| -> Dynamic {
+                let id = next_id.fetch_add(1, Ordering::SeqCst);
+                let (tx, rx) = mpsc::channel();
+                pending.lock().unwrap().insert(id, tx);
+                on_event(ScriptEvent::Prompt(ScriptPrompt {
+                    id,
+                    run_id,
+                    message: message.to_string(),
+                    default,
+                }));
+                match rx.recv_timeout(PROMPT_TIMEOUT) {
+                    Ok(Some(answer)) => Dynamic::from(answer),
+                    _ => {
+                        pending.lock().unwrap().remove(&id);
+                        Dynamic::UNIT
+                    }
+                }
+            }
+        };
+        let prompt_with_default = prompt.clone();
+        engine.register_fn("prompt", move |message: &str| prompt(message, None));
+        engine.register_fn("prompt", move |message: &str, default: &str| {
+            prompt_with_default(message, Some(default.to_string()))
+        });
+
+        engine
+    }
+
+    pub fn run(
+        &self,
+        data_dir: &Path,
+        jobs: &JobManager,
+        name: &str,
+        selection: Vec<String>,
+        cwd: &str,
+        on_event: ScriptCallback,
+    ) -> Result<ScriptResult, String> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err("Invalid script name".to_string());
+        }
+        let path = scripts_dir(data_dir).join(format!("{}.{}", name, SCRIPT_EXTENSION));
+        let script = fs::read_to_string(&path).map_err(|e| format!("Failed to read script: {}", e))?;
+
+        let run_id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let engine = self.build_engine(run_id, data_dir, jobs, output.clone(), on_event);
+
+        let mut scope = Scope::new();
+        let selection: Array = selection.into_iter().map(Dynamic::from).collect();
+        scope.push_constant("selection", selection);
+        scope.push_constant("cwd", cwd.to_string());
+
+        let value = engine
+            .eval_with_scope::<Dynamic>(&mut scope, &script)
+            .map_err(|e| format!("Script error: {}", e))?;
+
+        let output = output.lock().unwrap().clone();
+        Ok(ScriptResult {
+            run_id,
+            output,
+            value: (!value.is_unit()).then(|| value.to_string()),
+        })
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use crate::clipboard::file_uri;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SendKind {
+    Email,
+    Bluetooth,
+    KdeConnect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendTarget {
+    pub id: String,
+    pub name: String,
+    pub kind: SendKind,
+}
+
+pub trait SendBackend {
+    fn kind(&self) -> SendKind;
+    fn targets(&self) -> Vec<SendTarget>;
+    fn send(&self, device: &str, paths: &[String]) -> Result<(), String>;
+}
+
+fn has_command(name: &str) -> bool {
+    #[cfg(windows)]
+    let lookup = Command::new("where").arg(name).output();
+    #[cfg(not(windows))]
+    let lookup = Command::new("which").arg(name).output();
+    lookup.map(|o| o.status.success()).unwrap_or(false)
+}
+
+fn run(cmd: &mut Command, what: &str) -> Result<String, String> {
+    let output = cmd.output().map_err(|e| format!("Failed to run {}: {}", what, e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+struct EmailBackend;
+
+impl SendBackend for EmailBackend {
+    fn kind(&self) -> SendKind {
+        SendKind::Email
+    }
+
+    fn targets(&self) -> Vec<SendTarget> {
+        vec![SendTarget {
+            id: "email".to_string(),
+            name: "Email".to_string(),
+            kind: SendKind::Email,
+        }]
+    }
+
+    fn send(&self, _device: &str, paths: &[String]) -> Result<(), String> {
+        if cfg!(target_os = "linux") && has_command("xdg-email") {
+            let mut cmd = Command::new("xdg-email");
+            for path in paths {
+                cmd.arg("--attach").arg(path);
+            }
+            return cmd
+                .spawn()
+                .map(|_| ())
+                .map_err(|e| format!("Failed to open mail client: {}", e));
+        }
+
+        // mailto: cannot carry attachments, so list the paths in the body instead.
+        let body: String = paths.join("\n").bytes().map(|b| format!("%{:02X}", b)).collect();
+        let url = format!("mailto:?body={}", body);
+        #[cfg(target_os = "macos")]
+        let mut cmd = Command::new("open");
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut c = Command::new("cmd");
+            c.args(["/C", "start", ""]);
+            c
+        };
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let mut cmd = Command::new("xdg-open");
+        cmd.arg(url)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open mail client: {}", e))
+    }
+}
+
+struct BluetoothBackend;
+
+impl BluetoothBackend {
+    fn send_with_obexctl(device: &str, paths: &[String]) -> Result<(), String> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::process::Stdio;
+
+        let mut child = Command::new("obexctl")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to run obexctl: {}", e))?;
+
+        let mut stdin = child.stdin.take().ok_or("Failed to open obexctl input")?;
+        let stdout = child.stdout.take().ok_or("Failed to open obexctl output")?;
+
+        writeln!(stdin, "connect {} opp", device).map_err(|e| e.to_string())?;
+        for path in paths {
+            writeln!(stdin, "send {}", path).map_err(|e| e.to_string())?;
+        }
+
+        // obexctl is interactive; watch its output until every transfer settled.
+        let mut finished = 0;
+        let mut result = Ok(());
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let lower = line.to_lowercase();
+            if lower.contains("status: complete") {
+                finished += 1;
+            } else if lower.contains("status: error") || lower.contains("failed to") {
+                result = Err(line.trim().to_string());
+                break;
+            }
+            if finished == paths.len() {
+                break;
+            }
+        }
+
+        let _ = writeln!(stdin, "quit");
+        let _ = child.wait();
+        result
+    }
+}
+
+impl SendBackend for BluetoothBackend {
+    fn kind(&self) -> SendKind {
+        SendKind::Bluetooth
+    }
+
+    fn targets(&self) -> Vec<SendTarget> {
+        if !cfg!(target_os = "linux") || !has_command("bluetoothctl") {
+            return Vec::new();
+        }
+        let Ok(stdout) = run(Command::new("bluetoothctl").args(["devices", "Paired"]), "bluetoothctl") else {
+            return Vec::new();
+        };
+
+        stdout
+            .lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix("Device ")?;
+                let (address, name) = rest.split_once(' ').unwrap_or((rest, rest));
+                Some(SendTarget {
+                    id: address.to_string(),
+                    name: name.trim().to_string(),
+                    kind: SendKind::Bluetooth,
+                })
+            })
+            .collect()
+    }
+
+    fn send(&self, device: &str, paths: &[String]) -> Result<(), String> {
+        if has_command("bluetooth-sendto") {
+            run(
+                Command::new("bluetooth-sendto")
+                    .arg(format!("--device={}", device))
+                    .args(paths),
+                "bluetooth-sendto",
+            )
+            .map(|_| ())
+        } else if has_command("obexctl") {
+            Self::send_with_obexctl(device, paths)
+        } else {
+            Err("No Bluetooth file transfer tool found (bluetooth-sendto or obexctl)".to_string())
+        }
+    }
+}
+
+// Talks to the kdeconnectd daemon over the session bus through gdbus.
+struct KdeConnectBackend;
+
+const KDECONNECT_DEST: &str = "org.kde.kdeconnect";
+
+impl SendBackend for KdeConnectBackend {
+    fn kind(&self) -> SendKind {
+        SendKind::KdeConnect
+    }
+
+    fn targets(&self) -> Vec<SendTarget> {
+        if !cfg!(target_os = "linux") || !has_command("gdbus") {
+            return Vec::new();
+        }
+        let Ok(stdout) = run(
+            Command::new("gdbus").args([
+                "call",
+                "--session",
+                "--dest",
+                KDECONNECT_DEST,
+                "--object-path",
+                "/modules/kdeconnect",
+                "--method",
+                "org.kde.kdeconnect.daemon.deviceNames",
+                "true",
+                "true",
+            ]),
+            "gdbus",
+        ) else {
+            return Vec::new();
+        };
+
+        // The reply is a GVariant dict, e.g. `({'abc123': 'Phone', ...},)`.
+        let quoted: Vec<&str> = stdout.split('\'').skip(1).step_by(2).collect();
+        quoted
+            .chunks_exact(2)
+            .map(|pair| SendTarget {
+                id: pair[0].to_string(),
+                name: pair[1].to_string(),
+                kind: SendKind::KdeConnect,
+            })
+            .collect()
+    }
+
+    fn send(&self, device: &str, paths: &[String]) -> Result<(), String> {
+        if device.is_empty() || !device.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err("Invalid device id".to_string());
+        }
+        let object_path = format!("/modules/kdeconnect/devices/{}/share", device);
+        for path in paths {
+            run(
+                Command::new("gdbus").args([
+                    "call",
+                    "--session",
+                    "--dest",
+                    KDECONNECT_DEST,
+                    "--object-path",
+                    &object_path,
+                    "--method",
+                    "org.kde.kdeconnect.device.share.shareUrl",
+                    &file_uri(path),
+                ]),
+                "gdbus",
+            )
+            .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn backends() -> Vec<Box<dyn SendBackend>> {
+    vec![
+        Box::new(EmailBackend),
+        Box::new(BluetoothBackend),
+        Box::new(KdeConnectBackend),
+    ]
+}
+
+pub fn list_send_targets() -> Vec<SendTarget> {
+    backends().iter().flat_map(|b| b.targets()).collect()
+}
+
+pub fn send_to(target: &SendTarget, paths: &[String]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err("Nothing to send".to_string());
+    }
+    for path in paths {
+        if !Path::new(path).is_file() {
+            return Err(format!("{}: only files can be sent", path));
+        }
+    }
+
+    let backend = backends()
+        .into_iter()
+        .find(|b| b.kind() == target.kind)
+        .ok_or("Unknown send target")?;
+    backend.send(&target.id, paths)
+}
@@ -0,0 +1,30 @@
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::statefile;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+pub fn load(data_dir: &Path) -> Map<String, Value> {
+    statefile::read(&data_dir.join(SETTINGS_FILE))
+}
+
+pub fn get(data_dir: &Path, key: &str) -> Option<Value> {
+    load(data_dir).remove(key)
+}
+
+pub fn set(data_dir: &Path, key: &str, value: Value) -> Result<(), String> {
+    update(data_dir, |settings| {
+        if value.is_null() {
+            settings.remove(key);
+        } else {
+            settings.insert(key.to_string(), value);
+        }
+    })
+}
+
+// For changes that depend on the current value, which another window may
+// have just written.
+pub fn update<R>(data_dir: &Path, f: impl FnOnce(&mut Map<String, Value>) -> R) -> Result<R, String> {
+    statefile::update(&data_dir.join(SETTINGS_FILE), "settings", f)
+}
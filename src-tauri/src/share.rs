@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::humanize::format_size;
+use crate::rawpath;
+
+// Anyone on the network can connect, so what one request may take is bounded.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 100;
+const MAX_CONNECTIONS: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedFile {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareInfo {
+    pub url: String,
+    pub port: u16,
+    pub token: Option<String>,
+    pub one_time: bool,
+    pub files: Vec<SharedFile>,
+}
+
+pub struct ShareServer {
+    pub info: ShareInfo,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ShareServer {
+    pub fn is_running(&self) -> bool {
+        !self.stop.load(Ordering::SeqCst)
+    }
+
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Connecting a UDP socket sends nothing but makes the OS pick the outgoing
+// interface, which is the address other devices on the LAN can reach.
+fn lan_address() -> String {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|s| {
+            s.connect("8.8.8.8:80")?;
+            s.local_addr()
+        })
+        .map(|a| a.ip().to_string())
+        .unwrap_or_else(|_| "127.0.0.1".to_string())
+}
+
+fn random_token() -> Result<String, String> {
+    let mut bytes = [0u8; 12];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub(crate) fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Compares without stopping at the first difference, so response times don't
+// give the token away a character at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+fn index_page(files: &[SharedFile], token: Option<&str>) -> String {
+    let query = token.map(|t| format!("?token={}", t)).unwrap_or_default();
+    let rows: String = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            format!(
+                "<li><a href=\"/f/{}/{}{}\">{}</a> <small>{}</small></li>",
+                i,
+                percent_encode(&f.name),
+                query,
+                html_escape(&f.name),
+                format_size(f.size)
+            )
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width\">\
+         <title>HardBore share</title></head><body><h1>Shared files</h1><ul>{}</ul></body></html>",
+        rows
+    )
+}
+
+// With a one-time share, `used` marks the files already downloaded, or being
+// downloaded, each of which is only handed out once. Returns true once a file
+// has been sent completely.
+fn handle_connection(
+    mut stream: TcpStream,
+    files: &[SharedFile],
+    token: Option<&str>,
+    used: Option<&Mutex<Vec<bool>>>,
+) -> io::Result<bool> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Drain the headers; nothing in them is needed.
+    let mut complete = false;
+    for _ in 0..MAX_HEADERS {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            complete = true;
+            break;
+        }
+    }
+    if !complete {
+        respond(&mut stream, "431 Request Header Fields Too Large", "text/plain", b"Request too large")?;
+        return Ok(false);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or("/");
+    if method != "GET" && method != "HEAD" {
+        respond(&mut stream, "405 Method Not Allowed", "text/plain", b"Method not allowed")?;
+        return Ok(false);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if let Some(expected) = token {
+        let given = query
+            .split('&')
+            .find_map(|p| p.strip_prefix("token="))
+            .unwrap_or_default();
+        if !constant_time_eq(given.as_bytes(), expected.as_bytes()) {
+            respond(&mut stream, "403 Forbidden", "text/plain", b"Invalid or missing token")?;
+            return Ok(false);
+        }
+    }
+
+    if path == "/" {
+        let page = index_page(files, token);
+        respond(&mut stream, "200 OK", "text/html; charset=utf-8", page.as_bytes())?;
+        return Ok(false);
+    }
+
+    let index = path
+        .strip_prefix("/f/")
+        .and_then(|rest| rest.split('/').next())
+        .and_then(|i| i.parse::<usize>().ok())
+        .filter(|i| *i < files.len());
+    let Some(index) = index else {
+        respond(&mut stream, "404 Not Found", "text/plain", b"Not found")?;
+        return Ok(false);
+    };
+
+    let Some(used) = used.filter(|_| method == "GET") else {
+        return send_file(&mut stream, &files[index], method);
+    };
+    if std::mem::replace(&mut used.lock().unwrap()[index], true) {
+        respond(&mut stream, "410 Gone", "text/plain", b"Already downloaded")?;
+        return Ok(false);
+    }
+    let sent = send_file(&mut stream, &files[index], method);
+    // A download that broke off can be tried again.
+    if !matches!(sent, Ok(true)) {
+        used.lock().unwrap()[index] = false;
+    }
+    sent
+}
+
+fn send_file(stream: &mut TcpStream, file: &SharedFile, method: &str) -> io::Result<bool> {
+    let mut source = match File::open(rawpath::decode(&file.path)) {
+        Ok(f) => f,
+        Err(_) => {
+            respond(stream, "404 Not Found", "text/plain", b"Not found")?;
+            return Ok(false);
+        }
+    };
+    let size = source.metadata()?.len();
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\
+         Content-Disposition: attachment; filename*=UTF-8''{}\r\nConnection: close\r\n\r\n",
+        size,
+        percent_encode(&file.name)
+    )?;
+    if method == "HEAD" {
+        return Ok(false);
+    }
+
+    io::copy(&mut source, stream)?;
+    stream.flush()?;
+    Ok(true)
+}
+
+pub fn share_files(paths: &[String], use_token: bool, one_time: bool) -> Result<ShareServer, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let p = rawpath::decode(path);
+        let metadata = std::fs::metadata(&p).map_err(|e| format!("{}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("{}: only files can be shared", path));
+        }
+        files.push(SharedFile {
+            name: p.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            path: path.clone(),
+            size: metadata.len(),
+        });
+    }
+    if files.is_empty() {
+        return Err("No files to share".to_string());
+    }
+
+    let listener = TcpListener::bind("0.0.0.0:0").map_err(|e| format!("Failed to start server: {}", e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to start server: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    let token = if use_token || one_time { Some(random_token()?) } else { None };
+    let url = match &token {
+        Some(t) => format!("http://{}:{}/?token={}", lan_address(), port, t),
+        None => format!("http://{}:{}/", lan_address(), port),
+    };
+
+    let info = ShareInfo {
+        url,
+        port,
+        token: token.clone(),
+        one_time,
+        files: files.clone(),
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let used = one_time.then(|| Arc::new(Mutex::new(vec![false; files.len()])));
+    let handle = thread::spawn(move || {
+        while !stop_flag.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // Past the limit, further connections are simply dropped.
+                    if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                        continue;
+                    }
+                    let _ = stream.set_nonblocking(false);
+                    let files = files.clone();
+                    let token = token.clone();
+                    let stop_flag = stop_flag.clone();
+                    let used = used.clone();
+                    let connections = connections.clone();
+                    thread::spawn(move || {
+                        let sent =
+                            handle_connection(stream, &files, token.as_deref(), used.as_deref()).unwrap_or(false);
+                        // A one-time share ends once every file has been downloaded.
+                        if sent && used.is_some_and(|u| u.lock().unwrap().iter().all(|u| *u)) {
+                            stop_flag.store(true, Ordering::SeqCst);
+                        }
+                        connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(_) => break,
+            }
+        }
+        stop_flag.store(true, Ordering::SeqCst);
+    });
+
+    Ok(ShareServer {
+        info,
+        stop,
+        handle: Some(handle),
+    })
+}
@@ -0,0 +1,433 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Shortcut files are tiny; anything bigger is not worth parsing per listing.
+const MAX_SHORTCUT_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LaunchKind {
+    // `target` is a program run with `arguments`.
+    Application,
+    // `target` is a file or URL opened with its default handler.
+    Link,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LaunchTarget {
+    pub kind: LaunchKind,
+    pub name: Option<String>,
+    pub target: String,
+    pub arguments: Vec<String>,
+    pub working_dir: Option<String>,
+    pub icon: Option<String>,
+    pub terminal: bool,
+}
+
+pub fn read_shortcut(path: &Path) -> Option<LaunchTarget> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    if ext != "desktop" && ext != "lnk" {
+        return None;
+    }
+    if fs::metadata(path).ok()?.len() > MAX_SHORTCUT_SIZE {
+        return None;
+    }
+    match ext.as_str() {
+        "desktop" => parse_desktop(path, &fs::read_to_string(path).ok()?),
+        _ => parse_lnk(path, &fs::read(path).ok()?),
+    }
+}
+
+// The keys of the `[Desktop Entry]` group; actions and other groups are skipped.
+pub(crate) fn desktop_entry(content: &str) -> HashMap<&str, &str> {
+    let mut entry = HashMap::new();
+    let mut in_entry = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_entry) {
+            entry.insert(key.trim(), value.trim());
+        }
+    }
+    entry
+}
+
+fn parse_desktop(path: &Path, content: &str) -> Option<LaunchTarget> {
+    let entry = desktop_entry(content);
+
+    let name = entry.get("Name").map(|n| n.to_string());
+    let icon = entry.get("Icon").filter(|i| !i.is_empty()).map(|i| i.to_string());
+    let working_dir = entry.get("Path").filter(|p| !p.is_empty()).map(|p| p.to_string());
+    let terminal = entry.get("Terminal").is_some_and(|t| *t == "true");
+
+    match entry.get("Type").copied() {
+        Some("Link") => Some(LaunchTarget {
+            kind: LaunchKind::Link,
+            name,
+            target: entry.get("URL")?.to_string(),
+            arguments: Vec::new(),
+            working_dir,
+            icon,
+            terminal: false,
+        }),
+        Some("Application") => {
+            let args = expand_exec(entry.get("Exec")?, name.as_deref(), icon.as_deref(), path, &[]);
+            let (target, arguments) = args.split_first()?;
+            Some(LaunchTarget {
+                kind: LaunchKind::Application,
+                name,
+                target: target.clone(),
+                arguments: arguments.to_vec(),
+                working_dir,
+                icon,
+                terminal,
+            })
+        }
+        _ => None,
+    }
+}
+
+// Splits an Exec line per the Desktop Entry spec and expands its field codes.
+// `%f` and `%u` take the first of `files`, `%F` and `%U` all of them; a
+// launcher opens nothing, so for it they drop out.
+pub(crate) fn expand_exec(
+    exec: &str,
+    name: Option<&str>,
+    icon: Option<&str>,
+    path: &Path,
+    files: &[String],
+) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = exec.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ' ' {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '\\' if quoted => word.extend(chars.next()),
+                ' ' if !quoted => break,
+                _ => word.push(c),
+            }
+        }
+        words.push(word);
+    }
+
+    let mut args = Vec::new();
+    for word in words {
+        match word.as_str() {
+            "%f" | "%u" => args.extend(files.first().cloned()),
+            "%F" | "%U" => args.extend(files.iter().cloned()),
+            "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {}
+            "%i" => {
+                if let Some(icon) = icon {
+                    args.push("--icon".to_string());
+                    args.push(icon.to_string());
+                }
+            }
+            _ => {
+                let mut expanded = String::new();
+                let mut chars = word.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => expanded.push('%'),
+                        Some('c') => expanded.push_str(name.unwrap_or("")),
+                        Some('k') => expanded.push_str(&path.to_string_lossy()),
+                        _ => {}
+                    }
+                }
+                args.push(expanded);
+            }
+        }
+    }
+    args
+}
+
+const HAS_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+const LNK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn c_string(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|b| *b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+fn c_wide_string(data: &[u8], offset: usize) -> Option<String> {
+    let units: Vec<u16> = data
+        .get(offset..)?
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|u| *u != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+// LinkInfo holds the absolute target, either a local path or a UNC share plus
+// a suffix; the Unicode offsets are only present with a larger header.
+fn link_info_path(info: &[u8]) -> Option<String> {
+    let header_size = u32_at(info, 4)? as usize;
+    let flags = u32_at(info, 8)?;
+    let suffix_offset = u32_at(info, 24)? as usize;
+    let unicode = header_size >= 0x24;
+    let suffix = if unicode {
+        c_wide_string(info, u32_at(info, 32)? as usize)
+    } else {
+        c_string(info, suffix_offset)
+    }
+    .unwrap_or_default();
+
+    if flags & 0x1 != 0 {
+        let base = if unicode {
+            c_wide_string(info, u32_at(info, 28)? as usize)
+        } else {
+            c_string(info, u32_at(info, 16)? as usize)
+        }?;
+        return Some(base + suffix.as_str());
+    }
+    if flags & 0x2 != 0 {
+        let network = info.get(u32_at(info, 20)? as usize..)?;
+        let net_name = c_string(network, u32_at(network, 8)? as usize)?;
+        return Some(if suffix.is_empty() { net_name } else { format!("{}\\{}", net_name, suffix) });
+    }
+    None
+}
+
+// Windows command lines quote with `"` and only treat backslashes as escapes
+// right before a quote.
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            '\\' if chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started || !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if started || !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
+
+// MS-SHLLINK: a fixed header, optional item ID list and LinkInfo, then
+// length-prefixed strings in a fixed order.
+fn parse_lnk(path: &Path, data: &[u8]) -> Option<LaunchTarget> {
+    if u32_at(data, 0)? != 0x4C || data.get(4..20)? != LNK_CLSID {
+        return None;
+    }
+    let flags = u32_at(data, 20)?;
+    let mut offset = 0x4C;
+
+    if flags & HAS_TARGET_ID_LIST != 0 {
+        offset += 2 + u16_at(data, offset)? as usize;
+    }
+    let mut target = None;
+    if flags & HAS_LINK_INFO != 0 {
+        let size = u32_at(data, offset)? as usize;
+        target = data.get(offset..offset + size).and_then(link_info_path);
+        offset += size;
+    }
+
+    let unicode = flags & IS_UNICODE != 0;
+    let mut read_string = |present: bool| -> Option<String> {
+        if !present {
+            return None;
+        }
+        let count = u16_at(data, offset)? as usize;
+        offset += 2;
+        let value = if unicode {
+            let bytes = data.get(offset..offset + count * 2)?;
+            offset += count * 2;
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            let bytes = data.get(offset..offset + count)?;
+            offset += count;
+            String::from_utf8_lossy(bytes).to_string()
+        };
+        Some(value)
+    };
+    let _description = read_string(flags & HAS_NAME != 0);
+    let relative = read_string(flags & HAS_RELATIVE_PATH != 0);
+    let working_dir = read_string(flags & HAS_WORKING_DIR != 0);
+    let arguments = read_string(flags & HAS_ARGUMENTS != 0);
+    let icon = read_string(flags & HAS_ICON_LOCATION != 0);
+
+    // Without LinkInfo the target is only known relative to the shortcut.
+    let target = target.or_else(|| {
+        let relative = relative?.replace('\\', "/");
+        let base = path.parent().unwrap_or(Path::new(""));
+        Some(base.join(relative).to_string_lossy().to_string())
+    })?;
+
+    let extension = Path::new(&target.replace('\\', "/"))
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    // Only Windows runs programs straight from a shortcut. Elsewhere the
+    // target is handed to the default application like any other file, so a
+    // shortcut unpacked from an archive can't start the program beside it.
+    let kind = match extension.as_deref() {
+        Some("exe" | "com" | "bat" | "cmd") if cfg!(windows) => LaunchKind::Application,
+        _ => LaunchKind::Link,
+    };
+
+    Some(LaunchTarget {
+        kind,
+        name: path.file_stem().map(|s| s.to_string_lossy().to_string()),
+        target,
+        arguments: arguments.map(|a| split_command_line(&a)).unwrap_or_default(),
+        working_dir: working_dir.filter(|d| !d.is_empty()),
+        icon: icon.filter(|i| !i.is_empty()),
+        terminal: false,
+    })
+}
+
+// Desktop files can run anything, so like other file managers only launch
+// ones that are executable or installed in an applications directory.
+#[cfg(unix)]
+fn is_trusted(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let executable = fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0);
+    let installed = dirs::data_dir()
+        .into_iter()
+        .chain(
+            std::env::var("XDG_DATA_DIRS")
+                .ok()
+                .filter(|d| !d.is_empty())
+                .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string())
+                .split(':')
+                .map(PathBuf::from)
+                .collect::<Vec<_>>(),
+        )
+        .any(|dir| path.starts_with(dir.join("applications")));
+    executable || installed
+}
+
+#[cfg(not(unix))]
+fn is_trusted(_path: &Path) -> bool {
+    true
+}
+
+pub(crate) fn open_with_default(target: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut cmd = Command::new("open");
+    #[cfg(windows)]
+    let mut cmd = Command::new("explorer");
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut cmd = Command::new("xdg-open");
+
+    cmd.arg(target)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open: {}", e))
+}
+
+// Tried in order; `$TERMINAL` wins when set.
+fn terminal_commands() -> Vec<Command> {
+    let mut commands = Vec::new();
+    if let Ok(terminal) = std::env::var("TERMINAL") {
+        let mut c = Command::new(terminal);
+        c.arg("-e");
+        commands.push(c);
+    }
+    for (program, flag) in [
+        ("x-terminal-emulator", "-e"),
+        ("gnome-terminal", "--"),
+        ("konsole", "-e"),
+        ("xfce4-terminal", "-x"),
+        ("xterm", "-e"),
+    ] {
+        let mut c = Command::new(program);
+        c.arg(flag);
+        commands.push(c);
+    }
+    commands
+}
+
+pub fn launch(path: &Path, shortcut: &LaunchTarget) -> Result<(), String> {
+    // Off Windows only desktop files run programs; see `parse_lnk`.
+    let is_desktop = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("desktop"));
+    if shortcut.kind == LaunchKind::Link || (!is_desktop && !cfg!(windows)) {
+        if !shortcut.target.contains("://") && !Path::new(&shortcut.target).exists() {
+            return Err(format!("Shortcut target not found: {}", shortcut.target));
+        }
+        return open_with_default(&shortcut.target);
+    }
+
+    if is_desktop && !is_trusted(path) {
+        return Err(format!(
+            "{} is not trusted; make it executable to allow launching it",
+            path.display()
+        ));
+    }
+    run(shortcut)
+}
+
+// Starts an application target, in a terminal if it asks for one.
+pub(crate) fn run(shortcut: &LaunchTarget) -> Result<(), String> {
+    let commands = if shortcut.terminal {
+        terminal_commands()
+    } else {
+        vec![Command::new(&shortcut.target)]
+    };
+    let mut last_error = format!("Failed to launch {}", shortcut.target);
+    for mut cmd in commands {
+        if shortcut.terminal {
+            cmd.arg(&shortcut.target);
+        }
+        cmd.args(&shortcut.arguments);
+        if let Some(dir) = shortcut.working_dir.as_deref().filter(|d| Path::new(d).is_dir()) {
+            cmd.current_dir(dir);
+        }
+        match cmd.spawn() {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = format!("Failed to launch {}: {}", shortcut.target, e),
+        }
+    }
+    Err(last_error)
+}
@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::rawpath;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SnapshotSource {
+    Zfs,
+    Btrfs,
+    TimeMachine,
+    ShadowCopy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousVersion {
+    pub snapshot: String,
+    pub source: SnapshotSource,
+    pub path: String,
+    pub created: Option<i64>,
+    pub size: u64,
+    pub modified: i64,
+}
+
+fn unix_secs(time: std::io::Result<SystemTime>) -> Option<i64> {
+    time.ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+#[cfg(unix)]
+fn mount_root(path: &Path) -> Option<PathBuf> {
+    let dev = fs::metadata(path).ok()?.dev();
+    let mut current = path.to_path_buf();
+    while let Some(parent) = current.parent() {
+        match fs::metadata(parent) {
+            Ok(m) if m.dev() == dev => current = parent.to_path_buf(),
+            _ => break,
+        }
+    }
+    Some(current)
+}
+
+fn version_at(snapshot: &str, source: SnapshotSource, root: &Path, candidate: PathBuf) -> Option<PreviousVersion> {
+    let metadata = fs::symlink_metadata(&candidate).ok()?;
+    Some(PreviousVersion {
+        snapshot: snapshot.to_string(),
+        source,
+        path: rawpath::encode_path(&candidate),
+        created: fs::metadata(root).ok().and_then(|m| unix_secs(m.modified())),
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: unix_secs(metadata.modified()).unwrap_or(0),
+    })
+}
+
+#[cfg(unix)]
+fn zfs_versions(mount: &Path, relative: &Path) -> Vec<PreviousVersion> {
+    let snapshot_dir = mount.join(".zfs").join("snapshot");
+    let Ok(read_dir) = fs::read_dir(&snapshot_dir) else {
+        return Vec::new();
+    };
+
+    read_dir
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            version_at(&name, SnapshotSource::Zfs, &e.path(), e.path().join(relative))
+        })
+        .collect()
+}
+
+// Covers snapper (`.snapshots/<n>/snapshot/...`) and plain `.snapshots/<name>/...`
+// layouts; other tools keep snapshots outside the mounted subvolume.
+#[cfg(unix)]
+fn btrfs_versions(mount: &Path, relative: &Path) -> Vec<PreviousVersion> {
+    let snapshot_dir = mount.join(".snapshots");
+    let Ok(read_dir) = fs::read_dir(&snapshot_dir) else {
+        return Vec::new();
+    };
+
+    read_dir
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let snapper_root = e.path().join("snapshot");
+            let root = if snapper_root.is_dir() { snapper_root } else { e.path() };
+            version_at(&name, SnapshotSource::Btrfs, &root, root.join(relative))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn time_machine_versions(path: &Path) -> Vec<PreviousVersion> {
+    let Ok(output) = std::process::Command::new("tmutil").arg("listbackups").output() else {
+        return Vec::new();
+    };
+    let Ok(relative) = path.strip_prefix("/") else {
+        return Vec::new();
+    };
+
+    // Each backup holds one directory per backed-up volume, named after the volume.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .filter_map(|backup| {
+            let name = backup.file_name()?.to_string_lossy().to_string();
+            fs::read_dir(&backup)
+                .ok()?
+                .filter_map(|e| e.ok())
+                .find_map(|volume| {
+                    version_at(&name, SnapshotSource::TimeMachine, &backup, volume.path().join(relative))
+                })
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn shadow_copy_versions(path: &Path) -> Vec<PreviousVersion> {
+    use std::path::Component;
+
+    let Ok(output) = std::process::Command::new("vssadmin")
+        .args(["list", "shadows"])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let drive = match path.components().next() {
+        Some(Component::Prefix(prefix)) => prefix.as_os_str().to_string_lossy().to_uppercase(),
+        _ => return Vec::new(),
+    };
+    let relative: PathBuf = path.components().skip(2).collect();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut versions = Vec::new();
+    let mut original: Option<String> = None;
+
+    for line in stdout.lines().map(|l| l.trim()) {
+        if let Some(rest) = line.strip_prefix("Original Volume:") {
+            original = Some(rest.trim().to_uppercase());
+        } else if let Some(device) = line.strip_prefix("Shadow Copy Volume:") {
+            let on_drive = original
+                .as_deref()
+                .map(|o| o.starts_with(&format!("({})", drive)))
+                .unwrap_or(false);
+            if on_drive {
+                let device = device.trim();
+                let root = PathBuf::from(format!("{}\\", device));
+                let name = device.rsplit('\\').next().unwrap_or(device).to_string();
+                if let Some(v) = version_at(&name, SnapshotSource::ShadowCopy, &root, root.join(&relative)) {
+                    versions.push(v);
+                }
+            }
+        }
+    }
+
+    versions
+}
+
+pub fn list_previous_versions(target: &Path) -> Result<Vec<PreviousVersion>, String> {
+    let current = fs::symlink_metadata(target).ok();
+    #[allow(unused_mut)]
+    let mut versions: Vec<PreviousVersion> = Vec::new();
+
+    #[cfg(unix)]
+    if let Some(mount) = mount_root(target.parent().unwrap_or(target)) {
+        if let Ok(relative) = target.strip_prefix(&mount) {
+            versions.extend(zfs_versions(&mount, relative));
+            versions.extend(btrfs_versions(&mount, relative));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    versions.extend(time_machine_versions(target));
+
+    #[cfg(windows)]
+    versions.extend(shadow_copy_versions(target));
+
+    // Snapshots taken while the file was unchanged hold identical copies; only
+    // keep versions that actually differ from the current file and each other.
+    versions.sort_by_key(|v| std::cmp::Reverse(v.modified));
+    versions.dedup_by_key(|v| (v.modified, v.size));
+    if let Some(current) = current {
+        let current_modified = unix_secs(current.modified()).unwrap_or(0);
+        versions.retain(|v| !(v.modified == current_modified && v.size == current.len()));
+    }
+
+    Ok(versions)
+}
+
+// `version_path` is one of the encoded paths list_previous_versions gave out.
+pub fn restore_previous_version(target: &Path, version_path: &str, as_copy: bool) -> Result<String, String> {
+    let known = list_previous_versions(target)?;
+    if !known.iter().any(|v| v.path == version_path) {
+        return Err("Not a previous version of this path".to_string());
+    }
+
+    let source = &rawpath::decode(version_path);
+    let parent = target.parent().ok_or("Cannot get parent directory")?;
+
+    let destination = if as_copy {
+        // Built from the raw name, so one that isn't valid UTF-8 keeps its bytes.
+        let named = |suffix: &str| {
+            let mut name = target.file_stem().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            if let Some(ext) = target.extension() {
+                name.push(".");
+                name.push(ext);
+            }
+            parent.join(name)
+        };
+        let mut dest = named(" (restored)");
+        let mut counter = 2u32;
+        while dest.exists() {
+            dest = named(&format!(" (restored {counter})"));
+            counter += 1;
+        }
+        dest
+    } else {
+        target.to_path_buf()
+    };
+
+    if source.is_dir() {
+        if !as_copy {
+            return Err("Directories can only be restored as a copy".to_string());
+        }
+        crate::copy_dir_recursive(source, &destination)?;
+    } else if as_copy {
+        fs::copy(source, &destination).map_err(|e| format!("Failed to restore file: {}", e))?;
+    } else {
+        // Copy next to the target first so an interrupted restore never leaves a
+        // half-written file in place of the original.
+        let mut name = OsString::from(".");
+        name.push(target.file_name().unwrap_or_default());
+        name.push(".hardbore-restore");
+        let temp = parent.join(name);
+        fs::copy(source, &temp).map_err(|e| format!("Failed to restore file: {}", e))?;
+        fs::rename(&temp, &destination).map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            format!("Failed to restore file: {}", e)
+        })?;
+    }
+
+    Ok(rawpath::encode_path(&destination))
+}
@@ -0,0 +1,132 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 1000;
+const MAX_VALUE_LEN: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqliteTable {
+    pub name: String,
+    pub view: bool,
+    // None when counting failed, e.g. a view over a missing table.
+    pub row_count: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlitePreview {
+    pub tables: Vec<SqliteTable>,
+    pub table: Option<String>,
+    pub columns: Vec<String>,
+    // NULLs stay None so the frontend can tell them from the string "NULL".
+    pub rows: Vec<Vec<Option<String>>>,
+    pub truncated: bool,
+}
+
+// `.db` is used by plenty of other formats, so the header is checked too.
+fn is_sqlite(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .is_ok_and(|_| &header == SQLITE_MAGIC)
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn format_value(value: ValueRef) -> Option<String> {
+    let text = match value {
+        ValueRef::Null => return None,
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).to_string(),
+        ValueRef::Blob(b) => return Some(format!("<BLOB {} bytes>", b.len())),
+    };
+    Some(match text.char_indices().nth(MAX_VALUE_LEN) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    })
+}
+
+fn list_tables(conn: &Connection) -> Result<Vec<SqliteTable>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name, type FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .map_err(|e| format!("Failed to read schema: {}", e))?;
+    let names: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("Failed to read schema: {}", e))?;
+
+    Ok(names
+        .into_iter()
+        .map(|(name, kind)| {
+            let sql = format!("SELECT COUNT(*) FROM {}", quote_ident(&name));
+            let row_count = conn.query_row(&sql, [], |row| row.get::<_, i64>(0)).ok().map(|n| n.max(0) as u64);
+            SqliteTable {
+                name,
+                view: kind == "view",
+                row_count,
+            }
+        })
+        .collect())
+}
+
+// Opens the database read-only and lists its tables, along with the first
+// `limit` rows of `table` (or of the first table when none is given).
+pub fn preview_sqlite(path: &Path, table: Option<&str>, limit: Option<usize>) -> Result<SqlitePreview, String> {
+    if !is_sqlite(path) {
+        return Err("Not a SQLite database".to_string());
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let tables = list_tables(&conn)?;
+
+    let selected = match table {
+        Some(name) => Some(
+            tables
+                .iter()
+                .find(|t| t.name == name)
+                .ok_or_else(|| format!("No such table: {}", name))?,
+        ),
+        None => tables.iter().find(|t| !t.view).or(tables.first()),
+    };
+    let Some(selected) = selected.map(|t| t.name.clone()) else {
+        return Ok(SqlitePreview {
+            tables,
+            table: None,
+            columns: Vec::new(),
+            rows: Vec::new(),
+            truncated: false,
+        });
+    };
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let sql = format!("SELECT * FROM {} LIMIT {}", quote_ident(&selected), limit + 1);
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to query table: {}", e))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let mut rows = Vec::new();
+    let mut query = stmt.query([]).map_err(|e| format!("Failed to query table: {}", e))?;
+    while let Some(row) = query.next().map_err(|e| format!("Failed to read row: {}", e))? {
+        let values = (0..columns.len())
+            .map(|i| row.get_ref(i).map(format_value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read row: {}", e))?;
+        rows.push(values);
+    }
+    let truncated = rows.len() > limit;
+    rows.truncate(limit);
+
+    Ok(SqlitePreview {
+        tables,
+        table: Some(selected),
+        columns,
+        rows,
+        truncated,
+    })
+}
@@ -0,0 +1,127 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::durability;
+
+// The small JSON files holding favorites, settings, bookmarks and the like
+// are shared by every window and by the picker, which runs as a process of
+// its own. A change is made under an exclusive lock on a `.lock` file beside
+// it, from reading the file to replacing it, so two writers can't undo each
+// other's change; the new content goes to a temporary file renamed over the
+// old one, so a reader never sees half a file.
+const LOCK_SUFFIX: &str = ".lock";
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Missing and unreadable files count as empty, as they always have.
+pub fn read<T: DeserializeOwned + Default>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Hands `f` the current content and writes back what it leaves there, unless
+// it's unchanged. `what` names the content in errors.
+pub fn update<T, R>(path: &Path, what: &str, f: impl FnOnce(&mut T) -> R) -> Result<R, String>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let _lock = lock(path)?;
+    let before = fs::read_to_string(path).ok();
+    let mut value: T = before
+        .as_deref()
+        .and_then(|content| serde_json::from_str(content).ok())
+        .unwrap_or_default();
+    let result = f(&mut value);
+    let json = serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to serialize {}: {}", what, e))?;
+    if before.as_deref() != Some(json.as_str()) {
+        replace(path, &json).map_err(|e| format!("Failed to write {}: {}", what, e))?;
+    }
+    Ok(result)
+}
+
+// Overwrites the file with `value`, for state kept in memory anyway.
+pub fn write<T: Serialize>(path: &Path, what: &str, value: &T) -> Result<(), String> {
+    let value = serde_json::to_value(value).map_err(|e| format!("Failed to serialize {}: {}", what, e))?;
+    update(path, what, |current: &mut serde_json::Value| *current = value)
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Held until the returned file is dropped. Also creates the folder.
+fn lock(path: &Path) -> Result<File, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let lock_path = sidecar(path, LOCK_SUFFIX);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("Failed to open {}: {}", lock_path.display(), e))?;
+    file.lock().map_err(|e| format!("Failed to lock {}: {}", lock_path.display(), e))?;
+    Ok(file)
+}
+
+fn replace(path: &Path, content: &str) -> Result<(), String> {
+    let temp = sidecar(path, &format!(".{}.tmp", std::process::id()));
+    fs::write(&temp, content).map_err(|e| e.to_string())?;
+    if durability::enabled() {
+        durability::sync_file(&temp)?;
+    }
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.to_string());
+    }
+    if durability::enabled() {
+        durability::sync_parent(path)?;
+    }
+    Ok(())
+}
+
+fn json_files(dir: &Path) -> HashMap<String, (SystemTime, u64)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    read_dir
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let metadata = e.metadata().ok()?;
+            if !name.ends_with(".json") || !metadata.is_file() {
+                return None;
+            }
+            Some((name, (metadata.modified().ok()?, metadata.len())))
+        })
+        .collect()
+}
+
+// Calls `on_change` with the file name whenever a JSON file in `dir` is
+// written, created or removed, by this process or another one.
+pub fn watch(dir: PathBuf, on_change: impl Fn(&str) + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut known = json_files(&dir);
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let current = json_files(&dir);
+            let changed = current
+                .iter()
+                .filter(|(name, stamp)| known.get(*name) != Some(stamp))
+                .map(|(name, _)| name)
+                .chain(known.keys().filter(|name| !current.contains_key(*name)));
+            for name in changed {
+                on_change(name);
+            }
+            known = current;
+        }
+    });
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// JSON has to be parsed whole; bigger files get the plain text preview.
+const MAX_JSON_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_DEPTH: usize = 8;
+const MAX_CHILDREN: usize = 100;
+const MAX_NODES: usize = 5000;
+const MAX_VALUE_LEN: usize = 200;
+const MAX_JSON_LINES: usize = 1000;
+const MAX_ROWS: usize = 100;
+const MAX_TABLE_BYTES: usize = 4 * 1024 * 1024;
+const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JsonKind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonNode {
+    pub key: Option<String>,
+    pub kind: JsonKind,
+    // Scalars only, shortened past a couple hundred characters.
+    pub value: Option<String>,
+    // Objects and arrays: the full number of children, even when some are left out.
+    pub length: Option<usize>,
+    pub children: Vec<JsonNode>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonPreview {
+    pub root: JsonNode,
+    pub truncated: bool,
+    // Set for JSON Lines files, whose root is an array of the first records.
+    pub lines: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TablePreview {
+    pub delimiter: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub truncated: bool,
+}
+
+fn shorten(s: &str) -> String {
+    match s.char_indices().nth(MAX_VALUE_LEN) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+fn build_node(key: Option<String>, value: &Value, depth: usize, budget: &mut usize) -> JsonNode {
+    *budget = budget.saturating_sub(1);
+    let scalar = |kind, value: String| JsonNode {
+        key: key.clone(),
+        kind,
+        value: Some(value),
+        length: None,
+        children: Vec::new(),
+        truncated: false,
+    };
+    let children: Vec<(Option<String>, &Value)> = match value {
+        Value::Null => return scalar(JsonKind::Null, "null".to_string()),
+        Value::Bool(b) => return scalar(JsonKind::Bool, b.to_string()),
+        Value::Number(n) => return scalar(JsonKind::Number, n.to_string()),
+        Value::String(s) => return scalar(JsonKind::String, shorten(s)),
+        Value::Array(items) => items.iter().enumerate().map(|(i, v)| (Some(i.to_string()), v)).collect(),
+        Value::Object(map) => map.iter().map(|(k, v)| (Some(k.clone()), v)).collect(),
+    };
+
+    let length = children.len();
+    let mut nodes = Vec::new();
+    if depth < MAX_DEPTH {
+        for (child_key, child) in children.into_iter().take(MAX_CHILDREN) {
+            if *budget == 0 {
+                break;
+            }
+            nodes.push(build_node(child_key, child, depth + 1, budget));
+        }
+    }
+    JsonNode {
+        key,
+        kind: if value.is_array() { JsonKind::Array } else { JsonKind::Object },
+        value: None,
+        length: Some(length),
+        truncated: nodes.len() < length,
+        children: nodes,
+    }
+}
+
+fn any_truncated(node: &JsonNode) -> bool {
+    node.truncated || node.children.iter().any(any_truncated)
+}
+
+fn is_json_lines(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("jsonl") || e.eq_ignore_ascii_case("ndjson"))
+}
+
+// Returns the tree and the pretty-printed document, cut to `max_bytes`.
+pub fn json_preview(path: &Path, max_bytes: usize) -> Result<(JsonPreview, String, bool), String> {
+    let size = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?.len();
+    if size > MAX_JSON_SIZE {
+        return Err("File is too large to parse".to_string());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let lines = is_json_lines(path);
+    let value = if lines {
+        let records: Result<Vec<Value>, _> = content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .take(MAX_JSON_LINES)
+            .map(serde_json::from_str)
+            .collect();
+        Value::Array(records.map_err(|e| format!("Invalid JSON Lines: {}", e))?)
+    } else {
+        serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {}", e))?
+    };
+
+    let mut budget = MAX_NODES;
+    let root = build_node(None, &value, 0, &mut budget);
+    let pretty = serde_json::to_string_pretty(&value).map_err(|e| format!("Failed to format JSON: {}", e))?;
+    let text_truncated = pretty.len() > max_bytes;
+    let mut end = max_bytes.min(pretty.len());
+    while !pretty.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let preview = JsonPreview {
+        truncated: any_truncated(&root),
+        root,
+        lines,
+    };
+    Ok((preview, pretty[..end].to_string(), text_truncated))
+}
+
+// Splits records per RFC 4180: quoted fields may hold delimiters, doubled
+// quotes and line breaks. Stops after `limit` records.
+fn parse_records(text: &str, delimiter: char, limit: usize) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                if records.len() >= limit {
+                    return records;
+                }
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+// Picks the delimiter that splits the first lines into the same, largest
+// number of columns.
+fn detect_delimiter(text: &str) -> char {
+    let mut best = (',', 0usize);
+    for delimiter in DELIMITERS {
+        let records = parse_records(text, delimiter, 20);
+        let Some(columns) = records.first().map(Vec::len) else {
+            continue;
+        };
+        let consistent = records.iter().filter(|r| r.len() == columns).count();
+        if columns > 1 && consistent * 10 >= records.len() * 8 && columns > best.1 {
+            best = (delimiter, columns);
+        }
+    }
+    best.0
+}
+
+pub fn table_preview(path: &Path) -> Result<TablePreview, String> {
+    let mut buf = Vec::new();
+    fs::File::open(path)
+        .and_then(|f| f.take(MAX_TABLE_BYTES as u64).read_to_end(&mut buf))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    if buf.contains(&0) {
+        return Err("Not a text file".to_string());
+    }
+    let text = String::from_utf8_lossy(&buf);
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+
+    let is_tsv = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv"));
+    let delimiter = if is_tsv { '\t' } else { detect_delimiter(text) };
+    let mut records = parse_records(text, delimiter, MAX_ROWS + 2);
+    if records.is_empty() {
+        return Err("Empty file".to_string());
+    }
+    let headers = records.remove(0);
+    let truncated = records.len() > MAX_ROWS || buf.len() >= MAX_TABLE_BYTES;
+    records.truncate(MAX_ROWS);
+
+    Ok(TablePreview {
+        delimiter: delimiter.to_string(),
+        headers,
+        rows: records,
+        truncated,
+    })
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+#[cfg(unix)]
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::fs_engine::is_hidden;
+use crate::indexer::{SearchResult, SearchSource};
+use crate::rawpath;
+
+pub const SETTING: &str = "system_search";
+// Where Everything's HTTP server listens (Tools > Options > HTTP Server).
+pub const EVERYTHING_URL_SETTING: &str = "everything_url";
+
+#[cfg(windows)]
+const DEFAULT_EVERYTHING_URL: &str = "http://127.0.0.1";
+
+// Off by default: every search would also query another program.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static EVERYTHING_URL: RwLock<Option<String>> = RwLock::new(None);
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn set_everything_url(url: Option<String>) {
+    *EVERYTHING_URL.write().unwrap() = url.filter(|u| !u.trim().is_empty());
+}
+
+// Like the index, anything inside a dot folder counts as hidden; attributes
+// are only checked on the file itself.
+fn in_dot_dir(path: &Path) -> bool {
+    !cfg!(windows)
+        && path
+            .parent()
+            .is_some_and(|p| p.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')))
+}
+
+// The system databases lag behind the disk, so paths that no longer exist are dropped.
+fn to_result(path: &Path, source: SearchSource) -> Option<SearchResult> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let name = rawpath::encode(path.file_name()?);
+    Some(SearchResult {
+        hidden: is_hidden(&name, &metadata) || in_dot_dir(path),
+        name,
+        path: rawpath::encode(path.as_os_str()),
+        is_dir: metadata.is_dir(),
+        score: 0,
+        source,
+        stale: false,
+    })
+}
+
+#[cfg(unix)]
+fn output_paths(stdout: &[u8]) -> Vec<std::path::PathBuf> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    stdout
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| Path::new(OsStr::from_bytes(line)).to_path_buf())
+        .collect()
+}
+
+// plocate first; mlocate and findutils' locate take the same arguments.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_search(query: &str, limit: usize) -> Vec<SearchResult> {
+    for tool in ["plocate", "locate"] {
+        let Ok(output) = Command::new(tool)
+            .args(["--ignore-case", "--basename", "--limit", &limit.to_string(), "--"])
+            .arg(query)
+            .output()
+        else {
+            continue;
+        };
+        // A plain exit status of 1 only means nothing matched; a missing
+        // database comes with a message.
+        if !output.status.success() && !output.stderr.is_empty() {
+            continue;
+        }
+        return output_paths(&output.stdout)
+            .iter()
+            .filter_map(|p| to_result(p, SearchSource::Locate))
+            .collect();
+    }
+    Vec::new()
+}
+
+#[cfg(target_os = "macos")]
+fn system_search(query: &str, limit: usize) -> Vec<SearchResult> {
+    let Ok(output) = Command::new("mdfind").arg("-name").arg(query).output() else {
+        return Vec::new();
+    };
+    output_paths(&output.stdout)
+        .iter()
+        .take(limit)
+        .filter_map(|p| to_result(p, SearchSource::Spotlight))
+        .collect()
+}
+
+// Everything answers from its own NTFS index in milliseconds, which HardBore's
+// crawl can't match on a full disk. Only its HTTP server is reachable without
+// the SDK's DLL, so that has to be switched on in Everything.
+#[cfg(windows)]
+fn system_search(query: &str, limit: usize) -> Vec<SearchResult> {
+    #[derive(serde::Deserialize)]
+    struct Response {
+        results: Vec<Item>,
+    }
+    #[derive(serde::Deserialize)]
+    struct Item {
+        name: String,
+        path: String,
+    }
+
+    let url = EVERYTHING_URL
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_EVERYTHING_URL.to_string());
+    let response = ureq::get(&url)
+        .timeout(std::time::Duration::from_millis(500))
+        .query("search", query)
+        .query("json", "1")
+        .query("path_column", "1")
+        .query("count", &limit.to_string())
+        .call();
+    let Ok(response) = response else {
+        return Vec::new();
+    };
+    let Some(body) = response
+        .into_string()
+        .ok()
+        .and_then(|text| serde_json::from_str::<Response>(&text).ok())
+    else {
+        return Vec::new();
+    };
+    body.results
+        .iter()
+        .filter_map(|item| {
+            // Files at a drive's root come back with a bare "C:" as their folder.
+            let folder = if item.path.ends_with(':') {
+                format!("{}\\", item.path)
+            } else {
+                item.path.clone()
+            };
+            to_result(&Path::new(&folder).join(&item.name), SearchSource::Everything)
+        })
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn system_search(_query: &str, _limit: usize) -> Vec<SearchResult> {
+    Vec::new()
+}
+
+// Whole-disk matches from the platform's own file database, for paths
+// HardBore's index hasn't reached yet.
+pub fn search(query: &str, limit: usize, include_hidden: bool) -> Vec<SearchResult> {
+    if !enabled() || query.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut results = system_search(query, limit);
+    if !include_hidden {
+        results.retain(|r| !r.hidden);
+    }
+    results
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// A tag set by another file manager: Finder tags on macOS, the freedesktop
+// `user.xdg.tags` attribute on Linux.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTag {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+// Finder's label colors, by the index it stores next to each tag name.
+#[cfg(target_os = "macos")]
+fn finder_color(index: u8) -> Option<&'static str> {
+    match index {
+        1 => Some("gray"),
+        2 => Some("green"),
+        3 => Some("purple"),
+        4 => Some("blue"),
+        5 => Some("yellow"),
+        6 => Some("red"),
+        7 => Some("orange"),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn read_xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let c_name = CString::new(name).ok()?;
+    #[cfg(target_os = "macos")]
+    let get = |buf: *mut libc::c_void, len: usize| unsafe {
+        libc::getxattr(c_path.as_ptr(), c_name.as_ptr(), buf, len, 0, libc::XATTR_NOFOLLOW)
+    };
+    #[cfg(not(target_os = "macos"))]
+    let get = |buf: *mut libc::c_void, len: usize| unsafe { libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buf, len) };
+
+    let size = get(std::ptr::null_mut(), 0);
+    if size <= 0 {
+        return None;
+    }
+    let mut buf = vec![0u8; size as usize];
+    let read = get(buf.as_mut_ptr().cast(), buf.len());
+    if read < 0 {
+        return None;
+    }
+    buf.truncate(read as usize);
+    Some(buf)
+}
+
+// Tags are stored as "Name\n<color index>"; files tagged before Finder had
+// named tags only carry a color label in their FinderInfo.
+#[cfg(target_os = "macos")]
+pub fn read_tags(path: &Path) -> Vec<FileTag> {
+    let named = read_xattr(path, "com.apple.metadata:_kMDItemUserTags").and_then(|data| bplist::string_array(&data));
+    if let Some(named) = named.filter(|tags| !tags.is_empty()) {
+        return named
+            .into_iter()
+            .map(|tag| match tag.split_once('\n') {
+                Some((name, color)) => FileTag {
+                    name: name.to_string(),
+                    color: color.parse().ok().and_then(finder_color).map(str::to_string),
+                },
+                None => FileTag { name: tag, color: None },
+            })
+            .collect();
+    }
+
+    let label = read_xattr(path, "com.apple.FinderInfo")
+        .and_then(|info| info.get(9).copied())
+        .and_then(|flags| finder_color((flags >> 1) & 0x7));
+    label
+        .map(|color| FileTag {
+            name: color[..1].to_uppercase() + &color[1..],
+            color: Some(color.to_string()),
+        })
+        .into_iter()
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn read_tags(path: &Path) -> Vec<FileTag> {
+    let Some(data) = read_xattr(path, "user.xdg.tags") else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&data)
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| FileTag {
+            name: name.to_string(),
+            color: None,
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn read_tags(_path: &Path) -> Vec<FileTag> {
+    Vec::new()
+}
+
+// The page a downloaded file came from, as recorded by the browser.
+#[cfg(target_os = "macos")]
+pub fn where_from(path: &Path) -> Option<String> {
+    let data = read_xattr(path, "com.apple.metadata:kMDItemWhereFroms")?;
+    bplist::string_array(&data)?.into_iter().find(|url| !url.is_empty())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn where_from(path: &Path) -> Option<String> {
+    let data = read_xattr(path, "user.xdg.origin.url")?;
+    Some(String::from_utf8_lossy(&data).into_owned()).filter(|url| !url.is_empty())
+}
+
+#[cfg(not(unix))]
+pub fn where_from(_path: &Path) -> Option<String> {
+    None
+}
+
+// Just enough of Apple's binary property list format to read the string
+// arrays Spotlight keeps in extended attributes.
+#[cfg(target_os = "macos")]
+mod bplist {
+    struct Trailer {
+        offset_size: usize,
+        ref_size: usize,
+        object_count: usize,
+        top_object: usize,
+        offset_table: usize,
+    }
+
+    fn read_uint(data: &[u8], at: usize, size: usize) -> Option<usize> {
+        let bytes = data.get(at..at.checked_add(size)?)?;
+        if size > 8 {
+            return None;
+        }
+        Some(bytes.iter().fold(0u64, |n, &b| (n << 8) | b as u64) as usize)
+    }
+
+    fn trailer(data: &[u8]) -> Option<Trailer> {
+        if !data.starts_with(b"bplist00") || data.len() < 40 {
+            return None;
+        }
+        let t = data.len() - 32;
+        Some(Trailer {
+            offset_size: data[t + 6] as usize,
+            ref_size: data[t + 7] as usize,
+            object_count: read_uint(data, t + 8, 8)?,
+            top_object: read_uint(data, t + 16, 8)?,
+            offset_table: read_uint(data, t + 24, 8)?,
+        })
+    }
+
+    fn object_offset(data: &[u8], trailer: &Trailer, index: usize) -> Option<usize> {
+        if index >= trailer.object_count {
+            return None;
+        }
+        read_uint(data, trailer.offset_table + index * trailer.offset_size, trailer.offset_size)
+    }
+
+    // Counts of 15 or more follow the marker as an int object.
+    fn length(data: &[u8], at: usize) -> Option<(usize, usize)> {
+        let marker = *data.get(at)?;
+        if marker & 0x0F != 0x0F {
+            return Some(((marker & 0x0F) as usize, at + 1));
+        }
+        let int_marker = *data.get(at + 1)?;
+        if int_marker & 0xF0 != 0x10 {
+            return None;
+        }
+        let size = 1 << (int_marker & 0x0F);
+        Some((read_uint(data, at + 2, size)?, at + 2 + size))
+    }
+
+    fn string(data: &[u8], at: usize) -> Option<String> {
+        let (count, start) = length(data, at)?;
+        match data.get(at)? >> 4 {
+            0x5 => Some(String::from_utf8_lossy(data.get(start..start + count)?).into_owned()),
+            0x6 => {
+                let units: Vec<u16> = data
+                    .get(start..start + count * 2)?
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn string_array(data: &[u8]) -> Option<Vec<String>> {
+        let trailer = trailer(data)?;
+        let at = object_offset(data, &trailer, trailer.top_object)?;
+        if data.get(at)? >> 4 != 0xA {
+            return None;
+        }
+        let (count, refs) = length(data, at)?;
+        (0..count)
+            .map(|i| {
+                let index = read_uint(data, refs + i * trailer.ref_size, trailer.ref_size)?;
+                string(data, object_offset(data, &trailer, index)?)
+            })
+            .collect()
+    }
+}
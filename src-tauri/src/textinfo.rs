@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+// Past this many characters a line is slow to render and awkward to read.
+const WIDE_LINE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LineEndings {
+    None,
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Indentation {
+    None,
+    Tabs,
+    Spaces,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextStats {
+    pub line_endings: LineEndings,
+    pub bom: bool,
+    pub indentation: Indentation,
+    // The most common step between space-indented levels.
+    pub indent_width: Option<usize>,
+    pub line_count: usize,
+    // In characters.
+    pub longest_line: usize,
+    pub wide: bool,
+    // Control characters other than tab, newline, carriage return and form
+    // feed; a few usually mean the file isn't really text.
+    pub control_chars: usize,
+}
+
+fn line_endings(text: &str) -> LineEndings {
+    let bytes = text.as_bytes();
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    match (lf > 0, crlf > 0, cr > 0) {
+        (false, false, false) => LineEndings::None,
+        (true, false, false) => LineEndings::Lf,
+        (false, true, false) => LineEndings::Crlf,
+        (false, false, true) => LineEndings::Cr,
+        _ => LineEndings::Mixed,
+    }
+}
+
+pub fn text_stats(text: &str, bom: bool) -> TextStats {
+    let (mut tab_lines, mut space_lines) = (0, 0);
+    let mut steps = [0usize; 9];
+    let mut previous_indent = 0;
+    let mut longest_line = 0;
+    let mut line_count = 0;
+
+    let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+    for line in normalized.lines() {
+        line_count += 1;
+        longest_line = longest_line.max(line.chars().count());
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+        } else if line.starts_with(' ') {
+            space_lines += 1;
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            if indent > previous_indent && indent - previous_indent < steps.len() {
+                steps[indent - previous_indent] += 1;
+            }
+            previous_indent = indent;
+            continue;
+        }
+        previous_indent = 0;
+    }
+
+    let indentation = match (tab_lines > 0, space_lines > 0) {
+        (false, false) => Indentation::None,
+        (true, false) => Indentation::Tabs,
+        (false, true) => Indentation::Spaces,
+        (true, true) => Indentation::Mixed,
+    };
+    let indent_width = (2..steps.len())
+        .filter(|&w| steps[w] > 0)
+        .max_by_key(|&w| (steps[w], std::cmp::Reverse(w)));
+
+    TextStats {
+        line_endings: line_endings(text),
+        bom,
+        indentation,
+        indent_width: if space_lines > 0 { indent_width } else { None },
+        line_count,
+        longest_line,
+        wide: longest_line > WIDE_LINE,
+        control_chars: text
+            .chars()
+            .filter(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\u{c}'))
+            .count(),
+    }
+}
@@ -0,0 +1,303 @@
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use crate::encoding;
+
+// Only every Nth line start is kept, so an index of a huge log stays small;
+// the lines in between are skipped by scanning forward from a checkpoint.
+const CHECKPOINT_EVERY: usize = 1024;
+const SCAN_CHUNK: usize = 1024 * 1024;
+const SNIFF_BYTES: usize = 64 * 1024;
+const MAX_LINES: usize = 5000;
+const MAX_LINE_LEN: usize = 10_000;
+const MAX_CACHED: usize = 16;
+const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextRange {
+    pub start_line: usize,
+    pub lines: Vec<String>,
+    // Known once the index has reached the end of the file.
+    pub total_lines: Option<usize>,
+    // More lines follow this range.
+    pub truncated: bool,
+    pub encoding: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineMatch {
+    // 0-based, as taken by `preview_text_range`.
+    pub line: usize,
+    // Character offsets into `text`, as [start, end).
+    pub ranges: Vec<[usize; 2]>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSearch {
+    pub matches: Vec<LineMatch>,
+    pub match_count: usize,
+    // Stopped at `max_matches` before the end of the file.
+    pub truncated: bool,
+}
+
+struct LineIndex {
+    modified: Option<SystemTime>,
+    size: u64,
+    encoding: &'static Encoding,
+    checkpoints: Vec<u64>,
+    line_starts: usize,
+    scanned_to: u64,
+    complete: bool,
+    last_used: Instant,
+}
+
+static INDEXES: Mutex<Option<HashMap<PathBuf, LineIndex>>> = Mutex::new(None);
+
+impl LineIndex {
+    fn new(file: &mut fs::File, metadata: &fs::Metadata, label: Option<&str>) -> Result<Self, String> {
+        let mut head = Vec::new();
+        file.by_ref()
+            .take(SNIFF_BYTES as u64)
+            .read_to_end(&mut head)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let (encoding, bom_len) = encoding::resolve(&head, label, head.len() as u64 == metadata.len())?;
+        if !encoding.is_ascii_compatible() {
+            return Err(format!("{} files can't be paged by line", encoding.name()));
+        }
+        let size = metadata.len();
+        Ok(LineIndex {
+            modified: metadata.modified().ok(),
+            size,
+            encoding,
+            checkpoints: vec![bom_len as u64],
+            line_starts: usize::from(size > bom_len as u64),
+            scanned_to: bom_len as u64,
+            complete: size <= bom_len as u64,
+            last_used: Instant::now(),
+        })
+    }
+
+    fn is_stale(&self, metadata: &fs::Metadata) -> bool {
+        self.size != metadata.len() || self.modified != metadata.modified().ok()
+    }
+
+    // Scans until the start of line `line` is known or the file ends.
+    fn extend_to(&mut self, file: &mut fs::File, line: usize) -> Result<(), String> {
+        if self.complete || self.line_starts > line {
+            return Ok(());
+        }
+        file.seek(SeekFrom::Start(self.scanned_to))
+            .map_err(|e| format!("Failed to seek: {}", e))?;
+        let mut buf = vec![0u8; SCAN_CHUNK];
+        while !self.complete && self.line_starts <= line {
+            let n = file.read(&mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+            if n == 0 {
+                self.complete = true;
+                break;
+            }
+            for (i, b) in buf[..n].iter().enumerate() {
+                let start = self.scanned_to + i as u64 + 1;
+                if *b == b'\n' && start < self.size {
+                    if self.line_starts.is_multiple_of(CHECKPOINT_EVERY) {
+                        self.checkpoints.push(start);
+                    }
+                    self.line_starts += 1;
+                }
+            }
+            self.scanned_to += n as u64;
+            self.complete = self.scanned_to >= self.size;
+        }
+        Ok(())
+    }
+}
+
+// Reads one line, keeping at most `MAX_LINE_LEN` bytes of it. Returns false at EOF.
+fn read_line_capped(reader: &mut impl BufRead, line: &mut Vec<u8>) -> Result<bool, String> {
+    line.clear();
+    let mut read_any = false;
+    loop {
+        let available = reader.fill_buf().map_err(|e| format!("Failed to read file: {}", e))?;
+        if available.is_empty() {
+            return Ok(read_any);
+        }
+        read_any = true;
+        let (chunk, done) = match available.iter().position(|b| *b == b'\n') {
+            Some(end) => (&available[..end], end + 1),
+            None => (available, available.len()),
+        };
+        let room = MAX_LINE_LEN.saturating_sub(line.len());
+        line.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        let found_newline = done > chunk.len();
+        reader.consume(done);
+        if found_newline {
+            return Ok(true);
+        }
+    }
+}
+
+fn read_range(
+    index: &mut LineIndex,
+    file: &mut fs::File,
+    start_line: usize,
+    line_count: usize,
+) -> Result<TextRange, String> {
+    index.extend_to(file, start_line)?;
+    let checkpoint = (start_line / CHECKPOINT_EVERY).min(index.checkpoints.len() - 1);
+    file.seek(SeekFrom::Start(index.checkpoints[checkpoint]))
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    for _ in checkpoint * CHECKPOINT_EVERY..start_line {
+        if !read_line_capped(&mut reader, &mut line)? {
+            break;
+        }
+    }
+
+    let mut lines = Vec::new();
+    while lines.len() < line_count && read_line_capped(&mut reader, &mut line)? {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let (text, _) = index.encoding.decode_without_bom_handling(&line);
+        lines.push(text.into_owned());
+    }
+    let truncated = !reader.fill_buf().map_err(|e| format!("Failed to read file: {}", e))?.is_empty();
+
+    Ok(TextRange {
+        start_line,
+        lines,
+        total_lines: index.complete.then_some(index.line_starts),
+        truncated,
+        encoding: index.encoding.name().to_string(),
+    })
+}
+
+// Returns up to `line_count` lines from `start_line` (0-based) without loading
+// the whole file. The line index is built lazily and reused until the file changes.
+pub fn preview_text_range(
+    path: &Path,
+    start_line: usize,
+    line_count: usize,
+    label: Option<&str>,
+) -> Result<TextRange, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let metadata = file.metadata().map_err(|e| format!("Failed to read metadata: {}", e))?;
+    if metadata.is_dir() {
+        return Err("Cannot preview directories".to_string());
+    }
+    let key = path.to_path_buf();
+
+    // The index is taken out of the cache while scanning so other files can
+    // be paged at the same time.
+    let cached = INDEXES.lock().unwrap().get_or_insert_with(HashMap::new).remove(&key);
+    let requested = label.and_then(|l| Encoding::for_label(l.trim().as_bytes()));
+    let mut index = match cached {
+        Some(index) if !index.is_stale(&metadata) && requested.is_none_or(|e| e == index.encoding) => index,
+        _ => LineIndex::new(&mut file, &metadata, label)?,
+    };
+    index.last_used = Instant::now();
+    let result = read_range(&mut index, &mut file, start_line, line_count.clamp(1, MAX_LINES));
+
+    let mut guard = INDEXES.lock().unwrap();
+    let indexes = guard.get_or_insert_with(HashMap::new);
+    if indexes.len() >= MAX_CACHED {
+        if let Some(oldest) = indexes.iter().min_by_key(|(_, i)| i.last_used).map(|(p, _)| p.clone()) {
+            indexes.remove(&oldest);
+        }
+    }
+    indexes.insert(key, index);
+    result
+}
+
+// Case-insensitive unless the pattern has uppercase letters. Characters are
+// folded one to one so offsets line up with the original text.
+fn find_ranges(line: &str, needle: &[char], fold: bool, limit: usize) -> Vec<[usize; 2]> {
+    let fold_char = |c: char| if fold { c.to_lowercase().next().unwrap_or(c) } else { c };
+    let haystack: Vec<char> = line.chars().map(fold_char).collect();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() && ranges.len() < limit {
+        if haystack[i..i + needle.len()] == *needle {
+            ranges.push([i, i + needle.len()]);
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+// Streams the whole file, so matches past the preview window are found too.
+pub fn search_in_file(
+    path: &Path,
+    pattern: &str,
+    max_matches: usize,
+    label: Option<&str>,
+) -> Result<FileSearch, String> {
+    if pattern.is_empty() {
+        return Err("Empty search pattern".to_string());
+    }
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let metadata = file.metadata().map_err(|e| format!("Failed to read metadata: {}", e))?;
+    if metadata.is_dir() {
+        return Err("Cannot search directories".to_string());
+    }
+    let mut head = Vec::new();
+    file.by_ref()
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let (encoding, bom_len) = encoding::resolve(&head, label, head.len() as u64 == metadata.len())?;
+    if !encoding.is_ascii_compatible() {
+        return Err(format!("{} files can't be searched by line", encoding.name()));
+    }
+    file.seek(SeekFrom::Start(bom_len as u64))
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+
+    let fold = !pattern.chars().any(char::is_uppercase);
+    let needle: Vec<char> = match fold {
+        true => pattern.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect(),
+        false => pattern.chars().collect(),
+    };
+    let max_matches = max_matches.clamp(1, MAX_MATCHES);
+
+    let mut reader = BufReader::new(file);
+    let mut raw = Vec::new();
+    let mut matches = Vec::new();
+    let mut match_count = 0;
+    let mut line_number = 0;
+    let mut truncated = false;
+    while read_line_capped(&mut reader, &mut raw)? {
+        if raw.last() == Some(&b'\r') {
+            raw.pop();
+        }
+        let (text, _) = encoding.decode_without_bom_handling(&raw);
+        let ranges = find_ranges(&text, &needle, fold, max_matches - match_count);
+        if !ranges.is_empty() {
+            match_count += ranges.len();
+            matches.push(LineMatch {
+                line: line_number,
+                ranges,
+                text: text.into_owned(),
+            });
+            if match_count >= max_matches {
+                truncated = !reader.fill_buf().map_err(|e| format!("Failed to read file: {}", e))?.is_empty();
+                break;
+            }
+        }
+        line_number += 1;
+    }
+
+    Ok(FileSearch {
+        matches,
+        match_count,
+        truncated,
+    })
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::downloads::decode_percent;
+use crate::shortcuts::open_with_default;
+
+// Metainfo files for very large torrents run to a few MB of piece hashes.
+const MAX_TORRENT_SIZE: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentFile {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorrentInfo {
+    pub name: Option<String>,
+    // Unknown for magnets without an `xl` parameter.
+    pub total_size: Option<u64>,
+    pub files: Vec<TorrentFile>,
+    pub trackers: Vec<String>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub creation_date: Option<i64>,
+    pub piece_length: Option<u64>,
+    pub private: bool,
+    // Only known from a magnet link; hashing the info dict needs SHA-1.
+    pub info_hash: Option<String>,
+    pub magnet: Option<String>,
+}
+
+enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(d) => d.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    fn str(&self) -> Option<String> {
+        match self {
+            Bencode::Bytes(b) => Some(String::from_utf8_lossy(b).to_string()),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Bencode::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn list(&self) -> &[Bencode] {
+        match self {
+            Bencode::List(l) => l,
+            _ => &[],
+        }
+    }
+}
+
+// Nesting is capped so a crafted file can't exhaust the stack.
+fn parse(data: &[u8], pos: &mut usize, depth: usize) -> Option<Bencode> {
+    if depth > 64 {
+        return None;
+    }
+    match *data.get(*pos)? {
+        b'i' => {
+            let end = *pos + data[*pos..].iter().position(|b| *b == b'e')?;
+            let value = std::str::from_utf8(&data[*pos + 1..end]).ok()?.parse().ok()?;
+            *pos = end + 1;
+            Some(Bencode::Int(value))
+        }
+        b'l' => {
+            *pos += 1;
+            let mut items = Vec::new();
+            while *data.get(*pos)? != b'e' {
+                items.push(parse(data, pos, depth + 1)?);
+            }
+            *pos += 1;
+            Some(Bencode::List(items))
+        }
+        b'd' => {
+            *pos += 1;
+            let mut dict = BTreeMap::new();
+            while *data.get(*pos)? != b'e' {
+                let Bencode::Bytes(key) = parse(data, pos, depth + 1)? else {
+                    return None;
+                };
+                dict.insert(key, parse(data, pos, depth + 1)?);
+            }
+            *pos += 1;
+            Some(Bencode::Dict(dict))
+        }
+        b'0'..=b'9' => {
+            let colon = *pos + data[*pos..].iter().position(|b| *b == b':')?;
+            let len: usize = std::str::from_utf8(&data[*pos..colon]).ok()?.parse().ok()?;
+            let start = colon + 1;
+            let bytes = data.get(start..start.checked_add(len)?)?.to_vec();
+            *pos = start + len;
+            Some(Bencode::Bytes(bytes))
+        }
+        _ => None,
+    }
+}
+
+// BitTorrent v2 nests files as a tree of names whose leaves sit under an empty key.
+fn walk_file_tree(node: &Bencode, prefix: &str, files: &mut Vec<TorrentFile>) {
+    let Bencode::Dict(children) = node else {
+        return;
+    };
+    for (name, child) in children {
+        if name.is_empty() {
+            let size = child.get("length").and_then(Bencode::int).unwrap_or(0);
+            files.push(TorrentFile {
+                path: prefix.to_string(),
+                size: size.max(0) as u64,
+            });
+            continue;
+        }
+        let name = String::from_utf8_lossy(name);
+        let path = if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) };
+        walk_file_tree(child, &path, files);
+    }
+}
+
+fn parse_torrent(data: &[u8]) -> Result<TorrentInfo, String> {
+    let root = parse(data, &mut 0, 0).ok_or("Invalid torrent file")?;
+    let info = root.get("info").ok_or("Torrent has no info dictionary")?;
+    let name = info.get("name.utf-8").or_else(|| info.get("name")).and_then(Bencode::str);
+
+    let mut files = Vec::new();
+    if let Some(tree) = info.get("file tree") {
+        walk_file_tree(tree, "", &mut files);
+    } else if let Some(Bencode::List(list)) = info.get("files") {
+        for file in list {
+            let parts = file.get("path.utf-8").or_else(|| file.get("path")).map(Bencode::list).unwrap_or(&[]);
+            let path: Vec<String> = parts.iter().filter_map(Bencode::str).collect();
+            let size = file.get("length").and_then(Bencode::int).unwrap_or(0);
+            files.push(TorrentFile {
+                path: path.join("/"),
+                size: size.max(0) as u64,
+            });
+        }
+    } else if let Some(size) = info.get("length").and_then(Bencode::int) {
+        files.push(TorrentFile {
+            path: name.clone().unwrap_or_default(),
+            size: size.max(0) as u64,
+        });
+    }
+
+    // `announce-list` is tiers of trackers; `announce` is the single legacy one.
+    let mut trackers: Vec<String> = Vec::new();
+    for tier in root.get("announce-list").map(Bencode::list).unwrap_or(&[]) {
+        for tracker in tier.list().iter().filter_map(Bencode::str) {
+            if !trackers.contains(&tracker) {
+                trackers.push(tracker);
+            }
+        }
+    }
+    if let Some(announce) = root.get("announce").and_then(Bencode::str) {
+        if !trackers.contains(&announce) {
+            trackers.insert(0, announce);
+        }
+    }
+
+    Ok(TorrentInfo {
+        name,
+        total_size: Some(files.iter().map(|f| f.size).sum()),
+        files,
+        trackers,
+        comment: root.get("comment").and_then(Bencode::str),
+        created_by: root.get("created by").and_then(Bencode::str),
+        creation_date: root.get("creation date").and_then(Bencode::int),
+        piece_length: info.get("piece length").and_then(Bencode::int).map(|l| l.max(0) as u64),
+        private: info.get("private").and_then(Bencode::int) == Some(1),
+        info_hash: None,
+        magnet: None,
+    })
+}
+
+fn parse_magnet(uri: &str) -> Result<TorrentInfo, String> {
+    let query = uri.strip_prefix("magnet:?").ok_or("Not a magnet link")?;
+    let mut info = TorrentInfo {
+        name: None,
+        total_size: None,
+        files: Vec::new(),
+        trackers: Vec::new(),
+        comment: None,
+        created_by: None,
+        creation_date: None,
+        piece_length: None,
+        private: false,
+        info_hash: None,
+        magnet: Some(uri.to_string()),
+    };
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode_percent(&value.replace('+', " "));
+        match key {
+            "dn" => info.name = Some(value),
+            "xl" => info.total_size = value.parse().ok(),
+            "tr" => info.trackers.push(value),
+            "xt" => {
+                if let Some(hash) = value.strip_prefix("urn:btih:").or_else(|| value.strip_prefix("urn:btmh:")) {
+                    info.info_hash.get_or_insert_with(|| hash.to_lowercase());
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+// A `.magnet` file is a text file holding the link, as browsers save them.
+fn magnet_link(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read magnet file: {}", e))?;
+    content
+        .split_whitespace()
+        .find(|s| s.starts_with("magnet:?"))
+        .map(str::to_string)
+        .ok_or_else(|| "No magnet link in file".to_string())
+}
+
+fn is_magnet_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("magnet"))
+}
+
+pub fn read_torrent(path: &Path) -> Result<TorrentInfo, String> {
+    if is_magnet_file(path) {
+        return parse_magnet(&magnet_link(path)?);
+    }
+    let size = fs::metadata(path).map_err(|e| format!("Failed to read metadata: {}", e))?.len();
+    if size > MAX_TORRENT_SIZE {
+        return Err("Torrent file is too large".to_string());
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read torrent: {}", e))?;
+    parse_torrent(&data)
+}
+
+// Torrent clients register for both the file type and the magnet: scheme.
+pub fn open_in_client(path: &Path) -> Result<(), String> {
+    if is_magnet_file(path) {
+        return open_with_default(&magnet_link(path)?);
+    }
+    open_with_default(&path.to_string_lossy())
+}
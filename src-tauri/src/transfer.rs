@@ -0,0 +1,640 @@
+use jwalk::WalkDir;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::copyio;
+use crate::downloads::sha256_file;
+use crate::durability::{self, partial_path, PARTIAL_SUFFIX};
+use crate::filenames::{self, TargetFs};
+use crate::jobs::{JobHandle, JobKind};
+use crate::mounts::{self, get_mount_for_path};
+use crate::plugins;
+use crate::preflight;
+use crate::rawpath;
+
+const JOURNAL_DIR: &str = "transfers";
+const JOURNAL_EXTENSION: &str = "journal";
+// FAT stores modification times in two-second steps, so a timestamp with no
+// sub-second part only has to be this close.
+const MTIME_WINDOW: Duration = Duration::from_secs(2);
+// Files up to this size are copied several at a time where the mounts allow;
+// for them the per-file open/create/rename cost outweighs the data.
+const SMALL_FILE_LIMIT: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TransferMode {
+    Copy,
+    Move,
+}
+
+impl TransferMode {
+    pub fn job_kind(self) -> JobKind {
+        match self {
+            TransferMode::Copy => JobKind::Copy,
+            TransferMode::Move => JobKind::Move,
+        }
+    }
+}
+
+// Which existing destination files count as already copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SkipIdentical {
+    #[default]
+    Never,
+    // Same size and modification time, like rsync's quick check.
+    SizeAndTime,
+    // Same size and SHA-256; slower, but doesn't trust timestamps.
+    Checksum,
+}
+
+// First line of a journal file; every following line is a JSON-encoded
+// destination path that has been fully written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferPlan {
+    pub id: u64,
+    pub title: String,
+    pub mode: TransferMode,
+    pub sources: Vec<String>,
+    pub destination_dir: String,
+    pub total: u64,
+    #[serde(default)]
+    pub skip_identical: SkipIdentical,
+}
+
+#[derive(Debug, Clone)]
+pub struct InterruptedTransfer {
+    pub plan: TransferPlan,
+    pub processed: u64,
+}
+
+fn journal_path(data_dir: &Path, id: u64) -> PathBuf {
+    data_dir.join(JOURNAL_DIR).join(format!("{}.{}", id, JOURNAL_EXTENSION))
+}
+
+fn tree_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .skip_hidden(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| fs::symlink_metadata(e.path()).ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+// A torn last line from a crash mid-append is simply ignored.
+fn read_journal(path: &Path) -> Option<(TransferPlan, HashSet<PathBuf>)> {
+    let mut lines = BufReader::new(File::open(path).ok()?).lines();
+    let plan: TransferPlan = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+    let completed = lines
+        .map_while(|l| l.ok())
+        .filter_map(|l| serde_json::from_str::<String>(&l).ok())
+        .map(|l| rawpath::decode(&l))
+        .collect();
+    Some((plan, completed))
+}
+
+pub fn interrupted_transfers(data_dir: &Path) -> Vec<InterruptedTransfer> {
+    let Ok(entries) = fs::read_dir(data_dir.join(JOURNAL_DIR)) else {
+        return Vec::new();
+    };
+    let mut transfers: Vec<InterruptedTransfer> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == JOURNAL_EXTENSION))
+        .filter_map(|p| read_journal(&p))
+        .map(|(plan, completed)| InterruptedTransfer {
+            // A finished folder is journaled after every file in it; its
+            // size already covers theirs.
+            processed: completed
+                .iter()
+                .filter(|p| !p.ancestors().skip(1).any(|a| completed.contains(a)))
+                .map(|p| tree_size(p))
+                .sum(),
+            plan,
+        })
+        .collect();
+    transfers.sort_by_key(|t| t.plan.id);
+    transfers
+}
+
+pub fn load_transfer(data_dir: &Path, id: u64) -> Result<TransferPlan, String> {
+    read_journal(&journal_path(data_dir, id))
+        .map(|(plan, _)| plan)
+        .ok_or_else(|| "No interrupted transfer with this id".to_string())
+}
+
+// Drops an interrupted transfer's journal along with any half-written file it left.
+pub fn discard_transfer(data_dir: &Path, id: u64) -> Result<(), String> {
+    let plan = load_transfer(data_dir, id)?;
+    for source in &plan.sources {
+        let source = rawpath::decode(source);
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        let target = rawpath::decode(&plan.destination_dir).join(name);
+        let _ = fs::remove_file(partial_path(&target));
+        if target.is_dir() {
+            for entry in WalkDir::new(&target).skip_hidden(false).into_iter().filter_map(|e| e.ok()) {
+                if entry.file_name().to_string_lossy().ends_with(PARTIAL_SUFFIX) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+    fs::remove_file(journal_path(data_dir, id)).map_err(|e| format!("Failed to remove journal: {}", e))
+}
+
+struct Journal {
+    file: File,
+    completed: HashSet<PathBuf>,
+}
+
+impl Journal {
+    fn open(data_dir: &Path, plan: &TransferPlan) -> Result<Self, String> {
+        let path = journal_path(data_dir, plan.id);
+        if let Some((_, completed)) = read_journal(&path) {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open journal: {}", e))?;
+            return Ok(Self { file, completed });
+        }
+
+        fs::create_dir_all(data_dir.join(JOURNAL_DIR))
+            .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+        let mut file = File::create(&path).map_err(|e| format!("Failed to create journal: {}", e))?;
+        let header = serde_json::to_string(plan).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", header).map_err(|e| format!("Failed to write journal: {}", e))?;
+        Ok(Self {
+            file,
+            completed: HashSet::new(),
+        })
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.completed.contains(path)
+    }
+
+    fn record(&mut self, path: &Path) -> Result<(), String> {
+        if self.contains(path) {
+            return Ok(());
+        }
+        let line = serde_json::to_string(&rawpath::encode_path(path)).map_err(|e| e.to_string())?;
+        writeln!(self.file, "{}", line)
+            .and_then(|()| self.file.flush())
+            .map_err(|e| format!("Failed to write journal: {}", e))?;
+        self.completed.insert(path.to_path_buf());
+        Ok(())
+    }
+}
+
+fn is_identical(src: &Path, dst: &Path, skip: SkipIdentical) -> bool {
+    let (Ok(a), Ok(b)) = (fs::metadata(src), fs::symlink_metadata(dst)) else {
+        return false;
+    };
+    if !b.is_file() || a.len() != b.len() {
+        return false;
+    }
+    match skip {
+        SkipIdentical::Never => false,
+        SkipIdentical::SizeAndTime => match (a.modified(), b.modified()) {
+            (Ok(ma), Ok(mb)) => {
+                let coarse = |t: SystemTime| t.duration_since(UNIX_EPOCH).is_ok_and(|d| d.subsec_nanos() == 0);
+                let delta = ma.duration_since(mb).or_else(|_| mb.duration_since(ma));
+                ma == mb || ((coarse(ma) || coarse(mb)) && delta.is_ok_and(|d| d <= MTIME_WINDOW))
+            }
+            _ => false,
+        },
+        SkipIdentical::Checksum => match (sha256_file(src), sha256_file(dst)) {
+            (Ok(ha), Ok(hb)) => ha == hb,
+            _ => false,
+        },
+    }
+}
+
+struct Transfer<'a> {
+    job: &'a JobHandle,
+    journal: Mutex<Journal>,
+    processed: AtomicU64,
+    skip_identical: SkipIdentical,
+    skipped: AtomicU64,
+    target_fs: TargetFs,
+    buffer_size: usize,
+    // None when files have to go one at a time.
+    pool: Option<ThreadPool>,
+    // Everything sits on one network mount, which may copy without us.
+    server_side: bool,
+}
+
+impl Transfer<'_> {
+    // Names the destination filesystem can't store (a '?' on a FAT stick, say)
+    // are sanitized. `taken` holds the other names in the same directory, so a
+    // clash fails instead of overwriting a sibling.
+    fn dest_name(&self, name: &OsStr, taken: &mut HashSet<OsString>) -> Result<OsString, String> {
+        if filenames::validate_filename(name, self.target_fs).is_empty() {
+            return Ok(name.to_os_string());
+        }
+        let sanitized = OsString::from(filenames::sanitize_filename(name, self.target_fs));
+        if !taken.insert(sanitized.clone()) {
+            return Err(format!(
+                "{} can't be stored on the destination and {} is already taken",
+                name.to_string_lossy(),
+                sanitized.to_string_lossy()
+            ));
+        }
+        Ok(sanitized)
+    }
+
+    fn advance(&self, bytes: u64, current: &str) {
+        let processed = self.processed.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.job.progress(processed, Some(current));
+    }
+
+    fn record(&self, dst: &Path) -> Result<(), String> {
+        self.journal.lock().unwrap().record(dst)
+    }
+
+    // Data goes to a `.hardbore-partial` sibling first, so the real name only
+    // ever refers to a complete file.
+    fn copy_file(&self, src: &Path, dst: &Path) -> Result<(), String> {
+        if self.journal.lock().unwrap().contains(dst) {
+            self.advance(fs::metadata(src).map(|m| m.len()).unwrap_or(0), &src.to_string_lossy());
+            return Ok(());
+        }
+        if self.skip_identical != SkipIdentical::Never && is_identical(src, dst, self.skip_identical) {
+            self.skipped.fetch_add(1, Ordering::SeqCst);
+            self.advance(fs::metadata(src).map(|m| m.len()).unwrap_or(0), &src.to_string_lossy());
+            return self.record(dst);
+        }
+
+        let partial = partial_path(dst);
+        let mut input = File::open(src).map_err(|e| format!("Failed to open file: {}", e))?;
+        let mut output = File::create(&partial).map_err(|e| format!("Failed to create file: {}", e))?;
+        let current = src.to_string_lossy().to_string();
+        let mut written = 0u64;
+
+        let mut on_chunk = |n: u64| {
+            self.job.throttle(n);
+            written += n;
+            self.advance(n, &current);
+            self.job.checkpoint()
+        };
+        let result = self.job.checkpoint().and_then(|()| {
+            let server_copy = match self.server_side {
+                true => copyio::server_side_copy(&input, &output, &mut on_chunk),
+                false => None,
+            };
+            server_copy.unwrap_or_else(|| copyio::copy_data(&mut input, &mut output, self.buffer_size, &mut on_chunk))
+        });
+        // Keeping the source's mtime is what lets a later size+time check
+        // recognize this copy as identical.
+        let metadata = fs::metadata(src).ok();
+        if let Some(modified) = metadata.as_ref().and_then(|m| m.modified().ok()) {
+            let _ = output.set_modified(modified);
+        }
+        drop(output);
+
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            self.processed.fetch_sub(written, Ordering::SeqCst);
+            return Err(e);
+        }
+        if let Some(metadata) = metadata {
+            let _ = fs::set_permissions(&partial, metadata.permissions());
+        }
+        if durability::enabled() {
+            durability::sync_file(&partial)?;
+        }
+        durability::rename(&partial, dst).map_err(|e| format!("Failed to finalize file: {}", e))?;
+        self.record(dst)
+    }
+
+    // Background jobs stay on their own worker, whose lowered I/O priority
+    // the pool threads don't share.
+    fn copy_files(&self, files: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+        match &self.pool {
+            Some(pool) if files.len() > 1 && !self.job.is_background() => {
+                pool.install(|| files.par_iter().try_for_each(|(src, dst)| self.copy_file(src, dst)))
+            }
+            _ => files.iter().try_for_each(|(src, dst)| self.copy_file(src, dst)),
+        }
+    }
+
+    fn copy_tree(&self, src: &Path, dst: &Path) -> Result<(), String> {
+        if !src.is_dir() {
+            return self.copy_file(src, dst);
+        }
+
+        fs::create_dir_all(dst).map_err(|e| format!("Failed to create directory: {}", e))?;
+        if durability::enabled() {
+            durability::sync_parent(dst)?;
+        }
+        let entries = fs::read_dir(src)
+            .map_err(|e| format!("Failed to read directory: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read entry: {}", e))?;
+        let mut taken: HashSet<OsString> = entries.iter().map(|e| e.file_name()).collect();
+        let mut small_files = Vec::new();
+        for entry in entries {
+            let name = self.dest_name(&entry.file_name(), &mut taken)?;
+            let small = entry.file_type().is_ok_and(|t| t.is_file())
+                && entry.metadata().is_ok_and(|m| m.len() <= SMALL_FILE_LIMIT);
+            if small && self.pool.is_some() {
+                small_files.push((entry.path(), dst.join(name)));
+            } else {
+                self.copy_tree(&entry.path(), &dst.join(name))?;
+            }
+        }
+        self.copy_files(&small_files)
+    }
+
+    fn transfer(&self, src: &Path, dst: &Path, mode: TransferMode) -> Result<(), String> {
+        if mode == TransferMode::Move {
+            // A same-filesystem rename needs no byte copying at all.
+            let size = tree_size(src);
+            if durability::rename(src, dst).is_ok() {
+                self.advance(size, &src.to_string_lossy());
+                return self.record(dst);
+            }
+        }
+
+        self.copy_tree(src, dst)?;
+
+        if mode == TransferMode::Move {
+            if src.is_dir() {
+                fs::remove_dir_all(src).map_err(|e| format!("Failed to remove source directory: {}", e))?;
+            } else {
+                fs::remove_file(src).map_err(|e| format!("Failed to remove source file: {}", e))?;
+            }
+            if durability::enabled() {
+                durability::sync_parent(src)?;
+            }
+        }
+        self.record(dst)
+    }
+
+    fn transfer_all(&self, plan: &TransferPlan) -> Result<(), String> {
+        let dest_dir = &rawpath::decode(&plan.destination_dir);
+        let mut errors = Vec::new();
+        let mut taken: HashSet<OsString> = plan
+            .sources
+            .iter()
+            .filter_map(|s| rawpath::decode(s).file_name().map(OsStr::to_os_string))
+            .collect();
+
+        for source in &plan.sources {
+            let src_path = &rawpath::decode(source);
+            let Some(file_name) = src_path.file_name() else {
+                errors.push(format!("Invalid source path: {}", source));
+                continue;
+            };
+            let dest_path = match self.dest_name(file_name, &mut taken) {
+                Ok(name) => dest_dir.join(name),
+                Err(e) => {
+                    errors.push(format!("{}: {}", source, e));
+                    continue;
+                }
+            };
+            // Items finished before an interruption are already in place.
+            if self.journal.lock().unwrap().contains(&dest_path) {
+                self.processed.fetch_add(tree_size(&dest_path), Ordering::SeqCst);
+                continue;
+            }
+            if !src_path.exists() {
+                errors.push(format!("{}: Source does not exist", source));
+                continue;
+            }
+            if dest_dir.starts_with(src_path) {
+                errors.push(format!("{}: Cannot copy a folder into itself", source));
+                continue;
+            }
+            if dest_path == *src_path {
+                errors.push(format!("{}: Source and destination are the same", source));
+                continue;
+            }
+
+            match self.transfer(src_path, &dest_path, plan.mode) {
+                Ok(()) => {}
+                Err(e) if self.job.is_cancelled() => return Err(e),
+                Err(e) => errors.push(format!("{}: {}", source, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+// What a transfer's job is called, e.g. "Copying photo.jpg".
+pub fn transfer_title(sources: &[String], mode: TransferMode) -> String {
+    let verb = match mode {
+        TransferMode::Copy => "Copying",
+        TransferMode::Move => "Moving",
+    };
+    match sources {
+        [single] => format!("{} {}", verb, rawpath::decode(single).file_name().unwrap_or_default().to_string_lossy()),
+        _ => format!("{} {} items", verb, sources.len()),
+    }
+}
+
+pub fn transfer_paths(sources: &[String], destination_dir: &str) -> Vec<String> {
+    let mut paths = sources.to_vec();
+    paths.push(destination_dir.to_string());
+    paths
+}
+
+// Paths a transfer may have created or removed, for keeping the index
+// current. A name the destination can't store was sanitized on the way, so
+// both spellings are listed; one that doesn't exist is simply dropped.
+pub fn changed_paths(plan: &TransferPlan) -> Vec<String> {
+    let dest_dir = rawpath::decode(&plan.destination_dir);
+    let target_fs = filenames::target_fs_for(&dest_dir);
+    let mut paths = Vec::new();
+    for source in &plan.sources {
+        let Some(name) = rawpath::decode(source).file_name().map(OsStr::to_os_string) else {
+            continue;
+        };
+        if plan.mode == TransferMode::Move {
+            paths.push(source.clone());
+        }
+        let sanitized = filenames::sanitize_filename(&name, target_fs);
+        if sanitized != name.to_string_lossy() {
+            paths.push(rawpath::encode_path(&dest_dir.join(sanitized)));
+        }
+        paths.push(rawpath::encode_path(&dest_dir.join(name)));
+    }
+    paths
+}
+
+pub fn plan_transfer(
+    id: u64,
+    title: &str,
+    sources: Vec<String>,
+    destination_dir: String,
+    mode: TransferMode,
+    skip_identical: SkipIdentical,
+) -> TransferPlan {
+    TransferPlan {
+        id,
+        title: title.to_string(),
+        mode,
+        total: sources.iter().map(|s| tree_size(&rawpath::decode(s))).sum(),
+        sources,
+        destination_dir,
+        skip_identical,
+    }
+}
+
+// Bytes that have to be written to the destination's filesystem. A move only
+// needs room for the sources on other mounts; the rest are renamed in place.
+pub fn required_space(sources: &[String], destination_dir: &str, mode: TransferMode) -> u64 {
+    let target = get_mount_for_path(&rawpath::decode(destination_dir)).ok();
+    sources
+        .iter()
+        .filter(|s| match (mode, &target) {
+            (TransferMode::Move, Some(target)) => {
+                get_mount_for_path(&rawpath::decode(s)).map_or(true, |m| m.mount_point != target.mount_point)
+            }
+            _ => true,
+        })
+        .map(|s| tree_size(&rawpath::decode(s)))
+        .sum()
+}
+
+// Sources mostly share a parent, so each directory is looked up once.
+fn plan_dirs(plan: &TransferPlan) -> HashSet<&str> {
+    plan.sources
+        .iter()
+        .map(|s| s.rsplit_once(std::path::MAIN_SEPARATOR).map_or(s.as_str(), |(parent, _)| parent))
+        .chain(std::iter::once(plan.destination_dir.as_str()))
+        .collect()
+}
+
+// Slow mounts on either end get bigger reads and writes, so fewer round trips.
+// A configured size applies everywhere.
+fn buffer_size(plan: &TransferPlan) -> usize {
+    if let Some(size) = copyio::buffer_size() {
+        return size;
+    }
+    plan_dirs(plan)
+        .into_iter()
+        .map(|p| mounts::behavior_for(p).copy_buffer_size)
+        .max()
+        .unwrap_or(1 << 20)
+}
+
+// Parallel copies only pay off when every device involved handles them well;
+// a spinning source disk seeks just as badly as a spinning destination.
+fn copy_pool(plan: &TransferPlan) -> Option<ThreadPool> {
+    let threads = plan_dirs(plan)
+        .into_iter()
+        .map(mounts::copy_concurrency_for)
+        .min()
+        .unwrap_or(1);
+    if threads <= 1 {
+        return None;
+    }
+    ThreadPoolBuilder::new().num_threads(threads).build().ok()
+}
+
+// True when every source and the destination sit on the same network mount.
+fn same_network_mount(plan: &TransferPlan) -> bool {
+    let destination = rawpath::decode(&plan.destination_dir);
+    let Ok(target) = get_mount_for_path(&destination) else {
+        return false;
+    };
+    target.profile == mounts::MountProfile::NetworkSlow
+        && plan.sources.iter().all(|s| {
+            get_mount_for_path(&rawpath::decode(s)).is_ok_and(|m| m.mount_point == target.mount_point)
+        })
+}
+
+// The plugin VFS backend shared by every source and the destination, None when
+// all of them are local. Mixing backends would need a download and upload.
+pub fn remote_backend(sources: &[String], destination_dir: &str) -> Result<Option<String>, String> {
+    let backend = plugins::vfs_backend(destination_dir);
+    if sources.iter().all(|s| plugins::vfs_backend(s) == backend) {
+        return Ok(backend.map(str::to_string));
+    }
+    Err("Transfers between different storage backends are not supported yet".to_string())
+}
+
+// Runs a transfer inside one remote backend. `server_op` copies or moves a
+// single item there, so nothing is downloaded.
+pub fn run_remote_transfer(
+    plan: &TransferPlan,
+    job: &JobHandle,
+    mut server_op: impl FnMut(&str, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    job.start()?;
+    job.set_total(None);
+    let mut errors = Vec::new();
+    for source in &plan.sources {
+        job.checkpoint()?;
+        job.progress(0, Some(source));
+        let name = source.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        let destination = format!("{}/{}", plan.destination_dir.trim_end_matches('/'), name);
+        if destination == *source {
+            errors.push(format!("{}: Source and destination are the same", source));
+            continue;
+        }
+        if let Err(e) = server_op(source, &destination) {
+            errors.push(format!("{}: {}", source, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+// Runs or resumes a transfer. The journal is removed once the job ends on its
+// own, so only a crash or a killed process leaves one behind for `resume_job`.
+pub fn run_transfer(data_dir: &Path, plan: &TransferPlan, job: &JobHandle) -> Result<(), String> {
+    // Resumed and skip-identical transfers write less than their total, so
+    // only a fresh plain transfer has its space checked up front.
+    let required = match plan.mode {
+        _ if plan.skip_identical != SkipIdentical::Never || journal_path(data_dir, plan.id).exists() => 0,
+        TransferMode::Copy => plan.total,
+        TransferMode::Move => required_space(&plan.sources, &plan.destination_dir, plan.mode),
+    };
+    let destination = rawpath::decode(&plan.destination_dir);
+    preflight::check_destination(&destination, required).map_err(|e| e.message)?;
+
+    job.start()?;
+    job.set_total(Some(plan.total));
+
+    let transfer = Transfer {
+        job,
+        journal: Mutex::new(Journal::open(data_dir, plan)?),
+        processed: AtomicU64::new(0),
+        skip_identical: plan.skip_identical,
+        skipped: AtomicU64::new(0),
+        target_fs: filenames::target_fs_for(&destination),
+        buffer_size: buffer_size(plan),
+        pool: copy_pool(plan),
+        server_side: same_network_mount(plan),
+    };
+    let result = transfer.transfer_all(plan);
+    let skipped = transfer.skipped.load(Ordering::SeqCst);
+    if skipped > 0 {
+        tracing::info!(id = plan.id, skipped, "skipped identical files");
+    }
+    drop(transfer);
+    let _ = fs::remove_file(journal_path(data_dir, plan.id));
+    result
+}
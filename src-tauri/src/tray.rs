@@ -0,0 +1,170 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::indexer;
+use crate::jobs::{JobInfo, JobKind, JobManager};
+use crate::locations;
+use crate::profile;
+
+// The tray icon shown in `--background` mode, so HardBore can sit minimized
+// as an indexing daemon.
+const ID: &str = "hardbore";
+const STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+struct Tray {
+    icon: TrayIcon<Wry>,
+    status: MenuItem<Wry>,
+    pause: CheckMenuItem<Wry>,
+    jobs: JobManager,
+}
+
+static TRAY: Mutex<Option<Tray>> = Mutex::new(None);
+
+// Menu calls wait on the main thread, which may itself be waiting for the
+// lock in a menu handler, so they're made on a copy with the lock released.
+fn current() -> Option<Tray> {
+    TRAY.lock().unwrap().clone()
+}
+
+pub fn create(app_handle: &AppHandle, jobs: JobManager) -> Result<(), String> {
+    let (menu, status, pause) = build_menu(app_handle, &jobs).map_err(|e| e.to_string())?;
+    let menu_jobs = jobs.clone();
+    let mut builder = TrayIconBuilder::with_id(ID)
+        .tooltip("HardBore")
+        .menu(&menu)
+        .on_menu_event(move |app_handle, event| handle_menu(app_handle, &menu_jobs, event.id().0.as_str()));
+    if let Some(icon) = app_handle.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let icon = builder.build(app_handle).map_err(|e| format!("Failed to create the tray icon: {}", e))?;
+    *TRAY.lock().unwrap() = Some(Tray { icon, status, pause, jobs });
+
+    std::thread::spawn(|| loop {
+        std::thread::sleep(STATUS_INTERVAL);
+        let Some(tray) = current() else {
+            return;
+        };
+        let text = status_text(&tray.jobs);
+        let _ = tray.status.set_text(&text);
+        let _ = tray.icon.set_tooltip(Some(&text));
+        let _ = tray.pause.set_checked(tray.jobs.background_paused());
+    });
+    Ok(())
+}
+
+// Rebuilds the menu, e.g. after the favorites changed. Does nothing without a tray.
+pub fn refresh(app_handle: &AppHandle) {
+    let Some(tray) = current() else {
+        return;
+    };
+    let Ok((menu, status, pause)) = build_menu(app_handle, &tray.jobs) else {
+        return;
+    };
+    if tray.icon.set_menu(Some(menu)).is_ok() {
+        *TRAY.lock().unwrap() = Some(Tray { status, pause, ..tray });
+    }
+}
+
+fn build_menu(
+    app_handle: &AppHandle,
+    jobs: &JobManager,
+) -> tauri::Result<(Menu<Wry>, MenuItem<Wry>, CheckMenuItem<Wry>)> {
+    let status = MenuItem::with_id(app_handle, "status", status_text(jobs), false, None::<&str>)?;
+    let show = MenuItem::with_id(app_handle, "show", "Show HardBore", true, None::<&str>)?;
+
+    let favorites = profile::data_dir(app_handle)
+        .map(|dir| locations::favorites(&dir))
+        .unwrap_or_default();
+    let mut favorite_items = Vec::new();
+    for path in &favorites {
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        favorite_items.push(MenuItem::with_id(app_handle, format!("favorite:{}", path), name, true, None::<&str>)?);
+    }
+    if favorite_items.is_empty() {
+        favorite_items.push(MenuItem::with_id(app_handle, "no-favorites", "No favorites", false, None::<&str>)?);
+    }
+    let favorite_refs: Vec<&dyn IsMenuItem<Wry>> = favorite_items.iter().map(|i| i as &dyn IsMenuItem<Wry>).collect();
+    let favorites = Submenu::with_items(app_handle, "Favorites", true, &favorite_refs)?;
+
+    let pause = CheckMenuItem::with_id(
+        app_handle,
+        "pause",
+        "Pause background work",
+        true,
+        jobs.background_paused(),
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app_handle, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app_handle,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &show,
+            &favorites,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &pause,
+            &PredefinedMenuItem::separator(app_handle)?,
+            &quit,
+        ],
+    )?;
+    Ok((menu, status, pause))
+}
+
+fn handle_menu(app_handle: &AppHandle, jobs: &JobManager, id: &str) {
+    match id {
+        "show" => show_main(app_handle),
+        "pause" => {
+            let paused = !jobs.background_paused();
+            jobs.pause_background(paused);
+            if let Some(tray) = current() {
+                let _ = tray.pause.set_checked(paused);
+            }
+        }
+        "quit" => app_handle.exit(0),
+        _ => {
+            if let Some(path) = id.strip_prefix("favorite:") {
+                show_main(app_handle);
+                let _ = app_handle.emit_to("main", "open-location", path);
+            }
+        }
+    }
+}
+
+fn show_main(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+// One line for the menu and the tooltip: the index, then other work.
+fn status_text(jobs: &JobManager) -> String {
+    let mut parts = Vec::new();
+    match indexer::active().map(|i| i.get_status()) {
+        Some(status) if status.is_running => parts.push(format!("Indexing, {} files", status.indexed_count)),
+        Some(status) => parts.push(format!("{} files indexed", status.indexed_count)),
+        None => parts.push("Index not loaded".to_string()),
+    }
+    let active: Vec<JobInfo> = jobs.active().into_iter().filter(|j| j.kind != JobKind::Indexing).collect();
+    match active.as_slice() {
+        [] => {}
+        [job] => parts.push(match job.total {
+            Some(total) if total > 0 => format!("{} {}%", job.title, job.processed * 100 / total),
+            _ => job.title.clone(),
+        }),
+        active => parts.push(format!("{} jobs", active.len())),
+    }
+    if jobs.background_paused() {
+        parts.push("background work paused".to_string());
+    }
+    parts.join(" · ")
+}
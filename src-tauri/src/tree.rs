@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fs_engine::is_hidden;
+use crate::humanize::format_size;
+
+// A `tree`-style listing of a folder for pasting into docs, issues or chat.
+// Past this many lines the rest is summed up in a single one.
+const MAX_LINES: usize = 10_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeOptions {
+    // `|--` instead of box-drawing characters, for places that mangle them.
+    pub ascii: bool,
+    pub sizes: bool,
+    pub show_hidden: bool,
+    pub dirs_only: bool,
+    // Names to leave out, with `*` and `?` wildcards, e.g. `node_modules` or `*.o`.
+    pub ignore: Vec<String>,
+}
+
+struct Branches {
+    tee: &'static str,
+    last: &'static str,
+    pipe: &'static str,
+    blank: &'static str,
+}
+
+const UNICODE: Branches = Branches {
+    tee: "├── ",
+    last: "└── ",
+    pipe: "│   ",
+    blank: "    ",
+};
+
+const ASCII: Branches = Branches {
+    tee: "|-- ",
+    last: "`-- ",
+    pipe: "|   ",
+    blank: "    ",
+};
+
+struct Walk<'a> {
+    options: &'a TreeOptions,
+    branches: &'a Branches,
+    depth: usize,
+    lines: Vec<String>,
+    omitted: usize,
+    dirs: usize,
+    files: usize,
+}
+
+// `depth` levels below `path` are listed; 0 means no limit. Directory sizes
+// count everything beneath them that isn't ignored, listed or not.
+pub fn generate_tree(path: &Path, depth: usize, options: &TreeOptions) -> Result<String, String> {
+    if !path.is_dir() {
+        return Err(format!("Not a directory: {}", path.display()));
+    }
+    let mut walk = Walk {
+        options,
+        branches: if options.ascii { &ASCII } else { &UNICODE },
+        depth: if depth == 0 { usize::MAX } else { depth },
+        lines: Vec::new(),
+        omitted: 0,
+        dirs: 0,
+        files: 0,
+    };
+    let size = walk.directory(path, "", 1);
+
+    let mut text = if options.sizes {
+        format!("[{}]  {}", format_size(size), path.display())
+    } else {
+        path.display().to_string()
+    };
+    for line in &walk.lines {
+        text.push('\n');
+        text.push_str(line);
+    }
+    if walk.omitted > 0 {
+        text.push_str(&format!("\n… {} more entries", walk.omitted));
+    }
+    let dirs = match walk.dirs {
+        1 => "1 directory".to_string(),
+        n => format!("{} directories", n),
+    };
+    if options.dirs_only {
+        text.push_str(&format!("\n\n{}\n", dirs));
+    } else {
+        let files = match walk.files {
+            1 => "1 file".to_string(),
+            n => format!("{} files", n),
+        };
+        text.push_str(&format!("\n\n{}, {}\n", dirs, files));
+    }
+    Ok(text)
+}
+
+pub fn write_tree(text: &str, output: &Path) -> Result<(), String> {
+    fs::write(output, text).map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+}
+
+impl Walk<'_> {
+    // What's left after the hidden and ignored entries, folders first.
+    fn entries(&self, dir: &Path) -> Vec<(String, PathBuf, fs::Metadata)> {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<(String, PathBuf, fs::Metadata)> = read_dir
+            .filter_map(|e| e.ok())
+            .filter_map(|e| Some((e.file_name().to_string_lossy().to_string(), e.path(), e.metadata().ok()?)))
+            .filter(|(name, _, metadata)| self.options.show_hidden || !is_hidden(name, metadata))
+            .filter(|(name, _, _)| !self.options.ignore.iter().any(|p| wildcard_match(p, name)))
+            .collect();
+        entries.sort_by(|(a, _, a_meta), (b, _, b_meta)| {
+            b_meta.is_dir().cmp(&a_meta.is_dir()).then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        });
+        entries
+    }
+
+    // Lists the directory's entries under `prefix` and returns its size.
+    // Symlinks are shown but never followed, so loops can't happen.
+    fn directory(&mut self, dir: &Path, prefix: &str, level: usize) -> u64 {
+        let (shown, unlisted): (Vec<_>, Vec<_>) = self
+            .entries(dir)
+            .into_iter()
+            .partition(|(_, _, metadata)| !self.options.dirs_only || metadata.is_dir());
+        let mut total: u64 = unlisted.iter().map(|(_, _, metadata)| metadata.len()).sum();
+
+        for (i, (name, path, metadata)) in shown.iter().enumerate() {
+            let last = i + 1 == shown.len();
+            let is_dir = metadata.is_dir();
+            if is_dir {
+                self.dirs += 1;
+            } else {
+                self.files += 1;
+            }
+            // Claimed now, filled in once a folder's size is known.
+            let slot = (self.lines.len() < MAX_LINES).then(|| {
+                self.lines.push(String::new());
+                self.lines.len() - 1
+            });
+            if slot.is_none() {
+                self.omitted += 1;
+            }
+
+            let size = if !is_dir {
+                metadata.len()
+            } else if level < self.depth {
+                let branch = if last { self.branches.blank } else { self.branches.pipe };
+                self.directory(path, &format!("{}{}", prefix, branch), level + 1)
+            } else if self.options.sizes {
+                self.size_of(path)
+            } else {
+                0
+            };
+            total += size;
+
+            let Some(slot) = slot else {
+                continue;
+            };
+            let mut line = format!("{}{}", prefix, if last { self.branches.last } else { self.branches.tee });
+            if self.options.sizes {
+                line.push_str(&format!("[{}]  ", format_size(size)));
+            }
+            line.push_str(name);
+            if metadata.is_symlink() {
+                if let Ok(target) = fs::read_link(path) {
+                    line.push_str(&format!(" -> {}", target.display()));
+                }
+            } else if is_dir {
+                line.push('/');
+            }
+            self.lines[slot] = line;
+        }
+        total
+    }
+
+    // The size of a folder below the listed depth.
+    fn size_of(&self, dir: &Path) -> u64 {
+        self.entries(dir)
+            .iter()
+            .map(|(_, path, metadata)| if metadata.is_dir() { self.size_of(path) } else { metadata.len() })
+            .sum()
+    }
+}
+
+// Shell-style `*` and `?` matching against a whole name.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(unix)]
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::ffi::CStr;
+#[cfg(unix)]
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemUser {
+    pub uid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemGroup {
+    pub gid: u32,
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+#[cfg(unix)]
+static USER_NAMES: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+#[cfg(unix)]
+static GROUP_NAMES: OnceLock<Mutex<HashMap<u32, Option<String>>>> = OnceLock::new();
+// getpwent/getgrent iterate shared libc state and must never run concurrently.
+#[cfg(unix)]
+static ENUM_LOCK: Mutex<()> = Mutex::new(());
+
+#[cfg(unix)]
+fn lookup_user(uid: u32) -> Option<String> {
+    let mut buf_len = 1024;
+    loop {
+        let mut buf: Vec<libc::c_char> = vec![0; buf_len];
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+
+        if rc == libc::ERANGE && buf_len < 1 << 20 {
+            buf_len *= 2;
+            continue;
+        }
+        if rc != 0 || result.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(pwd.pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+#[cfg(unix)]
+fn lookup_group(gid: u32) -> Option<String> {
+    let mut buf_len = 1024;
+    loop {
+        let mut buf: Vec<libc::c_char> = vec![0; buf_len];
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        let rc = unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+
+        if rc == libc::ERANGE && buf_len < 1 << 20 {
+            buf_len *= 2;
+            continue;
+        }
+        if rc != 0 || result.is_null() {
+            return None;
+        }
+        let name = unsafe { CStr::from_ptr(grp.gr_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let cache = USER_NAMES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(name) = cache.lock().unwrap().get(&uid) {
+        return name.clone();
+    }
+    let name = lookup_user(uid);
+    cache.lock().unwrap().insert(uid, name.clone());
+    name
+}
+
+#[cfg(unix)]
+pub fn group_name(gid: u32) -> Option<String> {
+    let cache = GROUP_NAMES.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(name) = cache.lock().unwrap().get(&gid) {
+        return name.clone();
+    }
+    let name = lookup_group(gid);
+    cache.lock().unwrap().insert(gid, name.clone());
+    name
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn group_name(_gid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+pub fn list_users() -> Vec<SystemUser> {
+    let _guard = ENUM_LOCK.lock().unwrap();
+    let mut users = Vec::new();
+
+    unsafe {
+        libc::setpwent();
+        loop {
+            let pwd = libc::getpwent();
+            if pwd.is_null() {
+                break;
+            }
+            users.push(SystemUser {
+                uid: (*pwd).pw_uid,
+                name: CStr::from_ptr((*pwd).pw_name).to_string_lossy().into_owned(),
+            });
+        }
+        libc::endpwent();
+    }
+
+    users.sort_by_key(|u| u.uid);
+    users.dedup_by_key(|u| u.uid);
+    users
+}
+
+#[cfg(unix)]
+pub fn list_groups() -> Vec<SystemGroup> {
+    let _guard = ENUM_LOCK.lock().unwrap();
+    let mut groups = Vec::new();
+
+    unsafe {
+        libc::setgrent();
+        loop {
+            let grp = libc::getgrent();
+            if grp.is_null() {
+                break;
+            }
+
+            let mut members = Vec::new();
+            let mut member = (*grp).gr_mem;
+            while !member.is_null() && !(*member).is_null() {
+                members.push(CStr::from_ptr(*member).to_string_lossy().into_owned());
+                member = member.add(1);
+            }
+
+            groups.push(SystemGroup {
+                gid: (*grp).gr_gid,
+                name: CStr::from_ptr((*grp).gr_name).to_string_lossy().into_owned(),
+                members,
+            });
+        }
+        libc::endgrent();
+    }
+
+    groups.sort_by_key(|g| g.gid);
+    groups.dedup_by_key(|g| g.gid);
+    groups
+}
+
+#[cfg(not(unix))]
+pub fn list_users() -> Vec<SystemUser> {
+    Vec::new()
+}
+
+#[cfg(not(unix))]
+pub fn list_groups() -> Vec<SystemGroup> {
+    Vec::new()
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::clipboard::file_uri;
+
+const MAX_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScriptSource {
+    HardBore,
+    Nautilus,
+    Nemo,
+    Caja,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserScript {
+    pub name: String,
+    pub path: String,
+    pub submenu: Vec<String>,
+    pub source: ScriptSource,
+}
+
+fn script_dirs(data_dir: &Path) -> Vec<(ScriptSource, PathBuf)> {
+    let mut locations = vec![(ScriptSource::HardBore, data_dir.join("menu-scripts"))];
+    if let Some(data) = dirs::data_dir() {
+        locations.push((ScriptSource::Nautilus, data.join("nautilus/scripts")));
+        locations.push((ScriptSource::Nemo, data.join("nemo/scripts")));
+    }
+    if let Some(config) = dirs::config_dir() {
+        locations.push((ScriptSource::Caja, config.join("caja/scripts")));
+    }
+    locations
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+// Subdirectories become submenus, as in Nautilus.
+fn collect(dir: &Path, submenu: &[String], source: ScriptSource, depth: usize, out: &mut Vec<UserScript>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = read_dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if depth < MAX_DEPTH {
+                let mut nested = submenu.to_vec();
+                nested.push(name);
+                collect(&path, &nested, source, depth + 1, out);
+            }
+        } else if is_executable(&metadata) {
+            out.push(UserScript {
+                name,
+                path: path.to_string_lossy().to_string(),
+                submenu: submenu.to_vec(),
+                source,
+            });
+        }
+    }
+}
+
+pub fn list_user_scripts(data_dir: &Path) -> Vec<UserScript> {
+    let mut scripts = Vec::new();
+    for (source, dir) in script_dirs(data_dir) {
+        collect(&dir, &[], source, 0, &mut scripts);
+    }
+    scripts
+}
+
+fn env_prefix(source: ScriptSource) -> &'static str {
+    match source {
+        ScriptSource::Nemo => "NEMO",
+        ScriptSource::Caja => "CAJA",
+        ScriptSource::HardBore | ScriptSource::Nautilus => "NAUTILUS",
+    }
+}
+
+pub fn run_user_script(
+    data_dir: &Path,
+    path: &str,
+    selection: &[String],
+    current_dir: &str,
+) -> Result<(), String> {
+    // Only run scripts that actually live in one of the script directories.
+    let script = list_user_scripts(data_dir)
+        .into_iter()
+        .find(|s| s.path == path)
+        .ok_or("Not a known script")?;
+
+    // Each entry is newline-terminated, matching what Nautilus exports.
+    let paths: String = selection.iter().map(|p| format!("{}\n", p)).collect();
+    let uris: String = selection.iter().map(|p| format!("{}\n", file_uri(p))).collect();
+    let prefix = env_prefix(script.source);
+
+    // Selected items are passed as arguments relative to the current directory.
+    let args: Vec<String> = selection
+        .iter()
+        .map(|p| {
+            Path::new(p)
+                .strip_prefix(current_dir)
+                .map(|r| r.to_string_lossy().to_string())
+                .unwrap_or_else(|_| p.clone())
+        })
+        .collect();
+
+    Command::new(&script.path)
+        .args(&args)
+        .current_dir(current_dir)
+        .env(format!("{}_SCRIPT_SELECTED_FILE_PATHS", prefix), &paths)
+        .env(format!("{}_SCRIPT_SELECTED_URIS", prefix), &uris)
+        .env(format!("{}_SCRIPT_CURRENT_URI", prefix), file_uri(current_dir))
+        .env(format!("{}_SCRIPT_WINDOW_GEOMETRY", prefix), "")
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run script: {}", e))
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::locations;
+use crate::rawpath;
+use crate::settings;
+
+pub const SETTING: &str = "workspaces";
+
+// A named set of locations, e.g. everything open while working on one project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub tabs: Vec<String>,
+    #[serde(default)]
+    pub active_tab: usize,
+    #[serde(default)]
+    pub pinned: Vec<String>,
+}
+
+fn load(data_dir: &Path) -> Vec<Workspace> {
+    settings::get(data_dir, SETTING)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// Read and written back under one lock, so two windows saving at once both
+// keep their workspace.
+fn modify(data_dir: &Path, f: impl FnOnce(&mut Vec<Workspace>)) -> Result<(), String> {
+    settings::update(data_dir, |settings| {
+        let mut workspaces: Vec<Workspace> = settings
+            .get(SETTING)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default();
+        f(&mut workspaces);
+        let value = serde_json::to_value(workspaces).map_err(|e| format!("Failed to serialize workspaces: {}", e))?;
+        settings.insert(SETTING.to_string(), value);
+        Ok(())
+    })?
+}
+
+fn still_there(path: &str) -> bool {
+    locations::is_virtual(path) || rawpath::decode(path).is_dir()
+}
+
+pub fn list_workspaces(data_dir: &Path) -> Vec<Workspace> {
+    let mut workspaces = load(data_dir);
+    workspaces.sort_by_key(|w| w.name.to_lowercase());
+    workspaces
+}
+
+// Saving under an existing name replaces that workspace.
+pub fn save_workspace(data_dir: &Path, mut workspace: Workspace) -> Result<Workspace, String> {
+    workspace.name = workspace.name.trim().to_string();
+    if workspace.name.is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    if workspace.tabs.is_empty() {
+        return Err("A workspace needs at least one location".to_string());
+    }
+    workspace.active_tab = workspace.active_tab.min(workspace.tabs.len() - 1);
+
+    modify(data_dir, |workspaces| {
+        workspaces.retain(|w| w.name != workspace.name);
+        workspaces.push(workspace.clone());
+    })?;
+    Ok(workspace)
+}
+
+pub fn delete_workspace(data_dir: &Path, name: &str) -> Result<(), String> {
+    modify(data_dir, |workspaces| workspaces.retain(|w| w.name != name))
+}
+
+// Locations deleted since the workspace was saved are dropped rather than
+// failing the whole restore; the active tab follows the one it pointed at.
+pub fn open_workspace(data_dir: &Path, name: &str) -> Result<Workspace, String> {
+    let saved = load(data_dir)
+        .into_iter()
+        .find(|w| w.name == name)
+        .ok_or_else(|| format!("Unknown workspace: {}", name))?;
+
+    let active = saved.tabs.get(saved.active_tab).cloned();
+    let tabs: Vec<String> = saved.tabs.into_iter().filter(|t| still_there(t)).collect();
+    if tabs.is_empty() {
+        return Err(format!("None of the locations in {} exist anymore", name));
+    }
+    let active_tab = active.and_then(|a| tabs.iter().position(|t| *t == a)).unwrap_or(0);
+
+    Ok(Workspace {
+        name: saved.name,
+        tabs,
+        active_tab,
+        pinned: saved.pinned.into_iter().filter(|p| still_there(p)).collect(),
+    })
+}
@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import type { 
   FileEntry, 
   DirectoryContents, 
+  ListingPayload,
   SearchResult, 
   IndexerStatus, 
   FilePreview,
@@ -10,9 +11,30 @@ import type {
   SortField,
   SortDirection,
   MountPoint,
-  PickerConfig
+  MountInfo,
+  MountBehavior,
+  MultiProperties,
+  NameCheck,
+  SaveNameCheck,
+  SaveTarget,
+  Application,
+  ChooserRequest,
+  PortalStatus,
+  InstallReport,
+  PreflightError,
+  PickerConfig,
+  QuickBookmark,
+  Workspace,
+  FileManager,
+  ImportPreview,
+  TreeOptions,
+  ActivityEvent,
+  FsChange,
+  ClearReport,
+  JobInfo,
+  JobState
 } from './types';
-import { splitPath, parentDir } from './utils';
+import { splitPath, parentDir, formatSize, unpackListing } from './utils';
 
 export const currentDir = writable<DirectoryContents | null>(null);
 export const history = writable<string[]>([]);
@@ -21,29 +43,44 @@ export const selectedIndex = writable<number>(0);
 export const selectedIndices = writable<Set<number>>(new Set([0]));
 export const previewFile = writable<FilePreview | null>(null);
 export const previewLoading = writable<boolean>(false);
+export const previewSearchOpen = writable<boolean>(false);
 export const commandPaletteOpen = writable<boolean>(false);
 export const searchQuery = writable<string>('');
 export const searchResults = writable<SearchResult[]>([]);
 export const searchSelectedIndex = writable<number>(0);
 export const indexerStatus = writable<IndexerStatus | null>(null);
 export const favorites = writable<string[]>([]);
+export const quickBookmarks = writable<QuickBookmark[]>([]);
+export const workspaces = writable<Workspace[]>([]);
+export const activeWorkspace = writable<Workspace | null>(null);
 export const mountPoints = writable<MountPoint[]>([]);
+export const mountBehavior = writable<MountBehavior | null>(null);
 export const pickerConfig = writable<PickerConfig | null>(null);
 export const pickerSelection = writable<Set<string>>(new Set());
 export const isPickerMode = derived(pickerConfig, $config => $config?.mode !== 'Disabled');
 export const saveName = writable<string>('');
 export const isSaveMode = derived(pickerConfig, $config => $config?.mode === 'Save');
+export const saveType = writable<string | null>(null);
+// Whether the name typed in Save mode can be written where it's going.
+export const saveTarget = writable<SaveTarget | null>(null);
+export const appChooser = writable<ChooserRequest | null>(null);
+export const chooserApplications = writable<Application[]>([]);
 
 export interface ClipboardItem {
   paths: string[];
   operation: 'copy' | 'cut';
 }
 export const clipboard = writable<ClipboardItem | null>(null);
+export const jobs = writable<JobInfo[]>([]);
+
+// Each `--profile` keeps its own view settings; the key is switched once the
+// profile is known.
+let viewConfigKey = 'hardbore_view_config';
 
 function loadViewConfig(): ViewConfig {
   if (typeof window !== 'undefined') {
     try {
-      const saved = localStorage.getItem('hardbore_view_config');
+      const saved = localStorage.getItem(viewConfigKey);
       if (saved) {
         const parsed = JSON.parse(saved);
         return {
@@ -69,7 +106,7 @@ export const viewConfig = writable<ViewConfig>(loadViewConfig());
 if (typeof window !== 'undefined') {
   viewConfig.subscribe($config => {
     try {
-      localStorage.setItem('hardbore_view_config', JSON.stringify({
+      localStorage.setItem(viewConfigKey, JSON.stringify({
         showHidden: $config.showHidden,
         sort: $config.sort,
         sidebarOpen: $config.sidebarOpen
@@ -168,17 +205,26 @@ export function selectRange(from: number, to: number): void {
   selectedIndices.set(next);
 }
 
-export async function navigateTo(path: string, addToHistory = true): Promise<void> {
+// Below this many entries a listing is cheaper to receive as plain rows.
+const COLUMNAR_MIN_ENTRIES = 5000;
+
+// Listings of unchanged directories come from the backend's cache;
+// `forceRefresh` rereads the directory from disk.
+export async function navigateTo(path: string, addToHistory = true, forceRefresh = false): Promise<void> {
   try {
     const config = get(viewConfig);
-    const contents = await invoke<DirectoryContents>('read_dir', {
+    const payload = await invoke<ListingPayload>('read_dir_packed', {
       path,
-      showHidden: config.showHidden
+      showHidden: config.showHidden,
+      forceRefresh,
+      minEntries: COLUMNAR_MIN_ENTRIES
     });
+    const contents = unpackListing(payload);
     
     currentDir.set(contents);
     selectedIndex.set(0);
     selectedIndices.set(new Set([0]));
+    loadMountBehavior(path);
     
     if (addToHistory) {
       const hist = get(history);
@@ -196,6 +242,14 @@ export async function navigateTo(path: string, addToHistory = true): Promise<voi
   }
 }
 
+async function loadMountBehavior(path: string): Promise<void> {
+  try {
+    mountBehavior.set(await invoke<MountBehavior>('get_mount_behavior', { path }));
+  } catch {
+    mountBehavior.set(null);
+  }
+}
+
 export async function navigateUp(): Promise<void> {
   const parent = get(parentPath);
   if (parent) {
@@ -261,10 +315,10 @@ export function selectLast(): void {
   selectedIndices.set(new Set([last]));
 }
 
-export async function loadPreview(path: string): Promise<void> {
+export async function loadPreview(path: string, encoding: string | null = null): Promise<void> {
   previewLoading.set(true);
   try {
-    const preview = await invoke<FilePreview>('preview_file', { path, maxBytes: 65536 });
+    const preview = await invoke<FilePreview>('preview_file', { path, maxBytes: 65536, encoding });
     previewFile.set(preview);
   } catch (e) {
     previewFile.set(null);
@@ -311,8 +365,13 @@ function calculateNameMatchScore(fileName: string, query: string): number {
   return 25;
 }
 
+let searchRequest = 0;
+
+// Searches run in the background and can finish out of order; only the
+// latest one's results are shown.
 export async function search(query: string): Promise<void> {
   searchQuery.set(query);
+  const request = ++searchRequest;
   
   if (query.length < 2) {
     searchResults.set([]);
@@ -320,7 +379,12 @@ export async function search(query: string): Promise<void> {
   }
   
   try {
-    const results = await invoke<SearchResult[]>('search_files', { query, limit: 100 });
+    const results = await invoke<SearchResult[]>('search_files', {
+      query,
+      limit: 100,
+      includeHidden: get(viewConfig).showHidden
+    });
+    if (request !== searchRequest) return;
     const currentDirPath = get(currentPath);
     
     const scoredResults = results.map(result => ({
@@ -334,8 +398,20 @@ export async function search(query: string): Promise<void> {
     searchResults.set(scoredResults.slice(0, 50));
     searchSelectedIndex.set(0);
   } catch (e) {
-    searchResults.set([]);
+    if (request === searchRequest) searchResults.set([]);
+  }
+}
+
+// Forgets the results found gone from the disk and runs the search again.
+export async function removeStaleResults(): Promise<void> {
+  const paths = get(searchResults).filter(r => r.stale).map(r => r.path);
+  if (paths.length === 0) return;
+  try {
+    await invoke<number>('remove_stale_entries', { paths });
+  } catch (e) {
+    console.error('Failed to remove stale entries:', e);
   }
+  await search(get(searchQuery));
 }
 
 export function openCommandPalette(): void {
@@ -361,20 +437,24 @@ export async function jumpToSearchResult(): Promise<void> {
     if (result.is_dir) {
       await navigateTo(result.path);
     } else {
-      const parentPath = parentDir(result.path);
-      await navigateTo(parentPath);
-      
-      const items = get(entries);
-      const fileIndex = items.findIndex(e => e.path === result.path);
-      if (fileIndex >= 0) {
-        selectedIndex.set(fileIndex);
-        await loadPreview(result.path);
-        viewConfig.update(c => ({ ...c, previewOpen: true }));
-      }
+      await revealPath(result.path);
     }
   }
 }
 
+// Opens the file's folder with the file selected and previewed.
+export async function revealPath(path: string): Promise<void> {
+  await navigateTo(parentDir(path));
+
+  const items = get(entries);
+  const fileIndex = items.findIndex(e => e.path === path);
+  if (fileIndex >= 0) {
+    selectedIndex.set(fileIndex);
+    await loadPreview(path);
+    viewConfig.update(c => ({ ...c, previewOpen: true }));
+  }
+}
+
 export function toggleHidden(): void {
   viewConfig.update(c => ({ ...c, showHidden: !c.showHidden }));
   const path = get(currentPath);
@@ -397,19 +477,40 @@ export function setSort(field: SortField): void {
   }));
 }
 
+export const profileName = writable<string | null>(null);
+
+export async function loadProfile(): Promise<void> {
+  try {
+    const name = await invoke<string | null>('get_profile');
+    profileName.set(name);
+    if (name) {
+      viewConfigKey = `hardbore_view_config:${name}`;
+      viewConfig.set(loadViewConfig());
+    }
+  } catch (e) {
+    console.error('Failed to load profile:', e);
+  }
+}
+
 export async function initializeApp(): Promise<void> {
   try {
+    await loadProfile();
     const [indexedCount] = await Promise.all([
       invoke<number>('init_indexer'),
       loadPickerConfig(),
+      loadAppChooser(),
       loadFavorites(),
+      loadQuickBookmarks(),
+      loadWorkspaces(),
       loadMountPoints(),
+      loadJobs(),
     ]);
     
     const config = get(pickerConfig);
     if (config?.current_name) {
       saveName.set(config.current_name);
     }
+    saveType.set(config?.file_types?.[0] ?? null);
     
     const cwd = config?.start_dir || await invoke<string | null>('get_current_dir');
     const startPath = cwd || await invoke<string | null>('get_home');
@@ -420,7 +521,7 @@ export async function initializeApp(): Promise<void> {
 
     if (indexedCount === 0) {
       setTimeout(() => {
-        invoke('start_indexing', { path: '/', maxDepth: null });
+        invoke('start_indexing', { path: '/', maxDepth: null, sameFilesystem: true });
         const mounts = get(mountPoints);
         for (const mount of mounts) {
           invoke('start_indexing', { path: mount.path, maxDepth: null });
@@ -452,6 +553,181 @@ export async function loadFavorites(): Promise<void> {
   }
 }
 
+export async function loadQuickBookmarks(): Promise<void> {
+  try {
+    quickBookmarks.set(await invoke<QuickBookmark[]>('get_quick_bookmarks'));
+  } catch (e) {
+    console.error('Failed to load quick bookmarks:', e);
+  }
+}
+
+// Another window or process changed one of the backend's state files.
+export async function reloadStateFile(file: string): Promise<void> {
+  switch (file) {
+    case 'favorites.json':
+      await loadFavorites();
+      break;
+    case 'quick_bookmarks.json':
+      await loadQuickBookmarks();
+      break;
+    case 'settings.json':
+      await loadWorkspaces();
+      break;
+  }
+}
+
+// A path, or anything below it.
+function isWithin(path: string, root: string): boolean {
+  if (!path.startsWith(root)) return false;
+  return path.length === root.length || root.endsWith('/') || root.endsWith('\\') || '/\\'.includes(path[root.length]);
+}
+
+// Lists the folder again in place, keeping the selection on the entries that
+// are still there.
+async function reloadListing(path: string): Promise<void> {
+  const selected = new Set(get(selectedEntries).map(e => e.path));
+  const focused = get(selectedEntry)?.path;
+  const payload = await invoke<ListingPayload>('read_dir_packed', {
+    path,
+    showHidden: get(viewConfig).showHidden,
+    forceRefresh: true,
+    minEntries: COLUMNAR_MIN_ENTRIES
+  });
+  currentDir.set(unpackListing(payload));
+
+  const list = get(entries);
+  const focusedIndex = list.findIndex(e => e.path === focused);
+  const index = focusedIndex >= 0 ? focusedIndex : Math.min(get(selectedIndex), Math.max(list.length - 1, 0));
+  const indices = new Set<number>();
+  list.forEach((e, i) => {
+    if (selected.has(e.path)) indices.add(i);
+  });
+  selectedIndex.set(index);
+  selectedIndices.set(indices.size > 0 ? indices : new Set([index]));
+}
+
+async function applyFsChanges(changes: FsChange[]): Promise<void> {
+  const shown = get(currentDir);
+  if (!shown) return;
+
+  // Virtual locations list files from all over; they follow their entries.
+  if (shown.path.startsWith('hardbore://')) {
+    const listed = shown.entries.map(e => e.path);
+    const touched = changes.some(c =>
+      listed.some(p => c.paths.some(changed => isWithin(p, changed)) || c.moves.some(([from]) => isWithin(p, from)))
+    );
+    if (touched) await navigateTo(shown.path, false, true);
+    return;
+  }
+
+  // The folder being shown, or one above it, may have been renamed.
+  let path = shown.path;
+  for (const change of changes) {
+    for (const [from, to] of change.moves) {
+      if (isWithin(path, from)) path = to + path.slice(from.length);
+    }
+  }
+  const affected = path !== shown.path || changes.some(c => c.dirs.includes(path) || c.paths.some(p => isWithin(path, p)));
+  if (!affected) return;
+
+  // If it's gone, the closest folder that's left is shown instead.
+  for (;;) {
+    try {
+      await reloadListing(path);
+      return;
+    } catch (e) {
+      const up = parentDir(path);
+      if (up === path) {
+        errorMessage.set(String(e));
+        return;
+      }
+      path = up;
+    }
+  }
+}
+
+let pendingFsChanges: FsChange[] = [];
+let fsChangeTimer: ReturnType<typeof setTimeout> | undefined;
+
+// Another window, or something outside the app, changed files. A burst of
+// changes, like a bulk rename, is handled in one go.
+export function handleFsChange(change: FsChange): void {
+  pendingFsChanges.push(change);
+  if (fsChangeTimer) return;
+  fsChangeTimer = setTimeout(() => {
+    const changes = pendingFsChanges;
+    pendingFsChanges = [];
+    fsChangeTimer = undefined;
+    applyFsChanges(changes);
+  }, 150);
+}
+
+export async function setQuickBookmark(slot: number, path: string | null): Promise<void> {
+  try {
+    quickBookmarks.set(await invoke<QuickBookmark[]>('set_quick_bookmark', { slot, path }));
+  } catch (e) {
+    errorMessage.set(`Failed to set bookmark ${slot}: ${e}`);
+  }
+}
+
+export async function jumpToQuickBookmark(slot: number): Promise<void> {
+  const bookmark = get(quickBookmarks).find(b => b.slot === slot);
+  if (bookmark) {
+    await navigateTo(bookmark.path);
+  }
+}
+
+export async function loadWorkspaces(): Promise<void> {
+  try {
+    workspaces.set(await invoke<Workspace[]>('list_workspaces'));
+  } catch (e) {
+    console.error('Failed to load workspaces:', e);
+  }
+}
+
+// The locations visited in this window become the workspace's tabs, and the
+// current favorites its pinned locations.
+export async function saveWorkspace(name: string): Promise<void> {
+  try {
+    const workspace = await invoke<Workspace>('save_workspace', {
+      workspace: {
+        name,
+        tabs: get(history).filter(path => path),
+        active_tab: Math.max(get(historyIndex), 0),
+        pinned: get(favorites)
+      }
+    });
+    activeWorkspace.set(workspace);
+    await loadWorkspaces();
+  } catch (e) {
+    errorMessage.set(`Failed to save workspace: ${e}`);
+  }
+}
+
+export async function openWorkspace(name: string): Promise<void> {
+  try {
+    const workspace = await invoke<Workspace>('open_workspace', { name });
+    history.set(workspace.tabs);
+    historyIndex.set(workspace.active_tab);
+    activeWorkspace.set(workspace);
+    await navigateTo(workspace.tabs[workspace.active_tab], false);
+  } catch (e) {
+    errorMessage.set(`Failed to open workspace: ${e}`);
+  }
+}
+
+export async function deleteWorkspace(name: string): Promise<void> {
+  try {
+    await invoke('delete_workspace', { name });
+    if (get(activeWorkspace)?.name === name) {
+      activeWorkspace.set(null);
+    }
+    await loadWorkspaces();
+  } catch (e) {
+    errorMessage.set(`Failed to delete workspace: ${e}`);
+  }
+}
+
 export async function loadMountPoints(): Promise<void> {
   try {
     const mounts = await invoke<MountPoint[]>('get_mount_points');
@@ -479,6 +755,70 @@ export async function removeFavorite(path: string): Promise<void> {
   }
 }
 
+// What would be taken over from another file manager; nothing changes yet.
+export async function importFrom(app: FileManager): Promise<ImportPreview | null> {
+  try {
+    return await invoke<ImportPreview>('import_from', { app });
+  } catch (e) {
+    errorMessage.set(`Failed to read ${app} settings: ${e}`);
+    return null;
+  }
+}
+
+// Adds the previewed favorites and takes over its view settings.
+export async function applyImport(preview: ImportPreview): Promise<number> {
+  try {
+    const added = await invoke<number>('import_favorites', { paths: preview.favorites });
+    viewConfig.update(c => ({
+      ...c,
+      showHidden: preview.show_hidden ?? c.showHidden,
+      sort: preview.sort ?? c.sort
+    }));
+    await loadFavorites();
+    return added;
+  } catch (e) {
+    errorMessage.set(`Failed to import favorites: ${e}`);
+    return 0;
+  }
+}
+
+// A `tree`-style listing of `path`; a depth of 0 lists everything. With
+// `output` it is saved to that file as well.
+export async function generateTree(
+  path: string,
+  depth: number,
+  options: TreeOptions = {},
+  output?: string
+): Promise<string | null> {
+  try {
+    return await invoke<string>('generate_tree', { path, depth, options, output: output ?? null });
+  } catch (e) {
+    errorMessage.set(`Failed to generate tree: ${e}`);
+    return null;
+  }
+}
+
+// What changed under `path` recently, newest first. `since` is in Unix
+// seconds; the backend looks a week back without it.
+export async function getActivityTimeline(path: string, since?: number): Promise<ActivityEvent[]> {
+  try {
+    return await invoke<ActivityEvent[]>('get_activity_timeline', { path, since: since ?? null });
+  } catch (e) {
+    console.error('Failed to load activity timeline:', e);
+    return [];
+  }
+}
+
+// Empties the preview and icon caches, returning what that freed.
+export async function clearCaches(): Promise<ClearReport | null> {
+  try {
+    return await invoke<ClearReport>('clear_caches');
+  } catch (e) {
+    errorMessage.set(`Failed to clear caches: ${e}`);
+    return null;
+  }
+}
+
 export async function createDirectory(parentPath: string, name: string): Promise<void> {
   try {
     const newPath = `${parentPath}/${name}`;
@@ -498,6 +838,78 @@ export async function loadPickerConfig(): Promise<void> {
   }
 }
 
+export async function loadAppChooser(): Promise<void> {
+  try {
+    const request = await invoke<ChooserRequest | null>('get_app_chooser');
+    appChooser.set(request);
+    if (request) {
+      chooserApplications.set(await invoke<Application[]>('chooser_applications'));
+    }
+  } catch (e) {
+    console.error('Failed to load app chooser:', e);
+  }
+}
+
+export async function chooseApplication(appId: string): Promise<void> {
+  try {
+    await invoke('choose_application', { appId });
+  } catch (e) {
+    errorMessage.set(`Failed to choose application: ${e}`);
+  }
+}
+
+export async function getPortalStatus(): Promise<PortalStatus | null> {
+  try {
+    return await invoke<PortalStatus>('portal_status');
+  } catch (e) {
+    console.error('Failed to get portal status:', e);
+    return null;
+  }
+}
+
+export async function installPortalService(): Promise<InstallReport | null> {
+  try {
+    return await invoke<InstallReport>('install_portal_service');
+  } catch (e) {
+    errorMessage.set(`Failed to install the portal: ${e}`);
+    return null;
+  }
+}
+
+export async function isBackgroundMode(): Promise<boolean> {
+  try {
+    return await invoke<boolean>('is_background_mode');
+  } catch (e) {
+    return false;
+  }
+}
+
+export async function launcherSearch(query: string): Promise<SearchResult[]> {
+  if (query.length < 2) return [];
+  try {
+    return await invoke<SearchResult[]>('launcher_search', { query, limit: 20 });
+  } catch (e) {
+    console.error('Launcher search failed:', e);
+    return [];
+  }
+}
+
+export async function launcherOpen(path: string, reveal: boolean): Promise<void> {
+  try {
+    await invoke('launcher_open', { path, reveal });
+  } catch (e) {
+    console.error('Failed to open from the launcher:', e);
+  }
+}
+
+export async function hideLauncher(): Promise<void> {
+  try {
+    await invoke('hide_launcher');
+  } catch (e) {
+    console.error('Failed to hide the launcher:', e);
+  }
+}
+
 export function togglePickerSelection(path: string): void {
   pickerSelection.update($selection => {
     const newSelection = new Set($selection);
@@ -522,12 +934,34 @@ export async function confirmPickerSelection(): Promise<void> {
   try {
     const config = get(pickerConfig);
     if (config?.mode === 'Save') {
-      const name = get(saveName).trim();
+      let name = get(saveName).trim();
       const dir = get(currentPath);
       if (name && dir) {
+        const typed = await invoke<SaveNameCheck>('check_save_name', { name, fileType: get(saveType) });
+        if (typed.expected) {
+          const wanted = typed.expected.includes('/') ? `a ${typed.expected} file` : `a .${typed.expected} file`;
+          if (!confirm(`"${name}" isn't ${wanted}, the type chosen. Save it anyway?`)) {
+            return;
+          }
+        }
+        name = typed.name;
+        saveName.set(name);
+        const check = await invoke<NameCheck>('validate_filename', { name, directory: dir });
+        if (check.issues.length > 0) {
+          if (!confirm(`"${name}" can't be used as a file name here. Save as "${check.sanitized}" instead?`)) {
+            return;
+          }
+          name = check.sanitized;
+          saveName.set(name);
+        }
         const fullPath = `${dir}/${name}`;
-        const exists = await invoke<boolean>('path_exists', { path: fullPath });
-        if (exists && !confirm(`"${name}" already exists. Overwrite?`)) {
+        const target = await invoke<SaveTarget>('check_save_target', { path: fullPath });
+        saveTarget.set(target);
+        if (target.problem) {
+          errorMessage.set(target.problem);
+          return;
+        }
+        if (target.exists && !confirm(`"${name}" already exists. Overwrite?`)) {
           return;
         }
         await invoke('select_files', { paths: [fullPath] });
@@ -558,6 +992,86 @@ export async function confirmPickerSelection(): Promise<void> {
   }
 }
 
+let saveTargetCheck = 0;
+
+// Rechecked as the name is typed and the folder changes; only the latest
+// answer is kept.
+export async function checkSaveTarget(dir: string, name: string): Promise<void> {
+  const check = ++saveTargetCheck;
+  if (!dir || !name) {
+    saveTarget.set(null);
+    return;
+  }
+  try {
+    const target = await invoke<SaveTarget>('check_save_target', { path: `${dir}/${name}` });
+    if (check === saveTargetCheck) saveTarget.set(target);
+  } catch (e) {
+    console.error('Failed to check the save target:', e);
+  }
+}
+
+// Switching types in Save mode swaps the name's extension for the new one's,
+// as GTK's and Qt's dialogs do.
+export async function setSaveType(fileType: string): Promise<void> {
+  const name = get(saveName).trim();
+  const previous = get(saveType);
+  saveType.set(fileType);
+  if (!name || !previous) return;
+  try {
+    const before = await invoke<SaveNameCheck>('check_save_name', { name, fileType: previous });
+    const stem = name.replace(/\.[^.]+$/, '');
+    if (before.expected || stem === name || !stem) return;
+    const after = await invoke<SaveNameCheck>('check_save_name', { name: stem, fileType });
+    if (after.appended) saveName.set(after.name);
+  } catch (e) {
+    console.error('Failed to check save name:', e);
+  }
+}
+
+export async function loadJobs(): Promise<void> {
+  try {
+    jobs.set(await invoke<JobInfo[]>('list_jobs'));
+  } catch (e) {
+    console.error('Failed to load jobs:', e);
+  }
+}
+
+const FINISHED_JOB_STATES: JobState[] = ['Completed', 'Failed', 'Cancelled'];
+const jobWaiters = new Map<number, (job: JobInfo) => void>();
+
+export function handleJobProgress(job: JobInfo): void {
+  jobs.update((list) => {
+    const index = list.findIndex((j) => j.id === job.id);
+    return index === -1 ? [...list, job] : list.map((j, i) => (i === index ? job : j));
+  });
+  if (FINISHED_JOB_STATES.includes(job.state)) {
+    jobWaiters.get(job.id)?.(job);
+    jobWaiters.delete(job.id);
+  }
+}
+
+// The job may have finished before its id came back to the caller.
+function waitForJob(id: number): Promise<JobInfo> {
+  const finished = get(jobs).find((j) => j.id === id && FINISHED_JOB_STATES.includes(j.state));
+  if (finished) return Promise.resolve(finished);
+  return new Promise((resolve) => jobWaiters.set(id, resolve));
+}
+
+// Closing the window while jobs are running asks whether to let them finish
+// in the background or stop them; declining both keeps the window open.
+export async function handleCloseRequested(jobs: JobInfo[]): Promise<void> {
+  const running = jobs.length === 1 ? `"${jobs[0].title}" is` : `${jobs.length} jobs are`;
+  try {
+    if (confirm(`${running} still running. Close the window and let them finish in the background?`)) {
+      await invoke('finish_jobs_in_background');
+    } else if (confirm('Stop them and quit now?')) {
+      await invoke('force_quit');
+    }
+  } catch (e) {
+    errorMessage.set(`Failed to close: ${e}`);
+  }
+}
+
 export async function cancelPicker(): Promise<void> {
   try {
     await invoke('cancel_picker');
@@ -621,6 +1135,30 @@ export function cutToClipboard(paths: string[]): void {
   clipboard.set({ paths, operation: 'cut' });
 }
 
+const LARGE_MOVE_BYTES = 1024 * 1024 * 1024;
+
+// A move to another filesystem is a full copy followed by a delete, so large
+// ones are confirmed first. Lookup failures never block the move; running out
+// of space is caught by the preflight check.
+async function confirmCrossDeviceMove(sources: string[], destDir: string): Promise<boolean> {
+  try {
+    const target = await invoke<MountInfo>('get_mount_for_path', { path: destDir });
+    const mounts = await Promise.all(
+      sources.map((path) => invoke<MountInfo>('get_mount_for_path', { path }))
+    );
+    const crossing = sources.filter((_, i) => mounts[i].mount_point !== target.mount_point);
+    if (crossing.length === 0) return true;
+
+    const { total_size } = await invoke<MultiProperties>('get_properties_multi', { paths: crossing });
+    if (total_size < LARGE_MOVE_BYTES) return true;
+    return confirm(
+      `${formatSize(total_size)} will be copied to ${target.mount_point} (${target.fs_type}) and then deleted here. Continue?`
+    );
+  } catch {
+    return true;
+  }
+}
+
 export async function pasteFromClipboard(destinationDir?: string): Promise<void> {
   const clip = get(clipboard);
   if (!clip) return;
@@ -628,14 +1166,31 @@ export async function pasteFromClipboard(destinationDir?: string): Promise<void>
   const destDir = destinationDir || get(currentPath);
   if (!destDir) return;
   
+  const mode = clip.operation === 'copy' ? 'Copy' : 'Move';
   try {
-    if (clip.operation === 'copy') {
-      await invoke('batch_copy_paths', { sources: clip.paths, destinationDir: destDir });
-    } else {
-      await invoke('batch_move_paths', { sources: clip.paths, destinationDir: destDir });
-      clipboard.set(null);
+    const problem = await invoke<PreflightError | null>('preflight_transfer', {
+      sources: clip.paths,
+      destinationDir: destDir,
+      mode
+    });
+    if (problem) {
+      errorMessage.set(problem.message);
+      return;
     }
-    
+    if (mode === 'Move' && !(await confirmCrossDeviceMove(clip.paths, destDir))) return;
+
+    // Runs as a job, so it can be paused or cancelled, survives the window
+    // closing and resumes after a crash. The listing follows through
+    // fs-changed once it's done.
+    const id = await invoke<number>('start_transfer', { sources: clip.paths, destinationDir: destDir, mode });
+    waitForJob(id).then((job) => {
+      if (job.state === 'Failed') {
+        errorMessage.set(`Failed to paste: ${job.error}`);
+      } else if (job.state === 'Completed' && mode === 'Move' && get(clipboard) === clip) {
+        clipboard.set(null);
+      }
+    });
+
     await navigateTo(destDir, false);
   } catch (e) {
     errorMessage.set(`Failed to paste: ${e}`);
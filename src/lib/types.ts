@@ -1,15 +1,49 @@
+export type FileCategory =
+  | 'Document'
+  | 'Image'
+  | 'Audio'
+  | 'Video'
+  | 'Archive'
+  | 'Code'
+  | 'Executable'
+  | 'Font'
+  | 'DiskImage'
+  | 'Other';
+
+export type LaunchKind = 'Application' | 'Link';
+
+export interface LaunchTarget {
+  kind: LaunchKind;
+  name: string | null;
+  target: string;
+  arguments: string[];
+  working_dir: string | null;
+  icon: string | null;
+  terminal: boolean;
+}
+
 export interface FileEntry {
   name: string;
   path: string;
   is_dir: boolean;
   is_symlink: boolean;
   size: number;
+  disk_size: number;
   modified: number;
   permissions: string;
   owner: number;
   group: number;
+  owner_name: string | null;
+  group_name: string | null;
   extension: string | null;
   hidden: boolean;
+  placeholder: boolean;
+  category: FileCategory;
+  emblems: string[];
+  launch?: LaunchTarget;
+  columns?: Record<string, unknown>;
+  size_text: string;
+  modified_text: string;
 }
 
 export interface DirectoryContents {
@@ -18,14 +52,52 @@ export interface DirectoryContents {
   entries: FileEntry[];
   total_items: number;
   total_size: number;
+  total_disk_size: number;
+  total_size_text: string;
+}
+
+// Large listings as one array per field; see columnar.rs.
+export interface ColumnarListing {
+  path: string;
+  parent: string | null;
+  total_items: number;
+  total_size: number;
+  total_disk_size: number;
+  total_size_text: string;
+  name: string[];
+  entry_path: string[];
+  flags: number[];
+  size: number[];
+  disk_size: number[];
+  modified: number[];
+  permissions: string[];
+  owner: number[];
+  group: number[];
+  owner_name: (string | null)[];
+  group_name: (string | null)[];
+  extension: (string | null)[];
+  category: FileCategory[];
+  size_text: string[];
+  modified_text: string[];
+  emblems: Record<number, string[]>;
+  launch: Record<number, LaunchTarget>;
+  columns: Record<number, Record<string, unknown>>;
 }
 
+export type ListingPayload =
+  | ({ layout: 'Rows' } & DirectoryContents)
+  | ({ layout: 'Columns' } & ColumnarListing);
+
+export type SearchSource = 'Index' | 'Locate' | 'Spotlight' | 'Everything';
+
 export interface SearchResult {
   name: string;
   path: string;
   is_dir: boolean;
   hidden: boolean;
   score: number;
+  source: SearchSource;
+  stale: boolean;
 }
 
 export interface IndexerStatus {
@@ -35,7 +107,224 @@ export interface IndexerStatus {
   elapsed_ms: number;
 }
 
-export type PreviewType = 'Code' | 'Image' | 'Hex' | 'Auto';
+export interface IndexShard {
+  mount: string;
+  file_size: number;
+  mounted: boolean;
+}
+
+export interface CompactReport {
+  before_bytes: number;
+  after_bytes: number;
+}
+
+export type JournalBackend = 'Fanotify' | 'UsnJournal';
+
+export interface JournalStatus {
+  root: string;
+  backend: JournalBackend;
+  applied: number;
+  error: string | null;
+}
+
+export interface BreakdownEntry {
+  key: string | null;
+  count: number;
+  total_size: number;
+}
+
+export interface IndexBreakdown {
+  root: string;
+  total_count: number;
+  total_size: number;
+  by_extension: BreakdownEntry[];
+  by_category: BreakdownEntry[];
+  by_directory: BreakdownEntry[];
+}
+
+export type IndexOrder = 'Largest' | 'Smallest' | 'Newest' | 'Oldest' | 'Name';
+
+export interface IndexFilter {
+  root?: string | null;
+  extension?: string | null;
+  category?: FileCategory | null;
+  min_size?: number | null;
+  max_size?: number | null;
+  modified_after?: number | null;
+  modified_before?: number | null;
+  include_dirs?: boolean;
+  include_hidden?: boolean;
+  empty_only?: boolean;
+}
+
+export interface IndexedFile {
+  name: string;
+  path: string;
+  is_dir: boolean;
+  hidden: boolean;
+  extension: string | null;
+  size: number;
+  modified: number;
+  category: FileCategory | null;
+}
+
+export type ReclaimCategory = 'BuildArtifacts' | 'Dependencies' | 'Caches' | 'OldLargeFiles' | 'Duplicates';
+
+export interface ReclaimItem {
+  path: string;
+  size: number;
+  file_count: number;
+  modified: number;
+  related: string[];
+}
+
+export interface ReclaimGroup {
+  category: ReclaimCategory;
+  total_size: number;
+  items: ReclaimItem[];
+}
+
+export type PreviewType = 'Code' | 'Image' | 'Hex' | 'Auto' | 'Torrent' | 'Json' | 'Table' | 'Markdown' | 'Notebook' | 'Database';
+
+export type JsonKind = 'Object' | 'Array' | 'String' | 'Number' | 'Bool' | 'Null';
+
+export interface JsonNode {
+  key: string | null;
+  kind: JsonKind;
+  value: string | null;
+  length: number | null;
+  children: JsonNode[];
+  truncated: boolean;
+}
+
+export interface JsonPreview {
+  root: JsonNode;
+  truncated: boolean;
+  lines: boolean;
+}
+
+export interface TablePreview {
+  delimiter: string;
+  headers: string[];
+  rows: string[][];
+  truncated: boolean;
+}
+
+export interface SqliteTable {
+  name: string;
+  view: boolean;
+  row_count: number | null;
+}
+
+export interface SqlitePreview {
+  tables: SqliteTable[];
+  table: string | null;
+  columns: string[];
+  rows: (string | null)[][];
+  truncated: boolean;
+}
+
+export type LineEndings = 'None' | 'Lf' | 'Crlf' | 'Cr' | 'Mixed';
+
+export type Indentation = 'None' | 'Tabs' | 'Spaces' | 'Mixed';
+
+export interface TextStats {
+  line_endings: LineEndings;
+  bom: boolean;
+  indentation: Indentation;
+  indent_width: number | null;
+  line_count: number;
+  longest_line: number;
+  wide: boolean;
+  control_chars: number;
+}
+
+export interface TextRange {
+  start_line: number;
+  lines: string[];
+  total_lines: number | null;
+  truncated: boolean;
+  encoding: string;
+}
+
+export interface LineMatch {
+  line: number;
+  ranges: [number, number][];
+  text: string;
+}
+
+export interface FileSearch {
+  matches: LineMatch[];
+  match_count: number;
+  truncated: boolean;
+}
+
+export type DiffMode = 'Auto' | 'Text' | 'Binary';
+
+export interface ByteRange {
+  offset: number;
+  length: number;
+}
+
+export interface FileDiff {
+  mode: DiffMode;
+  identical: boolean;
+  size_a: number;
+  size_b: number;
+  unified: string | null;
+  additions: number;
+  deletions: number;
+  byte_ranges: ByteRange[];
+  truncated: boolean;
+}
+
+export type CellKind = 'Code' | 'Markdown' | 'Raw';
+
+export type OutputKind = 'Text' | 'Stderr' | 'Error' | 'Image';
+
+export interface CellOutput {
+  kind: OutputKind;
+  text: string | null;
+  image: string | null;
+  truncated: boolean;
+}
+
+export interface NotebookCell {
+  kind: CellKind;
+  source: string;
+  html: string | null;
+  execution_count: number | null;
+  outputs: CellOutput[];
+  truncated: boolean;
+}
+
+export interface NotebookPreview {
+  language: string | null;
+  kernel: string | null;
+  nbformat: number | null;
+  cell_count: number;
+  cells: NotebookCell[];
+  truncated: boolean;
+}
+
+export interface TorrentFile {
+  path: string;
+  size: number;
+}
+
+export interface TorrentInfo {
+  name: string | null;
+  total_size: number | null;
+  files: TorrentFile[];
+  trackers: string[];
+  comment: string | null;
+  created_by: string | null;
+  creation_date: number | null;
+  piece_length: number | null;
+  private: boolean;
+  info_hash: string | null;
+  magnet: string | null;
+}
 
 export interface FilePreview {
   path: string;
@@ -43,8 +332,438 @@ export interface FilePreview {
   size: number;
   text_content: string | null;
   hex_content: string | null;
+  html_content?: string | null;
+  encoding?: string | null;
+  text_stats?: TextStats | null;
   truncated: boolean;
   extension: string | null;
+  placeholder: boolean;
+  torrent?: TorrentInfo | null;
+  json?: JsonPreview | null;
+  table?: TablePreview | null;
+  notebook?: NotebookPreview | null;
+  database?: SqlitePreview | null;
+}
+
+export type CleanupKind = 'EmptyDirectory' | 'BrokenSymlink' | 'EmptyFile' | 'StaleTempFile';
+
+export interface CleanupCandidate {
+  path: string;
+  kind: CleanupKind;
+  size: number;
+  modified: number;
+}
+
+export interface CleanupReport {
+  removed: string[];
+  errors: string[];
+  freed_bytes: number;
+}
+
+export interface TypeCount {
+  kind: string;
+  count: number;
+}
+
+export interface MultiProperties {
+  count: number;
+  file_count: number;
+  dir_count: number;
+  total_size: number;
+  permissions: string | null;
+  owner: number | null;
+  group: number | null;
+  types: TypeCount[];
+  oldest_modified: number | null;
+  newest_modified: number | null;
+  errors: string[];
+  skipped_dirs: string[];
+}
+
+export interface SystemUser {
+  uid: number;
+  name: string;
+}
+
+export interface SystemGroup {
+  gid: number;
+  name: string;
+  members: string[];
+}
+
+export type AclTag = 'UserObj' | 'User' | 'GroupObj' | 'Group' | 'Mask' | 'Other';
+
+export interface AclEntry {
+  tag: AclTag;
+  id: number | null;
+  name: string | null;
+  read: boolean;
+  write: boolean;
+  execute: boolean;
+}
+
+export interface FileAcl {
+  path: string;
+  entries: AclEntry[];
+  default_entries: AclEntry[];
+  editable: boolean;
+  raw: string[];
+}
+
+export type SnapshotSource = 'Zfs' | 'Btrfs' | 'TimeMachine' | 'ShadowCopy';
+
+export interface PreviousVersion {
+  snapshot: string;
+  source: SnapshotSource;
+  path: string;
+  created: number | null;
+  size: number;
+  modified: number;
+}
+
+export type BackupTool = 'Restic' | 'Borg';
+
+export interface BackupSnapshot {
+  id: string;
+  time: number;
+  hostname: string | null;
+  paths: string[];
+}
+
+export interface BackupEntry {
+  name: string;
+  path: string;
+  is_dir: boolean;
+  size: number;
+  modified: number;
+}
+
+export type DownloadState = 'Queued' | 'Running' | 'Verifying' | 'Completed' | 'Failed' | 'Cancelled';
+
+export interface DownloadRequest {
+  url: string;
+  sha256?: string | null;
+}
+
+export interface DownloadStatus {
+  id: number;
+  url: string;
+  destination: string;
+  state: DownloadState;
+  downloaded: number;
+  total: number | null;
+  resumed: boolean;
+  error: string | null;
+}
+
+export type JobKind = 'Indexing' | 'Copy' | 'Move' | 'Download';
+
+export type JobState = 'Queued' | 'Running' | 'Paused' | 'Completed' | 'Failed' | 'Cancelled' | 'Interrupted';
+
+export interface JobInfo {
+  id: number;
+  kind: JobKind;
+  title: string;
+  state: JobState;
+  processed: number;
+  total: number | null;
+  current: string | null;
+  error: string | null;
+  background: boolean;
+  paths: string[];
+}
+
+export type TargetFs = 'Posix' | 'Apfs' | 'Ntfs' | 'Fat' | 'Exfat';
+
+export type NameIssue =
+  | 'Empty'
+  | 'DotName'
+  | 'ReservedCharacters'
+  | 'ReservedName'
+  | 'TrailingDotOrSpace'
+  | 'TooLong'
+  | 'InvalidUnicode';
+
+export interface NameCheck {
+  target_fs: TargetFs;
+  issues: NameIssue[];
+  sanitized: string;
+}
+
+export interface RenameOp {
+  from: string;
+  to: string;
+}
+
+export type TransferMode = 'Copy' | 'Move';
+
+export type SkipIdentical = 'Never' | 'SizeAndTime' | 'Checksum';
+
+export type PreflightProblem = 'NotADirectory' | 'NotWritable' | 'InsufficientSpace';
+
+export interface PreflightError {
+  problem: PreflightProblem;
+  destination: string;
+  message: string;
+  required: number | null;
+  available: number | null;
+}
+
+export interface SharedFile {
+  name: string;
+  path: string;
+  size: number;
+}
+
+export interface ShareInfo {
+  url: string;
+  port: number;
+  token: string | null;
+  one_time: boolean;
+  files: SharedFile[];
+}
+
+export type SendKind = 'Email' | 'Bluetooth' | 'KdeConnect';
+
+export interface SendTarget {
+  id: string;
+  name: string;
+  kind: SendKind;
+}
+
+export type PathFormat = 'Plain' | 'Quoted' | 'Uri' | 'Windows';
+
+export interface PtyOutput {
+  id: number;
+  data: string;
+}
+
+export interface PtyCwd {
+  id: number;
+  path: string;
+}
+
+export interface PtyExit {
+  id: number;
+  code: number | null;
+}
+
+export type PluginCapability = 'preview' | 'context-menu' | 'vfs';
+
+export interface PluginAction {
+  id: string;
+  title: string;
+  extensions: string[];
+  directories: boolean;
+}
+
+export interface PluginManifest {
+  id: string;
+  name: string;
+  version: string;
+  description: string | null;
+  command: string[];
+  capabilities: PluginCapability[];
+  preview_extensions: string[];
+  actions: PluginAction[];
+  vfs_schemes: string[];
+}
+
+export interface PluginInfo {
+  manifest: PluginManifest;
+  dir: string;
+  enabled: boolean;
+  granted: PluginCapability[];
+}
+
+export interface PluginPreview {
+  plugin_id: string;
+  content_type: 'text' | 'html';
+  content: string;
+}
+
+export interface PluginMenuItem {
+  plugin_id: string;
+  action_id: string;
+  title: string;
+}
+
+export interface PluginVfsEntry {
+  name: string;
+  uri: string;
+  is_dir: boolean;
+  size: number;
+  modified: number;
+}
+
+export interface ScriptInfo {
+  name: string;
+  path: string;
+  description: string | null;
+}
+
+export interface ScriptOutput {
+  run_id: number;
+  line: string;
+}
+
+export interface ScriptPrompt {
+  id: number;
+  run_id: number;
+  message: string;
+  default: string | null;
+}
+
+export interface ScriptResult {
+  run_id: number;
+  output: string[];
+  value: string | null;
+}
+
+export type MenuSource = 'Config' | 'ServiceMenu' | 'DBus';
+
+export interface ContextMenuEntry {
+  id: string;
+  title: string;
+  icon: string | null;
+  source: MenuSource;
+}
+
+export type ScriptSource = 'HardBore' | 'Nautilus' | 'Nemo' | 'Caja';
+
+export interface UserScript {
+  name: string;
+  path: string;
+  submenu: string[];
+  source: ScriptSource;
+}
+
+export interface FileHolder {
+  pid: number;
+  command: string;
+  path: string | null;
+}
+
+export interface EjectReport {
+  ejected: boolean;
+  blocking_jobs: JobInfo[];
+  holders: FileHolder[];
+  error: string | null;
+}
+
+export interface BenchmarkStep {
+  name: string;
+  items: number;
+  bytes: number | null;
+  duration_ms: number;
+  items_per_second: number;
+  error: string | null;
+}
+
+export interface BenchmarkReport {
+  version: string;
+  os: string;
+  arch: string;
+  cpus: number;
+  root: string;
+  steps: BenchmarkStep[];
+  total_ms: number;
+}
+
+export interface LogRecord {
+  timestamp: string;
+  level: string;
+  target: string;
+  message: string;
+}
+
+export interface LatencySummary {
+  kind: string;
+  samples: number;
+  p50_ms: number;
+  p90_ms: number;
+  p99_ms: number;
+  max_ms: number;
+}
+
+export interface IndexMetrics {
+  indexed_files: number;
+  database_bytes: number;
+}
+
+export interface LocalMetrics {
+  enabled: boolean;
+  since: number | null;
+  operations: Record<string, number>;
+  latencies: LatencySummary[];
+  index: IndexMetrics | null;
+}
+
+export interface QuickBookmark {
+  slot: number;
+  path: string;
+}
+
+export interface Workspace {
+  name: string;
+  tabs: string[];
+  active_tab: number;
+  pinned: string[];
+}
+
+export interface SavedSearch {
+  id: string;
+  query: string;
+  uri: string;
+}
+
+export interface DirectoryDelta {
+  path: string;
+  parent: string | null;
+  token: number;
+  reset: boolean;
+  added: FileEntry[];
+  removed: string[];
+  changed: FileEntry[];
+  total_items: number;
+  total_size: number;
+  total_disk_size: number;
+  total_size_text: string;
+}
+
+export interface ColumnInfo {
+  id: string;
+  label: string;
+}
+
+// Value of the `tags` column.
+export interface FileTag {
+  name: string;
+  color: string | null;
+}
+
+export type PackageFormat = 'AppImage' | 'Flatpak' | 'Snap';
+
+export interface PackageInfo {
+  format: PackageFormat;
+  path: string;
+  id: string | null;
+  name: string | null;
+  version: string | null;
+  summary: string | null;
+  icon: string | null;
+  integrated: boolean;
+}
+
+export type MountProfile = 'LocalFast' | 'LocalRotational' | 'Removable' | 'NetworkSlow';
+
+export interface MountBehavior {
+  profile: MountProfile;
+  auto_thumbnails: boolean;
+  recursive_sizes: boolean;
+  copy_buffer_size: number;
+  copy_concurrency: number;
+  watch_debounce_ms: number;
 }
 
 export interface MountPoint {
@@ -52,8 +771,23 @@ export interface MountPoint {
   path: string;
   device: string;
   fs_type: string;
+  profile: MountProfile;
 }
 
+export interface MountInfo {
+  mount_point: string;
+  fs_type: string;
+  total_space: number;
+  available_space: number;
+  profile: MountProfile;
+}
+
+export type SizeUnits = 'Iec' | 'Si';
+
+export type DateStyle = 'Short' | 'Locale' | 'Relative' | 'Iso';
+
+export type CopyBackend = 'IoUring' | 'ReadWrite';
+
 export type SortField = 'name' | 'size' | 'modified' | 'extension';
 export type SortDirection = 'asc' | 'desc';
 
@@ -77,4 +811,95 @@ export interface PickerConfig {
   file_types: string[] | null;
   start_dir: string | null;
   current_name: string | null;
+  restrict_to_dir: string | null;
+}
+
+export interface Application {
+  id: string;
+  name: string;
+  icon: string | null;
+  mime_types: string[];
+  is_default: boolean;
+}
+
+export interface PortalStatus {
+  binary: string | null;
+  portal_file: string | null;
+  dbus_service: string | null;
+  systemd_unit: string | null;
+  preferred: boolean;
+  activatable: boolean;
+  running: boolean;
+  frontend_running: boolean;
+  problems: string[];
+}
+
+export interface InstallReport {
+  written: string[];
+  status: PortalStatus;
+}
+
+export interface ChooserRequest {
+  choices: string[];
+  content_type: string | null;
+  target: string | null;
+  last_choice: string | null;
+}
+
+export interface SaveNameCheck {
+  name: string;
+  appended: boolean;
+  expected: string | null;
+}
+
+export interface SaveTarget {
+  path: string;
+  dir_writable: boolean;
+  exists: boolean;
+  writable: boolean;
+  problem: string | null;
+}
+
+export type FileManager = 'nautilus' | 'dolphin' | 'thunar' | 'double-commander' | 'total-commander';
+
+export interface ImportPreview {
+  app: FileManager;
+  sources: string[];
+  favorites: string[];
+  missing: string[];
+  show_hidden: boolean | null;
+  sort: SortConfig | null;
+}
+
+export interface TreeOptions {
+  ascii?: boolean;
+  sizes?: boolean;
+  show_hidden?: boolean;
+  dirs_only?: boolean;
+  ignore?: string[];
+}
+
+export type ActivityKind = 'created' | 'modified' | 'deleted' | 'moved';
+
+export interface ActivityEvent {
+  path: string;
+  kind: ActivityKind;
+  time: number;
+  is_dir: boolean;
+  from: string | null;
+  size: number | null;
+}
+
+export interface ClearReport {
+  files: number;
+  reclaimed: number;
+  reclaimed_text: string;
+}
+
+// Broadcast to every window when files change, by the app or outside it.
+export interface FsChange {
+  origin: 'app' | 'watcher';
+  paths: string[];
+  moves: [string, string][];
+  dirs: string[];
 }
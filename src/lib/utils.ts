@@ -1,3 +1,5 @@
+import type { DirectoryContents, FileEntry, ListingPayload } from './types';
+
 export function formatSize(bytes: number): string {
   const units = ['B', 'KB', 'MB', 'GB', 'TB'];
   let size = bytes;
@@ -15,6 +17,14 @@ export function formatSize(bytes: number): string {
   return `${size.toFixed(1)} ${units[unitIndex]}`;
 }
 
+// Names that aren't valid Unicode arrive with their stray bytes mapped into
+// the private use area (see rawpath.rs); show those as replacement characters.
+const RAW_ESCAPE_RE = /[\u{100000}-\u{1007FF}\u{10FF80}-\u{10FFFF}]/gu;
+
+export function displayName(name: string): string {
+  return name.replace(RAW_ESCAPE_RE, '\uFFFD');
+}
+
 const PATH_SEP_RE = /[\\/]/;
 
 export function splitPath(path: string): string[] {
@@ -208,3 +218,51 @@ export function getKeyString(e: KeyboardEvent): string {
   
   return parts.join('+');
 }
+
+const IS_DIR = 1;
+const IS_SYMLINK = 2;
+const HIDDEN = 4;
+const PLACEHOLDER = 8;
+
+export function unpackListing(payload: ListingPayload): DirectoryContents {
+  if (payload.layout === 'Rows') {
+    const { layout: _, ...contents } = payload;
+    return contents;
+  }
+  const entries: FileEntry[] = new Array(payload.name.length);
+  for (let i = 0; i < entries.length; i++) {
+    const flags = payload.flags[i];
+    entries[i] = {
+      name: payload.name[i],
+      path: payload.entry_path[i],
+      is_dir: (flags & IS_DIR) !== 0,
+      is_symlink: (flags & IS_SYMLINK) !== 0,
+      size: payload.size[i],
+      disk_size: payload.disk_size[i],
+      modified: payload.modified[i],
+      permissions: payload.permissions[i],
+      owner: payload.owner[i],
+      group: payload.group[i],
+      owner_name: payload.owner_name[i],
+      group_name: payload.group_name[i],
+      extension: payload.extension[i],
+      hidden: (flags & HIDDEN) !== 0,
+      placeholder: (flags & PLACEHOLDER) !== 0,
+      category: payload.category[i],
+      emblems: payload.emblems[i] ?? [],
+      launch: payload.launch[i],
+      columns: payload.columns[i],
+      size_text: payload.size_text[i],
+      modified_text: payload.modified_text[i]
+    };
+  }
+  return {
+    path: payload.path,
+    parent: payload.parent,
+    entries,
+    total_items: payload.total_items,
+    total_size: payload.total_size,
+    total_disk_size: payload.total_disk_size,
+    total_size_text: payload.total_size_text
+  };
+}